@@ -1,3 +1,5 @@
+#[cfg(feature = "admin")]
+use crate::utils::parse_pyth_feed_id;
 use crate::{
     config::GlobalOptions,
     processor::{self, process_set_user_flag},
@@ -12,8 +14,13 @@ use fixed::types::I80F48;
 use marginfi::state::marginfi_group::{BankConfigOpt, InterestRateConfigOpt};
 use marginfi::state::{
     marginfi_account::FLASHLOAN_ENABLED_FLAG,
-    marginfi_group::{BankOperationalState, RiskTier},
-    price::OracleSetup,
+    marginfi_group::{BankOperationalState, InterestRateCurve, RiskTier},
+    price::{OracleConfidencePolicy, OracleSetup},
+};
+#[cfg(feature = "admin")]
+use marginfi::{
+    constants::MAX_EXTRA_INTEREST_RATE_KINKS,
+    state::marginfi_group::{EmodeCategory, InterestRateKink},
 };
 #[cfg(feature = "dev")]
 use marginfi::{
@@ -93,6 +100,33 @@ pub enum GroupCommand {
     Update {
         admin: Option<Pubkey>,
     },
+    /// Set the weights of an e-mode category, zero weights disable it
+    #[cfg(feature = "admin")]
+    ConfigureEmode {
+        #[clap(help = "E-mode category, from 1")]
+        emode_category: u8,
+        #[clap(long)]
+        asset_weight_init: f64,
+        #[clap(long)]
+        asset_weight_maint: f64,
+        #[clap(long)]
+        liability_weight_init: f64,
+        #[clap(long)]
+        liability_weight_maint: f64,
+    },
+    /// Set the asset weights of deposits in a bank against debt in another bank, zero weights
+    /// remove the override
+    #[cfg(feature = "admin")]
+    ConfigureWeightOverride {
+        #[clap(long)]
+        asset_bank: Pubkey,
+        #[clap(long)]
+        liability_bank: Pubkey,
+        #[clap(long)]
+        asset_weight_init: f64,
+        #[clap(long)]
+        asset_weight_maint: f64,
+    },
     #[cfg(feature = "admin")]
     AddBank {
         #[clap(long)]
@@ -112,8 +146,8 @@ pub enum GroupCommand {
         deposit_limit_ui: u64,
         #[clap(long)]
         borrow_limit_ui: u64,
-        #[clap(long)]
-        pyth_oracle: Pubkey,
+        #[clap(long, help = "Bank oracle account, not needed for fixed price banks")]
+        pyth_oracle: Option<Pubkey>,
         #[clap(long)]
         optimal_utilization_rate: f64,
         #[clap(long)]
@@ -138,6 +172,16 @@ pub enum GroupCommand {
             default_value = "60"
         )]
         oracle_max_age: u16,
+        #[clap(long, help = "Max deviation of a pegged oracle from its peg, in bps")]
+        oracle_max_deviation_bps: Option<u16>,
+        #[clap(long, help = "Price of a fixed price bank, or peg of a pegged bank")]
+        fixed_price: Option<f64>,
+        #[clap(
+            long,
+            value_parser = parse_pyth_feed_id,
+            help = "Hex encoded feed id of the Pyth pull oracle accounts of the bank"
+        )]
+        pyth_push_feed_id: Option<[u8; 32]>,
     },
     #[cfg(feature = "admin")]
     HandleBankruptcy {
@@ -169,6 +213,12 @@ impl From<RiskTierArg> for RiskTier {
 pub enum OracleTypeArg {
     PythEma,
     Switchboard,
+    Aggregated,
+    PythPush,
+    SwitchboardPull,
+    StakePool,
+    Fixed,
+    Pegged,
 }
 
 impl From<OracleTypeArg> for OracleSetup {
@@ -176,6 +226,44 @@ impl From<OracleTypeArg> for OracleSetup {
         match value {
             OracleTypeArg::PythEma => OracleSetup::PythEma,
             OracleTypeArg::Switchboard => OracleSetup::SwitchboardV2,
+            OracleTypeArg::Aggregated => OracleSetup::Aggregated,
+            OracleTypeArg::PythPush => OracleSetup::PythPushOracle,
+            OracleTypeArg::SwitchboardPull => OracleSetup::SwitchboardPull,
+            OracleTypeArg::StakePool => OracleSetup::StakePool,
+            OracleTypeArg::Fixed => OracleSetup::Fixed,
+            OracleTypeArg::Pegged => OracleSetup::Pegged,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Parser, ArgEnum)]
+pub enum OracleConfidencePolicyArg {
+    Clamp,
+    Reject,
+}
+
+impl From<OracleConfidencePolicyArg> for OracleConfidencePolicy {
+    fn from(value: OracleConfidencePolicyArg) -> Self {
+        match value {
+            OracleConfidencePolicyArg::Clamp => OracleConfidencePolicy::Clamp,
+            OracleConfidencePolicyArg::Reject => OracleConfidencePolicy::Reject,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Parser, ArgEnum)]
+pub enum InterestRateCurveArg {
+    Linear,
+    MultiKink,
+    Adaptive,
+}
+
+impl From<InterestRateCurveArg> for InterestRateCurve {
+    fn from(value: InterestRateCurveArg) -> Self {
+        match value {
+            InterestRateCurveArg::Linear => InterestRateCurve::Linear,
+            InterestRateCurveArg::MultiKink => InterestRateCurve::MultiKink,
+            InterestRateCurveArg::Adaptive => InterestRateCurve::Adaptive,
         }
     }
 }
@@ -241,21 +329,101 @@ pub enum BankCommand {
         pf_fa: Option<f64>,
         #[clap(long, help = "Protocol IR fee")]
         pf_ir: Option<f64>,
+        #[clap(long, arg_enum, help = "Interest rate curve type")]
+        ir_curve: Option<InterestRateCurveArg>,
+        #[clap(
+            long,
+            help = "Utilization rate of an extra multi-kink curve kink, repeat for each kink (up to 2) along with --kink-ir"
+        )]
+        kink_ur: Vec<f64>,
+        #[clap(long, help = "Interest rate of an extra multi-kink curve kink")]
+        kink_ir: Vec<f64>,
+        #[clap(
+            long,
+            help = "Share of the plateau rate the adaptive curve moves by per year while utilization is fully off target"
+        )]
+        adaptive_speed: Option<f64>,
         #[clap(long, arg_enum, help = "Bank risk tier")]
         risk_tier: Option<RiskTierArg>,
         #[clap(long, arg_enum, help = "Bank oracle type")]
         oracle_type: Option<OracleTypeArg>,
-        #[clap(long, help = "Bank oracle account")]
-        oracle_key: Option<Pubkey>,
+        #[clap(
+            long,
+            help = "Bank oracle account, repeat for each aggregated oracle source or to add a fallback oracle. Stake pool banks take the pool state account then the SOL feed"
+        )]
+        oracle_key: Vec<Pubkey>,
         #[clap(long, help = "Soft USD init limit")]
         usd_init_limit: Option<u64>,
+        #[clap(long, help = "USD limit on the bank's liabilities, 0 to disable")]
+        usd_borrow_limit: Option<u64>,
         #[clap(long, help = "Oracle max age in seconds, 0 to use default value (60s)")]
         oracle_max_age: Option<u16>,
+        #[clap(
+            long,
+            help = "Max deviation from the median of aggregated oracle sources, of a fallback oracle from the primary one, or of a pegged oracle from its peg, in bps"
+        )]
+        oracle_max_deviation_bps: Option<u16>,
+        #[clap(long, help = "Price of a fixed price bank, or peg of a pegged bank")]
+        fixed_price: Option<f64>,
+        #[clap(
+            long,
+            value_parser = parse_pyth_feed_id,
+            help = "Hex encoded feed id of the Pyth pull oracle accounts of the bank"
+        )]
+        pyth_push_feed_id: Option<[u8; 32]>,
+        #[clap(
+            long,
+            help = "Max oracle confidence interval as a share of the price, 0 to use default value (0.05)"
+        )]
+        oracle_max_confidence: Option<f64>,
+        #[clap(
+            long,
+            help = "Multiple applied to the oracle confidence, 0 to use default value (2.12 for Pyth, 1.96 for Switchboard)"
+        )]
+        oracle_confidence_multiple: Option<f64>,
+        #[clap(
+            long,
+            arg_enum,
+            help = "Whether prices wider than the max confidence interval are clamped or rejected"
+        )]
+        oracle_confidence_policy: Option<OracleConfidencePolicyArg>,
+        #[clap(
+            long,
+            help = "Max oracle price change per second as a share of the last accepted price, 0 to disable the price guard"
+        )]
+        oracle_max_price_change: Option<f64>,
         #[clap(
             long,
             help = "Permissionless bad debt settlement, if true the group admin is not required to settle bad debt"
         )]
         permissionless_bad_debt_settlement: Option<bool>,
+        #[clap(
+            long,
+            help = "Liquidator fee charged on this bank's collateral, 0.025 by default"
+        )]
+        liquidation_liquidator_fee: Option<f64>,
+        #[clap(
+            long,
+            help = "Insurance fee charged on this bank's collateral, 0.025 by default, 0 for none"
+        )]
+        liquidation_insurance_fee: Option<f64>,
+        #[clap(
+            long,
+            help = "Max liquidator fee the fee grows to with how far the liquidatee is below maintenance, 0 for a fixed fee"
+        )]
+        liquidation_max_liquidator_fee: Option<f64>,
+        #[clap(
+            long,
+            help = "Max share of a liability one liquidation may repay, 0 to disable"
+        )]
+        liquidation_close_factor: Option<f64>,
+        #[clap(
+            long,
+            help = "Liabilities up to this native amount may be repaid in full regardless of the close factor"
+        )]
+        liquidation_full_close_threshold: Option<u64>,
+        #[clap(long, help = "E-mode category of the bank, 0 for none")]
+        emode_category: Option<u8>,
     },
     #[cfg(feature = "dev")]
     InspectPriceOracle {
@@ -384,6 +552,16 @@ pub enum AccountCommand {
         bank: Pubkey,
         ui_amount: f64,
     },
+    /// Largest amount the account can currently borrow from a bank
+    MaxBorrow {
+        #[clap(long)]
+        bank: Pubkey,
+    },
+    /// Largest amount the account can currently withdraw from a bank
+    MaxWithdraw {
+        #[clap(long)]
+        bank: Pubkey,
+    },
     Liquidate {
         #[clap(long)]
         liquidatee_marginfi_account: Pubkey,
@@ -393,6 +571,9 @@ pub enum AccountCommand {
         liability_bank: Pubkey,
         #[clap(long)]
         ui_asset_amount: f64,
+        /// Pay and receive with the signer's token accounts instead of the profile marginfi account
+        #[clap(long, action)]
+        with_tokens: bool,
     },
     Create,
     SetFlag {
@@ -522,6 +703,38 @@ fn group(subcmd: GroupCommand, global_options: &GlobalOptions) -> Result<()> {
         #[cfg(feature = "admin")]
         GroupCommand::Update { admin } => processor::group_configure(config, profile, admin),
         #[cfg(feature = "admin")]
+        GroupCommand::ConfigureEmode {
+            emode_category,
+            asset_weight_init,
+            asset_weight_maint,
+            liability_weight_init,
+            liability_weight_maint,
+        } => processor::group_configure_emode_category(
+            config,
+            profile,
+            emode_category,
+            EmodeCategory {
+                asset_weight_init: I80F48::from_num(asset_weight_init).into(),
+                asset_weight_maint: I80F48::from_num(asset_weight_maint).into(),
+                liability_weight_init: I80F48::from_num(liability_weight_init).into(),
+                liability_weight_maint: I80F48::from_num(liability_weight_maint).into(),
+            },
+        ),
+        #[cfg(feature = "admin")]
+        GroupCommand::ConfigureWeightOverride {
+            asset_bank,
+            liability_bank,
+            asset_weight_init,
+            asset_weight_maint,
+        } => processor::group_configure_weight_override(
+            config,
+            profile,
+            asset_bank,
+            liability_bank,
+            I80F48::from_num(asset_weight_init),
+            I80F48::from_num(asset_weight_maint),
+        ),
+        #[cfg(feature = "admin")]
         GroupCommand::AddBank {
             mint: bank_mint,
             seed,
@@ -542,6 +755,9 @@ fn group(subcmd: GroupCommand, global_options: &GlobalOptions) -> Result<()> {
             risk_tier,
             oracle_type,
             oracle_max_age,
+            oracle_max_deviation_bps,
+            fixed_price,
+            pyth_push_feed_id,
         } => processor::group_add_bank(
             config,
             profile,
@@ -564,6 +780,9 @@ fn group(subcmd: GroupCommand, global_options: &GlobalOptions) -> Result<()> {
             protocol_ir_fee,
             risk_tier,
             oracle_max_age,
+            oracle_max_deviation_bps,
+            fixed_price,
+            pyth_push_feed_id,
         ),
         #[cfg(feature = "admin")]
         GroupCommand::HandleBankruptcy { accounts } => {
@@ -614,12 +833,30 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
             if_ir,
             pf_fa,
             pf_ir,
+            ir_curve,
+            kink_ur,
+            kink_ir,
+            adaptive_speed,
             risk_tier,
             oracle_type,
             oracle_key,
             usd_init_limit,
+            usd_borrow_limit,
             oracle_max_age,
+            oracle_max_deviation_bps,
+            fixed_price,
+            pyth_push_feed_id,
+            oracle_max_confidence,
+            oracle_confidence_multiple,
+            oracle_confidence_policy,
+            oracle_max_price_change,
             permissionless_bad_debt_settlement,
+            liquidation_liquidator_fee,
+            liquidation_insurance_fee,
+            liquidation_max_liquidator_fee,
+            liquidation_close_factor,
+            liquidation_full_close_threshold,
+            emode_category,
         } => {
             let bank = config
                 .mfi_program
@@ -643,17 +880,24 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
                         spl_token::ui_amount_to_amount(ui_amount, bank.mint_decimals)
                     }),
                     operational_state: operational_state.map(|x| x.into()),
-                    oracle: oracle_key.map(|x| marginfi::state::marginfi_group::OracleConfig {
-                        setup: oracle_type
-                            .expect("Orcale type must be provided with oracle_key")
-                            .into(),
-                        keys: [
-                            x,
-                            Pubkey::default(),
-                            Pubkey::default(),
-                            Pubkey::default(),
-                            Pubkey::default(),
-                        ],
+                    oracle: (!oracle_key.is_empty()
+                        || matches!(oracle_type, Some(OracleTypeArg::Fixed)))
+                    .then(|| {
+                        assert!(
+                            oracle_key.len() <= marginfi::constants::MAX_ORACLE_KEYS,
+                            "At most {} oracle keys can be provided",
+                            marginfi::constants::MAX_ORACLE_KEYS
+                        );
+                        let mut keys = [Pubkey::default(); marginfi::constants::MAX_ORACLE_KEYS];
+                        keys[..oracle_key.len()].copy_from_slice(&oracle_key);
+
+                        marginfi::state::marginfi_group::OracleConfig {
+                            setup: oracle_type
+                                .expect("Orcale type must be provided with oracle_key")
+                                .into(),
+                            keys,
+                            pyth_push_feed_id: pyth_push_feed_id.unwrap_or_default(),
+                        }
                     }),
                     interest_rate_config: Some(InterestRateConfigOpt {
                         optimal_utilization_rate: opr_ur.map(|x| I80F48::from_num(x).into()),
@@ -663,11 +907,57 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
                         insurance_ir_fee: if_ir.map(|x| I80F48::from_num(x).into()),
                         protocol_fixed_fee_apr: pf_fa.map(|x| I80F48::from_num(x).into()),
                         protocol_ir_fee: pf_ir.map(|x| I80F48::from_num(x).into()),
+                        curve_type: ir_curve.map(|x| x.into()),
+                        extra_kinks: if kink_ur.is_empty() && kink_ir.is_empty() {
+                            None
+                        } else {
+                            assert_eq!(
+                                kink_ur.len(),
+                                kink_ir.len(),
+                                "Each --kink-ur must come with a --kink-ir"
+                            );
+                            assert!(
+                                kink_ur.len() <= MAX_EXTRA_INTEREST_RATE_KINKS,
+                                "Too many kinks"
+                            );
+
+                            let mut kinks =
+                                [InterestRateKink::default(); MAX_EXTRA_INTEREST_RATE_KINKS];
+                            for (kink, (ur, ir)) in
+                                kinks.iter_mut().zip(kink_ur.iter().zip(kink_ir.iter()))
+                            {
+                                kink.utilization_rate = I80F48::from_num(*ur).into();
+                                kink.interest_rate = I80F48::from_num(*ir).into();
+                            }
+
+                            Some(kinks)
+                        },
+                        adaptive_speed: adaptive_speed.map(|x| I80F48::from_num(x).into()),
                     }),
                     risk_tier: risk_tier.map(|x| x.into()),
                     total_asset_value_init_limit: usd_init_limit,
                     oracle_max_age,
+                    oracle_max_deviation_bps,
                     permissionless_bad_debt_settlement,
+                    fixed_price: fixed_price.map(|x| I80F48::from_num(x).into()),
+                    oracle_max_confidence: oracle_max_confidence
+                        .map(|x| I80F48::from_num(x).into()),
+                    oracle_confidence_multiple: oracle_confidence_multiple
+                        .map(|x| I80F48::from_num(x).into()),
+                    oracle_confidence_policy: oracle_confidence_policy.map(|x| x.into()),
+                    oracle_max_price_change: oracle_max_price_change
+                        .map(|x| I80F48::from_num(x).into()),
+                    liquidation_liquidator_fee: liquidation_liquidator_fee
+                        .map(|x| I80F48::from_num(x).into()),
+                    liquidation_insurance_fee: liquidation_insurance_fee
+                        .map(|x| I80F48::from_num(x).into()),
+                    liquidation_max_liquidator_fee: liquidation_max_liquidator_fee
+                        .map(|x| I80F48::from_num(x).into()),
+                    liquidation_close_factor: liquidation_close_factor
+                        .map(|x| I80F48::from_num(x).into()),
+                    liquidation_full_close_threshold,
+                    emode_category,
+                    total_liability_value_limit: usd_borrow_limit,
                 },
             )
         }
@@ -845,7 +1135,10 @@ fn process_account_subcmd(subcmd: AccountCommand, global_options: &GlobalOptions
 
     if !global_options.skip_confirmation {
         match subcmd {
-            AccountCommand::Get { .. } | AccountCommand::List => (),
+            AccountCommand::Get { .. }
+            | AccountCommand::List
+            | AccountCommand::MaxBorrow { .. }
+            | AccountCommand::MaxWithdraw { .. } => (),
             _ => get_consent(&subcmd, &profile)?,
         }
     }
@@ -869,11 +1162,18 @@ fn process_account_subcmd(subcmd: AccountCommand, global_options: &GlobalOptions
         AccountCommand::Borrow { bank, ui_amount } => {
             processor::marginfi_account_borrow(&profile, &config, bank, ui_amount)
         }
+        AccountCommand::MaxBorrow { bank } => {
+            processor::marginfi_account_max_borrow(&profile, &config, bank)
+        }
+        AccountCommand::MaxWithdraw { bank } => {
+            processor::marginfi_account_max_withdraw(&profile, &config, bank)
+        }
         AccountCommand::Liquidate {
             asset_bank: asset_bank_pk,
             liability_bank: liability_bank_pk,
             liquidatee_marginfi_account: liquidatee_marginfi_account_pk,
             ui_asset_amount,
+            with_tokens,
         } => processor::marginfi_account_liquidate(
             &profile,
             &config,
@@ -881,6 +1181,7 @@ fn process_account_subcmd(subcmd: AccountCommand, global_options: &GlobalOptions
             asset_bank_pk,
             liability_bank_pk,
             ui_asset_amount,
+            with_tokens,
         ),
         AccountCommand::Create => processor::marginfi_account_create(&profile, &config),
         AccountCommand::SetFlag {
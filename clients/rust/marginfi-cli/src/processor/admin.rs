@@ -1,44 +1,27 @@
-use anchor_client::anchor_lang::prelude::*;
-use anchor_client::anchor_lang::InstructionData;
 use anchor_spl::associated_token;
 use anyhow::Result;
-use marginfi::{
-    bank_authority_seed,
-    state::marginfi_group::{Bank, BankVaultType},
-};
-use solana_sdk::{
-    instruction::Instruction, message::Message, pubkey::Pubkey, transaction::Transaction,
-};
+use marginfi::state::marginfi_group::Bank;
+use marginfi_sdk::instructions;
+use solana_sdk::{message::Message, pubkey::Pubkey, transaction::Transaction};
 
 use crate::{
     config::Config,
-    utils::{process_transaction, ui_to_native},
+    utils::{load_bank_token_program, process_transaction, ui_to_native},
 };
 
 pub fn process_collect_fees(config: Config, bank_pk: Pubkey) -> Result<()> {
     let bank = config.mfi_program.account::<Bank>(bank_pk)?;
     let rpc_client = config.mfi_program.rpc();
-
-    let (liquidity_vault_authority, _) = Pubkey::find_program_address(
-        bank_authority_seed!(BankVaultType::Liquidity, bank_pk),
-        &marginfi::id(),
+    let token_program = load_bank_token_program(&rpc_client, &bank.mint)?;
+
+    let ix = instructions::lending_pool_collect_bank_fees(
+        &config.program_id,
+        bank.group,
+        bank_pk,
+        bank.mint,
+        token_program,
     );
 
-    let ix = Instruction {
-        program_id: marginfi::id(),
-        accounts: marginfi::accounts::LendingPoolCollectBankFees {
-            marginfi_group: bank.group,
-            bank: bank_pk,
-            fee_vault: bank.fee_vault,
-            token_program: spl_token::id(),
-            liquidity_vault_authority,
-            liquidity_vault: bank.liquidity_vault,
-            insurance_vault: bank.insurance_vault,
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingPoolCollectBankFees {}.data(),
-    };
-
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let signing_keypairs = config.get_signers(false);
 
@@ -63,13 +46,12 @@ pub fn process_withdraw_fees(
     let bank = config.mfi_program.account::<Bank>(bank_pk)?;
     let amount = ui_to_native(amount_ui, bank.mint_decimals);
     let dst_address = dst_address.unwrap_or(config.authority());
-    let ata = associated_token::get_associated_token_address(&dst_address, &bank.mint);
-
     let rpc_client = config.mfi_program.rpc();
-
-    let (fee_vault_authority, _) = Pubkey::find_program_address(
-        bank_authority_seed!(BankVaultType::Fee, bank_pk),
-        &marginfi::id(),
+    let token_program = load_bank_token_program(&rpc_client, &bank.mint)?;
+    let ata = associated_token::get_associated_token_address_with_program_id(
+        &dst_address,
+        &bank.mint,
+        &token_program,
     );
 
     let create_ata_ix =
@@ -77,23 +59,19 @@ pub fn process_withdraw_fees(
             &config.authority(),
             &config.authority(),
             &bank.mint,
-            &spl_token::id(),
+            &token_program,
         );
 
-    let ix = Instruction {
-        program_id: marginfi::id(),
-        accounts: marginfi::accounts::LendingPoolWithdrawFees {
-            marginfi_group: bank.group,
-            bank: bank_pk,
-            admin: config.authority(),
-            fee_vault: bank.fee_vault,
-            fee_vault_authority,
-            dst_token_account: ata,
-            token_program: spl_token::id(),
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingPoolWithdrawFees { amount }.data(),
-    };
+    let ix = instructions::lending_pool_withdraw_fees(
+        &config.program_id,
+        bank.group,
+        config.authority(),
+        bank_pk,
+        bank.mint,
+        ata,
+        token_program,
+        amount,
+    );
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let signing_keypairs = config.get_signers(false);
@@ -119,13 +97,12 @@ pub fn process_withdraw_insurance(
     let bank = config.mfi_program.account::<Bank>(bank_pk)?;
     let amount = ui_to_native(amount_ui, bank.mint_decimals);
     let dst_address = dst_address.unwrap_or(config.authority());
-    let ata = associated_token::get_associated_token_address(&dst_address, &bank.mint);
-
     let rpc_client = config.mfi_program.rpc();
-
-    let (insurance_vault_authority, _) = Pubkey::find_program_address(
-        bank_authority_seed!(BankVaultType::Insurance, bank_pk),
-        &marginfi::id(),
+    let token_program = load_bank_token_program(&rpc_client, &bank.mint)?;
+    let ata = associated_token::get_associated_token_address_with_program_id(
+        &dst_address,
+        &bank.mint,
+        &token_program,
     );
 
     let create_ata_ix =
@@ -133,23 +110,19 @@ pub fn process_withdraw_insurance(
             &config.authority(),
             &config.authority(),
             &bank.mint,
-            &spl_token::id(),
+            &token_program,
         );
 
-    let ix = Instruction {
-        program_id: marginfi::id(),
-        accounts: marginfi::accounts::LendingPoolWithdrawInsurance {
-            marginfi_group: bank.group,
-            bank: bank_pk,
-            admin: config.authority(),
-            insurance_vault: bank.insurance_vault,
-            insurance_vault_authority,
-            dst_token_account: ata,
-            token_program: spl_token::id(),
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingPoolWithdrawInsurance { amount }.data(),
-    };
+    let ix = instructions::lending_pool_withdraw_insurance(
+        &config.program_id,
+        bank.group,
+        config.authority(),
+        bank_pk,
+        bank.mint,
+        ata,
+        token_program,
+        amount,
+    );
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let signing_keypairs = config.get_signers(false);
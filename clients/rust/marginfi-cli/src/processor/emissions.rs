@@ -1,12 +1,11 @@
 use {
     crate::{config::Config, profile::Profile},
-    anchor_client::anchor_lang::{AnchorSerialize, InstructionData, ToAccountMetas},
+    anchor_client::anchor_lang::AnchorSerialize,
     anyhow::Result,
     marginfi::state::marginfi_account::MarginfiAccount,
+    marginfi_sdk::instructions,
     solana_client::rpc_filter::{Memcmp, RpcFilterType},
-    solana_sdk::{
-        instruction::Instruction, message::Message, pubkey::Pubkey, transaction::Transaction,
-    },
+    solana_sdk::{message::Message, pubkey::Pubkey, transaction::Transaction},
 };
 
 const CHUNK_SIZE: usize = 22;
@@ -44,14 +43,8 @@ pub fn claim_all_emissions_for_bank(
                 None
             }
         })
-        .map(|address| Instruction {
-            program_id: marginfi::id(),
-            accounts: marginfi::accounts::LendingAccountSettleEmissions {
-                marginfi_account: address,
-                bank: bank_pk,
-            }
-            .to_account_metas(Some(true)),
-            data: marginfi::instruction::LendingAccountSettleEmissions {}.data(),
+        .map(|address| {
+            instructions::lending_account_settle_emissions(&config.program_id, address, bank_pk)
         })
         .collect::<Vec<_>>();
 
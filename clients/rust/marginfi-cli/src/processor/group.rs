@@ -3,6 +3,7 @@ use std::mem::size_of;
 use anyhow::Result;
 use log::{debug, info, warn};
 use marginfi::state::marginfi_group::Bank;
+use marginfi_sdk::pda;
 use solana_address_lookup_table_program::{
     instruction::{create_lookup_table, extend_lookup_table},
     state::AddressLookupTable,
@@ -12,7 +13,7 @@ use solana_sdk::{
     account::Account, pubkey::Pubkey, signer::Signer, system_program, transaction::Transaction,
 };
 
-use crate::{config::Config, profile::Profile, utils};
+use crate::{config::Config, profile::Profile};
 
 const CHUNK_SIZE: usize = 22;
 const KEY_BATCH_SIZE: usize = 20;
@@ -100,7 +101,7 @@ pub fn process_update_lookup_tables(
     for (bank_pk, bank) in banks.iter() {
         keys.push(*bank_pk);
         keys.push(bank.liquidity_vault);
-        let (vault_auth, _) = utils::find_bank_vault_authority_pda(
+        let (vault_auth, _) = pda::find_bank_vault_authority_pda(
             bank_pk,
             marginfi::state::marginfi_group::BankVaultType::Liquidity,
             &marginfi::ID,
@@ -10,17 +10,9 @@ use {
     crate::{
         config::Config,
         profile::{self, get_cli_config_dir, load_profile, CliConfig, Profile},
-        utils::{
-            find_bank_emssions_token_account_pda, find_bank_vault_authority_pda,
-            find_bank_vault_pda, load_observation_account_metas, process_transaction,
-            EXP_10_I80F48,
-        },
-    },
-    anchor_client::{
-        anchor_lang::{InstructionData, ToAccountMetas},
-        Cluster,
+        utils::{process_transaction, EXP_10_I80F48},
     },
-    anchor_spl::token::{self, spl_token},
+    anchor_client::Cluster,
     anyhow::{anyhow, bail, Result},
     fixed::types::I80F48,
     log::info,
@@ -28,22 +20,16 @@ use {
         prelude::MarginfiGroup,
         state::{
             marginfi_account::{BankAccountWrapper, MarginfiAccount},
-            marginfi_group::{Bank, BankVaultType},
+            marginfi_group::Bank,
         },
     },
+    marginfi_sdk::{
+        instructions, pda::find_bank_emissions_token_account_pda, state::fetch_clock, MarginfiState,
+    },
     solana_client::rpc_filter::{Memcmp, RpcFilterType},
     solana_sdk::{
-        account_info::IntoAccountInfo,
-        clock::Clock,
-        commitment_config::CommitmentLevel,
-        compute_budget::ComputeBudgetInstruction,
-        instruction::{AccountMeta, Instruction},
-        message::Message,
-        pubkey::Pubkey,
-        signature::Keypair,
-        signer::Signer,
-        system_program,
-        sysvar::{self, Sysvar},
+        commitment_config::CommitmentLevel, compute_budget::ComputeBudgetInstruction,
+        message::Message, pubkey::Pubkey, signature::Keypair, signer::Signer,
         transaction::Transaction,
     },
     spl_associated_token_account::instruction::create_associated_token_account_idempotent,
@@ -58,20 +44,25 @@ use {
 
 #[cfg(feature = "dev")]
 use marginfi::state::price::{OraclePriceFeedAdapter, PriceAdapter};
-use marginfi::{constants::ZERO_AMOUNT_THRESHOLD, utils::NumTraitsWithTolerance};
 use solana_client::rpc_client::RpcClient;
 
 #[cfg(feature = "admin")]
 use {
-    crate::utils::{calc_emissions_rate, create_oracle_key_array, find_bank_emssions_auth_pda},
+    crate::utils::{calc_emissions_rate, create_oracle_key_array},
+    anchor_spl::{
+        token::spl_token,
+        token_2022::spl_token_2022::{self, extension::StateWithExtensions},
+    },
     marginfi::{
         constants::{EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE},
         prelude::GroupConfig,
         state::marginfi_group::{
-            BankConfig, BankConfigOpt, BankOperationalState, InterestRateConfig, WrappedI80F48,
+            BankConfig, BankConfigCompact, BankConfigOpt, BankOperationalState, EmodeCategory,
+            InterestRateConfig, WrappedI80F48,
         },
     },
-    solana_sdk::program_pack::Pack,
+    marginfi_sdk::pda::find_bank_with_seed_pda,
+    solana_sdk::{instruction::Instruction, program_pack::Pack},
     spl_associated_token_account::get_associated_token_address,
     std::io,
 };
@@ -88,8 +79,12 @@ use {
 
 pub fn group_get(config: Config, marginfi_group: Option<Pubkey>) -> Result<()> {
     if let Some(marginfi_group) = marginfi_group {
+        let group = config
+            .mfi_program
+            .account::<MarginfiGroup>(marginfi_group)?;
         println!("Address: {marginfi_group}");
         println!("=============");
+        print_group(&marginfi_group, &group);
         print_group_banks(config, marginfi_group)?;
     } else {
         group_get_all(config)?;
@@ -111,10 +106,38 @@ fn print_group(address: &Pubkey, group: &MarginfiGroup) {
     println!(
         r#"
 Group: {}
-Admin: {}
-"#,
+Admin: {}"#,
         address, group.admin
     );
+
+    for (index, emode) in group.emode_categories.iter().enumerate() {
+        if emode.is_active() {
+            println!(
+                r#"E-mode Category {}:
+  Asset Weight Init: {:?}, Maint: {:?}
+  Liab Weight Init: {:?}, Maint: {:?}"#,
+                index + 1,
+                I80F48::from(emode.asset_weight_init),
+                I80F48::from(emode.asset_weight_maint),
+                I80F48::from(emode.liability_weight_init),
+                I80F48::from(emode.liability_weight_maint),
+            );
+        }
+    }
+
+    for weight_override in group.weight_overrides.iter().filter(|o| o.is_active()) {
+        println!(
+            r#"Weight Override:
+  Asset Bank: {}, Liability Bank: {}
+  Asset Weight Init: {:?}, Maint: {:?}"#,
+            weight_override.asset_bank,
+            weight_override.liability_bank,
+            I80F48::from(weight_override.asset_weight_init),
+            I80F48::from(weight_override.asset_weight_maint),
+        );
+    }
+
+    println!();
 }
 
 pub fn print_group_banks(config: Config, marginfi_group: Pubkey) -> Result<()> {
@@ -145,7 +168,9 @@ Total Liabilities: {}
 Config:
   State: {:?}
   Risk Tier: {:?}
+  E-mode Category: {}
   USD Soft limit: {:?}
+  USD Borrow limit: {:?}
   Asset:
     Weight Init: {:?}, Maint: {:?}
     Limit: {}
@@ -154,11 +179,20 @@ Config:
     Limit: {}
   Interest Rate Config:
     Curve: opt_ur: {:?} pl_ir: {:?} max_ir: {:?}
+    Curve Type: {}, Kinks: {:?}, Adaptive: speed: {:?} pl_ir: {:?}
     Fees - Insurance: ir: {:?} fix: {:?}, Group: ir: {:?} fix: {:?}
   Oracle Setup:
     Type: {:?}
     Keys: {:#?}
     Max Age: {:#?}s
+    Max Deviation: {:?}bps
+    Fixed Price: {:?}
+    Confidence: max: {:?}, multiple: {:?}, policy: {:?}
+    Price Guard: max change: {:?}/s, last price: {:?} (slot {}), trips: {} (last {})
+    Last Price Source: {:?}
+  Liquidation Fees:
+    Liquidator: {:?} (max {:?}), Insurance: {:?}
+    Close Factor: {:?}, Full Close Threshold: {}
 Emissions:
   Flags: 0b{:b}
   Rate: {:?}
@@ -180,7 +214,9 @@ Last Update: {:?}h ago ({})
             / EXP_10_I80F48[bank.mint_decimals as usize],
         bank.config.operational_state,
         bank.config.risk_tier,
+        bank.config.emode_category,
         bank.config.total_asset_value_init_limit,
+        bank.total_liability_value_limit,
         bank.config.asset_weight_init,
         bank.config.asset_weight_maint,
         I80F48::from_num(bank.config.deposit_limit) / EXP_10_I80F48[bank.mint_decimals as usize],
@@ -190,6 +226,10 @@ Last Update: {:?}h ago ({})
         bank.config.interest_rate_config.optimal_utilization_rate,
         bank.config.interest_rate_config.plateau_interest_rate,
         bank.config.interest_rate_config.max_interest_rate,
+        bank.config.interest_rate_config.curve_type,
+        bank.config.interest_rate_config.extra_kinks,
+        bank.config.interest_rate_config.adaptive_speed,
+        bank.get_adaptive_plateau_interest_rate(),
         bank.config.interest_rate_config.insurance_ir_fee,
         bank.config.interest_rate_config.insurance_fee_fixed_apr,
         bank.config.interest_rate_config.protocol_ir_fee,
@@ -197,6 +237,22 @@ Last Update: {:?}h ago ({})
         bank.config.oracle_setup,
         bank.config.oracle_keys,
         bank.config.get_oracle_max_age(),
+        bank.config.oracle_max_deviation_bps,
+        I80F48::from(bank.fixed_price),
+        bank.get_oracle_confidence_config().max_confidence,
+        I80F48::from(bank.oracle_confidence_multiple),
+        bank.oracle_confidence_policy,
+        I80F48::from(bank.oracle_max_price_change),
+        I80F48::from(bank.last_oracle_price),
+        bank.last_oracle_price_slot,
+        bank.oracle_price_guard_trip_count,
+        bank.last_oracle_price_guard_trip_timestamp,
+        bank.last_oracle_price_source,
+        bank.get_liquidation_base_liquidator_fee(),
+        I80F48::from(bank.liquidation_max_liquidator_fee),
+        bank.get_liquidation_insurance_fee(),
+        I80F48::from(bank.liquidation_close_factor),
+        bank.liquidation_full_close_threshold,
         bank.flags,
         I80F48::from(bank.emissions_rate),
         bank.emissions_mint,
@@ -233,19 +289,14 @@ pub fn group_create(
 
     let marginfi_group_keypair = Keypair::new();
 
-    let init_marginfi_group_ixs_builder = config.mfi_program.request();
-
     let mut signing_keypairs = config.get_signers(false);
     signing_keypairs.push(&marginfi_group_keypair);
 
-    let init_marginfi_group_ixs = init_marginfi_group_ixs_builder
-        .accounts(marginfi::accounts::MarginfiGroupInitialize {
-            marginfi_group: marginfi_group_keypair.pubkey(),
-            admin,
-            system_program: system_program::id(),
-        })
-        .args(marginfi::instruction::MarginfiGroupInitialize {})
-        .instructions()?;
+    let init_marginfi_group_ixs = vec![instructions::marginfi_group_initialize(
+        &config.program_id,
+        marginfi_group_keypair.pubkey(),
+        admin,
+    )];
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let message = Message::new(&init_marginfi_group_ixs, Some(&config.authority()));
@@ -275,20 +326,13 @@ pub fn group_configure(config: Config, profile: Profile, admin: Option<Pubkey>)
     }
 
     let signing_keypairs = config.get_signers(false);
-    let configure_marginfi_group_ixs_builder = config
-        .mfi_program
-        .request()
-        .signer(*signing_keypairs.first().unwrap());
 
-    let configure_marginfi_group_ixs = configure_marginfi_group_ixs_builder
-        .accounts(marginfi::accounts::MarginfiGroupConfigure {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            admin: config.authority(),
-        })
-        .args(marginfi::instruction::MarginfiGroupConfigure {
-            config: GroupConfig { admin },
-        })
-        .instructions()?;
+    let configure_marginfi_group_ixs = vec![instructions::marginfi_group_configure(
+        &config.program_id,
+        profile.marginfi_group.unwrap(),
+        config.authority(),
+        GroupConfig { admin },
+    )];
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let message = Message::new(&configure_marginfi_group_ixs, Some(&config.authority()));
@@ -303,6 +347,86 @@ pub fn group_configure(config: Config, profile: Profile, admin: Option<Pubkey>)
     Ok(())
 }
 
+#[cfg(feature = "admin")]
+pub fn group_configure_emode_category(
+    config: Config,
+    profile: Profile,
+    emode_category: u8,
+    emode: EmodeCategory,
+) -> Result<()> {
+    let rpc_client = config.mfi_program.rpc();
+
+    if profile.marginfi_group.is_none() {
+        bail!("Marginfi group not specified in profile [{}]", profile.name);
+    }
+
+    let signing_keypairs = config.get_signers(false);
+
+    let configure_emode_category_ixs = vec![instructions::marginfi_group_configure_emode_category(
+        &config.program_id,
+        profile.marginfi_group.unwrap(),
+        config.authority(),
+        emode_category,
+        emode,
+    )];
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&configure_emode_category_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&signing_keypairs, recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!(
+            "e-mode category {} configured (sig: {})",
+            emode_category, sig
+        ),
+        Err(err) => println!("Error during e-mode category configuration:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
+#[cfg(feature = "admin")]
+pub fn group_configure_weight_override(
+    config: Config,
+    profile: Profile,
+    asset_bank: Pubkey,
+    liability_bank: Pubkey,
+    asset_weight_init: I80F48,
+    asset_weight_maint: I80F48,
+) -> Result<()> {
+    let rpc_client = config.mfi_program.rpc();
+
+    if profile.marginfi_group.is_none() {
+        bail!("Marginfi group not specified in profile [{}]", profile.name);
+    }
+
+    let signing_keypairs = config.get_signers(false);
+
+    let configure_weight_override_ixs =
+        vec![instructions::marginfi_group_configure_weight_override(
+            &config.program_id,
+            profile.marginfi_group.unwrap(),
+            config.authority(),
+            asset_bank,
+            liability_bank,
+            asset_weight_init.into(),
+            asset_weight_maint.into(),
+        )];
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&configure_weight_override_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&signing_keypairs, recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("weight override configured (sig: {})", sig),
+        Err(err) => println!("Error during weight override configuration:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 #[cfg(feature = "admin")]
 pub fn group_add_bank(
@@ -310,7 +434,7 @@ pub fn group_add_bank(
     profile: Profile,
     bank_mint: Pubkey,
     seed: bool,
-    oracle_key: Pubkey,
+    oracle_key: Option<Pubkey>,
     oracle_setup: crate::OracleTypeArg,
     asset_weight_init: f64,
     asset_weight_maint: f64,
@@ -327,6 +451,9 @@ pub fn group_add_bank(
     protocol_ir_fee: f64,
     risk_tier: crate::RiskTierArg,
     oracle_max_age: u16,
+    oracle_max_deviation_bps: Option<u16>,
+    fixed_price: Option<f64>,
+    pyth_push_feed_id: Option<[u8; 32]>,
 ) -> Result<()> {
     let rpc_client = config.mfi_program.rpc();
 
@@ -334,6 +461,21 @@ pub fn group_add_bank(
         bail!("Marginfi group not specified in profile [{}]", profile.name);
     }
 
+    // Fixed price banks are the only ones without an oracle account
+    let oracle_key = match (oracle_setup, oracle_key) {
+        (crate::OracleTypeArg::Fixed, _) => Pubkey::default(),
+        (_, Some(oracle_key)) => oracle_key,
+        (_, None) => bail!("An oracle account is required for {:?} banks", oracle_setup),
+    };
+    let oracle_max_deviation_bps = oracle_max_deviation_bps.unwrap_or_default();
+    let fixed_price: WrappedI80F48 = I80F48::from_num(fixed_price.unwrap_or_default()).into();
+    let pyth_push_feed_id = match (oracle_setup, pyth_push_feed_id) {
+        (crate::OracleTypeArg::PythPush, None) => {
+            bail!("A Pyth feed id is required for {:?} banks", oracle_setup)
+        }
+        (_, pyth_push_feed_id) => pyth_push_feed_id.unwrap_or_default(),
+    };
+
     let asset_weight_init: WrappedI80F48 = I80F48::from_num(asset_weight_init).into();
     let asset_weight_maint: WrappedI80F48 = I80F48::from_num(asset_weight_maint).into();
     let liability_weight_init: WrappedI80F48 = I80F48::from_num(liability_weight_init).into();
@@ -348,7 +490,8 @@ pub fn group_add_bank(
     let protocol_ir_fee: WrappedI80F48 = I80F48::from_num(protocol_ir_fee).into();
 
     let mint_account = rpc_client.get_account(&bank_mint)?;
-    let mint = spl_token::state::Mint::unpack(&mint_account.data)?;
+    let token_program = mint_account.owner;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data)?.base;
 
     let deposit_limit = deposit_limit_ui * 10_u64.pow(mint.decimals as u32);
     let borrow_limit = borrow_limit_ui * 10_u64.pow(mint.decimals as u32);
@@ -391,6 +534,10 @@ pub fn group_add_bank(
             oracle_setup,
             risk_tier,
             oracle_max_age,
+            oracle_max_deviation_bps,
+            fixed_price,
+            pyth_push_feed_id,
+            token_program,
         )?
     } else {
         create_bank_ix(
@@ -409,6 +556,10 @@ pub fn group_add_bank(
             oracle_setup,
             risk_tier,
             oracle_max_age,
+            oracle_max_deviation_bps,
+            fixed_price,
+            pyth_push_feed_id,
+            token_program,
         )?
     };
 
@@ -443,6 +594,10 @@ fn create_bank_ix_with_seed(
     oracle_setup: crate::OracleTypeArg,
     risk_tier: crate::RiskTierArg,
     oracle_max_age: u16,
+    oracle_max_deviation_bps: u16,
+    fixed_price: WrappedI80F48,
+    pyth_push_feed_id: [u8; 32],
+    token_program: Pubkey,
 ) -> Result<Vec<Instruction>> {
     use solana_sdk::commitment_config::CommitmentConfig;
 
@@ -453,10 +608,7 @@ fn create_bank_ix_with_seed(
     // Iterate through to find the next canonical seed
     for i in 0..u64::MAX {
         println!("Seed option enabled -- generating a PDA account");
-        let (pda, _) = Pubkey::find_program_address(
-            [group_key.as_ref(), bank_mint.as_ref(), &i.to_le_bytes()].as_slice(),
-            &marginfi::id(),
-        );
+        let (pda, _) = find_bank_with_seed_pda(&group_key, &bank_mint, i, &config.program_id);
         if rpc_client
             .get_account_with_commitment(&pda, CommitmentConfig::default())?
             .value
@@ -470,52 +622,16 @@ fn create_bank_ix_with_seed(
         }
     }
 
-    let add_bank_ixs_builder = config.mfi_program.request();
-    let add_bank_ixs = add_bank_ixs_builder
-        .accounts(marginfi::accounts::LendingPoolAddBankWithSeed {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            admin: config.authority(),
-            bank_mint,
-            bank: bank_pda,
-            fee_vault: find_bank_vault_pda(&bank_pda, BankVaultType::Fee, &config.program_id).0,
-            fee_vault_authority: find_bank_vault_authority_pda(
-                &bank_pda,
-                BankVaultType::Fee,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault: find_bank_vault_pda(
-                &bank_pda,
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault_authority: find_bank_vault_authority_pda(
-                &bank_pda,
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            liquidity_vault: find_bank_vault_pda(
-                &bank_pda,
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-            liquidity_vault_authority: find_bank_vault_authority_pda(
-                &bank_pda,
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-            rent: sysvar::rent::id(),
-            token_program: token::ID,
-            system_program: system_program::id(),
-            fee_payer: config.authority(),
-        })
-        .accounts(AccountMeta::new_readonly(oracle_key, false))
-        .args(marginfi::instruction::LendingPoolAddBankWithSeed {
-            bank_config: BankConfig {
+    let add_bank_ixs = vec![instructions::lending_pool_add_bank_with_seed(
+        &config.program_id,
+        group_key,
+        config.authority(),
+        config.authority(),
+        bank_mint,
+        token_program,
+        BankConfigCompact {
+            fixed_price,
+            ..BankConfig {
                 asset_weight_init,
                 asset_weight_maint,
                 liability_weight_init,
@@ -528,12 +644,14 @@ fn create_bank_ix_with_seed(
                 oracle_keys: create_oracle_key_array(oracle_key),
                 risk_tier: risk_tier.into(),
                 oracle_max_age,
+                oracle_max_deviation_bps,
+                pyth_push_feed_id,
                 ..BankConfig::default()
             }
-            .into(),
-            bank_seed,
-        })
-        .instructions()?;
+            .into()
+        },
+        bank_seed,
+    )];
 
     println!("Bank address (PDA): {}", bank_pda);
 
@@ -558,58 +676,22 @@ fn create_bank_ix(
     oracle_setup: crate::OracleTypeArg,
     risk_tier: crate::RiskTierArg,
     oracle_max_age: u16,
+    oracle_max_deviation_bps: u16,
+    fixed_price: WrappedI80F48,
+    pyth_push_feed_id: [u8; 32],
+    token_program: Pubkey,
 ) -> Result<Vec<Instruction>> {
-    let add_bank_ixs_builder = config.mfi_program.request();
-    let add_bank_ixs = add_bank_ixs_builder
-        .accounts(marginfi::accounts::LendingPoolAddBank {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            admin: config.authority(),
-            bank: bank_keypair.pubkey(),
-            bank_mint,
-            fee_vault: find_bank_vault_pda(
-                &bank_keypair.pubkey(),
-                BankVaultType::Fee,
-                &config.program_id,
-            )
-            .0,
-            fee_vault_authority: find_bank_vault_authority_pda(
-                &bank_keypair.pubkey(),
-                BankVaultType::Fee,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault: find_bank_vault_pda(
-                &bank_keypair.pubkey(),
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault_authority: find_bank_vault_authority_pda(
-                &bank_keypair.pubkey(),
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            liquidity_vault: find_bank_vault_pda(
-                &bank_keypair.pubkey(),
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-            liquidity_vault_authority: find_bank_vault_authority_pda(
-                &bank_keypair.pubkey(),
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-            rent: sysvar::rent::id(),
-            token_program: token::ID,
-            system_program: system_program::id(),
-            fee_payer: config.explicit_fee_payer(),
-        })
-        .accounts(AccountMeta::new_readonly(oracle_key, false))
-        .args(marginfi::instruction::LendingPoolAddBank {
-            bank_config: BankConfig {
+    let add_bank_ixs = vec![instructions::lending_pool_add_bank(
+        &config.program_id,
+        profile.marginfi_group.unwrap(),
+        config.authority(),
+        config.explicit_fee_payer(),
+        bank_keypair.pubkey(),
+        bank_mint,
+        token_program,
+        BankConfigCompact {
+            fixed_price,
+            ..BankConfig {
                 asset_weight_init,
                 asset_weight_maint,
                 liability_weight_init,
@@ -622,11 +704,13 @@ fn create_bank_ix(
                 oracle_keys: create_oracle_key_array(oracle_key),
                 risk_tier: risk_tier.into(),
                 oracle_max_age,
+                oracle_max_deviation_bps,
+                pyth_push_feed_id,
                 ..BankConfig::default()
             }
-            .into(),
-        })
-        .instructions()?;
+            .into()
+        },
+    )];
 
     println!("Bank address: {}", bank_keypair.pubkey());
 
@@ -643,28 +727,10 @@ pub fn group_handle_bankruptcy(
 ) -> Result<()> {
     let rpc_client = config.mfi_program.rpc();
 
-    if profile.marginfi_group.is_none() {
-        bail!("Marginfi group not specified in profile [{}]", profile.name);
-    }
-
-    let banks = HashMap::from_iter(load_all_banks(
-        config,
-        Some(profile.marginfi_group.unwrap()),
-    )?);
-
-    let marginfi_account = config
-        .mfi_program
-        .account::<MarginfiAccount>(marginfi_account_pk)?;
+    let mut state = load_marginfi_state(config, &profile)?;
+    state.load_marginfi_account(&rpc_client, marginfi_account_pk)?;
 
-    handle_bankruptcy_for_an_account(
-        config,
-        &profile,
-        &rpc_client,
-        &banks,
-        marginfi_account_pk,
-        &marginfi_account,
-        bank_pk,
-    )?;
+    handle_bankruptcy_for_an_account(config, &rpc_client, &state, marginfi_account_pk, bank_pk)?;
 
     Ok(())
 }
@@ -677,46 +743,32 @@ pub fn group_auto_handle_bankruptcy_for_an_account(
 ) -> Result<()> {
     let rpc_client = config.mfi_program.rpc();
 
-    if profile.marginfi_group.is_none() {
-        bail!("Marginfi group not specified in profile [{}]", profile.name);
-    }
+    let mut state = load_marginfi_state(config, &profile)?;
+    state.load_marginfi_account(&rpc_client, marginfi_account_pk)?;
 
-    let banks = HashMap::from_iter(load_all_banks(
-        config,
-        Some(profile.marginfi_group.unwrap()),
-    )?);
-    let marginfi_account = config
-        .mfi_program
-        .account::<MarginfiAccount>(marginfi_account_pk)?;
+    println!("Handling bankruptcy for account {}", marginfi_account_pk);
 
-    marginfi_account
-        .lending_account
-        .balances
-        .iter()
-        .filter(|b| {
-            b.active
-                && banks
-                    .get(&b.bank_pk)
-                    .unwrap()
-                    .get_liability_amount(b.liability_shares.into())
-                    .unwrap()
-                    .is_positive_with_tolerance(ZERO_AMOUNT_THRESHOLD)
-        })
-        .map(|b| b.bank_pk)
-        .collect::<Vec<Pubkey>>()
-        .iter()
-        .for_each(|bank_pk| {
-            handle_bankruptcy_for_an_account(
-                config,
-                &profile,
-                &rpc_client,
-                &banks,
-                marginfi_account_pk,
-                &marginfi_account,
-                *bank_pk,
-            )
-            .unwrap();
-        });
+    let handle_bankruptcy_ix =
+        state.handle_bankruptcy_all_ix(config.authority(), marginfi_account_pk)?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+
+    let signing_keypairs = config.get_signers(false);
+
+    let message = Message::new(
+        &[
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            handle_bankruptcy_ix,
+        ],
+        Some(&config.authority()),
+    );
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&signing_keypairs, recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Bankruptcy handled (sig: {})", sig),
+        Err(err) => println!("Error during bankruptcy handling:\n{:#?}", err),
+    };
 
     Ok(())
 }
@@ -724,53 +776,15 @@ pub fn group_auto_handle_bankruptcy_for_an_account(
 #[allow(dead_code)]
 fn handle_bankruptcy_for_an_account(
     config: &Config,
-    profile: &Profile,
     rpc_client: &RpcClient,
-    banks: &HashMap<Pubkey, Bank>,
+    state: &MarginfiState,
     marginfi_account_pk: Pubkey,
-    marginfi_account: &MarginfiAccount,
     bank_pk: Pubkey,
 ) -> Result<()> {
     println!("Handling bankruptcy for bank {}", bank_pk);
-    let mut handle_bankruptcy_ix = Instruction {
-        program_id: config.program_id,
-        accounts: marginfi::accounts::LendingPoolHandleBankruptcy {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            signer: config.authority(),
-            bank: bank_pk,
-            marginfi_account: marginfi_account_pk,
-            liquidity_vault: find_bank_vault_pda(
-                &bank_pk,
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault: find_bank_vault_pda(
-                &bank_pk,
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault_authority: find_bank_vault_authority_pda(
-                &bank_pk,
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            token_program: token::ID,
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingPoolHandleBankruptcy {}.data(),
-    };
 
-    handle_bankruptcy_ix
-        .accounts
-        .extend(load_observation_account_metas(
-            marginfi_account,
-            banks,
-            vec![bank_pk],
-            vec![],
-        ));
+    let handle_bankruptcy_ix =
+        state.handle_bankruptcy_ix(config.authority(), marginfi_account_pk, bank_pk)?;
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
 
@@ -788,9 +802,6 @@ fn handle_bankruptcy_for_an_account(
     Ok(())
 }
 
-#[cfg(feature = "admin")]
-const BANKRUPTCY_CHUNKS: usize = 4;
-
 #[cfg(feature = "admin")]
 pub fn handle_bankruptcy_for_accounts(
     config: &Config,
@@ -800,59 +811,31 @@ pub fn handle_bankruptcy_for_accounts(
     let mut instructions = vec![];
     let rpc_client = config.mfi_program.rpc();
 
-    let banks = HashMap::from_iter(load_all_banks(
-        config,
-        Some(profile.marginfi_group.unwrap()),
-    )?);
+    let mut state = load_marginfi_state(config, profile)?;
 
     for account in accounts {
-        let marginfi_account = config
-            .mfi_program
-            .account::<MarginfiAccount>(account)
-            .unwrap();
+        state.load_marginfi_account(&rpc_client, account)?;
 
-        marginfi_account
-            .lending_account
-            .balances
-            .iter()
-            .filter(|b| {
-                b.active
-                    && banks
-                        .get(&b.bank_pk)
-                        .unwrap()
-                        .get_liability_amount(b.liability_shares.into())
-                        .unwrap()
-                        .is_positive_with_tolerance(ZERO_AMOUNT_THRESHOLD)
-            })
-            .map(|b| b.bank_pk)
-            .collect::<Vec<Pubkey>>()
-            .iter()
-            .for_each(|bank_pk| {
-                instructions.push(
-                    make_bankruptcy_ix(
-                        config,
-                        profile,
-                        &banks,
-                        account,
-                        &marginfi_account,
-                        *bank_pk,
-                    )
-                    .unwrap(),
-                );
-            });
+        if state.liability_bank_pks(&account)?.is_empty() {
+            continue;
+        }
+
+        println!("Handling bankruptcy for account {}", account);
+        instructions.push(state.handle_bankruptcy_all_ix(config.fee_payer.pubkey(), account)?);
     }
 
     println!("Handling {} bankruptcies", instructions.len());
 
-    let chunks = instructions.chunks(BANKRUPTCY_CHUNKS);
-
-    for chunk in chunks {
+    // Every liability of an account is settled in a single instruction, one account per transaction
+    for ix in instructions {
         let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
 
         let signing_keypairs = config.get_signers(false);
 
-        let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
-        ixs.extend_from_slice(chunk);
+        let ixs = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            ix,
+        ];
 
         let message = Message::new(&ixs, Some(&config.authority()));
 
@@ -867,58 +850,6 @@ pub fn handle_bankruptcy_for_accounts(
 
     Ok(())
 }
-#[cfg(feature = "admin")]
-fn make_bankruptcy_ix(
-    config: &Config,
-    profile: &Profile,
-    banks: &HashMap<Pubkey, Bank>,
-    marginfi_account_pk: Pubkey,
-    marginfi_account: &MarginfiAccount,
-    bank_pk: Pubkey,
-) -> Result<Instruction> {
-    println!("Handling bankruptcy for bank {}", bank_pk);
-    let mut handle_bankruptcy_ix = Instruction {
-        program_id: config.program_id,
-        accounts: marginfi::accounts::LendingPoolHandleBankruptcy {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            signer: config.fee_payer.pubkey(),
-            bank: bank_pk,
-            marginfi_account: marginfi_account_pk,
-            liquidity_vault: find_bank_vault_pda(
-                &bank_pk,
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault: find_bank_vault_pda(
-                &bank_pk,
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault_authority: find_bank_vault_authority_pda(
-                &bank_pk,
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            token_program: token::ID,
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingPoolHandleBankruptcy {}.data(),
-    };
-
-    handle_bankruptcy_ix
-        .accounts
-        .extend(load_observation_account_metas(
-            marginfi_account,
-            banks,
-            vec![bank_pk],
-            vec![],
-        ));
-
-    Ok(handle_bankruptcy_ix)
-}
 
 pub fn process_set_user_flag(
     config: Config,
@@ -928,16 +859,13 @@ pub fn process_set_user_flag(
 ) -> Result<()> {
     let rpc_client = config.mfi_program.rpc();
 
-    let ix = Instruction {
-        accounts: marginfi::accounts::SetAccountFlag {
-            marginfi_account: marginfi_account_pk,
-            marginfi_group: profile.marginfi_group.unwrap(),
-            admin: config.authority(),
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::SetAccountFlag { flag }.data(),
-        program_id: marginfi::id(),
-    };
+    let ix = instructions::set_account_flag(
+        &config.program_id,
+        profile.marginfi_group.unwrap(),
+        marginfi_account_pk,
+        config.authority(),
+        flag,
+    );
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
 
@@ -992,9 +920,12 @@ pub fn bank_get(config: Config, bank_pk: Option<Pubkey>) -> Result<()> {
             insurance_vault_balance.amount
         );
         if bank.emissions_mint != Pubkey::default() {
-            let emissions_token_account =
-                find_bank_emssions_token_account_pda(address, bank.emissions_mint, marginfi::id())
-                    .0;
+            let emissions_token_account = find_bank_emissions_token_account_pda(
+                &address,
+                &bank.emissions_mint,
+                &config.program_id,
+            )
+            .0;
             let emissions_vault_balance =
                 rpc_client.get_token_account_balance(&emissions_token_account)?;
             println!(
@@ -1010,6 +941,18 @@ pub fn bank_get(config: Config, bank_pk: Option<Pubkey>) -> Result<()> {
     Ok(())
 }
 
+fn load_marginfi_state(config: &Config, profile: &Profile) -> Result<MarginfiState> {
+    let marginfi_group = profile
+        .marginfi_group
+        .ok_or_else(|| anyhow!("Marginfi group not specified in profile [{}]", profile.name))?;
+
+    Ok(MarginfiState::load(
+        &config.mfi_program.rpc(),
+        config.program_id,
+        marginfi_group,
+    )?)
+}
+
 fn load_all_banks(config: &Config, marginfi_group: Option<Pubkey>) -> Result<Vec<(Pubkey, Bank)>> {
     info!("Loading banks for group {:?}", marginfi_group);
     let filters = match marginfi_group {
@@ -1020,8 +963,7 @@ fn load_all_banks(config: &Config, marginfi_group: Option<Pubkey>) -> Result<Vec
         None => vec![],
     };
 
-    let mut clock = config.mfi_program.rpc().get_account(&sysvar::clock::ID)?;
-    let clock = Clock::from_account_info(&(&sysvar::clock::ID, &mut clock).into_account_info())?;
+    let clock = fetch_clock(&config.mfi_program.rpc())?;
 
     let mut banks_with_addresses = config.mfi_program.accounts::<Bank>(filters)?;
 
@@ -1045,20 +987,23 @@ pub fn bank_inspect_price_oracle(config: Config, bank_pk: Pubkey) -> Result<()>
     use marginfi::state::price::{OraclePriceType, PriceBias};
 
     let bank: Bank = config.mfi_program.account(bank_pk)?;
-    let mut price_oracle_account = config
+    let oracle_keys = bank.config.get_oracle_keys();
+    let mut price_oracle_accounts = config
         .mfi_program
         .rpc()
-        .get_account(&bank.config.oracle_keys[0])?;
-    let price_oracle_ai =
-        (&bank.config.oracle_keys[0], &mut price_oracle_account).into_account_info();
-
-    let opfa = OraclePriceFeedAdapter::try_from_bank_config_with_max_age(
-        &bank.config,
-        &[price_oracle_ai],
-        0,
-        u64::MAX,
-    )
-    .unwrap();
+        .get_multiple_accounts(oracle_keys)?
+        .into_iter()
+        .map(|maybe_account| maybe_account.unwrap())
+        .collect::<Vec<_>>();
+    let price_oracle_ais = oracle_keys
+        .iter()
+        .zip(price_oracle_accounts.iter_mut())
+        .map(|(key, account)| (key, account).into_account_info())
+        .collect::<Vec<_>>();
+
+    let opfa =
+        OraclePriceFeedAdapter::try_from_bank_with_max_age(&bank, &price_oracle_ais, 0, u64::MAX)
+            .unwrap();
 
     let (real_price, maint_asset_price, maint_liab_price, init_asset_price, init_liab_price) = (
         opfa.get_price_of_type(OraclePriceType::RealTime, None)?,
@@ -1112,15 +1057,57 @@ pub fn show_oracle_ages(config: Config, only_stale: bool) -> Result<()> {
                 .to_vec(),
         ))])?;
 
+    // Aggregated oracle sources, fallback oracles and stake pool SOL feeds are listed with the feeds
+    // of their own type.
+    let aggregated_keys = banks
+        .iter()
+        .flat_map(|(_, b)| match b.config.oracle_setup {
+            OracleSetup::Aggregated | OracleSetup::StakePool => b.config.get_oracle_keys().to_vec(),
+            _ => b.config.get_oracle_keys().iter().skip(1).copied().collect(),
+        })
+        .collect::<Vec<_>>();
+    let aggregated_setups: HashMap<Pubkey, OracleSetup> = HashMap::from_iter(
+        aggregated_keys
+            .iter()
+            .zip(
+                config
+                    .mfi_program
+                    .rpc()
+                    .get_multiple_accounts(&aggregated_keys)?,
+            )
+            .filter_map(|(key, maybe_account)| {
+                let account = maybe_account?;
+                let setup = if account.owner == marginfi::constants::PYTH_ID {
+                    OracleSetup::PythEma
+                } else if account.owner == switchboard_v2::SWITCHBOARD_PROGRAM_ID {
+                    OracleSetup::SwitchboardV2
+                } else {
+                    OracleSetup::None
+                };
+                Some((*key, setup))
+            }),
+    );
+
     let (pyth_feeds, swb_feeds): (Vec<_>, Vec<_>) = banks
         .into_iter()
-        .map(|(_, b)| {
-            (
-                b.config.oracle_setup,
-                b.config.oracle_max_age,
-                b.mint,
-                b.config.oracle_keys.clone().get(0).unwrap().clone(),
-            )
+        .flat_map(|(_, b)| {
+            b.config
+                .get_oracle_keys()
+                .iter()
+                .filter_map(|key| {
+                    let setup = match b.config.oracle_setup {
+                        OracleSetup::Aggregated | OracleSetup::StakePool => {
+                            *aggregated_setups.get(key)?
+                        }
+                        setup => aggregated_setups.get(key).copied().unwrap_or(setup),
+                    };
+                    Some((setup, b.config.oracle_max_age, b.mint, *key))
+                })
+                .collect::<Vec<_>>()
+        })
+        // Only Pyth EMA and Switchboard V2 feed accounts are read here, pull oracles are skipped.
+        .filter(|(setup, _, _, _)| {
+            matches!(setup, OracleSetup::PythEma | OracleSetup::SwitchboardV2)
         })
         .partition(|(setup, _, _, _)| match setup {
             OracleSetup::PythEma => true,
@@ -1324,32 +1311,17 @@ pub fn bank_setup_emissions(
         return Ok(());
     }
 
-    let ix = Instruction {
-        program_id: marginfi::id(),
-        accounts: marginfi::accounts::LendingPoolSetupEmissions {
-            marginfi_group: profile.marginfi_group.expect("marginfi group not set"),
-            admin: config.authority(),
-            bank,
-            emissions_mint: mint,
-            emissions_auth: find_bank_emssions_auth_pda(bank, mint, marginfi::id()).0,
-            emissions_token_account: find_bank_emssions_token_account_pda(
-                bank,
-                mint,
-                marginfi::id(),
-            )
-            .0,
-            emissions_funding_account: funding_account_ata,
-            token_program: spl_token::id(),
-            system_program: system_program::id(),
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingPoolSetupEmissions {
-            flags,
-            rate,
-            total_emissions,
-        }
-        .data(),
-    };
+    let ix = instructions::lending_pool_setup_emissions(
+        &config.program_id,
+        profile.marginfi_group.expect("marginfi group not set"),
+        config.authority(),
+        bank,
+        mint,
+        funding_account_ata,
+        flags,
+        rate,
+        total_emissions,
+    );
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let signing_keypairs = config.get_signers(false);
@@ -1441,30 +1413,17 @@ pub fn bank_update_emissions(
         return Ok(());
     }
 
-    let ix = Instruction {
-        program_id: marginfi::id(),
-        accounts: marginfi::accounts::LendingPoolUpdateEmissionsParameters {
-            marginfi_group: profile.marginfi_group.expect("marginfi group not set"),
-            admin: config.authority(),
-            bank: bank_pk,
-            emissions_mint: emission_mint,
-            emissions_token_account: find_bank_emssions_token_account_pda(
-                bank_pk,
-                emission_mint,
-                marginfi::id(),
-            )
-            .0,
-            emissions_funding_account: funding_account_ata,
-            token_program: spl_token::id(),
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingPoolUpdateEmissionsParameters {
-            emissions_flags,
-            emissions_rate,
-            additional_emissions,
-        }
-        .data(),
-    };
+    let ix = instructions::lending_pool_update_emissions_parameters(
+        &config.program_id,
+        profile.marginfi_group.expect("marginfi group not set"),
+        config.authority(),
+        bank_pk,
+        emission_mint,
+        funding_account_ata,
+        emissions_flags,
+        emissions_rate,
+        additional_emissions,
+    );
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let signing_keypairs = config.get_signers(false);
@@ -1490,25 +1449,15 @@ pub fn bank_configure(
 ) -> Result<()> {
     let rpc_client = config.mfi_program.rpc();
 
-    let configure_bank_ixs_builder = config.mfi_program.request();
     let signing_keypairs = config.get_signers(false);
 
-    let mut configure_bank_ixs = configure_bank_ixs_builder
-        .accounts(marginfi::accounts::LendingPoolConfigureBank {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            admin: config.authority(),
-            bank: bank_pk,
-        })
-        .args(marginfi::instruction::LendingPoolConfigureBank {
-            bank_config_opt: bank_config_opt.clone(),
-        })
-        .instructions()?;
-
-    if let Some(oracle) = &bank_config_opt.oracle {
-        configure_bank_ixs[0]
-            .accounts
-            .push(AccountMeta::new_readonly(oracle.keys[0], false));
-    }
+    let configure_bank_ixs = vec![instructions::lending_pool_configure_bank(
+        &config.program_id,
+        profile.marginfi_group.unwrap(),
+        config.authority(),
+        bank_pk,
+        bank_config_opt,
+    )];
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let message = Message::new(&configure_bank_ixs, Some(&config.authority()));
@@ -1818,15 +1767,100 @@ pub fn marginfi_account_get(
         .mfi_program
         .account::<MarginfiAccount>(marginfi_account_pk)?;
 
-    let group = marginfi_account.group;
+    let rpc_client = config.mfi_program.rpc();
+    let mut state = MarginfiState::load(&rpc_client, config.program_id, marginfi_account.group)?;
+    state.refresh_price_feeds(&rpc_client)?;
+    state.load_marginfi_account(&rpc_client, marginfi_account_pk)?;
 
-    let banks = HashMap::from_iter(load_all_banks(config, Some(group))?);
+    print_account(
+        marginfi_account_pk,
+        marginfi_account,
+        state.banks.clone(),
+        false,
+    )?;
 
-    print_account(marginfi_account_pk, marginfi_account, banks, false)?;
+    match state.account_health(&marginfi_account_pk) {
+        Ok(health) => print_account_health(&health),
+        Err(err) => println!("Health unavailable: {err}"),
+    }
 
     Ok(())
 }
 
+fn print_account_health(health: &AccountHealth) {
+    println!(
+        "Health:\n\
+        \tInit: {:.6} (assets: {:.6}, liabilities: {:.6})\n\
+        \tMaintenance: {:.6} (assets: {:.6}, liabilities: {:.6})\n\
+        \tEquity: {:.6} (assets: {:.6}, liabilities: {:.6})",
+        health.init.health(),
+        health.init.assets,
+        health.init.liabilities,
+        health.maintenance.health(),
+        health.maintenance.assets,
+        health.maintenance.liabilities,
+        health.equity.health(),
+        health.equity.assets,
+        health.equity.liabilities,
+    );
+
+    for balance in &health.balances {
+        println!(
+            "\tBank: {}, Init: {:.6}, Maintenance: {:.6}, Equity: {:.6}",
+            balance.bank_pk,
+            balance.init.health(),
+            balance.maintenance.health(),
+            balance.equity.health(),
+        );
+    }
+}
+
+pub fn marginfi_account_max_borrow(
+    profile: &Profile,
+    config: &Config,
+    bank_pk: Pubkey,
+) -> Result<()> {
+    let rpc_client = config.mfi_program.rpc();
+    let marginfi_account_pk = profile.get_marginfi_account();
+
+    let mut state = load_marginfi_state(config, profile)?;
+    state.refresh_price_feeds(&rpc_client)?;
+    state.load_marginfi_account(&rpc_client, marginfi_account_pk)?;
+
+    let amount = state.max_borrow(&rpc_client, &marginfi_account_pk, &bank_pk)?;
+    print_max_amount("borrow", state.get_bank(&bank_pk)?, amount);
+
+    Ok(())
+}
+
+pub fn marginfi_account_max_withdraw(
+    profile: &Profile,
+    config: &Config,
+    bank_pk: Pubkey,
+) -> Result<()> {
+    let rpc_client = config.mfi_program.rpc();
+    let marginfi_account_pk = profile.get_marginfi_account();
+
+    let mut state = load_marginfi_state(config, profile)?;
+    state.refresh_price_feeds(&rpc_client)?;
+    state.load_marginfi_account(&rpc_client, marginfi_account_pk)?;
+
+    let amount = state.max_withdraw(&rpc_client, &marginfi_account_pk, &bank_pk)?;
+    print_max_amount("withdraw", state.get_bank(&bank_pk)?, amount);
+
+    Ok(())
+}
+
+fn print_max_amount(action: &str, bank: &Bank, amount: u64) {
+    println!(
+        "Max {}: {} ({} native, mint: {})",
+        action,
+        I80F48::from_num(amount) / EXP_10_I80F48[bank.mint_decimals as usize],
+        amount,
+        bank.mint
+    );
+}
+
 pub fn marginfi_account_deposit(
     profile: &Profile,
     config: &Config,
@@ -1837,34 +1871,14 @@ pub fn marginfi_account_deposit(
     let signer = config.get_non_ms_authority_keypair()?;
     let marginfi_account_pk = profile.get_marginfi_account();
 
-    let bank = config.mfi_program.account::<Bank>(bank_pk)?;
+    let state = load_marginfi_state(config, profile)?;
+    let bank = state.get_bank(&bank_pk)?;
 
     let amount = (I80F48::from_num(ui_amount) * EXP_10_I80F48[bank.mint_decimals as usize])
         .floor()
         .to_num::<u64>();
 
-    // Check that bank belongs to the correct group
-    if bank.group != profile.marginfi_group.unwrap() {
-        bail!("Bank does not belong to group")
-    }
-
-    let deposit_ata =
-        anchor_spl::associated_token::get_associated_token_address(&signer.pubkey(), &bank.mint);
-
-    let ix = Instruction {
-        program_id: config.program_id,
-        accounts: marginfi::accounts::LendingAccountDeposit {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            marginfi_account: marginfi_account_pk,
-            signer: signer.pubkey(),
-            bank: bank_pk,
-            signer_token_account: deposit_ata,
-            bank_liquidity_vault: bank.liquidity_vault,
-            token_program: token::ID,
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingAccountDeposit { amount }.data(),
-    };
+    let ix = state.deposit_ix(marginfi_account_pk, signer.pubkey(), bank_pk, amount)?;
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let tx = Transaction::new_signed_with_payer(
@@ -1895,65 +1909,27 @@ pub fn marginfi_account_withdraw(
 
     let marginfi_account_pk = profile.get_marginfi_account();
 
-    let banks = HashMap::from_iter(load_all_banks(
-        config,
-        Some(profile.marginfi_group.unwrap()),
-    )?);
-    let bank = banks.get(&bank_pk).expect("Bank not found");
-
-    let marginfi_account = config
-        .mfi_program
-        .account::<MarginfiAccount>(marginfi_account_pk)?;
+    let mut state = load_marginfi_state(config, profile)?;
+    state.load_marginfi_account(&rpc_client, marginfi_account_pk)?;
+    let bank = state.get_bank(&bank_pk)?;
 
     let amount = (I80F48::from_num(ui_amount) * EXP_10_I80F48[bank.mint_decimals as usize])
         .floor()
         .to_num::<u64>();
 
-    // Check that bank belongs to the correct group
-    if bank.group != profile.marginfi_group.unwrap() {
-        bail!("Bank does not belong to group")
-    }
-
-    let withdraw_ata =
-        anchor_spl::associated_token::get_associated_token_address(&signer.pubkey(), &bank.mint);
-
-    let mut ix = Instruction {
-        program_id: config.program_id,
-        accounts: marginfi::accounts::LendingAccountWithdraw {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            marginfi_account: marginfi_account_pk,
-            signer: signer.pubkey(),
-            bank: bank_pk,
-            bank_liquidity_vault: bank.liquidity_vault,
-            token_program: token::ID,
-            destination_token_account: withdraw_ata,
-            bank_liquidity_vault_authority: find_bank_vault_authority_pda(
-                &bank_pk,
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingAccountWithdraw {
-            amount,
-            withdraw_all: if withdraw_all { Some(true) } else { None },
-        }
-        .data(),
-    };
-
-    ix.accounts.extend(load_observation_account_metas(
-        &marginfi_account,
-        &banks,
-        vec![],
-        if withdraw_all { vec![bank_pk] } else { vec![] },
-    ));
+    let ix = state.withdraw_ix(
+        marginfi_account_pk,
+        signer.pubkey(),
+        bank_pk,
+        amount,
+        withdraw_all,
+    )?;
 
     let create_ide_ata_ix = create_associated_token_account_idempotent(
         &signer.pubkey(),
         &signer.pubkey(),
         &bank.mint,
-        &spl_token::ID,
+        &state.get_token_program(&bank.mint)?,
     );
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
@@ -1984,61 +1960,21 @@ pub fn marginfi_account_borrow(
 
     let marginfi_account_pk = profile.get_marginfi_account();
 
-    let banks = HashMap::from_iter(load_all_banks(
-        config,
-        Some(profile.marginfi_group.unwrap()),
-    )?);
-    let bank = banks.get(&bank_pk).expect("Bank not found");
-
-    let marginfi_account = config
-        .mfi_program
-        .account::<MarginfiAccount>(marginfi_account_pk)?;
+    let mut state = load_marginfi_state(config, profile)?;
+    state.load_marginfi_account(&rpc_client, marginfi_account_pk)?;
+    let bank = state.get_bank(&bank_pk)?;
 
     let amount = (I80F48::from_num(ui_amount) * EXP_10_I80F48[bank.mint_decimals as usize])
         .floor()
         .to_num::<u64>();
 
-    // Check that bank belongs to the correct group
-    if bank.group != profile.marginfi_group.unwrap() {
-        bail!("Bank does not belong to group")
-    }
-
-    let withdraw_ata =
-        anchor_spl::associated_token::get_associated_token_address(&signer.pubkey(), &bank.mint);
-
-    let mut ix = Instruction {
-        program_id: config.program_id,
-        accounts: marginfi::accounts::LendingAccountBorrow {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            marginfi_account: marginfi_account_pk,
-            signer: signer.pubkey(),
-            bank: bank_pk,
-            bank_liquidity_vault: bank.liquidity_vault,
-            token_program: token::ID,
-            destination_token_account: withdraw_ata,
-            bank_liquidity_vault_authority: find_bank_vault_authority_pda(
-                &bank_pk,
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingAccountBorrow { amount }.data(),
-    };
-
-    ix.accounts.extend(load_observation_account_metas(
-        &marginfi_account,
-        &banks,
-        vec![bank_pk],
-        vec![],
-    ));
+    let ix = state.borrow_ix(marginfi_account_pk, signer.pubkey(), bank_pk, amount)?;
 
     let create_ide_ata_ix = create_associated_token_account_idempotent(
         &signer.pubkey(),
         &signer.pubkey(),
         &bank.mint,
-        &spl_token::ID,
+        &state.get_token_program(&bank.mint)?,
     );
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
@@ -2064,89 +2000,43 @@ pub fn marginfi_account_liquidate(
     asset_bank_pk: Pubkey,
     liability_bank_pk: Pubkey,
     ui_asset_amount: f64,
+    with_tokens: bool,
 ) -> Result<()> {
     let signer = config.get_non_ms_authority_keypair()?;
 
     let rpc_client = config.mfi_program.rpc();
 
-    let marginfi_account_pk = profile.get_marginfi_account();
-
-    let banks = HashMap::from_iter(load_all_banks(
-        config,
-        Some(profile.marginfi_group.unwrap()),
-    )?);
-    let asset_bank = banks.get(&asset_bank_pk).expect("Asset bank not found");
-    let liability_bank = banks
-        .get(&liability_bank_pk)
-        .expect("Liability bank not found");
-
-    let marginfi_account = config
-        .mfi_program
-        .account::<MarginfiAccount>(marginfi_account_pk)?;
-
-    let liquidatee_marginfi_account = config
-        .mfi_program
-        .account::<MarginfiAccount>(liquidatee_marginfi_account_pk)?;
+    let mut state = load_marginfi_state(config, profile)?;
+    state.load_marginfi_account(&rpc_client, liquidatee_marginfi_account_pk)?;
+    let asset_bank = state.get_bank(&asset_bank_pk)?;
 
     let asset_amount = (I80F48::from_num(ui_asset_amount)
         * EXP_10_I80F48[asset_bank.mint_decimals as usize])
         .floor()
         .to_num::<u64>();
 
-    // Check that banks belong to the correct group
-    if asset_bank.group != profile.marginfi_group.unwrap() {
-        bail!("Asset bank does not belong to group")
-    }
-    if liability_bank.group != profile.marginfi_group.unwrap() {
-        bail!("Liability bank does not belong to group")
-    }
-
-    let mut ix = Instruction {
-        program_id: config.program_id,
-        accounts: marginfi::accounts::LendingAccountLiquidate {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            asset_bank: asset_bank_pk,
-            liab_bank: liability_bank_pk,
-            liquidator_marginfi_account: marginfi_account_pk,
-            signer: signer.pubkey(),
-            liquidatee_marginfi_account: liquidatee_marginfi_account_pk,
-            bank_liquidity_vault_authority: find_bank_vault_authority_pda(
-                &liability_bank_pk,
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-            bank_liquidity_vault: liability_bank.liquidity_vault,
-            bank_insurance_vault: liability_bank.insurance_vault,
-            token_program: token::ID,
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::LendingAccountLiquidate { asset_amount }.data(),
+    let ix = if with_tokens {
+        state.liquidate_with_tokens_ix(
+            signer.pubkey(),
+            liquidatee_marginfi_account_pk,
+            asset_bank_pk,
+            liability_bank_pk,
+            asset_amount,
+        )?
+    } else {
+        let marginfi_account_pk = profile.get_marginfi_account();
+        state.load_marginfi_account(&rpc_client, marginfi_account_pk)?;
+
+        state.liquidate_ix(
+            marginfi_account_pk,
+            signer.pubkey(),
+            liquidatee_marginfi_account_pk,
+            asset_bank_pk,
+            liability_bank_pk,
+            asset_amount,
+        )?
     };
 
-    ix.accounts.push(AccountMeta {
-        pubkey: asset_bank.config.oracle_keys[0],
-        is_signer: false,
-        is_writable: false,
-    });
-    ix.accounts.push(AccountMeta {
-        pubkey: liability_bank.config.oracle_keys[0],
-        is_signer: false,
-        is_writable: false,
-    });
-    ix.accounts.extend(load_observation_account_metas(
-        &marginfi_account,
-        &banks,
-        vec![liability_bank_pk, asset_bank_pk],
-        vec![],
-    ));
-    ix.accounts.extend(load_observation_account_metas(
-        &liquidatee_marginfi_account,
-        &banks,
-        vec![],
-        vec![],
-    ));
-
     let cu_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
@@ -2172,18 +2062,13 @@ pub fn marginfi_account_create(profile: &Profile, config: &Config) -> Result<()>
 
     let marginfi_account_key = Keypair::new();
 
-    let ix = Instruction {
-        program_id: config.program_id,
-        accounts: marginfi::accounts::MarginfiAccountInitialize {
-            marginfi_group: profile.marginfi_group.unwrap(),
-            marginfi_account: marginfi_account_key.pubkey(),
-            system_program: system_program::ID,
-            authority: signer.pubkey(),
-            fee_payer: signer.pubkey(),
-        }
-        .to_account_metas(Some(true)),
-        data: marginfi::instruction::MarginfiAccountInitialize.data(),
-    };
+    let ix = instructions::marginfi_account_initialize(
+        &config.program_id,
+        profile.marginfi_group.unwrap(),
+        marginfi_account_key.pubkey(),
+        signer.pubkey(),
+        signer.pubkey(),
+    );
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let tx = Transaction::new_signed_with_payer(
@@ -1,25 +1,13 @@
 use crate::config::TxMode;
-use marginfi::bank_seed;
-use marginfi::constants::EMISSIONS_TOKEN_ACCOUNT_SEED;
 #[cfg(feature = "admin")]
-use marginfi::constants::{EMISSIONS_AUTH_SEED, MAX_ORACLE_KEYS};
+use marginfi::constants::MAX_ORACLE_KEYS;
 use {
     anyhow::{bail, Result},
     fixed::types::I80F48,
     fixed_macro::types::I80F48,
     log::error,
-    marginfi::{
-        bank_authority_seed,
-        state::{
-            marginfi_account::MarginfiAccount,
-            marginfi_group::{Bank, BankVaultType},
-        },
-    },
     solana_client::rpc_client::RpcClient,
-    solana_sdk::{
-        instruction::AccountMeta, pubkey::Pubkey, signature::Signature, transaction::Transaction,
-    },
-    std::collections::HashMap,
+    solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction},
 };
 
 pub fn process_transaction(
@@ -64,53 +52,6 @@ pub fn process_transaction(
     }
 }
 
-pub fn find_bank_vault_pda(
-    bank_pk: &Pubkey,
-    vault_type: BankVaultType,
-    program_id: &Pubkey,
-) -> (Pubkey, u8) {
-    Pubkey::find_program_address(bank_seed!(vault_type, bank_pk), program_id)
-}
-
-pub fn find_bank_vault_authority_pda(
-    bank_pk: &Pubkey,
-    vault_type: BankVaultType,
-    program_id: &Pubkey,
-) -> (Pubkey, u8) {
-    Pubkey::find_program_address(bank_authority_seed!(vault_type, bank_pk), program_id)
-}
-
-#[cfg(feature = "admin")]
-pub fn find_bank_emssions_auth_pda(
-    bank: Pubkey,
-    emissions_mint: Pubkey,
-    program_id: Pubkey,
-) -> (Pubkey, u8) {
-    Pubkey::find_program_address(
-        &[
-            EMISSIONS_AUTH_SEED.as_bytes(),
-            bank.as_ref(),
-            emissions_mint.as_ref(),
-        ],
-        &program_id,
-    )
-}
-
-pub fn find_bank_emssions_token_account_pda(
-    bank: Pubkey,
-    emissions_mint: Pubkey,
-    program_id: Pubkey,
-) -> (Pubkey, u8) {
-    Pubkey::find_program_address(
-        &[
-            EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
-            bank.as_ref(),
-            emissions_mint.as_ref(),
-        ],
-        &program_id,
-    )
-}
-
 #[cfg(feature = "admin")]
 pub fn create_oracle_key_array(oracle_key: Pubkey) -> [Pubkey; MAX_ORACLE_KEYS] {
     let mut oracle_keys = [Pubkey::default(); MAX_ORACLE_KEYS];
@@ -118,6 +59,22 @@ pub fn create_oracle_key_array(oracle_key: Pubkey) -> [Pubkey; MAX_ORACLE_KEYS]
     oracle_keys
 }
 
+/// Parse a Pyth feed id, 32 bytes hex encoded with or without a `0x` prefix.
+#[cfg(feature = "admin")]
+pub fn parse_pyth_feed_id(feed_id: &str) -> Result<[u8; 32]> {
+    let hex = feed_id.strip_prefix("0x").unwrap_or(feed_id);
+    if hex.len() != 64 || !hex.is_ascii() {
+        bail!("Pyth feed id must be 32 hex encoded bytes");
+    }
+
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)?;
+    }
+
+    Ok(bytes)
+}
+
 pub const EXP_10_I80F48: [I80F48; 15] = [
     I80F48!(1),
     I80F48!(10),
@@ -136,52 +93,10 @@ pub const EXP_10_I80F48: [I80F48; 15] = [
     I80F48!(100_000_000_000_000),
 ];
 
-pub fn load_observation_account_metas(
-    marginfi_account: &MarginfiAccount,
-    banks_map: &HashMap<Pubkey, Bank>,
-    include_banks: Vec<Pubkey>,
-    exclude_banks: Vec<Pubkey>,
-) -> Vec<AccountMeta> {
-    let mut bank_pks = marginfi_account
-        .lending_account
-        .balances
-        .iter()
-        .filter_map(|balance| balance.active.then_some(balance.bank_pk))
-        .collect::<Vec<_>>();
-
-    for bank_pk in include_banks {
-        if !bank_pks.contains(&bank_pk) {
-            bank_pks.push(bank_pk);
-        }
-    }
-
-    bank_pks.retain(|bank_pk| !exclude_banks.contains(bank_pk));
-
-    let mut banks = vec![];
-    for bank_pk in bank_pks.clone() {
-        let bank = banks_map.get(&bank_pk).unwrap();
-        banks.push(bank);
-    }
-
-    let account_metas = banks
-        .iter()
-        .zip(bank_pks.iter())
-        .flat_map(|(bank, bank_pk)| {
-            vec![
-                AccountMeta {
-                    pubkey: *bank_pk,
-                    is_signer: false,
-                    is_writable: false,
-                },
-                AccountMeta {
-                    pubkey: bank.config.oracle_keys[0],
-                    is_signer: false,
-                    is_writable: false,
-                },
-            ]
-        })
-        .collect::<Vec<_>>();
-    account_metas
+/// Token program owning the bank mint, either the token or the token-2022 program.
+#[cfg(feature = "admin")]
+pub fn load_bank_token_program(rpc_client: &RpcClient, bank_mint: &Pubkey) -> Result<Pubkey> {
+    Ok(rpc_client.get_account(bank_mint)?.owner)
 }
 
 #[cfg(feature = "admin")]
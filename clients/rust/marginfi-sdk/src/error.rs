@@ -0,0 +1,38 @@
+use {
+    marginfi::prelude::MarginfiError, solana_client::client_error::ClientError,
+    solana_sdk::pubkey::Pubkey,
+};
+
+pub type SdkResult<T> = Result<T, SdkError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SdkError {
+    #[error("RPC error: {0}")]
+    Rpc(#[from] ClientError),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Account {0} could not be deserialized")]
+    InvalidAccountData(Pubkey),
+    #[error("Bank {0} is not loaded")]
+    BankNotLoaded(Pubkey),
+    #[error("Marginfi account {0} is not loaded")]
+    MarginfiAccountNotLoaded(Pubkey),
+    #[error("Token program for mint {0} is not loaded")]
+    TokenProgramNotLoaded(Pubkey),
+    #[error("Account {account} does not belong to group {group}")]
+    GroupMismatch { account: Pubkey, group: Pubkey },
+    #[error("Program error: {0}")]
+    Program(anchor_lang::error::Error),
+}
+
+impl From<anchor_lang::error::Error> for SdkError {
+    fn from(err: anchor_lang::error::Error) -> Self {
+        SdkError::Program(err)
+    }
+}
+
+impl From<MarginfiError> for SdkError {
+    fn from(err: MarginfiError) -> Self {
+        SdkError::Program(err.into())
+    }
+}
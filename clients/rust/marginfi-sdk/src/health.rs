@@ -0,0 +1,371 @@
+//! Off-chain account health, computed with the program's own `RiskEngine`.
+
+use {
+    crate::error::SdkResult,
+    fixed::types::I80F48,
+    marginfi::{
+        prelude::{MarginfiError, MarginfiResult},
+        state::{
+            marginfi_account::{BankPriceSource, MarginfiAccount, RiskEngine, RiskRequirementType},
+            marginfi_group::{Bank, MarginfiGroup},
+            price::OraclePriceFeedAdapter,
+        },
+    },
+    solana_sdk::pubkey::Pubkey,
+    std::collections::HashMap,
+};
+
+/// Bank and price feed held in memory. A missing price feed behaves like a stale oracle on-chain.
+pub struct CachedBankPriceSource<'a> {
+    pub bank: &'a Bank,
+    pub price_feed: Option<&'a OraclePriceFeedAdapter>,
+}
+
+impl<'a> BankPriceSource for CachedBankPriceSource<'a> {
+    type BankRef<'s>
+        = &'a Bank
+    where
+        Self: 's;
+
+    fn load_bank(&self) -> MarginfiResult<&'a Bank> {
+        Ok(self.bank)
+    }
+
+    fn price_feed(&self) -> MarginfiResult<&OraclePriceFeedAdapter> {
+        self.price_feed
+            .ok_or_else(|| MarginfiError::StaleOracle.into())
+    }
+}
+
+/// Weighted assets and liabilities for one requirement type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HealthComponents {
+    pub assets: I80F48,
+    pub liabilities: I80F48,
+}
+
+impl HealthComponents {
+    pub fn health(&self) -> I80F48 {
+        self.assets - self.liabilities
+    }
+}
+
+impl From<(I80F48, I80F48)> for HealthComponents {
+    fn from((assets, liabilities): (I80F48, I80F48)) -> Self {
+        Self {
+            assets,
+            liabilities,
+        }
+    }
+}
+
+/// Contribution of a single balance to the account health.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BalanceHealth {
+    pub bank_pk: Pubkey,
+    pub mint: Pubkey,
+    /// Native token amounts.
+    pub asset_amount: I80F48,
+    pub liability_amount: I80F48,
+    pub init: HealthComponents,
+    pub maintenance: HealthComponents,
+    pub equity: HealthComponents,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountHealth {
+    pub init: HealthComponents,
+    pub maintenance: HealthComponents,
+    pub equity: HealthComponents,
+    /// Active balances, in balance order.
+    pub balances: Vec<BalanceHealth>,
+}
+
+impl AccountHealth {
+    /// Accounts below the maintenance requirement can be liquidated.
+    pub fn is_liquidatable(&self) -> bool {
+        self.maintenance.health() < I80F48::ZERO
+    }
+}
+
+/// Risk engine over in-memory banks and price feeds, with price feeds keyed by bank address.
+///
+/// Adapters carry the oracle config of their bank (confidence cap, peg, fixed price, ...), so banks
+/// sharing an oracle account still get separate feeds.
+pub fn load_risk_engine<'a>(
+    marginfi_group: &MarginfiGroup,
+    marginfi_account: &'a MarginfiAccount,
+    banks: &'a HashMap<Pubkey, Bank>,
+    price_feeds: &'a HashMap<Pubkey, OraclePriceFeedAdapter>,
+) -> SdkResult<RiskEngine<'a, CachedBankPriceSource<'a>>> {
+    let sources = marginfi_account
+        .lending_account
+        .balances
+        .iter()
+        .filter(|balance| balance.active)
+        .map(|balance| {
+            let bank = banks
+                .get(&balance.bank_pk)
+                .ok_or(crate::SdkError::BankNotLoaded(balance.bank_pk))?;
+
+            Ok(CachedBankPriceSource {
+                bank,
+                price_feed: price_feeds.get(&balance.bank_pk),
+            })
+        })
+        .collect::<SdkResult<Vec<_>>>()?;
+
+    Ok(RiskEngine::new_with_sources(
+        marginfi_group,
+        marginfi_account,
+        sources,
+    )?)
+}
+
+/// Init, maintenance and equity health of `marginfi_account`, with a per-balance breakdown.
+pub fn compute_account_health(
+    marginfi_group: &MarginfiGroup,
+    marginfi_account: &MarginfiAccount,
+    banks: &HashMap<Pubkey, Bank>,
+    price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+) -> SdkResult<AccountHealth> {
+    let risk_engine = load_risk_engine(marginfi_group, marginfi_account, banks, price_feeds)?;
+
+    let init = risk_engine.get_balance_health_components(RiskRequirementType::Initial)?;
+    let maintenance =
+        risk_engine.get_balance_health_components(RiskRequirementType::Maintenance)?;
+    let equity = risk_engine.get_balance_health_components(RiskRequirementType::Equity)?;
+
+    let balances = marginfi_account
+        .lending_account
+        .balances
+        .iter()
+        .filter(|balance| balance.active)
+        .zip(init.into_iter().zip(maintenance).zip(equity))
+        .map(|(balance, ((init, maintenance), equity))| {
+            let bank = &banks[&balance.bank_pk];
+
+            Ok(BalanceHealth {
+                bank_pk: balance.bank_pk,
+                mint: bank.mint,
+                asset_amount: bank.get_asset_amount(balance.asset_shares.into())?,
+                liability_amount: bank.get_liability_amount(balance.liability_shares.into())?,
+                init: (init.1, init.2).into(),
+                maintenance: (maintenance.1, maintenance.2).into(),
+                equity: (equity.1, equity.2).into(),
+            })
+        })
+        .collect::<SdkResult<Vec<_>>>()?;
+
+    let total = |select: fn(&BalanceHealth) -> HealthComponents| {
+        balances
+            .iter()
+            .map(select)
+            .fold(HealthComponents::default(), |total, components| {
+                HealthComponents {
+                    assets: total.assets + components.assets,
+                    liabilities: total.liabilities + components.liabilities,
+                }
+            })
+    };
+
+    Ok(AccountHealth {
+        init: total(|balance| balance.init),
+        maintenance: total(|balance| balance.maintenance),
+        equity: total(|balance| balance.equity),
+        balances,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test_utils::{bank, price_feed},
+        fixed_macro::types::I80F48,
+        marginfi::state::marginfi_group::EmodeCategory,
+    };
+
+    #[test]
+    fn account_health_matches_weighted_values() {
+        let (usdc_pk, sol_pk) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let banks = HashMap::from([(usdc_pk, bank(6)), (sol_pk, bank(9))]);
+        let price_feeds = HashMap::from([(usdc_pk, price_feed(1)), (sol_pk, price_feed(10))]);
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        let balances = &mut marginfi_account.lending_account.balances;
+        balances[0].active = true;
+        balances[0].bank_pk = usdc_pk;
+        balances[0].asset_shares = I80F48!(100_000_000).into();
+        balances[1].active = true;
+        balances[1].bank_pk = sol_pk;
+        balances[1].liability_shares = I80F48!(5_000_000_000).into();
+
+        let health = compute_account_health(
+            &MarginfiGroup::default(),
+            &marginfi_account,
+            &banks,
+            &price_feeds,
+        )
+        .unwrap();
+
+        assert_eq!(health.init.assets, I80F48!(75));
+        assert_eq!(health.init.liabilities, I80F48!(62.5));
+        assert_eq!(health.maintenance.health(), I80F48!(31.25));
+        assert_eq!(health.equity.health(), I80F48!(50));
+        assert!(!health.is_liquidatable());
+
+        assert_eq!(health.balances.len(), 2);
+        assert_eq!(health.balances[1].bank_pk, sol_pk);
+        assert_eq!(health.balances[1].liability_amount, I80F48!(5_000_000_000));
+        assert_eq!(health.balances[1].maintenance.liabilities, I80F48!(56.25));
+    }
+
+    #[test]
+    fn emode_weights_apply_when_every_balance_is_in_the_category() {
+        let (usdc_pk, sol_pk) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut banks = HashMap::from([(usdc_pk, bank(6)), (sol_pk, bank(9))]);
+        banks.get_mut(&usdc_pk).unwrap().config.emode_category = 1;
+        let price_feeds = HashMap::from([(usdc_pk, price_feed(1)), (sol_pk, price_feed(10))]);
+
+        let mut marginfi_group = MarginfiGroup::default();
+        marginfi_group
+            .configure_emode_category(
+                1,
+                EmodeCategory {
+                    asset_weight_init: I80F48!(0.875).into(),
+                    asset_weight_maint: I80F48!(0.9375).into(),
+                    liability_weight_init: I80F48!(1.0625).into(),
+                    liability_weight_maint: I80F48!(1.03125).into(),
+                },
+            )
+            .unwrap();
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        marginfi_account.emode_category = 1;
+        let balances = &mut marginfi_account.lending_account.balances;
+        balances[0].active = true;
+        balances[0].bank_pk = usdc_pk;
+        balances[0].asset_shares = I80F48!(100_000_000).into();
+        balances[1].active = true;
+        balances[1].bank_pk = sol_pk;
+        balances[1].liability_shares = I80F48!(5_000_000_000).into();
+
+        // SOL is not in the category, the bank weights apply.
+        let health =
+            compute_account_health(&marginfi_group, &marginfi_account, &banks, &price_feeds)
+                .unwrap();
+        assert_eq!(health.init.assets, I80F48!(75));
+        assert_eq!(health.init.liabilities, I80F48!(62.5));
+
+        banks.get_mut(&sol_pk).unwrap().config.emode_category = 1;
+
+        let health =
+            compute_account_health(&marginfi_group, &marginfi_account, &banks, &price_feeds)
+                .unwrap();
+        assert_eq!(health.init.assets, I80F48!(87.5));
+        assert_eq!(health.init.liabilities, I80F48!(53.125));
+        assert_eq!(health.maintenance.health(), I80F48!(42.1875));
+    }
+
+    #[test]
+    fn weight_overrides_apply_when_every_liability_has_one() {
+        let (usdc_pk, sol_pk, mnde_pk) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        let banks = HashMap::from([(usdc_pk, bank(6)), (sol_pk, bank(9)), (mnde_pk, bank(9))]);
+        let price_feeds = HashMap::from([
+            (usdc_pk, price_feed(1)),
+            (sol_pk, price_feed(10)),
+            (mnde_pk, price_feed(1)),
+        ]);
+
+        let mut marginfi_group = MarginfiGroup::default();
+        marginfi_group
+            .configure_weight_override(usdc_pk, sol_pk, I80F48!(0.5), I80F48!(0.625))
+            .unwrap();
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        let balances = &mut marginfi_account.lending_account.balances;
+        balances[0].active = true;
+        balances[0].bank_pk = usdc_pk;
+        balances[0].asset_shares = I80F48!(100_000_000).into();
+        balances[1].active = true;
+        balances[1].bank_pk = sol_pk;
+        balances[1].liability_shares = I80F48!(1_000_000_000).into();
+
+        let health =
+            compute_account_health(&marginfi_group, &marginfi_account, &banks, &price_feeds)
+                .unwrap();
+        assert_eq!(health.init.assets, I80F48!(50));
+        assert_eq!(health.maintenance.assets, I80F48!(62.5));
+        assert_eq!(health.equity.assets, I80F48!(100));
+
+        // MNDE debt has no override for USDC collateral, the bank weights apply.
+        marginfi_account.lending_account.balances[2].active = true;
+        marginfi_account.lending_account.balances[2].bank_pk = mnde_pk;
+        marginfi_account.lending_account.balances[2].liability_shares =
+            I80F48!(1_000_000_000).into();
+
+        let health =
+            compute_account_health(&marginfi_group, &marginfi_account, &banks, &price_feeds)
+                .unwrap();
+        assert_eq!(health.init.assets, I80F48!(75));
+    }
+
+    #[test]
+    fn banks_sharing_an_oracle_use_their_own_price_feed() {
+        let (usdc_pk, pegged_pk) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mint, oracle) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut banks = HashMap::from([(usdc_pk, bank(6)), (pegged_pk, bank(6))]);
+        for bank in banks.values_mut() {
+            bank.mint = mint;
+            bank.config.oracle_keys[0] = oracle;
+        }
+        let price_feeds = HashMap::from([(usdc_pk, price_feed(1)), (pegged_pk, price_feed(2))]);
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        let balances = &mut marginfi_account.lending_account.balances;
+        balances[0].active = true;
+        balances[0].bank_pk = usdc_pk;
+        balances[0].asset_shares = I80F48!(100_000_000).into();
+        balances[1].active = true;
+        balances[1].bank_pk = pegged_pk;
+        balances[1].asset_shares = I80F48!(100_000_000).into();
+
+        let health = compute_account_health(
+            &MarginfiGroup::default(),
+            &marginfi_account,
+            &banks,
+            &price_feeds,
+        )
+        .unwrap();
+
+        assert_eq!(health.equity.assets, I80F48!(300));
+    }
+
+    #[test]
+    fn missing_price_feed_is_a_stale_oracle() {
+        let usdc_pk = Pubkey::new_unique();
+        let banks = HashMap::from([(usdc_pk, bank(6))]);
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        marginfi_account.lending_account.balances[0].active = true;
+        marginfi_account.lending_account.balances[0].bank_pk = usdc_pk;
+        marginfi_account.lending_account.balances[0].asset_shares = I80F48!(1_000_000).into();
+
+        assert!(compute_account_health(
+            &MarginfiGroup::default(),
+            &marginfi_account,
+            &banks,
+            &HashMap::new()
+        )
+        .is_err());
+    }
+}
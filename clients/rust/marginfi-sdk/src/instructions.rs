@@ -0,0 +1,1193 @@
+//! One builder per marginfi program instruction.
+//!
+//! Builders are pure: callers provide every account, and instructions that touch the risk
+//! engine take their observation accounts pre-assembled (see
+//! [`crate::remaining_accounts::load_observation_account_metas`]). Instructions moving bank
+//! tokens take the bank mint and its token program, and pass the mint as the first remaining
+//! account for Token-2022 banks.
+
+use {
+    crate::{
+        pda::{
+            find_bank_emissions_auth_pda, find_bank_emissions_token_account_pda,
+            find_bank_receipt_mint_authority_pda, find_bank_receipt_mint_pda,
+            find_bank_vault_authority_pda, find_bank_vault_pda, find_bank_with_seed_pda,
+        },
+        remaining_accounts::maybe_add_bank_mint,
+    },
+    anchor_lang::{InstructionData, ToAccountMetas},
+    marginfi::{
+        prelude::GroupConfig,
+        state::{
+            marginfi_group::{
+                BankConfigCompact, BankConfigOpt, BankVaultType, EmodeCategory, WrappedI80F48,
+            },
+            price::OracleSetup,
+        },
+    },
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        system_program, sysvar,
+    },
+};
+
+// --------------------------------------------------------------------------------------------------------------------
+// marginfi group
+// --------------------------------------------------------------------------------------------------------------------
+
+pub fn marginfi_group_initialize(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::MarginfiGroupInitialize {
+            marginfi_group,
+            admin,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::MarginfiGroupInitialize {}.data(),
+    }
+}
+
+pub fn marginfi_group_configure(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    config: GroupConfig,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::MarginfiGroupConfigure {
+            marginfi_group,
+            admin,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::MarginfiGroupConfigure { config }.data(),
+    }
+}
+
+pub fn marginfi_group_configure_emode_category(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    emode_category: u8,
+    config: EmodeCategory,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::MarginfiGroupConfigureEmodeCategory {
+            marginfi_group,
+            admin,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::MarginfiGroupConfigureEmodeCategory {
+            emode_category,
+            config,
+        }
+        .data(),
+    }
+}
+
+pub fn marginfi_group_configure_weight_override(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    asset_bank: Pubkey,
+    liability_bank: Pubkey,
+    asset_weight_init: WrappedI80F48,
+    asset_weight_maint: WrappedI80F48,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::MarginfiGroupConfigureWeightOverride {
+            marginfi_group,
+            admin,
+            asset_bank,
+            liability_bank,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::MarginfiGroupConfigureWeightOverride {
+            asset_weight_init,
+            asset_weight_maint,
+        }
+        .data(),
+    }
+}
+
+// --------------------------------------------------------------------------------------------------------------------
+// lending pool
+// --------------------------------------------------------------------------------------------------------------------
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_pool_add_bank(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    fee_payer: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    token_program: Pubkey,
+    bank_config: BankConfigCompact,
+) -> Instruction {
+    // Fixed price banks have no oracle account
+    let oracle_key =
+        (!matches!(bank_config.oracle_setup, OracleSetup::Fixed)).then_some(bank_config.oracle_key);
+
+    let mut accounts = marginfi::accounts::LendingPoolAddBank {
+        marginfi_group,
+        admin,
+        fee_payer,
+        bank_mint,
+        bank,
+        liquidity_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        liquidity_vault: find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+        insurance_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Insurance,
+            program_id,
+        )
+        .0,
+        insurance_vault: find_bank_vault_pda(&bank, BankVaultType::Insurance, program_id).0,
+        fee_vault_authority: find_bank_vault_authority_pda(&bank, BankVaultType::Fee, program_id).0,
+        fee_vault: find_bank_vault_pda(&bank, BankVaultType::Fee, program_id).0,
+        rent: sysvar::rent::id(),
+        token_program,
+        system_program: system_program::id(),
+    }
+    .to_account_metas(Some(true));
+    accounts.extend(oracle_key.map(|oracle_key| AccountMeta::new_readonly(oracle_key, false)));
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingPoolAddBank { bank_config }.data(),
+    }
+}
+
+/// The bank address is the PDA returned by [`find_bank_with_seed_pda`] for `bank_seed`.
+#[allow(clippy::too_many_arguments)]
+pub fn lending_pool_add_bank_with_seed(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    fee_payer: Pubkey,
+    bank_mint: Pubkey,
+    token_program: Pubkey,
+    bank_config: BankConfigCompact,
+    bank_seed: u64,
+) -> Instruction {
+    // Fixed price banks have no oracle account
+    let oracle_key =
+        (!matches!(bank_config.oracle_setup, OracleSetup::Fixed)).then_some(bank_config.oracle_key);
+    let bank = find_bank_with_seed_pda(&marginfi_group, &bank_mint, bank_seed, program_id).0;
+
+    let mut accounts = marginfi::accounts::LendingPoolAddBankWithSeed {
+        marginfi_group,
+        admin,
+        fee_payer,
+        bank_mint,
+        bank,
+        liquidity_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        liquidity_vault: find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+        insurance_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Insurance,
+            program_id,
+        )
+        .0,
+        insurance_vault: find_bank_vault_pda(&bank, BankVaultType::Insurance, program_id).0,
+        fee_vault_authority: find_bank_vault_authority_pda(&bank, BankVaultType::Fee, program_id).0,
+        fee_vault: find_bank_vault_pda(&bank, BankVaultType::Fee, program_id).0,
+        rent: sysvar::rent::id(),
+        token_program,
+        system_program: system_program::id(),
+    }
+    .to_account_metas(Some(true));
+    accounts.extend(oracle_key.map(|oracle_key| AccountMeta::new_readonly(oracle_key, false)));
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingPoolAddBankWithSeed {
+            bank_config,
+            bank_seed,
+        }
+        .data(),
+    }
+}
+
+pub fn lending_pool_configure_bank(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    bank: Pubkey,
+    bank_config_opt: BankConfigOpt,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingPoolConfigureBank {
+        marginfi_group,
+        admin,
+        bank,
+    }
+    .to_account_metas(Some(true));
+
+    if let Some(oracle) = &bank_config_opt.oracle {
+        let oracle_keys = match oracle.setup {
+            OracleSetup::Aggregated => &oracle.keys[..],
+            // Primary and optional fallback oracle
+            _ => &oracle.keys[..2],
+        };
+        accounts.extend(
+            oracle_keys
+                .iter()
+                .filter(|oracle_key| **oracle_key != Pubkey::default())
+                .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+        );
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingPoolConfigureBank { bank_config_opt }.data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_pool_setup_emissions(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    bank: Pubkey,
+    emissions_mint: Pubkey,
+    emissions_funding_account: Pubkey,
+    flags: u64,
+    rate: u64,
+    total_emissions: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingPoolSetupEmissions {
+            marginfi_group,
+            admin,
+            bank,
+            emissions_mint,
+            emissions_auth: find_bank_emissions_auth_pda(&bank, &emissions_mint, program_id).0,
+            emissions_token_account: find_bank_emissions_token_account_pda(
+                &bank,
+                &emissions_mint,
+                program_id,
+            )
+            .0,
+            emissions_funding_account,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingPoolSetupEmissions {
+            flags,
+            rate,
+            total_emissions,
+        }
+        .data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_pool_update_emissions_parameters(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    bank: Pubkey,
+    emissions_mint: Pubkey,
+    emissions_funding_account: Pubkey,
+    emissions_flags: Option<u64>,
+    emissions_rate: Option<u64>,
+    additional_emissions: Option<u64>,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingPoolUpdateEmissionsParameters {
+            marginfi_group,
+            admin,
+            bank,
+            emissions_mint,
+            emissions_token_account: find_bank_emissions_token_account_pda(
+                &bank,
+                &emissions_mint,
+                program_id,
+            )
+            .0,
+            emissions_funding_account,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingPoolUpdateEmissionsParameters {
+            emissions_flags,
+            emissions_rate,
+            additional_emissions,
+        }
+        .data(),
+    }
+}
+
+pub fn lending_pool_setup_receipt_mint(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    bank: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingPoolSetupReceiptMint {
+            marginfi_group,
+            admin,
+            bank,
+            receipt_mint_authority: find_bank_receipt_mint_authority_pda(&bank, program_id).0,
+            receipt_mint: find_bank_receipt_mint_pda(&bank, program_id).0,
+            rent: sysvar::rent::id(),
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingPoolSetupReceiptMint {}.data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_pool_handle_bankruptcy(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    marginfi_account: Pubkey,
+    token_program: Pubkey,
+    observation_metas: Vec<AccountMeta>,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingPoolHandleBankruptcy {
+        marginfi_group,
+        signer,
+        bank,
+        marginfi_account,
+        liquidity_vault: find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+        insurance_vault: find_bank_vault_pda(&bank, BankVaultType::Insurance, program_id).0,
+        insurance_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Insurance,
+            program_id,
+        )
+        .0,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+    accounts.extend(observation_metas);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingPoolHandleBankruptcy {}.data(),
+    }
+}
+
+/// `banks` are the (bank, bank mint, token program) of every liability balance of the account,
+/// in balance order.
+pub fn lending_pool_handle_bankruptcy_all(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    signer: Pubkey,
+    marginfi_account: Pubkey,
+    banks: Vec<(Pubkey, Pubkey, Pubkey)>,
+    observation_metas: Vec<AccountMeta>,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingPoolHandleBankruptcyAll {
+        marginfi_group,
+        signer,
+        marginfi_account,
+    }
+    .to_account_metas(Some(true));
+    for (bank, bank_mint, token_program) in banks {
+        accounts.extend([
+            AccountMeta::new(bank, false),
+            AccountMeta::new(
+                find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+                false,
+            ),
+            AccountMeta::new(
+                find_bank_vault_pda(&bank, BankVaultType::Insurance, program_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(
+                find_bank_vault_authority_pda(&bank, BankVaultType::Insurance, program_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(token_program, false),
+        ]);
+        maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+    }
+    accounts.extend(observation_metas);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingPoolHandleBankruptcyAll {}.data(),
+    }
+}
+
+pub fn lending_pool_accrue_bank_interest(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    bank: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingPoolAccrueBankInterest {
+            marginfi_group,
+            bank,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingPoolAccrueBankInterest {}.data(),
+    }
+}
+
+/// Accrual that also refreshes the bank price guard from `oracle_keys`, the bank's
+/// `BankConfig::get_oracle_keys`.
+pub fn lending_pool_accrue_bank_interest_with_oracles(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    bank: Pubkey,
+    oracle_keys: &[Pubkey],
+) -> Instruction {
+    let mut ix = lending_pool_accrue_bank_interest(program_id, marginfi_group, bank);
+    ix.accounts.extend(
+        oracle_keys
+            .iter()
+            .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+    );
+
+    ix
+}
+
+pub fn lending_pool_collect_bank_fees(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    token_program: Pubkey,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingPoolCollectBankFees {
+        marginfi_group,
+        bank,
+        liquidity_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        liquidity_vault: find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+        insurance_vault: find_bank_vault_pda(&bank, BankVaultType::Insurance, program_id).0,
+        fee_vault: find_bank_vault_pda(&bank, BankVaultType::Fee, program_id).0,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingPoolCollectBankFees {}.data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_pool_withdraw_fees(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    dst_token_account: Pubkey,
+    token_program: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingPoolWithdrawFees {
+        marginfi_group,
+        bank,
+        admin,
+        fee_vault: find_bank_vault_pda(&bank, BankVaultType::Fee, program_id).0,
+        fee_vault_authority: find_bank_vault_authority_pda(&bank, BankVaultType::Fee, program_id).0,
+        dst_token_account,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingPoolWithdrawFees { amount }.data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_pool_withdraw_insurance(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    admin: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    dst_token_account: Pubkey,
+    token_program: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingPoolWithdrawInsurance {
+        marginfi_group,
+        bank,
+        admin,
+        insurance_vault: find_bank_vault_pda(&bank, BankVaultType::Insurance, program_id).0,
+        insurance_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Insurance,
+            program_id,
+        )
+        .0,
+        dst_token_account,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingPoolWithdrawInsurance { amount }.data(),
+    }
+}
+
+pub fn set_account_flag(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    admin: Pubkey,
+    flag: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::SetAccountFlag {
+            marginfi_group,
+            marginfi_account,
+            admin,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::SetAccountFlag { flag }.data(),
+    }
+}
+
+pub fn unset_account_flag(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    admin: Pubkey,
+    flag: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::UnsetAccountFlag {
+            marginfi_group,
+            marginfi_account,
+            admin,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::UnsetAccountFlag { flag }.data(),
+    }
+}
+
+// --------------------------------------------------------------------------------------------------------------------
+// marginfi account
+// --------------------------------------------------------------------------------------------------------------------
+
+pub fn marginfi_account_initialize(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    authority: Pubkey,
+    fee_payer: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::MarginfiAccountInitialize {
+            marginfi_group,
+            marginfi_account,
+            authority,
+            fee_payer,
+            system_program: system_program::id(),
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::MarginfiAccountInitialize {}.data(),
+    }
+}
+
+pub fn marginfi_account_set_emode(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    observation_metas: Vec<AccountMeta>,
+    emode_category: u8,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::MarginfiAccountSetEmode {
+        marginfi_group,
+        marginfi_account,
+        signer,
+    }
+    .to_account_metas(Some(true));
+    accounts.extend(observation_metas);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::MarginfiAccountSetEmode { emode_category }.data(),
+    }
+}
+
+pub fn set_new_account_authority(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    new_authority: Pubkey,
+    fee_payer: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::MarginfiAccountSetAccountAuthority {
+            marginfi_account,
+            marginfi_group,
+            signer,
+            new_authority,
+            fee_payer,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::SetNewAccountAuthority {}.data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_deposit(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    signer_token_account: Pubkey,
+    token_program: Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountDeposit {
+        marginfi_group,
+        marginfi_account,
+        signer,
+        bank,
+        signer_token_account,
+        bank_liquidity_vault: find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountDeposit { amount }.data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_repay(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    signer_token_account: Pubkey,
+    token_program: Pubkey,
+    amount: u64,
+    repay_all: Option<bool>,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountRepay {
+        marginfi_group,
+        marginfi_account,
+        signer,
+        bank,
+        signer_token_account,
+        bank_liquidity_vault: find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountRepay { amount, repay_all }.data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_withdraw(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    destination_token_account: Pubkey,
+    token_program: Pubkey,
+    observation_metas: Vec<AccountMeta>,
+    amount: u64,
+    withdraw_all: Option<bool>,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountWithdraw {
+        marginfi_group,
+        marginfi_account,
+        signer,
+        bank,
+        destination_token_account,
+        bank_liquidity_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        bank_liquidity_vault: find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+    accounts.extend(observation_metas);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountWithdraw {
+            amount,
+            withdraw_all,
+        }
+        .data(),
+    }
+}
+
+/// In a flashloan, `observation_metas` can leave out every balance except the borrowed bank and
+/// its oracles, which are needed while the bank's liability value limit is active.
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_borrow(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+    bank_mint: Pubkey,
+    destination_token_account: Pubkey,
+    token_program: Pubkey,
+    observation_metas: Vec<AccountMeta>,
+    amount: u64,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountBorrow {
+        marginfi_group,
+        marginfi_account,
+        signer,
+        bank,
+        destination_token_account,
+        bank_liquidity_vault_authority: find_bank_vault_authority_pda(
+            &bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        bank_liquidity_vault: find_bank_vault_pda(&bank, BankVaultType::Liquidity, program_id).0,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, bank_mint, &token_program);
+    accounts.extend(observation_metas);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountBorrow { amount }.data(),
+    }
+}
+
+pub fn lending_account_close_balance(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingAccountCloseBalance {
+            marginfi_group,
+            marginfi_account,
+            signer,
+            bank,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingAccountCloseBalance {}.data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_withdraw_emissions(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+    emissions_mint: Pubkey,
+    destination_account: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingAccountWithdrawEmissions {
+            marginfi_group,
+            marginfi_account,
+            signer,
+            bank,
+            emissions_mint,
+            emissions_auth: find_bank_emissions_auth_pda(&bank, &emissions_mint, program_id).0,
+            emissions_vault: find_bank_emissions_token_account_pda(
+                &bank,
+                &emissions_mint,
+                program_id,
+            )
+            .0,
+            destination_account,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingAccountWithdrawEmissions {}.data(),
+    }
+}
+
+pub fn lending_account_settle_emissions(
+    program_id: &Pubkey,
+    marginfi_account: Pubkey,
+    bank: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingAccountSettleEmissions {
+            marginfi_account,
+            bank,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingAccountSettleEmissions {}.data(),
+    }
+}
+
+/// Receipt tokens are minted to `destination_receipt_token_account`, a token account of the
+/// [`find_bank_receipt_mint_pda`] mint.
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_mint_receipt(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+    destination_receipt_token_account: Pubkey,
+    observation_metas: Vec<AccountMeta>,
+    amount: u64,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountMintReceipt {
+        marginfi_group,
+        marginfi_account,
+        signer,
+        bank,
+        receipt_mint: find_bank_receipt_mint_pda(&bank, program_id).0,
+        receipt_mint_authority: find_bank_receipt_mint_authority_pda(&bank, program_id).0,
+        destination_receipt_token_account,
+        token_program: anchor_spl::token::ID,
+    }
+    .to_account_metas(Some(true));
+    accounts.extend(observation_metas);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountMintReceipt { amount }.data(),
+    }
+}
+
+pub fn lending_account_redeem_receipt(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    bank: Pubkey,
+    signer_receipt_token_account: Pubkey,
+    amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingAccountRedeemReceipt {
+            marginfi_group,
+            marginfi_account,
+            signer,
+            bank,
+            receipt_mint: find_bank_receipt_mint_pda(&bank, program_id).0,
+            signer_receipt_token_account,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingAccountRedeemReceipt { amount }.data(),
+    }
+}
+
+/// `remaining_accounts` must hold, in order: the asset and liability bank oracles, the
+/// liquidator's observation accounts (including both banks), and the liquidatee's observation
+/// accounts. The liability bank mint is prepended for Token-2022 liability banks.
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_liquidate(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    asset_bank: Pubkey,
+    liab_bank: Pubkey,
+    liab_bank_mint: Pubkey,
+    liquidator_marginfi_account: Pubkey,
+    signer: Pubkey,
+    liquidatee_marginfi_account: Pubkey,
+    token_program: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    asset_amount: u64,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountLiquidate {
+        marginfi_group,
+        asset_bank,
+        liab_bank,
+        liquidator_marginfi_account,
+        signer,
+        liquidatee_marginfi_account,
+        bank_liquidity_vault_authority: find_bank_vault_authority_pda(
+            &liab_bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        bank_liquidity_vault: find_bank_vault_pda(&liab_bank, BankVaultType::Liquidity, program_id)
+            .0,
+        bank_insurance_vault: find_bank_vault_pda(&liab_bank, BankVaultType::Insurance, program_id)
+            .0,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, liab_bank_mint, &token_program);
+    accounts.extend(remaining_accounts);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountLiquidate { asset_amount }.data(),
+    }
+}
+
+/// `asset_banks` are the (asset bank, its oracle keys, `q_a` seized from it) of each liquidated
+/// collateral. `liquidator_observation_metas` must include the liability and asset banks, and
+/// `liquidatee_observation_metas` come last. The liability bank mint is prepended for Token-2022
+/// liability banks.
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_liquidate_multiple(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    liab_bank: Pubkey,
+    liab_bank_mint: Pubkey,
+    liab_bank_oracle_keys: &[Pubkey],
+    liquidator_marginfi_account: Pubkey,
+    signer: Pubkey,
+    liquidatee_marginfi_account: Pubkey,
+    token_program: Pubkey,
+    asset_banks: Vec<(Pubkey, Vec<Pubkey>, u64)>,
+    liquidator_observation_metas: Vec<AccountMeta>,
+    liquidatee_observation_metas: Vec<AccountMeta>,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountLiquidateMultiple {
+        marginfi_group,
+        liab_bank,
+        liquidator_marginfi_account,
+        signer,
+        liquidatee_marginfi_account,
+        bank_liquidity_vault_authority: find_bank_vault_authority_pda(
+            &liab_bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        bank_liquidity_vault: find_bank_vault_pda(&liab_bank, BankVaultType::Liquidity, program_id)
+            .0,
+        bank_insurance_vault: find_bank_vault_pda(&liab_bank, BankVaultType::Insurance, program_id)
+            .0,
+        token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, liab_bank_mint, &token_program);
+
+    let mut asset_amounts = Vec::with_capacity(asset_banks.len());
+    for (asset_bank, asset_bank_oracle_keys, asset_amount) in asset_banks {
+        accounts.push(AccountMeta::new(asset_bank, false));
+        accounts.extend(
+            asset_bank_oracle_keys
+                .into_iter()
+                .map(|oracle_key| AccountMeta::new_readonly(oracle_key, false)),
+        );
+        asset_amounts.push(asset_amount);
+    }
+    accounts.extend(
+        liab_bank_oracle_keys
+            .iter()
+            .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+    );
+    accounts.extend(liquidator_observation_metas);
+    accounts.extend(liquidatee_observation_metas);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountLiquidateMultiple { asset_amounts }.data(),
+    }
+}
+
+/// Liquidation by a liquidator without a marginfi account, paying the liability from
+/// `signer_liab_token_account` and receiving the collateral in `destination_asset_token_account`.
+///
+/// `remaining_accounts` must hold, in order: the asset and liability bank oracles, and the
+/// liquidatee's observation accounts. The liability and then the asset bank mints are prepended
+/// for Token-2022 banks.
+#[allow(clippy::too_many_arguments)]
+pub fn lending_account_liquidate_with_tokens(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    asset_bank: Pubkey,
+    asset_bank_mint: Pubkey,
+    liab_bank: Pubkey,
+    liab_bank_mint: Pubkey,
+    liquidatee_marginfi_account: Pubkey,
+    signer: Pubkey,
+    signer_liab_token_account: Pubkey,
+    destination_asset_token_account: Pubkey,
+    asset_token_program: Pubkey,
+    liab_token_program: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    asset_amount: u64,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountLiquidateWithTokens {
+        marginfi_group,
+        asset_bank,
+        liab_bank,
+        liquidatee_marginfi_account,
+        signer,
+        signer_liab_token_account,
+        destination_asset_token_account,
+        asset_bank_liquidity_vault_authority: find_bank_vault_authority_pda(
+            &asset_bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        asset_bank_liquidity_vault: find_bank_vault_pda(
+            &asset_bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        liab_bank_liquidity_vault_authority: find_bank_vault_authority_pda(
+            &liab_bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        liab_bank_liquidity_vault: find_bank_vault_pda(
+            &liab_bank,
+            BankVaultType::Liquidity,
+            program_id,
+        )
+        .0,
+        liab_bank_insurance_vault: find_bank_vault_pda(
+            &liab_bank,
+            BankVaultType::Insurance,
+            program_id,
+        )
+        .0,
+        asset_token_program,
+        liab_token_program,
+    }
+    .to_account_metas(Some(true));
+    maybe_add_bank_mint(&mut accounts, liab_bank_mint, &liab_token_program);
+    maybe_add_bank_mint(&mut accounts, asset_bank_mint, &asset_token_program);
+    accounts.extend(remaining_accounts);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountLiquidateWithTokens { asset_amount }.data(),
+    }
+}
+
+/// `end_index` is the index of the matching `lending_account_end_flashloan_v2` instruction in the
+/// transaction.
+pub fn lending_account_start_flashloan(
+    program_id: &Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    end_index: u64,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: marginfi::accounts::LendingAccountStartFlashloan {
+            marginfi_account,
+            signer,
+            ixs_sysvar: sysvar::instructions::id(),
+        }
+        .to_account_metas(Some(true)),
+        data: marginfi::instruction::LendingAccountStartFlashloan { end_index }.data(),
+    }
+}
+
+/// Ends the flashloan with `lending_account_end_flashloan_v2`, which checks the account health
+/// with the group e-mode categories and weight overrides.
+pub fn lending_account_end_flashloan(
+    program_id: &Pubkey,
+    marginfi_group: Pubkey,
+    marginfi_account: Pubkey,
+    signer: Pubkey,
+    observation_metas: Vec<AccountMeta>,
+) -> Instruction {
+    let mut accounts = marginfi::accounts::LendingAccountEndFlashloanV2 {
+        marginfi_account,
+        signer,
+        marginfi_group,
+    }
+    .to_account_metas(Some(true));
+    accounts.extend(observation_metas);
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: marginfi::instruction::LendingAccountEndFlashloanV2 {}.data(),
+    }
+}
@@ -0,0 +1,25 @@
+//! Client-side helpers for the marginfi v2 program.
+//!
+//! - [`instructions`] builds every instruction exposed by the program.
+//! - [`state`] fetches and caches group, bank and marginfi account state, and builds
+//!   instructions from it with remaining accounts in the order the program expects.
+//! - [`health`] computes account health off-chain with the program's risk engine.
+//! - [`simulation`] previews the health of an account after hypothetical actions.
+//! - [`liquidation`] sizes liquidations so they pass the program's checks.
+//! - [`remaining_accounts`] assembles observation accounts for the risk engine.
+//! - [`pda`] derives the program's PDAs.
+
+pub mod error;
+pub mod health;
+pub mod instructions;
+pub mod liquidation;
+pub mod pda;
+pub mod remaining_accounts;
+pub mod simulation;
+pub mod state;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use error::{SdkError, SdkResult};
+pub use state::MarginfiState;
@@ -0,0 +1,533 @@
+//! Liquidation sizing. A liquidation is replayed on copies of both accounts with the program's
+//! balance logic, then checked with the same conditions `lending_account_liquidate` enforces.
+
+use {
+    crate::{
+        error::{SdkError, SdkResult},
+        health::load_risk_engine,
+        simulation::{max_passing_amount, open_balance},
+    },
+    fixed::types::I80F48,
+    marginfi::{
+        prelude::MarginfiError,
+        state::{
+            marginfi_account::{
+                calc_amount, calc_value, BalanceSide, BankAccountWrapper, MarginfiAccount,
+                RiskRequirementType,
+            },
+            marginfi_group::{Bank, MarginfiGroup},
+            price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter, PriceBias},
+        },
+    },
+    solana_sdk::pubkey::Pubkey,
+    std::collections::HashMap,
+};
+
+/// Amounts moved by a liquidation, see `lending_account_liquidate` for the math.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidationAmounts {
+    /// `q_a`: collateral seized from the liquidatee.
+    pub asset_amount: u64,
+    /// `q_ll`: liability taken on by the liquidator.
+    pub liab_amount_liquidator: I80F48,
+    /// `q_lf`: liability repaid for the liquidatee, the difference goes to the insurance fund.
+    pub liab_amount_liquidatee: I80F48,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidationPlan {
+    pub asset_bank_pk: Pubkey,
+    pub liab_bank_pk: Pubkey,
+    pub amounts: LiquidationAmounts,
+    /// Value of the seized collateral minus the value of the liability taken on, at oracle price.
+    pub profit: I80F48,
+}
+
+/// Liability amounts for seizing `asset_amount` of collateral from a liquidatee with maintenance
+/// shortfall ratio `shortfall_ratio`, at the prices the program uses.
+pub fn calc_liquidation_amounts(
+    asset_bank_pk: &Pubkey,
+    asset_bank: &Bank,
+    liab_bank_pk: &Pubkey,
+    liab_bank: &Bank,
+    price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    asset_amount: u64,
+    shortfall_ratio: I80F48,
+) -> SdkResult<LiquidationAmounts> {
+    let asset_price = get_price_feed(asset_bank_pk, price_feeds)?
+        .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))?;
+    let liab_price = get_price_feed(liab_bank_pk, price_feeds)?
+        .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))?;
+
+    let liquidator_fee = asset_bank.get_liquidation_liquidator_fee(shortfall_ratio);
+    let insurance_fee = asset_bank.get_liquidation_insurance_fee();
+
+    let liab_amount = |discount: I80F48| -> SdkResult<I80F48> {
+        Ok(calc_amount(
+            calc_value(
+                I80F48::from_num(asset_amount),
+                asset_price,
+                asset_bank.mint_decimals,
+                Some(discount),
+            )?,
+            liab_price,
+            liab_bank.mint_decimals,
+        )?)
+    };
+
+    Ok(LiquidationAmounts {
+        asset_amount,
+        liab_amount_liquidator: liab_amount(I80F48::ONE - liquidator_fee)?,
+        liab_amount_liquidatee: liab_amount(I80F48::ONE - (liquidator_fee + insurance_fee))?,
+    })
+}
+
+/// Replay `lending_account_liquidate` off-chain and run the program's pre and post liquidation
+/// checks. The liquidator's initial health check is skipped when `liquidator_in_flashloan` is
+/// set, like on-chain.
+#[allow(clippy::too_many_arguments)]
+pub fn check_liquidation(
+    marginfi_group: &MarginfiGroup,
+    liquidatee: &MarginfiAccount,
+    liquidator: &MarginfiAccount,
+    liquidator_in_flashloan: bool,
+    banks: &HashMap<Pubkey, Bank>,
+    price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    asset_bank_pk: &Pubkey,
+    liab_bank_pk: &Pubkey,
+    asset_amount: u64,
+    current_timestamp: i64,
+) -> SdkResult<LiquidationAmounts> {
+    if asset_amount == 0 || asset_bank_pk == liab_bank_pk {
+        return Err(MarginfiError::IllegalLiquidation.into());
+    }
+
+    let current_timestamp = current_timestamp as u64;
+    let get_bank = |bank_pk: &Pubkey| {
+        banks
+            .get(bank_pk)
+            .copied()
+            .ok_or(SdkError::BankNotLoaded(*bank_pk))
+    };
+    let (mut asset_bank, mut liab_bank) = (get_bank(asset_bank_pk)?, get_bank(liab_bank_pk)?);
+
+    let risk_engine = load_risk_engine(marginfi_group, liquidatee, banks, price_feeds)?;
+    let pre_liquidation_health =
+        risk_engine.check_pre_liquidation_condition_and_get_account_health(liab_bank_pk)?;
+    let shortfall_ratio = risk_engine.get_maintenance_shortfall_ratio()?;
+
+    let amounts = calc_liquidation_amounts(
+        asset_bank_pk,
+        &asset_bank,
+        liab_bank_pk,
+        &liab_bank,
+        price_feeds,
+        asset_amount,
+        shortfall_ratio,
+    )?;
+
+    let (mut liquidatee, mut liquidator) = (*liquidatee, *liquidator);
+
+    // Liquidator pays off liability
+    open_balance(
+        &mut liquidator.lending_account,
+        liab_bank_pk,
+        current_timestamp,
+    )?;
+    BankAccountWrapper::find(
+        liab_bank_pk,
+        &mut liab_bank,
+        &mut liquidator.lending_account,
+    )?
+    .decrease_balance_in_liquidation_at(amounts.liab_amount_liquidator, current_timestamp)?;
+
+    // Liquidatee pays off `asset_amount` of collateral
+    BankAccountWrapper::find(
+        asset_bank_pk,
+        &mut asset_bank,
+        &mut liquidatee.lending_account,
+    )?
+    .withdraw_at(I80F48::from_num(asset_amount), current_timestamp)
+    .map_err(|_| MarginfiError::IllegalLiquidation)?;
+
+    // Liquidator receives `asset_amount` of collateral
+    open_balance(
+        &mut liquidator.lending_account,
+        asset_bank_pk,
+        current_timestamp,
+    )?;
+    BankAccountWrapper::find(
+        asset_bank_pk,
+        &mut asset_bank,
+        &mut liquidator.lending_account,
+    )?
+    .increase_balance_in_liquidation_at(I80F48::from_num(asset_amount), current_timestamp)?;
+
+    // Liquidatee receives liability payment
+    open_balance(
+        &mut liquidatee.lending_account,
+        liab_bank_pk,
+        current_timestamp,
+    )?;
+    let mut liquidatee_liab_account = BankAccountWrapper::find(
+        liab_bank_pk,
+        &mut liab_bank,
+        &mut liquidatee.lending_account,
+    )?;
+    let liquidatee_liability = liquidatee_liab_account
+        .bank
+        .get_liability_amount(liquidatee_liab_account.balance.liability_shares.into())?;
+    liquidatee_liab_account
+        .bank
+        .check_liquidation_close_factor(amounts.liab_amount_liquidatee, liquidatee_liability)?;
+    liquidatee_liab_account.deposit_at(amounts.liab_amount_liquidatee, current_timestamp)?;
+
+    let mut post_banks = HashMap::from([(*asset_bank_pk, asset_bank), (*liab_bank_pk, liab_bank)]);
+    for balance in liquidatee
+        .lending_account
+        .get_active_balances_iter()
+        .chain(liquidator.lending_account.get_active_balances_iter())
+    {
+        if !post_banks.contains_key(&balance.bank_pk) {
+            post_banks.insert(balance.bank_pk, get_bank(&balance.bank_pk)?);
+        }
+    }
+
+    load_risk_engine(marginfi_group, &liquidatee, &post_banks, price_feeds)?
+        .check_post_liquidation_condition_and_get_account_health(
+            liab_bank_pk,
+            pre_liquidation_health,
+        )?;
+
+    if !liquidator_in_flashloan {
+        load_risk_engine(marginfi_group, &liquidator, &post_banks, price_feeds)?
+            .check_account_health(RiskRequirementType::Initial)?;
+    }
+
+    Ok(amounts)
+}
+
+/// Most profitable liquidation of `liquidatee`, seizing as much collateral as the program allows
+/// for each asset / liability pair. `None` if the account can't be liquidated.
+pub fn find_best_liquidation(
+    marginfi_group: &MarginfiGroup,
+    liquidatee: &MarginfiAccount,
+    liquidator: &MarginfiAccount,
+    liquidator_in_flashloan: bool,
+    banks: &HashMap<Pubkey, Bank>,
+    price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    current_timestamp: i64,
+) -> SdkResult<Option<LiquidationPlan>> {
+    let balances = liquidatee
+        .lending_account
+        .get_active_balances_iter()
+        .collect::<Vec<_>>();
+
+    let mut best_plan: Option<LiquidationPlan> = None;
+
+    for asset_balance in balances
+        .iter()
+        .filter(|balance| !balance.is_empty(BalanceSide::Assets))
+    {
+        let asset_bank = banks
+            .get(&asset_balance.bank_pk)
+            .ok_or(SdkError::BankNotLoaded(asset_balance.bank_pk))?;
+        let asset_amount = asset_bank
+            .get_asset_amount(asset_balance.asset_shares.into())?
+            .checked_to_num::<u64>()
+            .unwrap_or(u64::MAX);
+
+        for liab_balance in balances
+            .iter()
+            .filter(|balance| !balance.is_empty(BalanceSide::Liabilities))
+        {
+            let check = |amount: u64| {
+                check_liquidation(
+                    marginfi_group,
+                    liquidatee,
+                    liquidator,
+                    liquidator_in_flashloan,
+                    banks,
+                    price_feeds,
+                    &asset_balance.bank_pk,
+                    &liab_balance.bank_pk,
+                    amount,
+                    current_timestamp,
+                )
+            };
+
+            let max_asset_amount = max_passing_amount(asset_amount, |amount| check(amount).is_ok());
+            if max_asset_amount == 0 {
+                continue;
+            }
+
+            let amounts = check(max_asset_amount)?;
+            let profit = calc_liquidation_profit(
+                &asset_balance.bank_pk,
+                asset_bank,
+                &liab_balance.bank_pk,
+                &banks[&liab_balance.bank_pk],
+                price_feeds,
+                &amounts,
+            )?;
+
+            if best_plan.map_or(true, |plan| profit > plan.profit) {
+                best_plan = Some(LiquidationPlan {
+                    asset_bank_pk: asset_balance.bank_pk,
+                    liab_bank_pk: liab_balance.bank_pk,
+                    amounts,
+                    profit,
+                });
+            }
+        }
+    }
+
+    Ok(best_plan)
+}
+
+/// Value of the seized collateral minus the value of the liability taken on, at oracle price.
+pub fn calc_liquidation_profit(
+    asset_bank_pk: &Pubkey,
+    asset_bank: &Bank,
+    liab_bank_pk: &Pubkey,
+    liab_bank: &Bank,
+    price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    amounts: &LiquidationAmounts,
+) -> SdkResult<I80F48> {
+    let asset_price = get_price_feed(asset_bank_pk, price_feeds)?
+        .get_price_of_type(OraclePriceType::RealTime, None)?;
+    let liab_price = get_price_feed(liab_bank_pk, price_feeds)?
+        .get_price_of_type(OraclePriceType::RealTime, None)?;
+
+    let asset_value = calc_value(
+        I80F48::from_num(amounts.asset_amount),
+        asset_price,
+        asset_bank.mint_decimals,
+        None,
+    )?;
+    let liab_value = calc_value(
+        amounts.liab_amount_liquidator,
+        liab_price,
+        liab_bank.mint_decimals,
+        None,
+    )?;
+
+    Ok(asset_value - liab_value)
+}
+
+fn get_price_feed<'a>(
+    bank_pk: &Pubkey,
+    price_feeds: &'a HashMap<Pubkey, OraclePriceFeedAdapter>,
+) -> SdkResult<&'a OraclePriceFeedAdapter> {
+    price_feeds
+        .get(bank_pk)
+        .ok_or_else(|| MarginfiError::StaleOracle.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            health::compute_account_health,
+            test_utils::{bank, price_feed},
+        },
+        fixed_macro::types::I80F48,
+        marginfi::state::marginfi_account::Balance,
+    };
+
+    /// Liquidatee with 100 SOL ($10, maint weight 0.5) of collateral and 600 USDC of debt, and a
+    /// liquidator with 2000 USDC of collateral.
+    fn setup() -> (
+        Pubkey,
+        Pubkey,
+        MarginfiAccount,
+        MarginfiAccount,
+        HashMap<Pubkey, Bank>,
+        HashMap<Pubkey, OraclePriceFeedAdapter>,
+    ) {
+        let (usdc_pk, sol_pk) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut usdc_bank = bank(6);
+        usdc_bank.config.asset_weight_init = I80F48::ONE.into();
+        usdc_bank.config.asset_weight_maint = I80F48::ONE.into();
+        usdc_bank.config.liability_weight_init = I80F48::ONE.into();
+        usdc_bank.config.liability_weight_maint = I80F48::ONE.into();
+        usdc_bank.total_asset_shares = I80F48!(2_000_000_000).into();
+        usdc_bank.total_liability_shares = I80F48!(600_000_000).into();
+
+        let mut sol_bank = bank(9);
+        sol_bank.config.asset_weight_init = I80F48!(0.25).into();
+        sol_bank.config.asset_weight_maint = I80F48!(0.5).into();
+        sol_bank.total_asset_shares = I80F48!(100_000_000_000).into();
+
+        let mut liquidatee: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        liquidatee.lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk: sol_pk,
+            asset_shares: I80F48!(100_000_000_000).into(),
+            ..Balance::empty_deactivated()
+        };
+        liquidatee.lending_account.balances[1] = Balance {
+            active: true,
+            bank_pk: usdc_pk,
+            liability_shares: I80F48!(600_000_000).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        let mut liquidator: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        liquidator.lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk: usdc_pk,
+            asset_shares: I80F48!(2_000_000_000).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        (
+            usdc_pk,
+            sol_pk,
+            liquidatee,
+            liquidator,
+            HashMap::from([(usdc_pk, usdc_bank), (sol_pk, sol_bank)]),
+            HashMap::from([(usdc_pk, price_feed(1)), (sol_pk, price_feed(10))]),
+        )
+    }
+
+    #[test]
+    fn liquidation_amounts_match_program_fees() {
+        let (usdc_pk, sol_pk, liquidatee, liquidator, banks, price_feeds) = setup();
+
+        let amounts = check_liquidation(
+            &MarginfiGroup::default(),
+            &liquidatee,
+            &liquidator,
+            false,
+            &banks,
+            &price_feeds,
+            &sol_pk,
+            &usdc_pk,
+            1_000_000_000,
+            0,
+        )
+        .unwrap();
+
+        // $10 of SOL, discounted by the 2.5% liquidator and insurance fees.
+        assert_eq!(amounts.liab_amount_liquidator.round(), I80F48!(9_750_000));
+        assert_eq!(amounts.liab_amount_liquidatee.round(), I80F48!(9_500_000));
+    }
+
+    #[test]
+    fn best_liquidation_leaves_liquidatee_at_most_at_maintenance() {
+        let (usdc_pk, sol_pk, liquidatee, liquidator, banks, price_feeds) = setup();
+
+        let plan = find_best_liquidation(
+            &MarginfiGroup::default(),
+            &liquidatee,
+            &liquidator,
+            false,
+            &banks,
+            &price_feeds,
+            0,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(plan.asset_bank_pk, sol_pk);
+        assert_eq!(plan.liab_bank_pk, usdc_pk);
+        assert!(plan.profit > I80F48::ZERO);
+
+        // Health is -100 and each SOL seized improves it by 4.5 (9.5 repaid, 5 of collateral).
+        assert_eq!(plan.amounts.asset_amount / 1_000_000_000, 22);
+
+        // One more unit of collateral would push the liquidatee above maintenance.
+        assert!(check_liquidation(
+            &MarginfiGroup::default(),
+            &liquidatee,
+            &liquidator,
+            false,
+            &banks,
+            &price_feeds,
+            &sol_pk,
+            &usdc_pk,
+            plan.amounts.asset_amount + 1,
+            0,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn liquidator_fee_grows_with_shortfall_up_to_max() {
+        let (usdc_pk, sol_pk, liquidatee, liquidator, mut banks, price_feeds) = setup();
+        banks
+            .get_mut(&sol_pk)
+            .unwrap()
+            .liquidation_max_liquidator_fee = I80F48!(0.1).into();
+
+        let amounts = check_liquidation(
+            &MarginfiGroup::default(),
+            &liquidatee,
+            &liquidator,
+            false,
+            &banks,
+            &price_feeds,
+            &sol_pk,
+            &usdc_pk,
+            1_000_000_000,
+            0,
+        )
+        .unwrap();
+
+        // $500 of weighted collateral against $600 of debt is a 1/6 shortfall, over the 7.5%
+        // headroom, the liquidator fee is capped at 10%.
+        assert_eq!(amounts.liab_amount_liquidator.round(), I80F48!(9_000_000));
+        assert_eq!(amounts.liab_amount_liquidatee.round(), I80F48!(8_750_000));
+    }
+
+    #[test]
+    fn liquidation_is_capped_by_close_factor() {
+        let (usdc_pk, sol_pk, liquidatee, liquidator, mut banks, price_feeds) = setup();
+        banks.get_mut(&usdc_pk).unwrap().liquidation_close_factor = I80F48!(0.1).into();
+
+        let plan = find_best_liquidation(
+            &MarginfiGroup::default(),
+            &liquidatee,
+            &liquidator,
+            false,
+            &banks,
+            &price_feeds,
+            0,
+        )
+        .unwrap()
+        .unwrap();
+
+        // At most 60 USDC of the 600 USDC debt is repaid, 9.5 USDC per SOL seized.
+        assert!(plan.amounts.liab_amount_liquidatee <= I80F48!(60_000_000));
+        assert_eq!(plan.amounts.asset_amount / 1_000_000_000, 6);
+    }
+
+    #[test]
+    fn healthy_account_is_not_liquidated() {
+        let (_, _, mut liquidatee, liquidator, banks, price_feeds) = setup();
+        liquidatee.lending_account.balances[1].liability_shares = I80F48!(100_000_000).into();
+
+        assert!(!compute_account_health(
+            &MarginfiGroup::default(),
+            &liquidatee,
+            &banks,
+            &price_feeds
+        )
+        .unwrap()
+        .is_liquidatable());
+        assert!(find_best_liquidation(
+            &MarginfiGroup::default(),
+            &liquidatee,
+            &liquidator,
+            false,
+            &banks,
+            &price_feeds,
+            0
+        )
+        .unwrap()
+        .is_none());
+    }
+}
@@ -0,0 +1,89 @@
+use {
+    marginfi::{
+        bank_authority_seed, bank_seed,
+        constants::{
+            EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED, RECEIPT_MINT_AUTHORITY_SEED,
+            RECEIPT_MINT_SEED,
+        },
+        state::marginfi_group::BankVaultType,
+    },
+    solana_sdk::pubkey::Pubkey,
+};
+
+pub fn find_bank_vault_pda(
+    bank_pk: &Pubkey,
+    vault_type: BankVaultType,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(bank_seed!(vault_type, bank_pk), program_id)
+}
+
+pub fn find_bank_vault_authority_pda(
+    bank_pk: &Pubkey,
+    vault_type: BankVaultType,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(bank_authority_seed!(vault_type, bank_pk), program_id)
+}
+
+pub fn find_bank_emissions_auth_pda(
+    bank_pk: &Pubkey,
+    emissions_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            EMISSIONS_AUTH_SEED.as_bytes(),
+            bank_pk.as_ref(),
+            emissions_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+pub fn find_bank_emissions_token_account_pda(
+    bank_pk: &Pubkey,
+    emissions_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
+            bank_pk.as_ref(),
+            emissions_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// Bank receipt mint created by `lending_pool_setup_receipt_mint`.
+pub fn find_bank_receipt_mint_pda(bank_pk: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[RECEIPT_MINT_SEED.as_bytes(), bank_pk.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_bank_receipt_mint_authority_pda(bank_pk: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[RECEIPT_MINT_AUTHORITY_SEED.as_bytes(), bank_pk.as_ref()],
+        program_id,
+    )
+}
+
+/// Bank address used by `lending_pool_add_bank_with_seed`.
+pub fn find_bank_with_seed_pda(
+    marginfi_group: &Pubkey,
+    bank_mint: &Pubkey,
+    bank_seed: u64,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi_group.as_ref(),
+            bank_mint.as_ref(),
+            &bank_seed.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
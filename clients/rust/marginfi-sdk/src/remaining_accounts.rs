@@ -0,0 +1,103 @@
+use {
+    crate::error::{SdkError, SdkResult},
+    marginfi::state::{marginfi_account::MarginfiAccount, marginfi_group::Bank},
+    solana_sdk::{instruction::AccountMeta, pubkey::Pubkey},
+    std::collections::HashMap,
+};
+
+/// Banks observed by the risk engine for `marginfi_account`: its active balances, plus
+/// `include_banks` not already present, minus `exclude_banks`.
+///
+/// Balances that are about to be opened (e.g. first borrow from a bank) must be passed in
+/// `include_banks`, and balances that are about to be closed (e.g. withdraw all) in `exclude_banks`.
+pub fn observed_bank_pks(
+    marginfi_account: &MarginfiAccount,
+    include_banks: &[Pubkey],
+    exclude_banks: &[Pubkey],
+) -> Vec<Pubkey> {
+    let mut bank_pks = marginfi_account
+        .lending_account
+        .balances
+        .iter()
+        .filter_map(|balance| balance.active.then_some(balance.bank_pk))
+        .collect::<Vec<_>>();
+
+    for bank_pk in include_banks {
+        if !bank_pks.contains(bank_pk) {
+            bank_pks.push(*bank_pk);
+        }
+    }
+
+    bank_pks.retain(|bank_pk| !exclude_banks.contains(bank_pk));
+
+    bank_pks
+}
+
+/// Observation accounts in the order `RiskEngine` expects them: each observed bank followed by
+/// its oracles, in balance order.
+pub fn load_observation_account_metas(
+    marginfi_account: &MarginfiAccount,
+    banks: &HashMap<Pubkey, Bank>,
+    include_banks: &[Pubkey],
+    exclude_banks: &[Pubkey],
+) -> SdkResult<Vec<AccountMeta>> {
+    let mut account_metas = vec![];
+
+    for bank_pk in observed_bank_pks(marginfi_account, include_banks, exclude_banks) {
+        let bank = banks
+            .get(&bank_pk)
+            .ok_or(SdkError::BankNotLoaded(bank_pk))?;
+
+        account_metas.push(AccountMeta::new_readonly(bank_pk, false));
+        account_metas.extend(
+            bank.config
+                .get_oracle_keys()
+                .iter()
+                .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+        );
+    }
+
+    Ok(account_metas)
+}
+
+/// Token-2022 banks expect the bank mint as the first remaining account.
+pub fn maybe_add_bank_mint(
+    accounts: &mut Vec<AccountMeta>,
+    bank_mint: Pubkey,
+    token_program: &Pubkey,
+) {
+    if token_program == &anchor_spl::token_2022::ID {
+        accounts.push(AccountMeta::new_readonly(bank_mint, false));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn observed_banks_keep_balance_order() {
+        let banks = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let new_bank = Pubkey::new_unique();
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        for (balance, bank_pk) in marginfi_account
+            .lending_account
+            .balances
+            .iter_mut()
+            .zip(banks)
+        {
+            balance.active = true;
+            balance.bank_pk = bank_pk;
+        }
+
+        assert_eq!(
+            observed_bank_pks(&marginfi_account, &[banks[1], new_bank], &[banks[0]]),
+            vec![banks[1], banks[2], new_bank]
+        );
+    }
+}
@@ -0,0 +1,569 @@
+//! "What-if" previews: hypothetical actions are applied to a copy of a marginfi account and its
+//! banks with the program's own balance logic, then the copy is run through the risk engine.
+
+use {
+    crate::{
+        error::{SdkError, SdkResult},
+        health::{compute_account_health, load_risk_engine, AccountHealth},
+    },
+    fixed::types::I80F48,
+    marginfi::{
+        prelude::MarginfiError,
+        state::{
+            marginfi_account::{
+                Balance, BankAccountWrapper, LendingAccount, MarginfiAccount, RequirementType,
+                RiskRequirementType,
+            },
+            marginfi_group::{Bank, MarginfiGroup},
+            price::{OraclePriceFeedAdapter, PriceAdapter, PriceBias},
+        },
+    },
+    solana_sdk::pubkey::Pubkey,
+    std::collections::HashMap,
+};
+
+/// Hypothetical balance change, amounts in native token units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Deposit { bank_pk: Pubkey, amount: u64 },
+    Repay { bank_pk: Pubkey, amount: u64 },
+    Withdraw { bank_pk: Pubkey, amount: u64 },
+    Borrow { bank_pk: Pubkey, amount: u64 },
+}
+
+impl Action {
+    pub fn bank_pk(&self) -> Pubkey {
+        match self {
+            Action::Deposit { bank_pk, .. }
+            | Action::Repay { bank_pk, .. }
+            | Action::Withdraw { bank_pk, .. }
+            | Action::Borrow { bank_pk, .. } => *bank_pk,
+        }
+    }
+}
+
+/// Largest amounts that would still pass the initial health check and the bank limits. Vault
+/// liquidity is not accounted for, see [`crate::MarginfiState::max_borrow`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BankLimits {
+    pub bank_pk: Pubkey,
+    pub max_borrow: u64,
+    pub max_withdraw: u64,
+}
+
+#[derive(Debug)]
+pub struct SimulationReport {
+    pub before: AccountHealth,
+    pub after: AccountHealth,
+    /// Error `check_account_risk_tiers` raises for the resulting balances, if any.
+    pub risk_tier_violation: Option<SdkError>,
+    /// Limits after the actions, for every bank with a loaded price feed, sorted by bank.
+    pub limits: Vec<BankLimits>,
+}
+
+/// Copy of a marginfi account and of the banks it touches.
+#[derive(Clone)]
+pub struct SimulatedAccount<'a> {
+    marginfi_group: &'a MarginfiGroup,
+    pub marginfi_account: MarginfiAccount,
+    /// Copies of the banks of the account's balances, updated by the applied actions.
+    pub banks: HashMap<Pubkey, Bank>,
+    group_banks: &'a HashMap<Pubkey, Bank>,
+    current_timestamp: u64,
+}
+
+impl<'a> SimulatedAccount<'a> {
+    /// `current_timestamp` is used to settle emissions, like the clock sysvar on-chain.
+    pub fn new(
+        marginfi_group: &'a MarginfiGroup,
+        marginfi_account: &MarginfiAccount,
+        group_banks: &'a HashMap<Pubkey, Bank>,
+        current_timestamp: i64,
+    ) -> SdkResult<Self> {
+        let banks = marginfi_account
+            .lending_account
+            .get_active_balances_iter()
+            .map(|balance| {
+                group_banks
+                    .get(&balance.bank_pk)
+                    .map(|bank| (balance.bank_pk, *bank))
+                    .ok_or(SdkError::BankNotLoaded(balance.bank_pk))
+            })
+            .collect::<SdkResult<_>>()?;
+
+        Ok(Self {
+            marginfi_group,
+            marginfi_account: *marginfi_account,
+            banks,
+            group_banks,
+            current_timestamp: current_timestamp as u64,
+        })
+    }
+
+    /// Apply `action` the way the matching instruction would, before its health check.
+    pub fn apply(&mut self, action: &Action) -> SdkResult<()> {
+        let bank_pk = action.bank_pk();
+        let mut bank = match self.banks.get(&bank_pk) {
+            Some(bank) => *bank,
+            None => *self
+                .group_banks
+                .get(&bank_pk)
+                .ok_or(SdkError::BankNotLoaded(bank_pk))?,
+        };
+
+        let lending_account = &mut self.marginfi_account.lending_account;
+
+        // Deposits and borrows open a balance if needed.
+        if matches!(action, Action::Deposit { .. } | Action::Borrow { .. }) {
+            open_balance(lending_account, &bank_pk, self.current_timestamp)?;
+        }
+
+        let mut bank_account = BankAccountWrapper::find(&bank_pk, &mut bank, lending_account)?;
+
+        match *action {
+            Action::Deposit { amount, .. } => {
+                bank_account.deposit_at(I80F48::from_num(amount), self.current_timestamp)?
+            }
+            Action::Repay { amount, .. } => {
+                bank_account.repay_at(I80F48::from_num(amount), self.current_timestamp)?
+            }
+            Action::Withdraw { amount, .. } => {
+                bank_account.withdraw_at(I80F48::from_num(amount), self.current_timestamp)?
+            }
+            Action::Borrow { amount, .. } => {
+                bank_account.borrow_at(I80F48::from_num(amount), self.current_timestamp)?
+            }
+        }
+
+        self.banks.insert(bank_pk, bank);
+
+        Ok(())
+    }
+
+    pub fn health(
+        &self,
+        price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    ) -> SdkResult<AccountHealth> {
+        compute_account_health(
+            self.marginfi_group,
+            &self.marginfi_account,
+            &self.banks,
+            price_feeds,
+        )
+    }
+
+    /// Same check as `RiskEngine::check_account_init_health`: initial health and risk tiers.
+    pub fn check_init_health(
+        &self,
+        price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    ) -> SdkResult<()> {
+        load_risk_engine(
+            self.marginfi_group,
+            &self.marginfi_account,
+            &self.banks,
+            price_feeds,
+        )?
+        .check_account_health(RiskRequirementType::Initial)?;
+
+        Ok(())
+    }
+
+    pub fn check_risk_tiers(
+        &self,
+        price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    ) -> SdkResult<()> {
+        load_risk_engine(
+            self.marginfi_group,
+            &self.marginfi_account,
+            &self.banks,
+            price_feeds,
+        )?
+        .check_account_risk_tiers()?;
+
+        Ok(())
+    }
+
+    /// Largest amount of the bank's assets held by the account that can be withdrawn.
+    pub fn max_withdraw(
+        &self,
+        bank_pk: &Pubkey,
+        price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    ) -> SdkResult<u64> {
+        let Some(balance) = self.marginfi_account.lending_account.get_balance(bank_pk) else {
+            return Ok(0);
+        };
+
+        let asset_amount = self.banks[bank_pk]
+            .get_asset_amount(balance.asset_shares.into())?
+            .checked_to_num::<u64>()
+            .unwrap_or(u64::MAX);
+
+        Ok(
+            self.max_passing(asset_amount, price_feeds, |amount| Action::Withdraw {
+                bank_pk: *bank_pk,
+                amount,
+            }),
+        )
+    }
+
+    /// Largest amount of the bank's token that can be borrowed, withdrawing any deposit in the
+    /// bank first.
+    pub fn max_borrow(
+        &self,
+        bank_pk: &Pubkey,
+        price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    ) -> SdkResult<u64> {
+        let bank = match self.banks.get(bank_pk) {
+            Some(bank) => bank,
+            None => self
+                .group_banks
+                .get(bank_pk)
+                .ok_or(SdkError::BankNotLoaded(*bank_pk))?,
+        };
+
+        // Liabilities can't exceed the assets of the bank, see `Bank::check_utilization_ratio`.
+        let total_assets = bank
+            .get_asset_amount(bank.total_asset_shares.into())?
+            .checked_to_num::<u64>()
+            .unwrap_or(u64::MAX);
+
+        // Priced like the borrow instruction does, see `Bank::check_total_liability_value_limit`.
+        let liability_value_limit_price = match price_feeds.get(bank_pk) {
+            Some(price_feed) if bank.liability_value_limit_active() => {
+                Some(price_feed.get_price_of_type(
+                    RequirementType::Initial.get_oracle_price_type(),
+                    Some(PriceBias::High),
+                )?)
+            }
+            _ => None,
+        };
+
+        Ok(max_passing_amount(total_assets, |amount| {
+            let mut simulated = self.clone();
+            simulated
+                .apply(&Action::Borrow {
+                    bank_pk: *bank_pk,
+                    amount,
+                })
+                .is_ok()
+                && liability_value_limit_price.map_or(true, |price| {
+                    simulated.banks[bank_pk]
+                        .check_total_liability_value_limit(price)
+                        .is_ok()
+                })
+                && simulated.check_init_health(price_feeds).is_ok()
+        }))
+    }
+
+    /// Largest amount up to `upper_bound` for which `action` applies and the account passes the
+    /// initial health check.
+    fn max_passing(
+        &self,
+        upper_bound: u64,
+        price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+        action: impl Fn(u64) -> Action,
+    ) -> u64 {
+        max_passing_amount(upper_bound, |amount| {
+            let mut simulated = self.clone();
+            simulated.apply(&action(amount)).is_ok()
+                && simulated.check_init_health(price_feeds).is_ok()
+        })
+    }
+}
+
+/// Activate a balance for `bank_pk` if the account has none, like
+/// `BankAccountWrapper::find_or_create` does on-chain.
+pub(crate) fn open_balance(
+    lending_account: &mut LendingAccount,
+    bank_pk: &Pubkey,
+    current_timestamp: u64,
+) -> SdkResult<()> {
+    if lending_account.get_balance(bank_pk).is_some() {
+        return Ok(());
+    }
+
+    let empty_index = lending_account
+        .get_first_empty_balance()
+        .ok_or(MarginfiError::LendingAccountBalanceSlotsFull)?;
+
+    lending_account.balances[empty_index] = Balance {
+        active: true,
+        bank_pk: *bank_pk,
+        last_update: current_timestamp,
+        ..Balance::empty_deactivated()
+    };
+
+    Ok(())
+}
+
+/// Binary search for the largest amount up to `upper_bound` that `passes`, which is expected to
+/// hold up to some amount and fail above it. Returns zero if no amount passes.
+pub(crate) fn max_passing_amount(upper_bound: u64, passes: impl Fn(u64) -> bool) -> u64 {
+    if passes(upper_bound) {
+        return upper_bound;
+    }
+
+    // `low` passes (or is zero), `high` fails.
+    let (mut low, mut high) = (0, upper_bound);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if passes(mid) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    low
+}
+
+/// Apply `actions` in order to a copy of `marginfi_account`, and report its health before and
+/// after, along with how much could still be borrowed or withdrawn from each bank.
+///
+/// Fails if any action would be rejected by the program before its health check, e.g. repaying
+/// more than is owed or exceeding a deposit limit.
+pub fn simulate_actions(
+    marginfi_group: &MarginfiGroup,
+    marginfi_account: &MarginfiAccount,
+    banks: &HashMap<Pubkey, Bank>,
+    price_feeds: &HashMap<Pubkey, OraclePriceFeedAdapter>,
+    actions: &[Action],
+    current_timestamp: i64,
+) -> SdkResult<SimulationReport> {
+    let before = compute_account_health(marginfi_group, marginfi_account, banks, price_feeds)?;
+
+    let mut simulated =
+        SimulatedAccount::new(marginfi_group, marginfi_account, banks, current_timestamp)?;
+    for action in actions {
+        simulated.apply(action)?;
+    }
+
+    let after = simulated.health(price_feeds)?;
+    let risk_tier_violation = simulated.check_risk_tiers(price_feeds).err();
+
+    let mut bank_pks = banks
+        .keys()
+        .filter(|bank_pk| price_feeds.contains_key(bank_pk))
+        .copied()
+        .collect::<Vec<_>>();
+    bank_pks.sort();
+
+    let limits = bank_pks
+        .into_iter()
+        .map(|bank_pk| {
+            Ok(BankLimits {
+                bank_pk,
+                max_borrow: simulated.max_borrow(&bank_pk, price_feeds)?,
+                max_withdraw: simulated.max_withdraw(&bank_pk, price_feeds)?,
+            })
+        })
+        .collect::<SdkResult<_>>()?;
+
+    Ok(SimulationReport {
+        before,
+        after,
+        risk_tier_violation,
+        limits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test_utils::{bank, price_feed},
+        fixed_macro::types::I80F48,
+        marginfi::state::marginfi_group::RiskTier,
+    };
+
+    struct Setup {
+        usdc_pk: Pubkey,
+        sol_pk: Pubkey,
+        banks: HashMap<Pubkey, Bank>,
+        price_feeds: HashMap<Pubkey, OraclePriceFeedAdapter>,
+    }
+
+    fn setup() -> Setup {
+        let (usdc_pk, sol_pk) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let mut sol_bank = bank(9);
+        sol_bank.total_asset_shares = I80F48!(1_000_000_000_000).into();
+
+        Setup {
+            usdc_pk,
+            sol_pk,
+            banks: HashMap::from([(usdc_pk, bank(6)), (sol_pk, sol_bank)]),
+            price_feeds: HashMap::from([(usdc_pk, price_feed(1)), (sol_pk, price_feed(10))]),
+        }
+    }
+
+    #[test]
+    fn simulate_deposit_and_borrow() {
+        let Setup {
+            usdc_pk,
+            sol_pk,
+            banks,
+            price_feeds,
+        } = setup();
+        let marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+
+        let report = simulate_actions(
+            &MarginfiGroup::default(),
+            &marginfi_account,
+            &banks,
+            &price_feeds,
+            &[
+                Action::Deposit {
+                    bank_pk: usdc_pk,
+                    amount: 100_000_000,
+                },
+                Action::Borrow {
+                    bank_pk: sol_pk,
+                    amount: 5_000_000_000,
+                },
+            ],
+            0,
+        )
+        .unwrap();
+
+        assert!(report.before.balances.is_empty());
+        assert_eq!(report.after.init.assets, I80F48!(75));
+        assert_eq!(report.after.init.liabilities, I80F48!(62.5));
+        assert!(report.risk_tier_violation.is_none());
+
+        // 12.5 of free collateral: 1 SOL at 10 * 1.25, or 16.66 USDC at 1 * 0.75.
+        let sol_limits = report.limits.iter().find(|l| l.bank_pk == sol_pk).unwrap();
+        assert_eq!(sol_limits.max_borrow, 1_000_000_000);
+        assert_eq!(sol_limits.max_withdraw, 0);
+
+        let usdc_limits = report.limits.iter().find(|l| l.bank_pk == usdc_pk).unwrap();
+        assert_eq!(usdc_limits.max_withdraw, 16_666_666);
+
+        // The original account is left untouched.
+        assert!(marginfi_account
+            .lending_account
+            .get_active_balances_iter()
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn simulate_reports_isolated_tier_violation() {
+        let Setup {
+            usdc_pk,
+            sol_pk,
+            mut banks,
+            price_feeds,
+        } = setup();
+        banks.get_mut(&sol_pk).unwrap().config.risk_tier = RiskTier::Isolated;
+        banks.get_mut(&usdc_pk).unwrap().total_asset_shares = I80F48!(1_000_000_000).into();
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        marginfi_account.lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk: usdc_pk,
+            asset_shares: I80F48!(100_000_000).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        let report = simulate_actions(
+            &MarginfiGroup::default(),
+            &marginfi_account,
+            &banks,
+            &price_feeds,
+            &[
+                Action::Borrow {
+                    bank_pk: sol_pk,
+                    amount: 1_000_000_000,
+                },
+                Action::Borrow {
+                    bank_pk: usdc_pk,
+                    amount: 110_000_000,
+                },
+            ],
+            0,
+        )
+        .unwrap();
+
+        assert!(report.risk_tier_violation.is_some());
+        assert!(report.limits.iter().all(|l| l.max_borrow == 0));
+    }
+
+    #[test]
+    fn max_borrow_respects_borrow_limit_and_isolated_tier() {
+        let Setup {
+            usdc_pk,
+            sol_pk,
+            mut banks,
+            mut price_feeds,
+        } = setup();
+        banks.get_mut(&sol_pk).unwrap().config.borrow_limit = 500_000_000;
+
+        let isolated_pk = Pubkey::new_unique();
+        let mut isolated_bank = bank(6);
+        isolated_bank.config.risk_tier = RiskTier::Isolated;
+        isolated_bank.total_asset_shares = I80F48!(1_000_000_000).into();
+        banks.insert(isolated_pk, isolated_bank);
+        price_feeds.insert(isolated_pk, price_feed(1));
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        marginfi_account.lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk: usdc_pk,
+            asset_shares: I80F48!(100_000_000).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        let marginfi_group = MarginfiGroup::default();
+        let mut simulated =
+            SimulatedAccount::new(&marginfi_group, &marginfi_account, &banks, 0).unwrap();
+
+        // Health alone would allow 6 SOL, and the limit is exclusive.
+        assert_eq!(
+            simulated.max_borrow(&sol_pk, &price_feeds).unwrap(),
+            499_999_999
+        );
+        assert_eq!(
+            simulated.max_borrow(&isolated_pk, &price_feeds).unwrap(),
+            60_000_000
+        );
+
+        // An isolated liability can't sit next to another one.
+        simulated
+            .apply(&Action::Borrow {
+                bank_pk: sol_pk,
+                amount: 100_000_000,
+            })
+            .unwrap();
+        assert_eq!(simulated.max_borrow(&isolated_pk, &price_feeds).unwrap(), 0);
+    }
+
+    #[test]
+    fn max_borrow_respects_liability_value_limit() {
+        let Setup {
+            usdc_pk,
+            sol_pk,
+            mut banks,
+            price_feeds,
+        } = setup();
+        banks.get_mut(&sol_pk).unwrap().total_liability_value_limit = 30;
+
+        let mut marginfi_account: MarginfiAccount = bytemuck::Zeroable::zeroed();
+        marginfi_account.lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk: usdc_pk,
+            asset_shares: I80F48!(100_000_000).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        let marginfi_group = MarginfiGroup::default();
+        let simulated =
+            SimulatedAccount::new(&marginfi_group, &marginfi_account, &banks, 0).unwrap();
+
+        // Health alone would allow 6 SOL, $30 of liabilities is 3 SOL.
+        assert_eq!(
+            simulated.max_borrow(&sol_pk, &price_feeds).unwrap(),
+            3_000_000_000
+        );
+    }
+}
@@ -0,0 +1,920 @@
+//! Cached group state and instruction builders driven by it.
+
+use {
+    crate::{
+        error::{SdkError, SdkResult},
+        health::{compute_account_health, AccountHealth},
+        instructions,
+        remaining_accounts::load_observation_account_metas,
+        simulation::{simulate_actions, Action, SimulatedAccount, SimulationReport},
+    },
+    anchor_lang::{AccountDeserialize, Discriminator},
+    anchor_spl::{
+        associated_token::get_associated_token_address_with_program_id,
+        token_2022::spl_token_2022::{self, extension::StateWithExtensions},
+    },
+    fixed::types::I80F48,
+    marginfi::{
+        constants::ZERO_AMOUNT_THRESHOLD,
+        prelude::{MarginfiError, MarginfiGroup},
+        state::{
+            marginfi_account::{BalanceSide, MarginfiAccount},
+            marginfi_group::Bank,
+            price::OraclePriceFeedAdapter,
+        },
+        utils::NumTraitsWithTolerance,
+    },
+    solana_account_decoder::UiAccountEncoding,
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::{Memcmp, RpcFilterType},
+    },
+    solana_sdk::{
+        account::{from_account, Account},
+        account_info::IntoAccountInfo,
+        clock::Clock,
+        instruction::AccountMeta,
+        instruction::Instruction,
+        pubkey::Pubkey,
+        sysvar,
+    },
+    std::{collections::HashMap, mem::size_of},
+};
+
+/// `get_multiple_accounts` limit.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Snapshot of a marginfi group: the group, all of its banks (with interest accrued to the
+/// cluster clock at load time), the token program of every bank mint, bank price feeds once
+/// loaded, and any marginfi accounts loaded so far.
+#[derive(Clone)]
+pub struct MarginfiState {
+    pub program_id: Pubkey,
+    pub group_pk: Pubkey,
+    pub group: MarginfiGroup,
+    pub banks: HashMap<Pubkey, Bank>,
+    pub token_programs: HashMap<Pubkey, Pubkey>,
+    /// Price feeds keyed by bank address, each built with its bank's oracle config. Feeds the
+    /// program would reject (e.g. stale) are left out, so health checks relying on them fail like
+    /// they would on-chain.
+    pub price_feeds: HashMap<Pubkey, OraclePriceFeedAdapter>,
+    pub marginfi_accounts: HashMap<Pubkey, MarginfiAccount>,
+}
+
+impl MarginfiState {
+    pub fn load(rpc_client: &RpcClient, program_id: Pubkey, group_pk: Pubkey) -> SdkResult<Self> {
+        let group = fetch_account::<MarginfiGroup>(rpc_client, &group_pk)?;
+
+        let mut state = Self {
+            program_id,
+            group_pk,
+            group,
+            banks: HashMap::new(),
+            token_programs: HashMap::new(),
+            price_feeds: HashMap::new(),
+            marginfi_accounts: HashMap::new(),
+        };
+        state.refresh_banks(rpc_client)?;
+
+        Ok(state)
+    }
+
+    /// Reload every bank of the group and accrue interest to the current cluster time.
+    pub fn refresh_banks(&mut self, rpc_client: &RpcClient) -> SdkResult<()> {
+        let clock = fetch_clock(rpc_client)?;
+
+        let mut banks = fetch_program_accounts::<Bank>(
+            rpc_client,
+            &self.program_id,
+            vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                8 + size_of::<Pubkey>() + size_of::<u8>(),
+                self.group_pk.to_bytes().to_vec(),
+            ))],
+        )?;
+
+        for bank in banks.values_mut() {
+            bank.accrue_interest(clock.unix_timestamp)?;
+        }
+
+        let mut mints = banks.values().map(|bank| bank.mint).collect::<Vec<_>>();
+        mints.sort();
+        mints.dedup();
+        mints.retain(|mint| !self.token_programs.contains_key(mint));
+
+        for chunk in mints.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let accounts = rpc_client.get_multiple_accounts(chunk)?;
+            for (mint, account) in chunk.iter().zip(accounts) {
+                let account = account.ok_or(SdkError::AccountNotFound(*mint))?;
+                self.token_programs.insert(*mint, account.owner);
+            }
+        }
+
+        self.banks = banks;
+
+        Ok(())
+    }
+
+    /// Reload the price feed of every bank, validated against the current cluster time.
+    pub fn refresh_price_feeds(&mut self, rpc_client: &RpcClient) -> SdkResult<()> {
+        let clock = fetch_clock(rpc_client)?;
+
+        let oracle_keys = self
+            .banks
+            .values()
+            .flat_map(|bank| bank.config.get_oracle_keys().iter().copied())
+            .collect::<Vec<_>>();
+
+        let mut oracle_accounts = HashMap::new();
+        for oracle_keys in oracle_keys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let accounts = rpc_client.get_multiple_accounts(oracle_keys)?;
+            for (oracle_key, account) in oracle_keys.iter().zip(accounts) {
+                if let Some(account) = account {
+                    oracle_accounts.insert(*oracle_key, account);
+                }
+            }
+        }
+
+        let mut price_feeds = HashMap::new();
+        for (bank_pk, bank) in self.banks.iter() {
+            let Some(mut accounts) = bank
+                .config
+                .get_oracle_keys()
+                .iter()
+                .map(|oracle_key| {
+                    oracle_accounts
+                        .get(oracle_key)
+                        .map(|account| (*oracle_key, account.clone()))
+                })
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+
+            if let Ok(price_feed) = load_price_feed(bank, &mut accounts, clock.unix_timestamp) {
+                price_feeds.insert(*bank_pk, price_feed);
+            }
+        }
+
+        self.price_feeds = price_feeds;
+
+        Ok(())
+    }
+
+    pub fn load_marginfi_account(
+        &mut self,
+        rpc_client: &RpcClient,
+        marginfi_account_pk: Pubkey,
+    ) -> SdkResult<&MarginfiAccount> {
+        let marginfi_account = fetch_account::<MarginfiAccount>(rpc_client, &marginfi_account_pk)?;
+
+        if marginfi_account.group != self.group_pk {
+            return Err(SdkError::GroupMismatch {
+                account: marginfi_account_pk,
+                group: self.group_pk,
+            });
+        }
+
+        self.marginfi_accounts
+            .insert(marginfi_account_pk, marginfi_account);
+
+        self.get_marginfi_account(&marginfi_account_pk)
+    }
+
+    /// Load every marginfi account of the group, returning their addresses.
+    pub fn load_all_marginfi_accounts(&mut self, rpc_client: &RpcClient) -> SdkResult<Vec<Pubkey>> {
+        let accounts = fetch_program_accounts::<MarginfiAccount>(
+            rpc_client,
+            &self.program_id,
+            vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                8,
+                self.group_pk.to_bytes().to_vec(),
+            ))],
+        )?;
+
+        let addresses = accounts.keys().copied().collect();
+        self.marginfi_accounts.extend(accounts);
+
+        Ok(addresses)
+    }
+
+    /// Load every marginfi account of the group owned by `authority`, returning their addresses.
+    pub fn load_marginfi_accounts_for_authority(
+        &mut self,
+        rpc_client: &RpcClient,
+        authority: Pubkey,
+    ) -> SdkResult<Vec<Pubkey>> {
+        let accounts = fetch_program_accounts::<MarginfiAccount>(
+            rpc_client,
+            &self.program_id,
+            vec![
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8, self.group_pk.to_bytes().to_vec())),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    8 + size_of::<Pubkey>(),
+                    authority.to_bytes().to_vec(),
+                )),
+            ],
+        )?;
+
+        let addresses = accounts.keys().copied().collect();
+        self.marginfi_accounts.extend(accounts);
+
+        Ok(addresses)
+    }
+
+    pub fn get_bank(&self, bank_pk: &Pubkey) -> SdkResult<&Bank> {
+        self.banks
+            .get(bank_pk)
+            .ok_or(SdkError::BankNotLoaded(*bank_pk))
+    }
+
+    pub fn get_marginfi_account(
+        &self,
+        marginfi_account_pk: &Pubkey,
+    ) -> SdkResult<&MarginfiAccount> {
+        self.marginfi_accounts
+            .get(marginfi_account_pk)
+            .ok_or(SdkError::MarginfiAccountNotLoaded(*marginfi_account_pk))
+    }
+
+    pub fn get_token_program(&self, mint: &Pubkey) -> SdkResult<Pubkey> {
+        self.token_programs
+            .get(mint)
+            .copied()
+            .ok_or(SdkError::TokenProgramNotLoaded(*mint))
+    }
+
+    /// Associated token account of `owner` for the bank mint, under the mint's token program.
+    pub fn get_bank_ata(&self, owner: &Pubkey, bank_pk: &Pubkey) -> SdkResult<Pubkey> {
+        let bank = self.get_bank(bank_pk)?;
+        let token_program = self.get_token_program(&bank.mint)?;
+
+        Ok(get_associated_token_address_with_program_id(
+            owner,
+            &bank.mint,
+            &token_program,
+        ))
+    }
+
+    /// Associated token account of `owner` for the bank receipt mint.
+    pub fn get_receipt_ata(&self, owner: &Pubkey, bank_pk: &Pubkey) -> SdkResult<Pubkey> {
+        let bank = self.get_bank(bank_pk)?;
+
+        if bank.receipt_mint == Pubkey::default() {
+            return Err(MarginfiError::ReceiptMintNotSetup.into());
+        }
+
+        Ok(get_associated_token_address_with_program_id(
+            owner,
+            &bank.receipt_mint,
+            &anchor_spl::token::ID,
+        ))
+    }
+
+    pub fn observation_account_metas(
+        &self,
+        marginfi_account_pk: &Pubkey,
+        include_banks: &[Pubkey],
+        exclude_banks: &[Pubkey],
+    ) -> SdkResult<Vec<AccountMeta>> {
+        load_observation_account_metas(
+            self.get_marginfi_account(marginfi_account_pk)?,
+            &self.banks,
+            include_banks,
+            exclude_banks,
+        )
+    }
+
+    /// Banks in which the marginfi account holds a non-dust liability.
+    pub fn liability_bank_pks(&self, marginfi_account_pk: &Pubkey) -> SdkResult<Vec<Pubkey>> {
+        let marginfi_account = self.get_marginfi_account(marginfi_account_pk)?;
+
+        let mut bank_pks = vec![];
+        for balance in marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .filter(|balance| balance.active)
+        {
+            let liability = self
+                .get_bank(&balance.bank_pk)?
+                .get_liability_amount(balance.liability_shares.into())?;
+            if liability.is_positive_with_tolerance(ZERO_AMOUNT_THRESHOLD) {
+                bank_pks.push(balance.bank_pk);
+            }
+        }
+
+        Ok(bank_pks)
+    }
+
+    /// Health of a loaded marginfi account, computed with the program's risk engine. Requires
+    /// price feeds to be loaded.
+    pub fn account_health(&self, marginfi_account_pk: &Pubkey) -> SdkResult<AccountHealth> {
+        compute_account_health(
+            &self.group,
+            self.get_marginfi_account(marginfi_account_pk)?,
+            &self.banks,
+            &self.price_feeds,
+        )
+    }
+
+    /// Preview the health of a loaded marginfi account after `actions`, see [`simulate_actions`].
+    /// Requires price feeds to be loaded.
+    pub fn simulate_actions(
+        &self,
+        marginfi_account_pk: &Pubkey,
+        actions: &[Action],
+        current_timestamp: i64,
+    ) -> SdkResult<SimulationReport> {
+        simulate_actions(
+            &self.group,
+            self.get_marginfi_account(marginfi_account_pk)?,
+            &self.banks,
+            &self.price_feeds,
+            actions,
+            current_timestamp,
+        )
+    }
+
+    /// Tokens held by the liquidity vault of the bank, the most that can leave it at once.
+    pub fn fetch_vault_liquidity(
+        &self,
+        rpc_client: &RpcClient,
+        bank_pk: &Pubkey,
+    ) -> SdkResult<u64> {
+        let liquidity_vault = self.get_bank(bank_pk)?.liquidity_vault;
+        let account = rpc_client.get_account(&liquidity_vault)?;
+
+        let token_account =
+            StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+                .map_err(|_| SdkError::InvalidAccountData(liquidity_vault))?;
+
+        Ok(token_account.base.amount)
+    }
+
+    /// Largest amount a loaded marginfi account can borrow from the bank right now, in native
+    /// units. The borrow has to pass `check_account_init_health` (weights, init discount and risk
+    /// tiers), the bank borrow limit and the liquidity actually held by the vault. Requires price
+    /// feeds to be loaded.
+    pub fn max_borrow(
+        &self,
+        rpc_client: &RpcClient,
+        marginfi_account_pk: &Pubkey,
+        bank_pk: &Pubkey,
+    ) -> SdkResult<u64> {
+        let max_borrow = self
+            .simulated_account(rpc_client, marginfi_account_pk)?
+            .max_borrow(bank_pk, &self.price_feeds)?;
+
+        Ok(max_borrow.min(self.fetch_vault_liquidity(rpc_client, bank_pk)?))
+    }
+
+    /// Largest amount a loaded marginfi account can withdraw from its deposit in the bank right
+    /// now, in native units. Same constraints as [`MarginfiState::max_borrow`].
+    pub fn max_withdraw(
+        &self,
+        rpc_client: &RpcClient,
+        marginfi_account_pk: &Pubkey,
+        bank_pk: &Pubkey,
+    ) -> SdkResult<u64> {
+        let max_withdraw = self
+            .simulated_account(rpc_client, marginfi_account_pk)?
+            .max_withdraw(bank_pk, &self.price_feeds)?;
+
+        Ok(max_withdraw.min(self.fetch_vault_liquidity(rpc_client, bank_pk)?))
+    }
+
+    fn simulated_account(
+        &self,
+        rpc_client: &RpcClient,
+        marginfi_account_pk: &Pubkey,
+    ) -> SdkResult<SimulatedAccount<'_>> {
+        SimulatedAccount::new(
+            &self.group,
+            self.get_marginfi_account(marginfi_account_pk)?,
+            &self.banks,
+            fetch_clock(rpc_client)?.unix_timestamp,
+        )
+    }
+
+    // ----------------------------------------------------------------------------------------------------------------
+    // instructions
+    // ----------------------------------------------------------------------------------------------------------------
+
+    pub fn deposit_ix(
+        &self,
+        marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        bank_pk: Pubkey,
+        amount: u64,
+    ) -> SdkResult<Instruction> {
+        let bank = self.get_bank(&bank_pk)?;
+        let token_program = self.get_token_program(&bank.mint)?;
+
+        Ok(instructions::lending_account_deposit(
+            &self.program_id,
+            self.group_pk,
+            marginfi_account_pk,
+            signer,
+            bank_pk,
+            bank.mint,
+            self.get_bank_ata(&signer, &bank_pk)?,
+            token_program,
+            amount,
+        ))
+    }
+
+    pub fn repay_ix(
+        &self,
+        marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        bank_pk: Pubkey,
+        amount: u64,
+        repay_all: bool,
+    ) -> SdkResult<Instruction> {
+        let bank = self.get_bank(&bank_pk)?;
+        let token_program = self.get_token_program(&bank.mint)?;
+
+        Ok(instructions::lending_account_repay(
+            &self.program_id,
+            self.group_pk,
+            marginfi_account_pk,
+            signer,
+            bank_pk,
+            bank.mint,
+            self.get_bank_ata(&signer, &bank_pk)?,
+            token_program,
+            amount,
+            repay_all.then_some(true),
+        ))
+    }
+
+    /// Withdrawing everything closes the balance, so the bank is left out of the observation
+    /// accounts.
+    pub fn withdraw_ix(
+        &self,
+        marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        bank_pk: Pubkey,
+        amount: u64,
+        withdraw_all: bool,
+    ) -> SdkResult<Instruction> {
+        let bank = self.get_bank(&bank_pk)?;
+        let token_program = self.get_token_program(&bank.mint)?;
+        let exclude_banks = if withdraw_all { vec![bank_pk] } else { vec![] };
+
+        Ok(instructions::lending_account_withdraw(
+            &self.program_id,
+            self.group_pk,
+            marginfi_account_pk,
+            signer,
+            bank_pk,
+            bank.mint,
+            self.get_bank_ata(&signer, &bank_pk)?,
+            token_program,
+            self.observation_account_metas(&marginfi_account_pk, &[], &exclude_banks)?,
+            amount,
+            withdraw_all.then_some(true),
+        ))
+    }
+
+    /// Borrowing may open a new balance, so the bank is always part of the observation accounts.
+    pub fn borrow_ix(
+        &self,
+        marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        bank_pk: Pubkey,
+        amount: u64,
+    ) -> SdkResult<Instruction> {
+        let bank = self.get_bank(&bank_pk)?;
+        let token_program = self.get_token_program(&bank.mint)?;
+
+        Ok(instructions::lending_account_borrow(
+            &self.program_id,
+            self.group_pk,
+            marginfi_account_pk,
+            signer,
+            bank_pk,
+            bank.mint,
+            self.get_bank_ata(&signer, &bank_pk)?,
+            token_program,
+            self.observation_account_metas(&marginfi_account_pk, &[bank_pk], &[])?,
+            amount,
+        ))
+    }
+
+    /// Both marginfi accounts must be loaded.
+    pub fn liquidate_ix(
+        &self,
+        liquidator_marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        liquidatee_marginfi_account_pk: Pubkey,
+        asset_bank_pk: Pubkey,
+        liab_bank_pk: Pubkey,
+        asset_amount: u64,
+    ) -> SdkResult<Instruction> {
+        let asset_bank = self.get_bank(&asset_bank_pk)?;
+        let liab_bank = self.get_bank(&liab_bank_pk)?;
+        let token_program = self.get_token_program(&liab_bank.mint)?;
+
+        let mut remaining_accounts = asset_bank
+            .config
+            .get_oracle_keys()
+            .iter()
+            .chain(liab_bank.config.get_oracle_keys())
+            .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false))
+            .collect::<Vec<_>>();
+        remaining_accounts.extend(self.observation_account_metas(
+            &liquidator_marginfi_account_pk,
+            &[liab_bank_pk, asset_bank_pk],
+            &[],
+        )?);
+        remaining_accounts.extend(self.observation_account_metas(
+            &liquidatee_marginfi_account_pk,
+            &[],
+            &[],
+        )?);
+
+        Ok(instructions::lending_account_liquidate(
+            &self.program_id,
+            self.group_pk,
+            asset_bank_pk,
+            liab_bank_pk,
+            liab_bank.mint,
+            liquidator_marginfi_account_pk,
+            signer,
+            liquidatee_marginfi_account_pk,
+            token_program,
+            remaining_accounts,
+            asset_amount,
+        ))
+    }
+
+    /// Liquidation of several collaterals, the (asset bank, `q_a`) of `assets`, against one
+    /// liability. Both marginfi accounts must be loaded.
+    pub fn liquidate_multiple_ix(
+        &self,
+        liquidator_marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        liquidatee_marginfi_account_pk: Pubkey,
+        assets: &[(Pubkey, u64)],
+        liab_bank_pk: Pubkey,
+    ) -> SdkResult<Instruction> {
+        let liab_bank = self.get_bank(&liab_bank_pk)?;
+
+        let mut asset_banks = Vec::with_capacity(assets.len());
+        let mut liquidator_include_banks = vec![liab_bank_pk];
+        for (asset_bank_pk, asset_amount) in assets {
+            let asset_bank = self.get_bank(asset_bank_pk)?;
+            asset_banks.push((
+                *asset_bank_pk,
+                asset_bank.config.get_oracle_keys().to_vec(),
+                *asset_amount,
+            ));
+            liquidator_include_banks.push(*asset_bank_pk);
+        }
+
+        Ok(instructions::lending_account_liquidate_multiple(
+            &self.program_id,
+            self.group_pk,
+            liab_bank_pk,
+            liab_bank.mint,
+            liab_bank.config.get_oracle_keys(),
+            liquidator_marginfi_account_pk,
+            signer,
+            liquidatee_marginfi_account_pk,
+            self.get_token_program(&liab_bank.mint)?,
+            asset_banks,
+            self.observation_account_metas(
+                &liquidator_marginfi_account_pk,
+                &liquidator_include_banks,
+                &[],
+            )?,
+            self.observation_account_metas(&liquidatee_marginfi_account_pk, &[], &[])?,
+        ))
+    }
+
+    /// Liquidation paid from and into the signer's token accounts of both bank mints, without a
+    /// liquidator marginfi account. The liquidatee marginfi account must be loaded.
+    pub fn liquidate_with_tokens_ix(
+        &self,
+        signer: Pubkey,
+        liquidatee_marginfi_account_pk: Pubkey,
+        asset_bank_pk: Pubkey,
+        liab_bank_pk: Pubkey,
+        asset_amount: u64,
+    ) -> SdkResult<Instruction> {
+        let asset_bank = self.get_bank(&asset_bank_pk)?;
+        let liab_bank = self.get_bank(&liab_bank_pk)?;
+
+        let mut remaining_accounts = asset_bank
+            .config
+            .get_oracle_keys()
+            .iter()
+            .chain(liab_bank.config.get_oracle_keys())
+            .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false))
+            .collect::<Vec<_>>();
+        remaining_accounts.extend(self.observation_account_metas(
+            &liquidatee_marginfi_account_pk,
+            &[],
+            &[],
+        )?);
+
+        Ok(instructions::lending_account_liquidate_with_tokens(
+            &self.program_id,
+            self.group_pk,
+            asset_bank_pk,
+            asset_bank.mint,
+            liab_bank_pk,
+            liab_bank.mint,
+            liquidatee_marginfi_account_pk,
+            signer,
+            self.get_bank_ata(&signer, &liab_bank_pk)?,
+            self.get_bank_ata(&signer, &asset_bank_pk)?,
+            self.get_token_program(&asset_bank.mint)?,
+            self.get_token_program(&liab_bank.mint)?,
+            remaining_accounts,
+            asset_amount,
+        ))
+    }
+
+    pub fn setup_receipt_mint_ix(&self, admin: Pubkey, bank_pk: Pubkey) -> SdkResult<Instruction> {
+        self.get_bank(&bank_pk)?;
+
+        Ok(instructions::lending_pool_setup_receipt_mint(
+            &self.program_id,
+            self.group_pk,
+            admin,
+            bank_pk,
+        ))
+    }
+
+    /// Receipt tokens are minted to the signer's associated token account of the receipt mint.
+    /// Tokenizing every share closes the balance, so the bank is then left out of the observation
+    /// accounts.
+    pub fn mint_receipt_ix(
+        &self,
+        marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        bank_pk: Pubkey,
+        amount: u64,
+    ) -> SdkResult<Instruction> {
+        let marginfi_account = self.get_marginfi_account(&marginfi_account_pk)?;
+
+        let closes_balance = marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .any(|balance| {
+                balance.active
+                    && balance.bank_pk == bank_pk
+                    && (I80F48::from(balance.asset_shares) - I80F48::from_num(amount))
+                        .is_zero_with_tolerance(ZERO_AMOUNT_THRESHOLD)
+                    && balance.is_empty(BalanceSide::Liabilities)
+            });
+        let exclude_banks = if closes_balance {
+            vec![bank_pk]
+        } else {
+            vec![]
+        };
+
+        Ok(instructions::lending_account_mint_receipt(
+            &self.program_id,
+            self.group_pk,
+            marginfi_account_pk,
+            signer,
+            bank_pk,
+            self.get_receipt_ata(&signer, &bank_pk)?,
+            self.observation_account_metas(&marginfi_account_pk, &[], &exclude_banks)?,
+            amount,
+        ))
+    }
+
+    /// Receipt tokens are burnt from the signer's associated token account of the receipt mint.
+    pub fn redeem_receipt_ix(
+        &self,
+        marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        bank_pk: Pubkey,
+        amount: u64,
+    ) -> SdkResult<Instruction> {
+        Ok(instructions::lending_account_redeem_receipt(
+            &self.program_id,
+            self.group_pk,
+            marginfi_account_pk,
+            signer,
+            bank_pk,
+            self.get_receipt_ata(&signer, &bank_pk)?,
+            amount,
+        ))
+    }
+
+    pub fn handle_bankruptcy_ix(
+        &self,
+        signer: Pubkey,
+        marginfi_account_pk: Pubkey,
+        bank_pk: Pubkey,
+    ) -> SdkResult<Instruction> {
+        let bank = self.get_bank(&bank_pk)?;
+        let token_program = self.get_token_program(&bank.mint)?;
+
+        Ok(instructions::lending_pool_handle_bankruptcy(
+            &self.program_id,
+            self.group_pk,
+            signer,
+            bank_pk,
+            bank.mint,
+            marginfi_account_pk,
+            token_program,
+            self.observation_account_metas(&marginfi_account_pk, &[bank_pk], &[])?,
+        ))
+    }
+
+    /// Settles every liability balance of a bankrupt account in one instruction.
+    pub fn handle_bankruptcy_all_ix(
+        &self,
+        signer: Pubkey,
+        marginfi_account_pk: Pubkey,
+    ) -> SdkResult<Instruction> {
+        let marginfi_account = self.get_marginfi_account(&marginfi_account_pk)?;
+
+        let mut banks = vec![];
+        for balance in marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .filter(|balance| balance.active && !balance.is_empty(BalanceSide::Liabilities))
+        {
+            let bank = self.get_bank(&balance.bank_pk)?;
+            banks.push((
+                balance.bank_pk,
+                bank.mint,
+                self.get_token_program(&bank.mint)?,
+            ));
+        }
+
+        Ok(instructions::lending_pool_handle_bankruptcy_all(
+            &self.program_id,
+            self.group_pk,
+            signer,
+            marginfi_account_pk,
+            banks,
+            self.observation_account_metas(&marginfi_account_pk, &[], &[])?,
+        ))
+    }
+
+    pub fn set_emode_ix(
+        &self,
+        marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        emode_category: u8,
+    ) -> SdkResult<Instruction> {
+        Ok(instructions::marginfi_account_set_emode(
+            &self.program_id,
+            self.group_pk,
+            marginfi_account_pk,
+            signer,
+            self.observation_account_metas(&marginfi_account_pk, &[], &[])?,
+            emode_category,
+        ))
+    }
+
+    pub fn end_flashloan_ix(
+        &self,
+        marginfi_account_pk: Pubkey,
+        signer: Pubkey,
+        include_banks: &[Pubkey],
+        exclude_banks: &[Pubkey],
+    ) -> SdkResult<Instruction> {
+        Ok(instructions::lending_account_end_flashloan(
+            &self.program_id,
+            self.group_pk,
+            marginfi_account_pk,
+            signer,
+            self.observation_account_metas(&marginfi_account_pk, include_banks, exclude_banks)?,
+        ))
+    }
+
+    pub fn collect_bank_fees_ix(&self, bank_pk: Pubkey) -> SdkResult<Instruction> {
+        let bank = self.get_bank(&bank_pk)?;
+
+        Ok(instructions::lending_pool_collect_bank_fees(
+            &self.program_id,
+            self.group_pk,
+            bank_pk,
+            bank.mint,
+            self.get_token_program(&bank.mint)?,
+        ))
+    }
+
+    pub fn withdraw_fees_ix(
+        &self,
+        admin: Pubkey,
+        bank_pk: Pubkey,
+        dst_owner: Pubkey,
+        amount: u64,
+    ) -> SdkResult<Instruction> {
+        let bank = self.get_bank(&bank_pk)?;
+
+        Ok(instructions::lending_pool_withdraw_fees(
+            &self.program_id,
+            self.group_pk,
+            admin,
+            bank_pk,
+            bank.mint,
+            self.get_bank_ata(&dst_owner, &bank_pk)?,
+            self.get_token_program(&bank.mint)?,
+            amount,
+        ))
+    }
+
+    pub fn withdraw_insurance_ix(
+        &self,
+        admin: Pubkey,
+        bank_pk: Pubkey,
+        dst_owner: Pubkey,
+        amount: u64,
+    ) -> SdkResult<Instruction> {
+        let bank = self.get_bank(&bank_pk)?;
+
+        Ok(instructions::lending_pool_withdraw_insurance(
+            &self.program_id,
+            self.group_pk,
+            admin,
+            bank_pk,
+            bank.mint,
+            self.get_bank_ata(&dst_owner, &bank_pk)?,
+            self.get_token_program(&bank.mint)?,
+            amount,
+        ))
+    }
+}
+
+/// Price feed of `bank` read from its oracle accounts, in `BankConfig::get_oracle_keys` order,
+/// validated against `current_timestamp` like the program does.
+pub fn load_price_feed(
+    bank: &Bank,
+    oracle_accounts: &mut [(Pubkey, Account)],
+    current_timestamp: i64,
+) -> SdkResult<OraclePriceFeedAdapter> {
+    let oracle_ais = oracle_accounts
+        .iter_mut()
+        .map(|(oracle_key, oracle_account)| (&*oracle_key, oracle_account).into_account_info())
+        .collect::<Vec<_>>();
+
+    Ok(OraclePriceFeedAdapter::try_from_bank(
+        bank,
+        &oracle_ais,
+        current_timestamp,
+    )?)
+}
+
+pub fn fetch_clock(rpc_client: &RpcClient) -> SdkResult<Clock> {
+    let account = rpc_client.get_account(&sysvar::clock::ID)?;
+
+    from_account::<Clock, _>(&account).ok_or(SdkError::InvalidAccountData(sysvar::clock::ID))
+}
+
+pub fn fetch_account<T: AccountDeserialize>(
+    rpc_client: &RpcClient,
+    address: &Pubkey,
+) -> SdkResult<T> {
+    let account = rpc_client.get_account(address)?;
+
+    T::try_deserialize(&mut account.data.as_slice())
+        .map_err(|_| SdkError::InvalidAccountData(*address))
+}
+
+/// All program accounts of type `T` matching `filters`, keyed by address.
+pub fn fetch_program_accounts<T: AccountDeserialize + Discriminator>(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    filters: Vec<RpcFilterType>,
+) -> SdkResult<HashMap<Pubkey, T>> {
+    let mut filters = filters;
+    filters.insert(
+        0,
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, T::discriminator().to_vec())),
+    );
+
+    let accounts = rpc_client.get_program_accounts_with_config(
+        program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?;
+
+    accounts
+        .into_iter()
+        .map(|(address, account)| {
+            T::try_deserialize(&mut account.data.as_slice())
+                .map(|value| (address, value))
+                .map_err(|_| SdkError::InvalidAccountData(address))
+        })
+        .collect()
+}
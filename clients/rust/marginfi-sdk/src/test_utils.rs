@@ -0,0 +1,39 @@
+use {
+    fixed::types::I80F48,
+    fixed_macro::types::I80F48,
+    marginfi::state::{
+        marginfi_group::{Bank, BankOperationalState},
+        price::{OraclePriceFeedAdapter, PythEmaPriceFeed},
+    },
+    pyth_sdk_solana::Price,
+    solana_sdk::pubkey::Pubkey,
+};
+
+/// Operational bank without limits, with share values of one and init weights of 0.75 / 1.25.
+pub fn bank(mint_decimals: u8) -> Bank {
+    let mut bank: Bank = bytemuck::Zeroable::zeroed();
+    bank.mint = Pubkey::new_unique();
+    bank.mint_decimals = mint_decimals;
+    bank.asset_share_value = I80F48::ONE.into();
+    bank.liability_share_value = I80F48::ONE.into();
+    bank.config.asset_weight_init = I80F48!(0.75).into();
+    bank.config.asset_weight_maint = I80F48!(0.875).into();
+    bank.config.liability_weight_init = I80F48!(1.25).into();
+    bank.config.liability_weight_maint = I80F48!(1.125).into();
+    bank.config.operational_state = BankOperationalState::Operational;
+    bank.config.deposit_limit = u64::MAX;
+    bank.config.borrow_limit = u64::MAX;
+    bank
+}
+
+/// Price feed with no confidence interval, so every bias resolves to `price`.
+pub fn price_feed(price: i64) -> OraclePriceFeedAdapter {
+    let price = Price {
+        price,
+        conf: 0,
+        expo: 0,
+        publish_time: 0,
+    };
+
+    OraclePriceFeedAdapter::PythEma(PythEmaPriceFeed::new(price, price))
+}
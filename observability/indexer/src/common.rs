@@ -156,18 +156,22 @@ pub fn load_observation_account_metas(
         .iter()
         .zip(bank_pks.iter())
         .flat_map(|(bank, bank_pk)| {
-            vec![
-                AccountMeta {
-                    pubkey: *bank_pk,
-                    is_signer: false,
-                    is_writable: false,
-                },
-                AccountMeta {
-                    pubkey: bank.config.oracle_keys[0],
-                    is_signer: false,
-                    is_writable: false,
-                },
-            ]
+            let mut metas = vec![AccountMeta {
+                pubkey: *bank_pk,
+                is_signer: false,
+                is_writable: false,
+            }];
+            metas.extend(
+                bank.config
+                    .get_oracle_keys()
+                    .iter()
+                    .map(|oracle_key| AccountMeta {
+                        pubkey: *oracle_key,
+                        is_signer: false,
+                        is_writable: false,
+                    }),
+            );
+            metas
         })
         .collect::<Vec<_>>();
     account_metas
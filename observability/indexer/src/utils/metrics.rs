@@ -1,6 +1,5 @@
-use super::marginfi_account_dup::RiskEngine2;
 use crate::utils::big_query::DATE_FORMAT_STR;
-use crate::utils::snapshot::{BankAccounts, OracleData, Snapshot};
+use crate::utils::snapshot::{BankAccounts, Snapshot};
 use anyhow::anyhow;
 use chrono::{NaiveDateTime, Utc};
 use fixed::types::I80F48;
@@ -8,11 +7,12 @@ use fixed_macro::types::I80F48;
 use itertools::Itertools;
 use marginfi::constants::ZERO_AMOUNT_THRESHOLD;
 use marginfi::prelude::MarginfiGroup;
-use marginfi::state::marginfi_account::{
-    calc_value, MarginfiAccount, RequirementType, RiskRequirementType,
-};
+use marginfi::state::marginfi_account::{calc_value, MarginfiAccount, RequirementType};
 use marginfi::state::marginfi_group::BankOperationalState;
-use marginfi::state::price::{OraclePriceFeedAdapter, OraclePriceType, PriceBias};
+use marginfi::state::price::{
+    AggregatedPriceFeed, OraclePriceFeedAdapter, OraclePriceType, OracleSetup, PriceBias,
+};
+use marginfi_sdk::health::compute_account_health;
 use serde::Serialize;
 use solana_sdk::pubkey::Pubkey;
 use std::collections::HashMap;
@@ -73,13 +73,15 @@ impl MarginfiGroupMetrics {
                     .bank
                     .config
                     .get_weights(RequirementType::Maintenance);
-                let oralce = snapshot.price_feeds.get(&price_feed_pk).ok_or_else(|| {
-                    anyhow!(
-                        "Price feed {} not found for bank {}",
-                        price_feed_pk,
-                        bank_pk
-                    )
-                })?;
+                let oralce = snapshot
+                    .get_bank_oracle_data(&bank_accounts.bank)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Price feed {} not found for bank {}",
+                            price_feed_pk,
+                            bank_pk
+                        )
+                    })?;
 
                 let (real_price, maint_asset_price, maint_liab_price) = (
                     oralce.get_price_of_type(OraclePriceType::RealTime, None),
@@ -247,13 +249,15 @@ impl LendingPoolBankMetrics {
             .config
             .get_weights(RequirementType::Initial);
         let price_feed_pk = bank_accounts.bank.config.oracle_keys[0];
-        let oracle = snapshot.price_feeds.get(&price_feed_pk).ok_or_else(|| {
-            anyhow!(
-                "Price feed {} not found for bank {}",
-                price_feed_pk,
-                bank_pk
-            )
-        })?;
+        let oracle = snapshot
+            .get_bank_oracle_data(&bank_accounts.bank)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Price feed {} not found for bank {}",
+                    price_feed_pk,
+                    bank_pk
+                )
+            })?;
 
         let price = oracle.get_price_of_type(OraclePriceType::RealTime, None);
 
@@ -321,8 +325,7 @@ impl LendingPoolBankMetrics {
         };
         let (lending_apr, borrowing_apr, group_fee_apr, insurance_fee_apr) = bank_accounts
             .bank
-            .config
-            .interest_rate_config
+            .get_interest_rate_config()
             .calc_interest_rate(utilization_rate)
             .ok_or_else(|| anyhow!("Bad math during IR calcs"))?;
 
@@ -459,38 +462,53 @@ impl MarginfiAccountMetrics {
                 .iter()
                 .map(|(bank_pk, bank_accounts)| (*bank_pk, bank_accounts.clone().bank)),
         );
-        let price_feeds =
-            HashMap::from_iter(snapshot.price_feeds.iter().map(|(oracle_pk, oracle_data)| {
-                match oracle_data {
-                    OracleData::Pyth(price_feed) => (
-                        *oracle_pk,
-                        OraclePriceFeedAdapter::PythEma(price_feed.clone()),
-                    ),
-                    OracleData::Switchboard(pf) => (
-                        *oracle_pk,
-                        OraclePriceFeedAdapter::SwitchboardV2(pf.clone()),
-                    ),
-                }
-            }));
+        // Keyed by bank like the SDK, banks sharing an oracle can still be priced differently.
+        let price_feeds: HashMap<Pubkey, OraclePriceFeedAdapter> = banks
+            .iter()
+            .filter_map(|(bank_pk, bank)| {
+                let price_feed = match bank.config.oracle_setup {
+                    OracleSetup::Aggregated => {
+                        let sources = bank
+                            .config
+                            .get_oracle_keys()
+                            .iter()
+                            .filter_map(|oracle_key| snapshot.price_feeds.get(oracle_key))
+                            .map(|oracle_data| oracle_data.to_price_feed_adapter())
+                            .collect::<Vec<_>>();
+
+                        OraclePriceFeedAdapter::Aggregated(
+                            AggregatedPriceFeed::try_new(
+                                sources,
+                                bank.config.oracle_max_deviation_bps,
+                            )
+                            .ok()?,
+                        )
+                    }
+                    _ => snapshot.get_bank_oracle_data(bank)?.to_price_feed_adapter(),
+                };
+
+                Some((*bank_pk, price_feed))
+            })
+            .collect();
 
-        let risk_engine = RiskEngine2::load(marginfi_account, &banks, &price_feeds)?;
+        let marginfi_group = snapshot
+            .marginfi_groups
+            .get(&marginfi_account.group)
+            .ok_or_else(|| anyhow!("Group {} missing from snapshot", marginfi_account.group))?;
+        let health =
+            compute_account_health(marginfi_group, marginfi_account, &banks, &price_feeds)?;
 
-        let (total_assets_usd, total_liabilities_usd) = risk_engine.get_equity_components()?;
         let (total_assets_usd, total_liabilities_usd) = (
-            total_assets_usd.to_num::<f64>(),
-            total_liabilities_usd.to_num::<f64>(),
+            health.equity.assets.to_num::<f64>(),
+            health.equity.liabilities.to_num::<f64>(),
         );
-        let (total_assets_usd_maintenance, total_liabilities_usd_maintenance) =
-            risk_engine.get_account_health_components(RiskRequirementType::Maintenance)?;
         let (total_assets_usd_maintenance, total_liabilities_usd_maintenance) = (
-            total_assets_usd_maintenance.to_num::<f64>(),
-            total_liabilities_usd_maintenance.to_num::<f64>(),
+            health.maintenance.assets.to_num::<f64>(),
+            health.maintenance.liabilities.to_num::<f64>(),
         );
-        let (total_assets_usd_initial, total_liabilities_usd_initial) =
-            risk_engine.get_account_health_components(RiskRequirementType::Initial)?;
         let (total_assets_usd_initial, total_liabilities_usd_initial) = (
-            total_assets_usd_initial.to_num::<f64>(),
-            total_liabilities_usd_initial.to_num::<f64>(),
+            health.init.assets.to_num::<f64>(),
+            health.init.liabilities.to_num::<f64>(),
         );
 
         let positions = marginfi_account
@@ -512,8 +530,7 @@ impl MarginfiAccountMetrics {
                 let price_feed_pk = bank.config.oracle_keys[0];
 
                 let oracle_data = snapshot
-                    .price_feeds
-                    .get(&price_feed_pk)
+                    .get_bank_oracle_data(bank)
                     .ok_or_else(|| {
                         anyhow!(
                             "Price feed {} not found for bank {}",
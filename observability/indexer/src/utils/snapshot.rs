@@ -39,6 +39,14 @@ pub enum AccountRoutingType {
     Bank(Pubkey, BankUpdateRoutingType),
     PriceFeedPyth,
     PriceFeedSwitchboard,
+    /// Pyth pull feed, along with the feed id expected by the bank.
+    PriceFeedPythPush([u8; 32]),
+    PriceFeedSwitchboardPull,
+    /// Source of an aggregated oracle, Pyth or Switchboard depending on the account owner, along
+    /// with the Pyth feed id expected by the bank.
+    PriceFeedAggregatedSource([u8; 32]),
+    /// Stake pool or Marinade state of a stake pool bank, along with the key of its SOL feed.
+    StakePoolState(Pubkey),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -53,6 +61,18 @@ pub enum BankUpdateRoutingType {
 pub enum OracleData {
     Pyth(PythEmaPriceFeed),
     Switchboard(SwitchboardV2PriceFeed),
+    PythPush(PythPushOraclePriceFeed),
+    SwitchboardPull(SwitchboardPullPriceFeed),
+    StakePool {
+        sol_feed: Box<OracleData>,
+        exchange_rate: I80F48,
+    },
+    Fixed(FixedPriceFeed),
+    Pegged {
+        feed: Box<OracleData>,
+        peg: I80F48,
+        max_deviation_bps: u16,
+    },
 }
 
 impl OracleData {
@@ -68,6 +88,48 @@ impl OracleData {
             OracleData::Switchboard(price_feed) => price_feed
                 .get_price_of_type(oracle_price_type, bias)
                 .unwrap(),
+            OracleData::PythPush(price_feed) => price_feed
+                .get_price_of_type(oracle_price_type, bias)
+                .unwrap(),
+            OracleData::SwitchboardPull(price_feed) => price_feed
+                .get_price_of_type(oracle_price_type, bias)
+                .unwrap(),
+            OracleData::StakePool {
+                sol_feed,
+                exchange_rate,
+            } => sol_feed.get_price_of_type(oracle_price_type, bias) * *exchange_rate,
+            OracleData::Fixed(price_feed) => price_feed
+                .get_price_of_type(oracle_price_type, bias)
+                .unwrap(),
+            OracleData::Pegged { .. } => self
+                .to_price_feed_adapter()
+                .get_price_of_type(oracle_price_type, bias)
+                .unwrap(),
+        }
+    }
+
+    pub fn to_price_feed_adapter(&self) -> OraclePriceFeedAdapter {
+        match self {
+            OracleData::Pyth(pf) => OraclePriceFeedAdapter::PythEma(pf.clone()),
+            OracleData::Switchboard(pf) => OraclePriceFeedAdapter::SwitchboardV2(pf.clone()),
+            OracleData::PythPush(pf) => OraclePriceFeedAdapter::PythPushOracle(pf.clone()),
+            OracleData::SwitchboardPull(pf) => OraclePriceFeedAdapter::SwitchboardPull(pf.clone()),
+            OracleData::StakePool {
+                sol_feed,
+                exchange_rate,
+            } => OraclePriceFeedAdapter::StakePool(StakePoolPriceFeed::new(
+                sol_feed.to_price_feed_adapter(),
+                *exchange_rate,
+            )),
+            OracleData::Fixed(pf) => OraclePriceFeedAdapter::Fixed(pf.clone()),
+            OracleData::Pegged {
+                feed,
+                peg,
+                max_deviation_bps,
+            } => OraclePriceFeedAdapter::Pegged(
+                PeggedPriceFeed::try_new(feed.to_price_feed_adapter(), *peg, *max_deviation_bps)
+                    .unwrap(),
+            ),
         }
     }
 }
@@ -82,6 +144,8 @@ pub struct Snapshot {
     pub banks: HashMap<Pubkey, BankAccounts>,
     pub marginfi_accounts: HashMap<Pubkey, MarginfiAccount>,
     pub price_feeds: HashMap<Pubkey, OracleData>,
+    /// Exchange rate and SOL feed of each stake pool bank oracle, keyed by pool state.
+    pub stake_pools: HashMap<Pubkey, (I80F48, Pubkey)>,
 }
 
 impl Display for Snapshot {
@@ -118,6 +182,7 @@ impl Snapshot {
             banks: HashMap::new(),
             marginfi_accounts: HashMap::new(),
             price_feeds: HashMap::new(),
+            stake_pools: HashMap::new(),
         }
     }
 
@@ -224,6 +289,58 @@ impl Snapshot {
                             .insert(oracle_address, AccountRoutingType::PriceFeedSwitchboard);
                         accounts_to_fetch.push(oracle_address);
                     }
+                    OracleSetup::PythPushOracle => {
+                        let oracle_address = bank.config.oracle_keys[0];
+                        self.routing_lookup.insert(
+                            oracle_address,
+                            AccountRoutingType::PriceFeedPythPush(bank.config.pyth_push_feed_id),
+                        );
+                        accounts_to_fetch.push(oracle_address);
+                    }
+                    OracleSetup::SwitchboardPull => {
+                        let oracle_address = bank.config.oracle_keys[0];
+                        self.routing_lookup
+                            .insert(oracle_address, AccountRoutingType::PriceFeedSwitchboardPull);
+                        accounts_to_fetch.push(oracle_address);
+                    }
+                    OracleSetup::Fixed => (),
+                    OracleSetup::Pegged => {
+                        let oracle_address = bank.config.oracle_keys[0];
+                        self.routing_lookup.insert(
+                            oracle_address,
+                            AccountRoutingType::PriceFeedAggregatedSource(
+                                bank.config.pyth_push_feed_id,
+                            ),
+                        );
+                        accounts_to_fetch.push(oracle_address);
+                    }
+                    OracleSetup::StakePool => {
+                        let (pool_address, sol_feed_address) =
+                            (bank.config.oracle_keys[0], bank.config.oracle_keys[1]);
+                        self.routing_lookup.insert(
+                            sol_feed_address,
+                            AccountRoutingType::PriceFeedAggregatedSource(
+                                bank.config.pyth_push_feed_id,
+                            ),
+                        );
+                        self.routing_lookup.insert(
+                            pool_address,
+                            AccountRoutingType::StakePoolState(sol_feed_address),
+                        );
+                        accounts_to_fetch.push(sol_feed_address);
+                        accounts_to_fetch.push(pool_address);
+                    }
+                    OracleSetup::Aggregated => {
+                        for oracle_address in bank.config.get_oracle_keys() {
+                            self.routing_lookup.insert(
+                                *oracle_address,
+                                AccountRoutingType::PriceFeedAggregatedSource(
+                                    bank.config.pyth_push_feed_id,
+                                ),
+                            );
+                            accounts_to_fetch.push(*oracle_address);
+                        }
+                    }
                 }
 
                 self.banks.insert(
@@ -323,6 +440,82 @@ impl Snapshot {
                 self.price_feeds
                     .insert(*account_pubkey, OracleData::Switchboard(pf));
             }
+            AccountRoutingType::PriceFeedPythPush(feed_id) => {
+                let mut account = account.clone();
+                let ai = (account_pubkey, &mut account).into_account_info();
+                let pf = PythPushOraclePriceFeed::load_checked(&ai, feed_id, 0, u64::MAX).unwrap();
+                self.price_feeds
+                    .insert(*account_pubkey, OracleData::PythPush(pf));
+            }
+            AccountRoutingType::PriceFeedSwitchboardPull => {
+                let mut account = account.clone();
+                let ai = (account_pubkey, &mut account).into_account_info();
+                let pf = SwitchboardPullPriceFeed::load_checked(&ai, 0, u64::MAX).unwrap();
+                self.price_feeds
+                    .insert(*account_pubkey, OracleData::SwitchboardPull(pf));
+            }
+            AccountRoutingType::PriceFeedAggregatedSource(feed_id) => {
+                let mut account = account.clone();
+                let ai = (account_pubkey, &mut account).into_account_info();
+                let oracle_data = if ai.owner.eq(&marginfi::constants::PYTH_ID) {
+                    OracleData::Pyth(PythEmaPriceFeed::load_checked(&ai, 0, u64::MAX).unwrap())
+                } else if ai.owner.eq(&marginfi::constants::PYTH_PUSH_ORACLE_ID) {
+                    OracleData::PythPush(
+                        PythPushOraclePriceFeed::load_checked(&ai, feed_id, 0, u64::MAX).unwrap(),
+                    )
+                } else if ai.owner.eq(&marginfi::constants::SWITCHBOARD_PULL_ID) {
+                    OracleData::SwitchboardPull(
+                        SwitchboardPullPriceFeed::load_checked(&ai, 0, u64::MAX).unwrap(),
+                    )
+                } else {
+                    OracleData::Switchboard(
+                        SwitchboardV2PriceFeed::load_checked(&ai, 0, u64::MAX).unwrap(),
+                    )
+                };
+                self.price_feeds.insert(*account_pubkey, oracle_data);
+            }
+            AccountRoutingType::StakePoolState(sol_feed_pk) => {
+                let mut account = account.clone();
+                let ai = (account_pubkey, &mut account).into_account_info();
+                let exchange_rate = load_stake_pool_exchange_rate(&ai).unwrap();
+                self.stake_pools
+                    .insert(*account_pubkey, (exchange_rate, *sol_feed_pk));
+            }
+        }
+
+        self.refresh_stake_pool_feeds();
+    }
+
+    /// Oracle data of `bank`, fixed and pegged prices applied on top of the underlying feed.
+    pub fn get_bank_oracle_data(&self, bank: &Bank) -> Option<OracleData> {
+        match bank.config.oracle_setup {
+            OracleSetup::Fixed => Some(OracleData::Fixed(FixedPriceFeed::new(
+                bank.fixed_price.into(),
+            ))),
+            OracleSetup::Pegged => self
+                .price_feeds
+                .get(&bank.config.oracle_keys[0])
+                .map(|feed| OracleData::Pegged {
+                    feed: Box::new(feed.clone()),
+                    peg: bank.fixed_price.into(),
+                    max_deviation_bps: bank.config.oracle_max_deviation_bps,
+                }),
+            _ => self.price_feeds.get(&bank.config.oracle_keys[0]).cloned(),
+        }
+    }
+
+    /// Reprice stake pool oracles off the latest exchange rates and SOL feeds.
+    fn refresh_stake_pool_feeds(&mut self) {
+        for (pool_pk, (exchange_rate, sol_feed_pk)) in self.stake_pools.iter() {
+            if let Some(sol_feed) = self.price_feeds.get(sol_feed_pk).cloned() {
+                self.price_feeds.insert(
+                    *pool_pk,
+                    OracleData::StakePool {
+                        sol_feed: Box::new(sol_feed),
+                        exchange_rate: *exchange_rate,
+                    },
+                );
+            }
         }
     }
 }
@@ -244,17 +244,26 @@ impl AccountsState {
         account_info
     }
 
+    /// Uninitialized vault account, created and initialized by `lending_pool_add_bank`.
     pub fn new_vault_account<'bump>(
         &'bump self,
         vault_type: BankVaultType,
-        mint_pubkey: &'bump Pubkey,
-        owner: &'bump Pubkey,
         bank: &'bump Pubkey,
     ) -> (AccountInfo<'bump>, u8) {
         let (vault_address, seed_bump) = get_vault_address(bank, vault_type);
 
         (
-            self.new_token_account_with_pubkey(vault_address, mint_pubkey, owner, 0, Rent::free()),
+            AccountInfo::new(
+                self.bump.alloc(vault_address),
+                false,
+                true,
+                self.bump.alloc(0),
+                self.bump.alloc_slice_fill_copy(SplAccount::LEN, 0u8),
+                // Reassigned to the token program on creation
+                self.bump.alloc(system_program::ID),
+                false,
+                Epoch::default(),
+            ),
             seed_bump,
         )
     }
@@ -1,8 +1,8 @@
 use account_state::{AccountInfoCache, AccountsState};
 use anchor_lang::{
     prelude::{
-        Account, AccountInfo, AccountLoader, Context, Program, Pubkey, Rent, Signer, SolanaSysvar,
-        Sysvar,
+        AccountInfo, AccountLoader, Context, Interface, InterfaceAccount, Program, Pubkey, Rent,
+        Signer, SolanaSysvar, Sysvar,
     },
     Discriminator, Key,
 };
@@ -168,30 +168,17 @@ impl<'bump> MarginfiFuzzContext<'bump> {
         let mint = state.new_token_mint(rent, initial_bank_config.mint_decimals);
         let (liquidity_vault_authority, liquidity_vault_authority_bump) =
             state.new_vault_authority(BankVaultType::Liquidity, bank.key);
-        let (liquidity_vault, liquidity_vault_bump) = state.new_vault_account(
-            BankVaultType::Liquidity,
-            mint.key,
-            liquidity_vault_authority.key,
-            bank.key,
-        );
+        let (liquidity_vault, liquidity_vault_bump) =
+            state.new_vault_account(BankVaultType::Liquidity, bank.key);
 
         let (insurance_vault_authority, insurance_vault_authority_bump) =
             state.new_vault_authority(BankVaultType::Insurance, bank.key);
-        let (insurance_vault, insurance_vault_bump) = state.new_vault_account(
-            BankVaultType::Insurance,
-            mint.key,
-            insurance_vault_authority.key,
-            bank.key,
-        );
+        let (insurance_vault, insurance_vault_bump) =
+            state.new_vault_account(BankVaultType::Insurance, bank.key);
 
         let (fee_vault_authority, fee_vault_authority_bump) =
             state.new_vault_authority(BankVaultType::Fee, bank.key);
-        let (fee_vault, fee_vault_bump) = state.new_vault_account(
-            BankVaultType::Fee,
-            mint.key,
-            fee_vault_authority.key,
-            bank.key,
-        );
+        let (fee_vault, fee_vault_bump) = state.new_vault_account(BankVaultType::Fee, bank.key);
 
         let oracle = state.new_oracle_account(
             rent,
@@ -226,16 +213,16 @@ impl<'bump> MarginfiFuzzContext<'bump> {
                     marginfi_group: AccountLoader::try_from(&self.marginfi_group).unwrap(),
                     admin: Signer::try_from(&self.owner).unwrap(),
                     fee_payer: Signer::try_from(&self.owner).unwrap(),
-                    bank_mint: Box::new(Account::try_from(&mint).unwrap()),
+                    bank_mint: Box::new(InterfaceAccount::try_from(&mint).unwrap()),
                     bank: AccountLoader::try_from_unchecked(&marginfi::ID, &bank).unwrap(),
                     liquidity_vault_authority: liquidity_vault_authority.clone(),
-                    liquidity_vault: Box::new(Account::try_from(&liquidity_vault).unwrap()),
+                    liquidity_vault: liquidity_vault.clone(),
                     insurance_vault_authority: insurance_vault_authority.clone(),
-                    insurance_vault: Box::new(Account::try_from(&insurance_vault).unwrap()),
+                    insurance_vault: insurance_vault.clone(),
                     fee_vault_authority: fee_vault_authority.clone(),
-                    fee_vault: Box::new(Account::try_from(&fee_vault).unwrap()),
+                    fee_vault: fee_vault.clone(),
                     rent: Sysvar::from_account_info(&self.rent_sysvar).unwrap(),
-                    token_program: Program::try_from(&self.token_program).unwrap(),
+                    token_program: Interface::try_from(&self.token_program).unwrap(),
                     system_program: Program::try_from(&self.system_program).unwrap(),
                 },
                 &[oracle.clone()],
@@ -274,7 +261,8 @@ impl<'bump> MarginfiFuzzContext<'bump> {
                     marginfi::state::marginfi_group::RiskTier::Isolated
                 },
                 ..Default::default()
-            },
+            }
+            .into(),
         )
         .unwrap();
 
@@ -359,7 +347,7 @@ impl<'bump> MarginfiFuzzContext<'bump> {
                     signer_token_account: marginfi_account.token_accounts[bank_idx.0 as usize]
                         .clone(),
                     bank_liquidity_vault: bank.liquidity_vault.clone(),
-                    token_program: Program::try_from(&self.token_program)?,
+                    token_program: Interface::try_from(&self.token_program)?,
                 },
                 &[],
                 BTreeMap::new(),
@@ -407,7 +395,7 @@ impl<'bump> MarginfiFuzzContext<'bump> {
                     signer_token_account: marginfi_account.token_accounts[bank_idx.0 as usize]
                         .clone(),
                     bank_liquidity_vault: bank.liquidity_vault.clone(),
-                    token_program: Program::try_from(&self.token_program)?,
+                    token_program: Interface::try_from(&self.token_program)?,
                 },
                 &[],
                 BTreeMap::new(),
@@ -465,12 +453,12 @@ impl<'bump> MarginfiFuzzContext<'bump> {
                     marginfi_account: AccountLoader::try_from(&marginfi_account.margin_account)?,
                     signer: Signer::try_from(&self.owner)?,
                     bank: AccountLoader::try_from(&bank.bank)?,
-                    token_program: Program::try_from(&self.token_program)?,
-                    destination_token_account: Account::try_from(
+                    token_program: Interface::try_from(&self.token_program)?,
+                    destination_token_account: InterfaceAccount::try_from(
                         &marginfi_account.token_accounts[bank_idx.0 as usize].clone(),
                     )?,
                     bank_liquidity_vault_authority: bank.liquidity_vault_authority.clone(),
-                    bank_liquidity_vault: Account::try_from(&bank.liquidity_vault)?,
+                    bank_liquidity_vault: InterfaceAccount::try_from(&bank.liquidity_vault)?,
                 },
                 &marginfi_account.get_remaining_accounts(
                     &self.get_bank_map(),
@@ -520,12 +508,12 @@ impl<'bump> MarginfiFuzzContext<'bump> {
                     marginfi_account: AccountLoader::try_from(&marginfi_account.margin_account)?,
                     signer: Signer::try_from(&self.owner)?,
                     bank: AccountLoader::try_from(&bank.bank)?,
-                    token_program: Program::try_from(&self.token_program)?,
-                    destination_token_account: Account::try_from(
+                    token_program: Interface::try_from(&self.token_program)?,
+                    destination_token_account: InterfaceAccount::try_from(
                         &marginfi_account.token_accounts[bank_idx.0 as usize].clone(),
                     )?,
                     bank_liquidity_vault_authority: bank.liquidity_vault_authority.clone(),
-                    bank_liquidity_vault: Account::try_from(&bank.liquidity_vault)?,
+                    bank_liquidity_vault: InterfaceAccount::try_from(&bank.liquidity_vault)?,
                 },
                 &marginfi_account.get_remaining_accounts(
                     &self.get_bank_map(),
@@ -615,11 +603,11 @@ impl<'bump> MarginfiFuzzContext<'bump> {
                         &liquidatee_account.margin_account.clone(),
                     )?,
                     bank_liquidity_vault_authority: liab_bank.liquidity_vault_authority.clone(),
-                    bank_liquidity_vault: Box::new(Account::try_from(
+                    bank_liquidity_vault: Box::new(InterfaceAccount::try_from(
                         &liab_bank.liquidity_vault.clone(),
                     )?),
                     bank_insurance_vault: liab_bank.insurance_vault.clone(),
-                    token_program: Program::try_from(&self.token_program)?,
+                    token_program: Interface::try_from(&self.token_program)?,
                 },
                 &remaining_accounts,
                 BTreeMap::new(),
@@ -671,9 +659,11 @@ impl<'bump> MarginfiFuzzContext<'bump> {
                     &marginfi_account.margin_account.clone(),
                 )?,
                 liquidity_vault: bank.liquidity_vault.clone(),
-                insurance_vault: Box::new(Account::try_from(&bank.insurance_vault.clone())?),
+                insurance_vault: Box::new(InterfaceAccount::try_from(
+                    &bank.insurance_vault.clone(),
+                )?),
                 insurance_vault_authority: bank.insurance_vault_authority.clone(),
-                token_program: Program::try_from(&self.token_program)?,
+                token_program: Interface::try_from(&self.token_program)?,
             },
             &marginfi_account.get_remaining_accounts(&self.get_bank_map(), vec![], vec![]),
             BTreeMap::new(),
@@ -848,13 +838,20 @@ mod tests {
         .unwrap();
 
         {
+            let marginfi_group_al = AccountLoader::<MarginfiGroup>::try_from_unchecked(
+                &marginfi::id(),
+                &a.marginfi_group,
+            )
+            .unwrap();
+            let marginfi_group = marginfi_group_al.load().unwrap();
             let marginfi_account = marginfi_account_ai.load().unwrap();
             let margin_account = &a.marginfi_accounts[0];
             let bank_map = a.get_bank_map();
             let remaining_accounts =
                 &margin_account.get_remaining_accounts(&bank_map, vec![], vec![]);
 
-            let re = RiskEngine::new(&marginfi_account, remaining_accounts).unwrap();
+            let re =
+                RiskEngine::new(&marginfi_group, &marginfi_account, remaining_accounts).unwrap();
 
             let health = re
                 .get_account_health(
@@ -908,13 +905,20 @@ mod tests {
         .unwrap();
 
         {
+            let marginfi_group_al = AccountLoader::<MarginfiGroup>::try_from_unchecked(
+                &marginfi::id(),
+                &a.marginfi_group,
+            )
+            .unwrap();
+            let marginfi_group = marginfi_group_al.load().unwrap();
             let marginfi_account = marginfi_account_ai.load().unwrap();
             let margin_account = &a.marginfi_accounts[0];
             let bank_map = a.get_bank_map();
             let remaining_accounts =
                 &margin_account.get_remaining_accounts(&bank_map, vec![], vec![]);
 
-            let re = RiskEngine::new(&marginfi_account, remaining_accounts).unwrap();
+            let re =
+                RiskEngine::new(&marginfi_group, &marginfi_account, remaining_accounts).unwrap();
 
             let health = re
                 .get_account_health(
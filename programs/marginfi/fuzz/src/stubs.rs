@@ -1,6 +1,9 @@
-use anchor_lang::prelude::{AccountInfo, Clock, Pubkey};
+use anchor_lang::prelude::{AccountInfo, Clock, ProgramError, Pubkey, Rent};
 use lazy_static::lazy_static;
-use solana_program::{entrypoint::ProgramResult, instruction::Instruction, program_stubs};
+use solana_program::{
+    entrypoint::ProgramResult, instruction::Instruction, packet::PACKET_DATA_SIZE, program_stubs,
+    program_utils::limited_deserialize, system_instruction::SystemInstruction, system_program,
+};
 
 use crate::log;
 
@@ -57,6 +60,10 @@ impl program_stubs::SyscallStubs for TestSyscallStubs {
             }
         }
 
+        if instruction.program_id == system_program::ID {
+            return process_system_instruction(instruction, &new_account_infos);
+        }
+
         spl_token::processor::Processor::process(
             &instruction.program_id,
             &new_account_infos,
@@ -74,6 +81,39 @@ impl program_stubs::SyscallStubs for TestSyscallStubs {
         }
         solana_program::entrypoint::SUCCESS
     }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe {
+            *(var_addr as *mut _ as *mut Rent) = Rent::free();
+        }
+        solana_program::entrypoint::SUCCESS
+    }
+}
+
+/// Only account creation is needed, for the bank vaults created in `lending_pool_add_bank`.
+/// Fuzz accounts are pre-allocated, so creating one just assigns it to its new owner.
+fn process_system_instruction(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+) -> ProgramResult {
+    match limited_deserialize(&instruction.data, PACKET_DATA_SIZE as u64)
+        .map_err(|_| ProgramError::InvalidInstructionData)?
+    {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            let new_account = &account_infos[1];
+            assert_eq!(new_account.data_len() as u64, space);
+
+            **new_account.try_borrow_mut_lamports()? += lamports;
+            new_account.assign(&owner);
+
+            Ok(())
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
 }
 
 pub fn test_syscall_stubs(unix_timestamp: Option<i64>) {
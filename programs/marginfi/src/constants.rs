@@ -14,6 +14,9 @@ pub const FEE_VAULT_SEED: &str = "fee_vault";
 pub const EMISSIONS_AUTH_SEED: &str = "emissions_auth_seed";
 pub const EMISSIONS_TOKEN_ACCOUNT_SEED: &str = "emissions_token_account_seed";
 
+pub const RECEIPT_MINT_SEED: &str = "receipt_mint";
+pub const RECEIPT_MINT_AUTHORITY_SEED: &str = "receipt_mint_auth";
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "devnet")] {
         pub const PYTH_ID: Pubkey = pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
@@ -24,14 +27,47 @@ cfg_if::cfg_if! {
     }
 }
 
-/// TODO: Make these variable per bank
+/// Pyth receiver program, owner of the pull oracle `PriceUpdateV2` accounts.
+pub const PYTH_PUSH_ORACLE_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "devnet")] {
+        pub const SWITCHBOARD_PULL_ID: Pubkey = pubkey!("Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2");
+    } else {
+        pub const SWITCHBOARD_PULL_ID: Pubkey = pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+    }
+}
+
+/// SPL stake pool program, owner of the pool state of jitoSOL, bSOL and other stake pool LSTs.
+pub const SPL_STAKE_POOL_ID: Pubkey = pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+/// Marinade liquid staking program, owner of the mSOL state account.
+pub const MARINADE_ID: Pubkey = pubkey!("MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD");
+
+/// Default liquidation fees, used when a bank doesn't configure its own.
 pub const LIQUIDATION_LIQUIDATOR_FEE: I80F48 = I80F48!(0.025);
 pub const LIQUIDATION_INSURANCE_FEE: I80F48 = I80F48!(0.025);
 
 pub const SECONDS_PER_YEAR: I80F48 = I80F48!(31_536_000);
+/// Terms of the series used to compound interest, the exponent is scaled down to at most 1 first.
+pub const INTEREST_COMPOUNDING_SERIES_TERMS: u32 = 16;
+
+/// Kinks a multi-kink interest rate curve can have past the `optimal_utilization_rate` one.
+pub const MAX_EXTRA_INTEREST_RATE_KINKS: usize = 2;
+/// Lowest plateau rate an adaptive interest rate curve can drift down to.
+pub const MIN_ADAPTIVE_PLATEAU_INTEREST_RATE: I80F48 = I80F48!(0.001);
 
 pub const MAX_PRICE_AGE_SEC: u64 = 60;
 
+/// Longest time a price guard move is measured over. A reference price older than this only
+/// allows a window's worth of movement, and is replaced by the next price recorded by the crank
+/// or a liquidation.
+pub const ORACLE_PRICE_GUARD_WINDOW_SEC: i64 = 600;
+
+/// Longest time past the bank's `oracle_max_age` a primary oracle can be stale while a fallback
+/// oracle stands in for it. The fallback is checked against the last primary price, which tells
+/// little about the current price once it's older than this, so the bank price is stale then.
+pub const FALLBACK_ORACLE_MAX_PRIMARY_STALENESS_SEC: u64 = 600;
+
 /// Range that contains 95% price data distribution
 ///
 /// https://docs.pyth.network/price-feeds/best-practices#confidence-intervals
@@ -44,6 +80,17 @@ pub const MAX_CONF_INTERVAL: I80F48 = I80F48!(0.05);
 pub const USDC_EXPONENT: i32 = 6;
 
 pub const MAX_ORACLE_KEYS: usize = 5;
+/// An aggregated oracle needs at least two sources to guard against a single bad feed.
+pub const MIN_AGGREGATED_ORACLE_KEYS: usize = 2;
+
+/// E-mode categories a group can define. Categories are numbered from 1, 0 means no e-mode.
+pub const MAX_EMODE_CATEGORIES: usize = 4;
+pub const EMODE_CATEGORY_NONE: u8 = 0;
+
+/// (asset bank, liability bank) weight overrides a group can define.
+pub const MAX_WEIGHT_OVERRIDES: usize = 6;
+
+pub const BPS_DENOMINATOR: I80F48 = I80F48!(10_000);
 
 /// Any balance below 1 SPL token amount is treated as none,
 /// this is to account for any artifacts resulting from binary fraction arithemtic.
@@ -124,3 +171,4 @@ pub const EXP_10: [i128; MAX_EXP_10] = [
 
 /// Value where total_asset_value_init_limit is considered inactive
 pub const TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE: u64 = 0;
+pub const TOTAL_LIABILITY_VALUE_LIMIT_INACTIVE: u64 = 0;
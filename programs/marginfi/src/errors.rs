@@ -93,6 +93,28 @@ pub enum MarginfiError {
     IllegalAccountAuthorityTransfer,
     #[msg("Unauthorized")] // 6045
     Unauthorized,
+    #[msg("Token22 Banks require mint account as first remaining account")] // 6046
+    T22MintRequired,
+    #[msg("Mint has an unsupported Token22 extension")] // 6047
+    UnsupportedMintExtension,
+    #[msg("Oracle sources deviate more than the bank allows")] // 6048
+    OracleDeviationExceeded,
+    #[msg("Oracle confidence interval is wider than the bank allows")] // 6049
+    OracleConfidenceTooWide,
+    #[msg("Oracle price moved faster than the bank allows")] // 6050
+    OraclePriceGuardTripped,
+    #[msg("Bank receipt mint already set up")] // 6051
+    ReceiptMintAlreadySetup,
+    #[msg("Bank has no receipt mint")] // 6052
+    ReceiptMintNotSetup,
+    #[msg("Invalid e-mode category")] // 6053
+    InvalidEmodeCategory,
+    #[msg("Weight override table is full")] // 6054
+    WeightOverrideTableFull,
+    #[msg("Bank liability value capacity exceeded")] // 6055
+    BankLiabilityValueCapacityExceeded,
+    #[msg("Pyth price update is for another feed than the bank's")] // 6056
+    PythPushFeedIdMismatch,
 }
 
 impl From<MarginfiError> for ProgramError {
@@ -1,4 +1,7 @@
-use crate::{prelude::*, state::marginfi_group::BankConfigOpt};
+use crate::{
+    prelude::*,
+    state::marginfi_group::{BankConfigOpt, EmodeCategory},
+};
 use anchor_lang::prelude::*;
 
 // Event headers
@@ -30,6 +33,22 @@ pub struct MarginfiGroupConfigureEvent {
     pub config: GroupConfig,
 }
 
+#[event]
+pub struct MarginfiGroupConfigureEmodeCategoryEvent {
+    pub header: GroupEventHeader,
+    pub emode_category: u8,
+    pub config: EmodeCategory,
+}
+
+#[event]
+pub struct MarginfiGroupConfigureWeightOverrideEvent {
+    pub header: GroupEventHeader,
+    pub asset_bank: Pubkey,
+    pub liability_bank: Pubkey,
+    pub asset_weight_init: f64,
+    pub asset_weight_maint: f64,
+}
+
 #[event]
 pub struct LendingPoolBankCreateEvent {
     pub header: GroupEventHeader,
@@ -76,6 +95,31 @@ pub struct LendingPoolBankHandleBankruptcyEvent {
     pub socialized_amount: f64,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct BankruptcySettlement {
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub bad_debt: f64,
+    pub covered_amount: f64,
+    pub socialized_amount: f64,
+}
+
+#[event]
+pub struct LendingPoolHandleBankruptcyAllEvent {
+    pub header: AccountEventHeader,
+    pub settlements: Vec<BankruptcySettlement>,
+}
+
+#[event]
+pub struct LendingPoolBankPriceGuardTripEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub last_price: f64,
+    pub last_price_slot: u64,
+    pub price: f64,
+}
+
 // marginfi account events
 
 #[event]
@@ -83,6 +127,12 @@ pub struct MarginfiAccountCreateEvent {
     pub header: AccountEventHeader,
 }
 
+#[event]
+pub struct MarginfiAccountSetEmodeEvent {
+    pub header: AccountEventHeader,
+    pub emode_category: u8,
+}
+
 #[event]
 pub struct LendingAccountDepositEvent {
     pub header: AccountEventHeader,
@@ -117,6 +167,24 @@ pub struct LendingAccountWithdrawEvent {
     pub close_balance: bool,
 }
 
+#[event]
+pub struct LendingAccountMintReceiptEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub receipt_mint: Pubkey,
+    pub amount: u64,
+    pub asset_amount: f64,
+}
+
+#[event]
+pub struct LendingAccountRedeemReceiptEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub receipt_mint: Pubkey,
+    pub amount: u64,
+    pub asset_amount: f64,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct LiquidationBalances {
     pub liquidatee_asset_balance: f64,
@@ -140,6 +208,48 @@ pub struct LendingAccountLiquidateEvent {
     pub post_balances: LiquidationBalances,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LiquidationAssetBalances {
+    pub asset_bank: Pubkey,
+    pub asset_mint: Pubkey,
+    pub asset_amount: u64,
+    pub liquidatee_asset_pre_balance: f64,
+    pub liquidatee_asset_post_balance: f64,
+    pub liquidator_asset_pre_balance: f64,
+    pub liquidator_asset_post_balance: f64,
+}
+
+#[event]
+pub struct LendingAccountLiquidateMultipleEvent {
+    pub header: AccountEventHeader,
+    pub liquidatee_marginfi_account: Pubkey,
+    pub liquidatee_marginfi_account_authority: Pubkey,
+    pub liability_bank: Pubkey,
+    pub liability_mint: Pubkey,
+    pub liquidatee_pre_health: f64,
+    pub liquidatee_post_health: f64,
+    pub liquidatee_liability_pre_balance: f64,
+    pub liquidatee_liability_post_balance: f64,
+    pub liquidator_liability_pre_balance: f64,
+    pub liquidator_liability_post_balance: f64,
+    pub assets: Vec<LiquidationAssetBalances>,
+}
+
+#[event]
+pub struct LendingAccountLiquidateWithTokensEvent {
+    pub header: GroupEventHeader,
+    pub liquidatee_marginfi_account: Pubkey,
+    pub liquidatee_marginfi_account_authority: Pubkey,
+    pub asset_bank: Pubkey,
+    pub asset_mint: Pubkey,
+    pub liability_bank: Pubkey,
+    pub liability_mint: Pubkey,
+    pub asset_amount: u64,
+    pub liability_amount: u64,
+    pub liquidatee_pre_health: f64,
+    pub liquidatee_post_health: f64,
+}
+
 #[event]
 pub struct MarginfiAccountTransferAccountAuthorityEvent {
     pub header: AccountEventHeader,
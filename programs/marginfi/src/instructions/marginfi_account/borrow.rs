@@ -4,12 +4,20 @@ use crate::{
     events::{AccountEventHeader, LendingAccountBorrowEvent},
     prelude::{MarginfiError, MarginfiGroup, MarginfiResult},
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, RequirementType, RiskEngine, DISABLED_FLAG,
+            IN_FLASHLOAN_FLAG,
+        },
         marginfi_group::{Bank, BankVaultType},
+        price::{OraclePriceFeedAdapter, PriceAdapter, PriceBias},
     },
+    utils,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Transfer};
+use anchor_spl::{
+    token::Transfer,
+    token_interface::{TokenAccount, TokenInterface},
+};
 use fixed::types::I80F48;
 use solana_program::{clock::Clock, sysvar::Sysvar};
 
@@ -17,11 +25,22 @@ use solana_program::{clock::Clock, sysvar::Sysvar};
 /// 2. Create the user's bank account for the asset borrowed if it does not exist yet
 /// 3. Record liability increase in the bank account
 /// 4. Transfer funds from the bank's liquidity vault to the signer's token account
-/// 5. Verify that the user account is in a healthy state
+/// 5. Verify that the bank liabilities are within the USD liability value limit, if active
+/// 6. Verify that the user account is in a healthy state
+///
+/// Token22 banks expect the bank mint as the first remaining account.
+///
+/// In a flashloan the health check is left to the end of the flashloan. While the bank's liability
+/// value limit is active, the remaining accounts still need the bank followed by its oracle
+/// accounts, other balances can be left out.
 ///
 /// Will error if there is an existing asset <=> withdrawing is not allowed.
-pub fn lending_account_borrow(ctx: Context<LendingAccountBorrow>, amount: u64) -> MarginfiResult {
+pub fn lending_account_borrow<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingAccountBorrow<'info>>,
+    amount: u64,
+) -> MarginfiResult {
     let LendingAccountBorrow {
+        marginfi_group: marginfi_group_loader,
         marginfi_account: marginfi_account_loader,
         destination_token_account,
         bank_liquidity_vault,
@@ -44,6 +63,13 @@ pub fn lending_account_borrow(ctx: Context<LendingAccountBorrow>, amount: u64) -
         bank_loader.key(),
     )?;
 
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_bank_mint = utils::maybe_take_bank_mint(
+        &mut remaining_accounts,
+        &*bank_loader.load()?,
+        token_program.key,
+    )?;
+
     {
         let mut bank = bank_loader.load_mut()?;
         let liquidity_vault_authority_bump = bank.liquidity_vault_authority_bump;
@@ -62,6 +88,7 @@ pub fn lending_account_borrow(ctx: Context<LendingAccountBorrow>, amount: u64) -
                 to: destination_token_account.to_account_info(),
                 authority: bank_liquidity_vault_authority.to_account_info(),
             },
+            maybe_bank_mint.as_ref(),
             token_program.to_account_info(),
             bank_signer!(
                 BankVaultType::Liquidity,
@@ -83,13 +110,72 @@ pub fn lending_account_borrow(ctx: Context<LendingAccountBorrow>, amount: u64) -
         });
     }
 
+    let liability_value_limit_active = bank_loader.load()?.liability_value_limit_active();
+
+    // Liabilities are valued at the initial requirement price, biased high
+    let price_type = RequirementType::Initial.get_oracle_price_type();
+
+    if marginfi_account.get_flag(IN_FLASHLOAN_FLAG) {
+        // The health check is skipped in flashloans, the liability value limit is not. Only the
+        // borrowed bank's price is loaded.
+        if liability_value_limit_active {
+            let bank = bank_loader.load()?;
+            let oracle_ais = get_bank_oracle_ais(&bank_loader.key(), &bank, remaining_accounts)?;
+            let price = OraclePriceFeedAdapter::try_from_bank(
+                &bank,
+                oracle_ais,
+                Clock::get()?.unix_timestamp,
+            )?
+            .get_price_of_type(price_type, Some(PriceBias::High))?;
+
+            bank.check_total_liability_value_limit(price)?;
+        }
+
+        return Ok(());
+    }
+
+    // Assuming `remaining_accounts` holds only oracle accounts
+    let risk_engine = RiskEngine::new_no_flashloan_check(
+        &*marginfi_group_loader.load()?,
+        &marginfi_account,
+        remaining_accounts,
+    )?;
+
+    if liability_value_limit_active {
+        let price = risk_engine.get_bank_price(
+            &bank_loader.key(),
+            RequirementType::Initial,
+            Some(PriceBias::High),
+        )?;
+        bank_loader
+            .load()?
+            .check_total_liability_value_limit(price)?;
+    }
+
     // Check account health, if below threshold fail transaction
-    // Assuming `ctx.remaining_accounts` holds only oracle accounts
-    RiskEngine::check_account_init_health(&marginfi_account, ctx.remaining_accounts)?;
+    risk_engine.check_init_health()?;
 
     Ok(())
 }
 
+/// Oracle accounts of `bank`, following the bank account in `remaining_ais`.
+fn get_bank_oracle_ais<'a, 'info>(
+    bank_pk: &Pubkey,
+    bank: &Bank,
+    remaining_ais: &'a [AccountInfo<'info>],
+) -> MarginfiResult<&'a [AccountInfo<'info>]> {
+    let oracle_ais_start = remaining_ais
+        .iter()
+        .position(|ai| ai.key == bank_pk)
+        .ok_or(MarginfiError::MissingPythOrBankAccount)?
+        + 1;
+    let oracle_ais_end = oracle_ais_start + bank.config.get_oracle_keys().len();
+
+    remaining_ais
+        .get(oracle_ais_start..oracle_ais_end)
+        .ok_or_else(|| MarginfiError::MissingPythOrBankAccount.into())
+}
+
 #[derive(Accounts)]
 pub struct LendingAccountBorrow<'info> {
     pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
@@ -112,7 +198,7 @@ pub struct LendingAccountBorrow<'info> {
     pub bank: AccountLoader<'info, Bank>,
 
     #[account(mut)]
-    pub destination_token_account: Account<'info, TokenAccount>,
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: Seed constraint check
     #[account(
@@ -133,7 +219,7 @@ pub struct LendingAccountBorrow<'info> {
         ],
         bump = bank.load() ?.liquidity_vault_bump,
     )]
-    pub bank_liquidity_vault: Account<'info, TokenAccount>,
+    pub bank_liquidity_vault: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
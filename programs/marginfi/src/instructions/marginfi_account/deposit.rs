@@ -7,9 +7,10 @@ use crate::{
         marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
         marginfi_group::Bank,
     },
+    utils,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, Transfer};
+use anchor_spl::{token::Transfer, token_interface::TokenInterface};
 use fixed::types::I80F48;
 use solana_program::clock::Clock;
 use solana_program::sysvar::Sysvar;
@@ -19,8 +20,14 @@ use solana_program::sysvar::Sysvar;
 /// 3. Record asset increase in the bank account
 /// 4. Transfer funds from the signer's token account to the bank's liquidity vault
 ///
+/// Token22 banks expect the bank mint as the first remaining account,
+/// deposits are credited with the amount received after the mint transfer fee.
+///
 /// Will error if there is an existing liability <=> repaying is not allowed.
-pub fn lending_account_deposit(ctx: Context<LendingAccountDeposit>, amount: u64) -> MarginfiResult {
+pub fn lending_account_deposit<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingAccountDeposit<'info>>,
+    amount: u64,
+) -> MarginfiResult {
     let LendingAccountDeposit {
         marginfi_account: marginfi_account_loader,
         signer,
@@ -34,13 +41,19 @@ pub fn lending_account_deposit(ctx: Context<LendingAccountDeposit>, amount: u64)
     let mut bank = bank_loader.load_mut()?;
     let mut marginfi_account = marginfi_account_loader.load_mut()?;
 
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut remaining_accounts, &bank, token_program.key)?;
+
     check!(
         !marginfi_account.get_flag(DISABLED_FLAG),
         MarginfiError::AccountDisabled
     );
 
+    let clock = Clock::get()?;
+
     bank.accrue_interest(
-        Clock::get()?.unix_timestamp,
+        clock.unix_timestamp,
         #[cfg(not(feature = "client"))]
         bank_loader.key(),
     )?;
@@ -51,7 +64,12 @@ pub fn lending_account_deposit(ctx: Context<LendingAccountDeposit>, amount: u64)
         &mut marginfi_account.lending_account,
     )?;
 
-    bank_account.deposit(I80F48::from_num(amount))?;
+    let amount_post_fee = match maybe_bank_mint.as_ref() {
+        Some(mint) => utils::calculate_post_fee_spl_deposit_amount(mint, amount, clock.epoch)?,
+        None => amount,
+    };
+
+    bank_account.deposit(I80F48::from_num(amount_post_fee))?;
     bank_account.deposit_spl_transfer(
         amount,
         Transfer {
@@ -59,6 +77,7 @@ pub fn lending_account_deposit(ctx: Context<LendingAccountDeposit>, amount: u64)
             to: bank_liquidity_vault.to_account_info(),
             authority: signer.to_account_info(),
         },
+        maybe_bank_mint.as_ref(),
         token_program.to_account_info(),
     )?;
 
@@ -71,7 +90,7 @@ pub fn lending_account_deposit(ctx: Context<LendingAccountDeposit>, amount: u64)
         },
         bank: bank_loader.key(),
         mint: bank.mint,
-        amount,
+        amount: amount_post_fee,
     });
 
     Ok(())
@@ -113,5 +132,5 @@ pub struct LendingAccountDeposit<'info> {
     )]
     pub bank_liquidity_vault: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
@@ -0,0 +1,75 @@
+use crate::{
+    check,
+    constants::EMODE_CATEGORY_NONE,
+    events::{AccountEventHeader, MarginfiAccountSetEmodeEvent},
+    prelude::*,
+    state::marginfi_account::{MarginfiAccount, RiskEngine, DISABLED_FLAG},
+};
+use anchor_lang::prelude::*;
+
+/// 1. Opt the account into e-mode category `emode_category`, or out of e-mode with category 0
+/// 2. Verify that the user account is in a healthy state
+///
+/// Category weights apply while every balance of the account is in a bank of the category, the
+/// bank weights apply otherwise.
+pub fn marginfi_account_set_emode(
+    ctx: Context<MarginfiAccountSetEmode>,
+    emode_category: u8,
+) -> MarginfiResult {
+    let MarginfiAccountSetEmode {
+        marginfi_group: marginfi_group_loader,
+        marginfi_account: marginfi_account_loader,
+        signer,
+    } = ctx.accounts;
+
+    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+    let marginfi_group = marginfi_group_loader.load()?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    check!(
+        emode_category == EMODE_CATEGORY_NONE
+            || marginfi_group.get_emode_category(emode_category).is_some(),
+        MarginfiError::InvalidEmodeCategory
+    );
+
+    marginfi_account.emode_category = emode_category;
+
+    // Leaving e-mode can lower the account health
+    RiskEngine::check_account_init_health(
+        &marginfi_group,
+        &marginfi_account,
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(MarginfiAccountSetEmodeEvent {
+        header: AccountEventHeader {
+            signer: Some(signer.key()),
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        emode_category,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MarginfiAccountSetEmode<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+}
@@ -7,7 +7,9 @@ use solana_program::{
 use crate::{
     check,
     prelude::*,
-    state::marginfi_account::{MarginfiAccount, RiskEngine, DISABLED_FLAG, IN_FLASHLOAN_FLAG},
+    state::marginfi_account::{
+        BalanceSide, MarginfiAccount, RiskEngine, DISABLED_FLAG, IN_FLASHLOAN_FLAG,
+    },
 };
 
 pub fn lending_account_start_flashloan(
@@ -41,7 +43,7 @@ const END_FL_IX_MARGINFI_ACCOUNT_AI_IDX: usize = 0;
 
 /// Checklist
 /// 1. `end_flashloan` ix index is after `start_flashloan` ix index
-/// 2. Ixs has an `end_flashloan` ix present
+/// 2. Ixs has an `end_flashloan` or `end_flashloan_v2` ix present
 /// 3. `end_flashloan` ix is for the marginfi program
 /// 3. `end_flashloan` ix is for the same marginfi account
 /// 4. Account is not disabled
@@ -84,7 +86,9 @@ pub fn check_flashloan_can_start(
 
     check!(
         unchecked_end_fl_ix.data[..8]
-            .eq(&crate::instruction::LendingAccountEndFlashloan::DISCRIMINATOR),
+            .eq(&crate::instruction::LendingAccountEndFlashloan::DISCRIMINATOR)
+            || unchecked_end_fl_ix.data[..8]
+                .eq(&crate::instruction::LendingAccountEndFlashloanV2::DISCRIMINATOR),
         MarginfiError::IllegalFlashloan
     );
 
@@ -120,6 +124,9 @@ pub fn check_flashloan_can_start(
     Ok(())
 }
 
+/// Legacy end of a flashloan, without the group e-mode categories and weight overrides the
+/// health check needs. Only flashloans leaving the account without debt can end here, the account
+/// is then healthy whatever its weights. Other flashloans end with `lending_account_end_flashloan_v2`.
 pub fn lending_account_end_flashloan(
     ctx: Context<LendingAccountEndFlashloan>,
 ) -> MarginfiResult<()> {
@@ -133,7 +140,17 @@ pub fn lending_account_end_flashloan(
 
     marginfi_account.unset_flag(IN_FLASHLOAN_FLAG);
 
-    RiskEngine::check_account_init_health(&marginfi_account, ctx.remaining_accounts)?;
+    check!(
+        !marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .any(|balance| {
+                balance.active && matches!(balance.get_side(), Some(BalanceSide::Liabilities))
+            }),
+        MarginfiError::IllegalFlashloan,
+        "Flashloans leaving debt must end with lending_account_end_flashloan_v2"
+    );
 
     Ok(())
 }
@@ -145,3 +162,36 @@ pub struct LendingAccountEndFlashloan<'info> {
     #[account(address = marginfi_account.load()?.authority)]
     pub signer: Signer<'info>,
 }
+
+pub fn lending_account_end_flashloan_v2(
+    ctx: Context<LendingAccountEndFlashloanV2>,
+) -> MarginfiResult<()> {
+    check!(
+        get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT,
+        MarginfiError::IllegalFlashloan,
+        "End flashloan ix should not be in CPI"
+    );
+
+    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+
+    marginfi_account.unset_flag(IN_FLASHLOAN_FLAG);
+
+    RiskEngine::check_account_init_health(
+        &*ctx.accounts.marginfi_group.load()?,
+        &marginfi_account,
+        ctx.remaining_accounts,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountEndFlashloanV2<'info> {
+    #[account(mut)]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+    #[account(address = marginfi_account.load()?.authority)]
+    pub signer: Signer<'info>,
+    /// Last, to keep `END_FL_IX_MARGINFI_ACCOUNT_AI_IDX` unchanged
+    #[account(address = marginfi_account.load()?.group)]
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+}
@@ -1,6 +1,4 @@
-use crate::constants::{
-    INSURANCE_VAULT_SEED, LIQUIDATION_INSURANCE_FEE, LIQUIDATION_LIQUIDATOR_FEE,
-};
+use crate::constants::INSURANCE_VAULT_SEED;
 use crate::events::{AccountEventHeader, LendingAccountLiquidateEvent, LiquidationBalances};
 use crate::state::marginfi_account::{calc_amount, calc_value, RiskEngine};
 use crate::state::marginfi_group::{Bank, BankVaultType};
@@ -10,9 +8,12 @@ use crate::{
     constants::{LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED},
     state::marginfi_account::{BankAccountWrapper, MarginfiAccount},
 };
-use crate::{check, debug, prelude::*};
+use crate::{check, debug, prelude::*, utils};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Transfer};
+use anchor_spl::{
+    token::Transfer,
+    token_interface::{TokenAccount, TokenInterface},
+};
 use fixed::types::I80F48;
 use solana_program::clock::Clock;
 use solana_program::sysvar::Sysvar;
@@ -31,6 +32,7 @@ use solana_program::sysvar::Sysvar;
 /// - `p_a`: Price of `A`
 /// - `f_l`: Liquidation fee
 /// - `f_i`: Insurance fee
+/// - `s`: Maintenance shortfall ratio of the liquidatee, `(liabilities - assets) / liabilities`
 ///
 /// The liquidator invokes this instruction with `q_a` as input (the total amount of collateral to be liquidated).
 /// This is done because `q_a` is the most bounded variable in this process, as if the `q_a` is larger than what the liquidatee has, the instruction will fail.
@@ -39,6 +41,10 @@ use solana_program::sysvar::Sysvar;
 /// Fees:
 /// The liquidator fee is charged in the conversion between the market value of the collateral being liquidated and the liability being covered by the liquidator.
 /// The value of the liability is discounted by the liquidation fee.
+/// The liquidation fee is a Dutch auction, `f_l = min(f_l_base + s, f_l_max)`, cheap for slightly unhealthy accounts
+/// and growing with how far the account is underwater.
+///
+/// The liability bank close factor caps `q_lf` to a share of the liquidatee's liability.
 ///
 /// The insurance fee is taken from the difference between liability being paid by the liquidator and the liability being received by the liquidatee.
 /// This difference is deposited into the insurance fund.
@@ -65,8 +71,10 @@ use solana_program::sysvar::Sysvar;
 /// assuming that the liquidatee liability token balance doesn't become positive (doesn't become counted as collateral),
 /// and that the liquidatee collateral token balance doesn't become negative (doesn't become counted as liability).
 ///
-pub fn lending_account_liquidate(
-    ctx: Context<LendingAccountLiquidate>,
+/// Token22 liability banks expect the liability bank mint as the first remaining account.
+///
+pub fn lending_account_liquidate<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingAccountLiquidate<'info>>,
     asset_amount: u64,
 ) -> MarginfiResult {
     check!(
@@ -87,9 +95,11 @@ pub fn lending_account_liquidate(
         ..
     } = ctx.accounts;
 
+    let marginfi_group = ctx.accounts.marginfi_group.load()?;
     let mut liquidator_marginfi_account = liquidator_marginfi_account_loader.load_mut()?;
     let mut liquidatee_marginfi_account = liquidatee_marginfi_account_loader.load_mut()?;
-    let current_timestamp = Clock::get()?.unix_timestamp;
+    let clock = Clock::get()?;
+    let current_timestamp = clock.unix_timestamp;
 
     {
         ctx.accounts.asset_bank.load_mut()?.accrue_interest(
@@ -104,14 +114,51 @@ pub fn lending_account_liquidate(
         )?;
     }
 
-    let pre_liquidation_health = {
-        let liquidatee_accounts_starting_pos =
-            ctx.remaining_accounts.len() - liquidatee_marginfi_account.get_remaining_accounts_len();
-        let liquidatee_remaining_accounts =
-            &ctx.remaining_accounts[liquidatee_accounts_starting_pos..];
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_liab_bank_mint = utils::maybe_take_bank_mint(
+        &mut remaining_accounts,
+        &*ctx.accounts.liab_bank.load()?,
+        ctx.accounts.token_program.key,
+    )?;
+
+    // Asset and liability bank oracles lead the remaining accounts.
+    let asset_oracles_len = ctx
+        .accounts
+        .asset_bank
+        .load()?
+        .config
+        .get_oracle_keys()
+        .len();
+    let liab_oracles_len = ctx
+        .accounts
+        .liab_bank
+        .load()?
+        .config
+        .get_oracle_keys()
+        .len();
+    let oracles_len = asset_oracles_len + liab_oracles_len;
+
+    let (pre_liquidation_health, shortfall_ratio) = {
+        let liquidatee_accounts_starting_pos = remaining_accounts
+            .len()
+            .checked_sub(
+                liquidatee_marginfi_account.get_remaining_accounts_len(remaining_accounts)?,
+            )
+            .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+        let liquidatee_remaining_accounts = &remaining_accounts[liquidatee_accounts_starting_pos..];
+
+        let risk_engine = RiskEngine::new(
+            &marginfi_group,
+            &liquidatee_marginfi_account,
+            liquidatee_remaining_accounts,
+        )?;
 
-        RiskEngine::new(&liquidatee_marginfi_account, liquidatee_remaining_accounts)?
-            .check_pre_liquidation_condition_and_get_account_health(&ctx.accounts.liab_bank.key())?
+        (
+            risk_engine.check_pre_liquidation_condition_and_get_account_health(
+                &ctx.accounts.liab_bank.key(),
+            )?,
+            risk_engine.get_maintenance_shortfall_ratio()?,
+        )
     };
 
     // ##Accounting changes##
@@ -120,52 +167,20 @@ pub fn lending_account_liquidate(
         let asset_amount = I80F48::from_num(asset_amount);
 
         let mut asset_bank = ctx.accounts.asset_bank.load_mut()?;
-        let asset_price = {
-            let oracle_ais = &ctx.remaining_accounts[0..1];
-            let asset_pf = OraclePriceFeedAdapter::try_from_bank_config(
-                &asset_bank.config,
-                oracle_ais,
-                current_timestamp,
-            )?;
-            asset_pf.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))?
-        };
-
         let mut liab_bank = ctx.accounts.liab_bank.load_mut()?;
-        let liab_price = {
-            let oracle_ais = &ctx.remaining_accounts[1..2];
-            let liab_pf = OraclePriceFeedAdapter::try_from_bank_config(
-                &liab_bank.config,
-                oracle_ais,
-                current_timestamp,
-            )?;
-            liab_pf.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))?
-        };
-
-        let final_discount = I80F48::ONE - (LIQUIDATION_INSURANCE_FEE + LIQUIDATION_LIQUIDATOR_FEE);
-        let liquidator_discount = I80F48::ONE - LIQUIDATION_LIQUIDATOR_FEE;
-
-        // Quantity of liability to be paid off by liquidator
-        let liab_amount_liquidator = calc_amount(
-            calc_value(
-                asset_amount,
-                asset_price,
-                asset_bank.mint_decimals,
-                Some(liquidator_discount),
-            )?,
-            liab_price,
-            liab_bank.mint_decimals,
-        )?;
 
-        // Quantity of liability to be received by liquidatee
-        let liab_amount_final = calc_amount(
-            calc_value(
-                asset_amount,
-                asset_price,
-                asset_bank.mint_decimals,
-                Some(final_discount),
-            )?,
-            liab_price,
-            liab_bank.mint_decimals,
+        let (liab_amount_liquidator, liab_amount_final) = calc_liquidation_liab_amounts(
+            &mut asset_bank,
+            &remaining_accounts[0..asset_oracles_len],
+            #[cfg(not(feature = "client"))]
+            ctx.accounts.asset_bank.key(),
+            &mut liab_bank,
+            &remaining_accounts[asset_oracles_len..oracles_len],
+            #[cfg(not(feature = "client"))]
+            ctx.accounts.liab_bank.key(),
+            asset_amount,
+            shortfall_ratio,
+            &clock,
         )?;
 
         // Insurance fund fee
@@ -268,6 +283,13 @@ pub fn lending_account_liquidate(
                     liquidatee_liab_bank_account.balance.liability_shares.into(),
                 )?;
 
+            liquidatee_liab_bank_account
+                .bank
+                .check_liquidation_close_factor(
+                    liab_amount_final,
+                    liquidatee_liability_pre_balance,
+                )?;
+
             liquidatee_liab_bank_account.increase_balance(liab_amount_final)?;
 
             let liquidatee_liability_post_balance =
@@ -287,6 +309,7 @@ pub fn lending_account_liquidate(
                         .bank_liquidity_vault_authority
                         .to_account_info(),
                 },
+                maybe_liab_bank_mint.as_ref(),
                 ctx.accounts.token_program.to_account_info(),
                 bank_signer!(
                     BankVaultType::Liquidity,
@@ -325,20 +348,26 @@ pub fn lending_account_liquidate(
 
     // ## Risk checks ##
 
-    let (liquidator_remaining_accounts, liquidatee_remaining_accounts) = ctx.remaining_accounts
-        [2..]
-        .split_at(liquidator_marginfi_account.get_remaining_accounts_len());
+    let accounts_remaining_accounts = &remaining_accounts[oracles_len..];
+    let (liquidator_remaining_accounts, liquidatee_remaining_accounts) =
+        accounts_remaining_accounts.split_at(
+            liquidator_marginfi_account.get_remaining_accounts_len(accounts_remaining_accounts)?,
+        );
 
     // Verify liquidatee liquidation post health
-    let post_liquidation_health =
-        RiskEngine::new(&liquidatee_marginfi_account, liquidatee_remaining_accounts)?
-            .check_post_liquidation_condition_and_get_account_health(
-                &ctx.accounts.liab_bank.key(),
-                pre_liquidation_health,
-            )?;
+    let post_liquidation_health = RiskEngine::new(
+        &marginfi_group,
+        &liquidatee_marginfi_account,
+        liquidatee_remaining_accounts,
+    )?
+    .check_post_liquidation_condition_and_get_account_health(
+        &ctx.accounts.liab_bank.key(),
+        pre_liquidation_health,
+    )?;
 
     // Verify liquidator account health
-    RiskEngine::check_account_init_health(
+    RiskEngine::check_liquidator_init_health(
+        &marginfi_group,
         &liquidator_marginfi_account,
         liquidator_remaining_accounts,
     )?;
@@ -365,6 +394,94 @@ pub fn lending_account_liquidate(
     Ok(())
 }
 
+/// Prices a liquidation seizing `asset_amount` of `asset_bank` collateral to pay down `liab_bank`
+/// debt, recording the oracle prices with the bank price guards.
+///
+/// Returns (`q_ll`, `q_lf`), see [`lending_account_liquidate`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn calc_liquidation_liab_amounts(
+    asset_bank: &mut Bank,
+    asset_oracle_ais: &[AccountInfo],
+    #[cfg(not(feature = "client"))] asset_bank_pk: Pubkey,
+    liab_bank: &mut Bank,
+    liab_oracle_ais: &[AccountInfo],
+    #[cfg(not(feature = "client"))] liab_bank_pk: Pubkey,
+    asset_amount: I80F48,
+    shortfall_ratio: I80F48,
+    clock: &Clock,
+) -> MarginfiResult<(I80F48, I80F48)> {
+    let asset_price = {
+        let asset_pf = OraclePriceFeedAdapter::try_from_bank(
+            asset_bank,
+            asset_oracle_ais,
+            clock.unix_timestamp,
+        )?;
+
+        // Liquidations go on while the price guard is tripped, only passing prices are recorded
+        asset_bank.update_oracle_price_guard(
+            asset_pf.get_price_of_type(OraclePriceType::RealTime, None)?,
+            asset_pf.get_price_source(),
+            clock,
+            #[cfg(not(feature = "client"))]
+            asset_bank_pk,
+        )?;
+
+        asset_pf.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))?
+    };
+
+    let liab_price = {
+        let liab_pf = OraclePriceFeedAdapter::try_from_bank(
+            liab_bank,
+            liab_oracle_ais,
+            clock.unix_timestamp,
+        )?;
+
+        liab_bank.update_oracle_price_guard(
+            liab_pf.get_price_of_type(OraclePriceType::RealTime, None)?,
+            liab_pf.get_price_source(),
+            clock,
+            #[cfg(not(feature = "client"))]
+            liab_bank_pk,
+        )?;
+
+        liab_pf.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))?
+    };
+
+    // Liquidation fees are charged by the bank whose collateral is being liquidated, the
+    // liquidator fee grows with how far the liquidatee is below maintenance
+    let liquidator_fee = asset_bank.get_liquidation_liquidator_fee(shortfall_ratio);
+    let insurance_fee = asset_bank.get_liquidation_insurance_fee();
+
+    let final_discount = I80F48::ONE - (insurance_fee + liquidator_fee);
+    let liquidator_discount = I80F48::ONE - liquidator_fee;
+
+    // Quantity of liability to be paid off by liquidator
+    let liab_amount_liquidator = calc_amount(
+        calc_value(
+            asset_amount,
+            asset_price,
+            asset_bank.mint_decimals,
+            Some(liquidator_discount),
+        )?,
+        liab_price,
+        liab_bank.mint_decimals,
+    )?;
+
+    // Quantity of liability to be received by liquidatee
+    let liab_amount_final = calc_amount(
+        calc_value(
+            asset_amount,
+            asset_price,
+            asset_bank.mint_decimals,
+            Some(final_discount),
+        )?,
+        liab_price,
+        liab_bank.mint_decimals,
+    )?;
+
+    Ok((liab_amount_liquidator, liab_amount_final))
+}
+
 #[derive(Accounts)]
 pub struct LendingAccountLiquidate<'info> {
     pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
@@ -418,7 +535,7 @@ pub struct LendingAccountLiquidate<'info> {
         ],
         bump = liab_bank.load()?.liquidity_vault_bump
     )]
-    pub bank_liquidity_vault: Box<Account<'info, TokenAccount>>,
+    pub bank_liquidity_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// CHECK: Seed constraint
     #[account(
@@ -431,5 +548,5 @@ pub struct LendingAccountLiquidate<'info> {
     )]
     pub bank_insurance_vault: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
@@ -0,0 +1,466 @@
+use super::calc_liquidation_liab_amounts;
+use crate::{
+    bank_signer, check,
+    constants::{INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED},
+    debug,
+    events::{AccountEventHeader, LendingAccountLiquidateMultipleEvent, LiquidationAssetBalances},
+    prelude::*,
+    state::{
+        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine},
+        marginfi_group::{Bank, BankVaultType},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Transfer,
+    token_interface::{TokenAccount, TokenInterface},
+};
+use fixed::types::I80F48;
+use solana_program::{clock::Clock, sysvar::Sysvar};
+
+/// Liquidates several collateral positions of a margin account that is in a unhealthy state
+/// against a single liability, like [`super::lending_account_liquidate`] called once per asset
+/// bank.
+///
+/// `asset_amounts[i]` is the `q_a` seized from the i-th asset bank. Each seizure is priced and
+/// charged the fees of its asset bank, the liability payments are summed into a single
+/// repayment, close factor check and insurance fund transfer. The liquidatee health is checked
+/// once before and once after all the seizures.
+///
+/// Remaining accounts:
+/// 1. Liability bank mint, for Token22 liability banks
+/// 2. For each asset amount, the asset bank (writable) followed by its oracles
+/// 3. Liability bank oracles
+/// 4. Liquidator bank and oracle accounts, including the banks of balances the liquidation opens
+/// 5. Liquidatee bank and oracle accounts
+pub fn lending_account_liquidate_multiple<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingAccountLiquidateMultiple<'info>>,
+    asset_amounts: Vec<u64>,
+) -> MarginfiResult {
+    check!(
+        !asset_amounts.is_empty(),
+        MarginfiError::IllegalLiquidation,
+        "No asset amounts provided"
+    );
+
+    check!(
+        asset_amounts.iter().all(|amount| *amount > 0),
+        MarginfiError::IllegalLiquidation,
+        "Asset amounts must be positive"
+    );
+
+    let LendingAccountLiquidateMultiple {
+        liquidator_marginfi_account: liquidator_marginfi_account_loader,
+        liquidatee_marginfi_account: liquidatee_marginfi_account_loader,
+        ..
+    } = ctx.accounts;
+
+    let marginfi_group = ctx.accounts.marginfi_group.load()?;
+    let mut liquidator_marginfi_account = liquidator_marginfi_account_loader.load_mut()?;
+    let mut liquidatee_marginfi_account = liquidatee_marginfi_account_loader.load_mut()?;
+    let clock = Clock::get()?;
+    let current_timestamp = clock.unix_timestamp;
+    let liab_bank_pk = ctx.accounts.liab_bank.key();
+
+    ctx.accounts.liab_bank.load_mut()?.accrue_interest(
+        current_timestamp,
+        #[cfg(not(feature = "client"))]
+        liab_bank_pk,
+    )?;
+
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_liab_bank_mint = utils::maybe_take_bank_mint(
+        &mut remaining_accounts,
+        &*ctx.accounts.liab_bank.load()?,
+        ctx.accounts.token_program.key,
+    )?;
+
+    // Asset banks with their oracles, then the liability bank oracles, lead the remaining accounts.
+    let mut asset_banks: Vec<(AccountLoader<Bank>, &[AccountInfo], I80F48)> =
+        Vec::with_capacity(asset_amounts.len());
+    let mut cursor = 0;
+
+    for asset_amount in asset_amounts.iter() {
+        let asset_bank_ai = remaining_accounts
+            .get(cursor)
+            .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+        let asset_bank_loader = AccountLoader::<Bank>::try_from(asset_bank_ai)?;
+        let asset_bank_pk = asset_bank_loader.key();
+
+        check!(
+            asset_bank_pk != liab_bank_pk,
+            MarginfiError::IllegalLiquidation,
+            "Asset and liability bank cannot be the same"
+        );
+
+        check!(
+            asset_banks
+                .iter()
+                .all(|(loader, ..)| loader.key() != asset_bank_pk),
+            MarginfiError::IllegalLiquidation,
+            "Asset banks cannot repeat"
+        );
+
+        let oracles_len = {
+            let mut asset_bank = asset_bank_loader.load_mut()?;
+
+            check!(
+                asset_bank.group == ctx.accounts.marginfi_group.key(),
+                MarginfiError::InvalidBankAccount
+            );
+
+            asset_bank.accrue_interest(
+                current_timestamp,
+                #[cfg(not(feature = "client"))]
+                asset_bank_pk,
+            )?;
+
+            asset_bank.config.get_oracle_keys().len()
+        };
+
+        let oracle_ais = remaining_accounts
+            .get(cursor + 1..cursor + 1 + oracles_len)
+            .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+
+        asset_banks.push((
+            asset_bank_loader,
+            oracle_ais,
+            I80F48::from_num(*asset_amount),
+        ));
+        cursor += 1 + oracles_len;
+    }
+
+    let liab_oracles_len = ctx
+        .accounts
+        .liab_bank
+        .load()?
+        .config
+        .get_oracle_keys()
+        .len();
+    let liab_oracle_ais = remaining_accounts
+        .get(cursor..cursor + liab_oracles_len)
+        .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+    let accounts_remaining_accounts = &remaining_accounts[cursor + liab_oracles_len..];
+
+    // The liquidatee accounts trail the liquidator ones, which may include balances the
+    // liquidation is about to open.
+    let liquidatee_accounts_starting_pos = accounts_remaining_accounts
+        .len()
+        .checked_sub(
+            liquidatee_marginfi_account.get_remaining_accounts_len(accounts_remaining_accounts)?,
+        )
+        .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+    let (liquidator_remaining_accounts, liquidatee_remaining_accounts) =
+        accounts_remaining_accounts.split_at(liquidatee_accounts_starting_pos);
+
+    let (pre_liquidation_health, shortfall_ratio) = {
+        let risk_engine = RiskEngine::new(
+            &marginfi_group,
+            &liquidatee_marginfi_account,
+            liquidatee_remaining_accounts,
+        )?;
+
+        (
+            risk_engine.check_pre_liquidation_condition_and_get_account_health(&liab_bank_pk)?,
+            risk_engine.get_maintenance_shortfall_ratio()?,
+        )
+    };
+
+    // ##Accounting changes##
+
+    let mut liab_bank = ctx.accounts.liab_bank.load_mut()?;
+
+    let mut liab_amount_liquidator = I80F48::ZERO;
+    let mut liab_amount_final = I80F48::ZERO;
+    let mut assets = Vec::with_capacity(asset_banks.len());
+
+    for (asset_bank_loader, asset_oracle_ais, asset_amount) in asset_banks.iter() {
+        let asset_bank_pk = asset_bank_loader.key();
+        let mut asset_bank = asset_bank_loader.load_mut()?;
+
+        let (asset_liab_amount_liquidator, asset_liab_amount_final) =
+            calc_liquidation_liab_amounts(
+                &mut asset_bank,
+                asset_oracle_ais,
+                #[cfg(not(feature = "client"))]
+                asset_bank_pk,
+                &mut liab_bank,
+                liab_oracle_ais,
+                #[cfg(not(feature = "client"))]
+                liab_bank_pk,
+                *asset_amount,
+                shortfall_ratio,
+                &clock,
+            )?;
+
+        debug!(
+            "asset_bank: {}, asset_amount: {}, liab_quantity_liq: {}, liab_q_final: {}",
+            asset_bank_pk, asset_amount, asset_liab_amount_liquidator, asset_liab_amount_final
+        );
+
+        liab_amount_liquidator = liab_amount_liquidator
+            .checked_add(asset_liab_amount_liquidator)
+            .ok_or(MarginfiError::MathError)?;
+        liab_amount_final = liab_amount_final
+            .checked_add(asset_liab_amount_final)
+            .ok_or(MarginfiError::MathError)?;
+
+        // Liquidatee pays off `asset_quantity` amount of collateral
+        let (liquidatee_asset_pre_balance, liquidatee_asset_post_balance) = {
+            let mut bank_account = BankAccountWrapper::find(
+                &asset_bank_pk,
+                &mut asset_bank,
+                &mut liquidatee_marginfi_account.lending_account,
+            )?;
+
+            let pre_balance = bank_account
+                .bank
+                .get_asset_amount(bank_account.balance.asset_shares.into())?;
+
+            bank_account
+                .withdraw(*asset_amount)
+                .map_err(|_| MarginfiError::IllegalLiquidation)?;
+
+            let post_balance = bank_account
+                .bank
+                .get_asset_amount(bank_account.balance.asset_shares.into())?;
+
+            (pre_balance, post_balance)
+        };
+
+        // Liquidator receives `asset_quantity` amount of collateral
+        let (liquidator_asset_pre_balance, liquidator_asset_post_balance) = {
+            let mut bank_account = BankAccountWrapper::find_or_create(
+                &asset_bank_pk,
+                &mut asset_bank,
+                &mut liquidator_marginfi_account.lending_account,
+            )?;
+
+            let pre_balance = bank_account
+                .bank
+                .get_asset_amount(bank_account.balance.asset_shares.into())?;
+
+            bank_account.increase_balance_in_liquidation(*asset_amount)?;
+
+            let post_balance = bank_account
+                .bank
+                .get_asset_amount(bank_account.balance.asset_shares.into())?;
+
+            (pre_balance, post_balance)
+        };
+
+        assets.push(LiquidationAssetBalances {
+            asset_bank: asset_bank_pk,
+            asset_mint: asset_bank.mint,
+            asset_amount: asset_amount.to_num::<u64>(),
+            liquidatee_asset_pre_balance: liquidatee_asset_pre_balance.to_num::<f64>(),
+            liquidatee_asset_post_balance: liquidatee_asset_post_balance.to_num::<f64>(),
+            liquidator_asset_pre_balance: liquidator_asset_pre_balance.to_num::<f64>(),
+            liquidator_asset_post_balance: liquidator_asset_post_balance.to_num::<f64>(),
+        });
+    }
+
+    // Insurance fund fee
+    let insurance_fund_fee = liab_amount_liquidator - liab_amount_final;
+
+    assert!(
+        insurance_fund_fee >= I80F48::ZERO,
+        "Insurance fund fee cannot be negative"
+    );
+
+    debug!(
+        "liab_quantity_liq: {}, liab_q_final: {}, insurance_fund_fee: {}",
+        liab_amount_liquidator, liab_amount_final, insurance_fund_fee
+    );
+
+    // Liquidator pays off liability
+    let (liquidator_liability_pre_balance, liquidator_liability_post_balance) = {
+        let mut bank_account = BankAccountWrapper::find_or_create(
+            &liab_bank_pk,
+            &mut liab_bank,
+            &mut liquidator_marginfi_account.lending_account,
+        )?;
+
+        let pre_balance = bank_account
+            .bank
+            .get_liability_amount(bank_account.balance.liability_shares.into())?;
+
+        bank_account.decrease_balance_in_liquidation(liab_amount_liquidator)?;
+
+        let post_balance = bank_account
+            .bank
+            .get_liability_amount(bank_account.balance.liability_shares.into())?;
+
+        (pre_balance, post_balance)
+    };
+
+    let (insurance_fee_to_transfer, insurance_fee_dust) = (
+        insurance_fund_fee
+            .checked_to_num::<u64>()
+            .ok_or(MarginfiError::MathError)?,
+        insurance_fund_fee.frac(),
+    );
+
+    // Liquidatee receives liability payment
+    let (liquidatee_liability_pre_balance, liquidatee_liability_post_balance) = {
+        let liab_bank_liquidity_authority_bump = liab_bank.liquidity_vault_authority_bump;
+
+        let mut bank_account = BankAccountWrapper::find_or_create(
+            &liab_bank_pk,
+            &mut liab_bank,
+            &mut liquidatee_marginfi_account.lending_account,
+        )?;
+
+        let pre_balance = bank_account
+            .bank
+            .get_liability_amount(bank_account.balance.liability_shares.into())?;
+
+        bank_account
+            .bank
+            .check_liquidation_close_factor(liab_amount_final, pre_balance)?;
+
+        bank_account.increase_balance(liab_amount_final)?;
+
+        let post_balance = bank_account
+            .bank
+            .get_liability_amount(bank_account.balance.liability_shares.into())?;
+
+        // ## SPL transfer ##
+        // Insurance fund receives fee
+        bank_account.withdraw_spl_transfer(
+            insurance_fee_to_transfer,
+            Transfer {
+                from: ctx.accounts.bank_liquidity_vault.to_account_info(),
+                to: ctx.accounts.bank_insurance_vault.to_account_info(),
+                authority: ctx
+                    .accounts
+                    .bank_liquidity_vault_authority
+                    .to_account_info(),
+            },
+            maybe_liab_bank_mint.as_ref(),
+            ctx.accounts.token_program.to_account_info(),
+            bank_signer!(
+                BankVaultType::Liquidity,
+                liab_bank_pk,
+                liab_bank_liquidity_authority_bump
+            ),
+        )?;
+
+        (pre_balance, post_balance)
+    };
+
+    liab_bank.collected_insurance_fees_outstanding =
+        I80F48::from(liab_bank.collected_insurance_fees_outstanding)
+            .checked_add(insurance_fee_dust)
+            .ok_or(MarginfiError::MathError)?
+            .into();
+
+    let liability_mint = liab_bank.mint;
+    drop(liab_bank);
+
+    // ## Risk checks ##
+
+    // Verify liquidatee liquidation post health
+    let post_liquidation_health = RiskEngine::new(
+        &marginfi_group,
+        &liquidatee_marginfi_account,
+        liquidatee_remaining_accounts,
+    )?
+    .check_post_liquidation_condition_and_get_account_health(
+        &liab_bank_pk,
+        pre_liquidation_health,
+    )?;
+
+    // Verify liquidator account health
+    RiskEngine::check_liquidator_init_health(
+        &marginfi_group,
+        &liquidator_marginfi_account,
+        liquidator_remaining_accounts,
+    )?;
+
+    emit!(LendingAccountLiquidateMultipleEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: liquidator_marginfi_account_loader.key(),
+            marginfi_account_authority: liquidator_marginfi_account.authority,
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+        },
+        liquidatee_marginfi_account: liquidatee_marginfi_account_loader.key(),
+        liquidatee_marginfi_account_authority: liquidatee_marginfi_account.authority,
+        liability_bank: liab_bank_pk,
+        liability_mint,
+        liquidatee_pre_health: pre_liquidation_health.to_num::<f64>(),
+        liquidatee_post_health: post_liquidation_health.to_num::<f64>(),
+        liquidatee_liability_pre_balance: liquidatee_liability_pre_balance.to_num::<f64>(),
+        liquidatee_liability_post_balance: liquidatee_liability_post_balance.to_num::<f64>(),
+        liquidator_liability_pre_balance: liquidator_liability_pre_balance.to_num::<f64>(),
+        liquidator_liability_post_balance: liquidator_liability_post_balance.to_num::<f64>(),
+        assets,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountLiquidateMultiple<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = liab_bank.load()?.group == marginfi_group.key()
+    )]
+    pub liab_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        constraint = liquidator_marginfi_account.load()?.group == marginfi_group.key()
+    )]
+    pub liquidator_marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = liquidator_marginfi_account.load()?.authority
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = liquidatee_marginfi_account.load()?.group == marginfi_group.key()
+    )]
+    pub liquidatee_marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            liab_bank.key().as_ref(),
+        ],
+        bump = liab_bank.load()?.liquidity_vault_authority_bump
+    )]
+    pub bank_liquidity_vault_authority: AccountInfo<'info>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_SEED.as_bytes(),
+            liab_bank.key().as_ref(),
+        ],
+        bump = liab_bank.load()?.liquidity_vault_bump
+    )]
+    pub bank_liquidity_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            liab_bank.key().as_ref(),
+        ],
+        bump = liab_bank.load()?.insurance_vault_bump
+    )]
+    pub bank_insurance_vault: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,392 @@
+use super::calc_liquidation_liab_amounts;
+use crate::{
+    bank_signer, check,
+    constants::{INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED},
+    debug,
+    events::{GroupEventHeader, LendingAccountLiquidateWithTokensEvent},
+    prelude::*,
+    state::{
+        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine},
+        marginfi_group::{Bank, BankVaultType},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Transfer,
+    token_interface::{TokenAccount, TokenInterface},
+};
+use fixed::types::I80F48;
+use solana_program::{clock::Clock, sysvar::Sysvar};
+
+/// Liquidates a position owned by a margin account that is in a unhealthy state, like
+/// [`super::lending_account_liquidate`], for a liquidator without a marginfi account.
+///
+/// The liquidator repays `q_ll` of the liability, rounded up to a whole token amount, from a token
+/// account and receives the `q_a` of collateral seized into a token account. Pricing, fees, the
+/// close factor and the liquidatee checks are the ones of [`super::lending_account_liquidate`],
+/// what the liquidator pays above `q_lf` goes to the insurance fund.
+///
+/// Remaining accounts:
+/// 1. Liability bank mint, for Token22 liability banks
+/// 2. Asset bank mint, for Token22 asset banks
+/// 3. Asset bank oracles, then liability bank oracles
+/// 4. Liquidatee bank and oracle accounts
+pub fn lending_account_liquidate_with_tokens<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingAccountLiquidateWithTokens<'info>>,
+    asset_amount: u64,
+) -> MarginfiResult {
+    check!(
+        asset_amount > 0,
+        MarginfiError::IllegalLiquidation,
+        "Asset amount must be positive"
+    );
+
+    check!(
+        ctx.accounts.asset_bank.key() != ctx.accounts.liab_bank.key(),
+        MarginfiError::IllegalLiquidation,
+        "Asset and liability bank cannot be the same"
+    );
+
+    let LendingAccountLiquidateWithTokens {
+        liquidatee_marginfi_account: liquidatee_marginfi_account_loader,
+        ..
+    } = ctx.accounts;
+
+    let marginfi_group = ctx.accounts.marginfi_group.load()?;
+    let mut liquidatee_marginfi_account = liquidatee_marginfi_account_loader.load_mut()?;
+    let clock = Clock::get()?;
+    let current_timestamp = clock.unix_timestamp;
+
+    {
+        ctx.accounts.asset_bank.load_mut()?.accrue_interest(
+            current_timestamp,
+            #[cfg(not(feature = "client"))]
+            ctx.accounts.asset_bank.key(),
+        )?;
+        ctx.accounts.liab_bank.load_mut()?.accrue_interest(
+            current_timestamp,
+            #[cfg(not(feature = "client"))]
+            ctx.accounts.liab_bank.key(),
+        )?;
+    }
+
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_liab_bank_mint = utils::maybe_take_bank_mint(
+        &mut remaining_accounts,
+        &*ctx.accounts.liab_bank.load()?,
+        ctx.accounts.liab_token_program.key,
+    )?;
+    let maybe_asset_bank_mint = utils::maybe_take_bank_mint(
+        &mut remaining_accounts,
+        &*ctx.accounts.asset_bank.load()?,
+        ctx.accounts.asset_token_program.key,
+    )?;
+
+    let asset_oracles_len = ctx
+        .accounts
+        .asset_bank
+        .load()?
+        .config
+        .get_oracle_keys()
+        .len();
+    let liab_oracles_len = ctx
+        .accounts
+        .liab_bank
+        .load()?
+        .config
+        .get_oracle_keys()
+        .len();
+    let oracles_len = asset_oracles_len + liab_oracles_len;
+
+    let liquidatee_remaining_accounts = remaining_accounts
+        .get(oracles_len..)
+        .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+
+    let (pre_liquidation_health, shortfall_ratio) = {
+        let risk_engine = RiskEngine::new(
+            &marginfi_group,
+            &liquidatee_marginfi_account,
+            liquidatee_remaining_accounts,
+        )?;
+
+        (
+            risk_engine.check_pre_liquidation_condition_and_get_account_health(
+                &ctx.accounts.liab_bank.key(),
+            )?,
+            risk_engine.get_maintenance_shortfall_ratio()?,
+        )
+    };
+
+    // ##Accounting changes##
+
+    let liab_spl_amount = {
+        let asset_amount = I80F48::from_num(asset_amount);
+
+        let mut asset_bank = ctx.accounts.asset_bank.load_mut()?;
+        let mut liab_bank = ctx.accounts.liab_bank.load_mut()?;
+
+        let (liab_amount_liquidator, liab_amount_final) = calc_liquidation_liab_amounts(
+            &mut asset_bank,
+            &remaining_accounts[0..asset_oracles_len],
+            #[cfg(not(feature = "client"))]
+            ctx.accounts.asset_bank.key(),
+            &mut liab_bank,
+            &remaining_accounts[asset_oracles_len..oracles_len],
+            #[cfg(not(feature = "client"))]
+            ctx.accounts.liab_bank.key(),
+            asset_amount,
+            shortfall_ratio,
+            &clock,
+        )?;
+
+        // The liquidator pays whole tokens, rounding in favor of the insurance fund
+        let liab_spl_amount = liab_amount_liquidator
+            .checked_ceil()
+            .and_then(|amount| amount.checked_to_num::<u64>())
+            .ok_or(MarginfiError::MathError)?;
+
+        // Insurance fund fee
+        let insurance_fund_fee = I80F48::from_num(liab_spl_amount) - liab_amount_final;
+
+        assert!(
+            insurance_fund_fee >= I80F48::ZERO,
+            "Insurance fund fee cannot be negative"
+        );
+
+        debug!(
+            "liab_spl_amount: {}, liab_q_final: {}, asset_amount: {}, insurance_fund_fee: {}",
+            liab_spl_amount, liab_amount_final, asset_amount, insurance_fund_fee
+        );
+
+        // Liquidatee pays off `asset_quantity` amount of collateral, sent to the liquidator
+        {
+            let asset_liquidity_authority_bump = asset_bank.liquidity_vault_authority_bump;
+
+            let mut bank_account = BankAccountWrapper::find(
+                &ctx.accounts.asset_bank.key(),
+                &mut asset_bank,
+                &mut liquidatee_marginfi_account.lending_account,
+            )?;
+
+            bank_account
+                .withdraw(asset_amount)
+                .map_err(|_| MarginfiError::IllegalLiquidation)?;
+
+            bank_account.withdraw_spl_transfer(
+                asset_amount.to_num::<u64>(),
+                Transfer {
+                    from: ctx.accounts.asset_bank_liquidity_vault.to_account_info(),
+                    to: ctx
+                        .accounts
+                        .destination_asset_token_account
+                        .to_account_info(),
+                    authority: ctx
+                        .accounts
+                        .asset_bank_liquidity_vault_authority
+                        .to_account_info(),
+                },
+                maybe_asset_bank_mint.as_ref(),
+                ctx.accounts.asset_token_program.to_account_info(),
+                bank_signer!(
+                    BankVaultType::Liquidity,
+                    ctx.accounts.asset_bank.key(),
+                    asset_liquidity_authority_bump
+                ),
+            )?;
+        }
+
+        let (insurance_fee_to_transfer, insurance_fee_dust) = (
+            insurance_fund_fee
+                .checked_to_num::<u64>()
+                .ok_or(MarginfiError::MathError)?,
+            insurance_fund_fee.frac(),
+        );
+
+        // Liquidatee receives liability payment, paid by the liquidator
+        {
+            let liab_liquidity_authority_bump = liab_bank.liquidity_vault_authority_bump;
+
+            let mut bank_account = BankAccountWrapper::find(
+                &ctx.accounts.liab_bank.key(),
+                &mut liab_bank,
+                &mut liquidatee_marginfi_account.lending_account,
+            )?;
+
+            let liquidatee_liability = bank_account
+                .bank
+                .get_liability_amount(bank_account.balance.liability_shares.into())?;
+
+            bank_account
+                .bank
+                .check_liquidation_close_factor(liab_amount_final, liquidatee_liability)?;
+
+            bank_account.increase_balance(liab_amount_final)?;
+
+            // ## SPL transfer ##
+            let liab_spl_amount_pre_fee = match maybe_liab_bank_mint.as_ref() {
+                Some(mint) => {
+                    utils::calculate_pre_fee_spl_deposit_amount(mint, liab_spl_amount, clock.epoch)?
+                }
+                None => liab_spl_amount,
+            };
+
+            bank_account.deposit_spl_transfer(
+                liab_spl_amount_pre_fee,
+                Transfer {
+                    from: ctx.accounts.signer_liab_token_account.to_account_info(),
+                    to: ctx.accounts.liab_bank_liquidity_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+                maybe_liab_bank_mint.as_ref(),
+                ctx.accounts.liab_token_program.to_account_info(),
+            )?;
+
+            // Insurance fund receives fee
+            bank_account.withdraw_spl_transfer(
+                insurance_fee_to_transfer,
+                Transfer {
+                    from: ctx.accounts.liab_bank_liquidity_vault.to_account_info(),
+                    to: ctx.accounts.liab_bank_insurance_vault.to_account_info(),
+                    authority: ctx
+                        .accounts
+                        .liab_bank_liquidity_vault_authority
+                        .to_account_info(),
+                },
+                maybe_liab_bank_mint.as_ref(),
+                ctx.accounts.liab_token_program.to_account_info(),
+                bank_signer!(
+                    BankVaultType::Liquidity,
+                    ctx.accounts.liab_bank.key(),
+                    liab_liquidity_authority_bump
+                ),
+            )?;
+        }
+
+        liab_bank.collected_insurance_fees_outstanding =
+            I80F48::from(liab_bank.collected_insurance_fees_outstanding)
+                .checked_add(insurance_fee_dust)
+                .ok_or(MarginfiError::MathError)?
+                .into();
+
+        liab_spl_amount
+    };
+
+    // ## Risk checks ##
+
+    // Verify liquidatee liquidation post health
+    let post_liquidation_health = RiskEngine::new(
+        &marginfi_group,
+        &liquidatee_marginfi_account,
+        liquidatee_remaining_accounts,
+    )?
+    .check_post_liquidation_condition_and_get_account_health(
+        &ctx.accounts.liab_bank.key(),
+        pre_liquidation_health,
+    )?;
+
+    emit!(LendingAccountLiquidateWithTokensEvent {
+        header: GroupEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+        },
+        liquidatee_marginfi_account: liquidatee_marginfi_account_loader.key(),
+        liquidatee_marginfi_account_authority: liquidatee_marginfi_account.authority,
+        asset_bank: ctx.accounts.asset_bank.key(),
+        asset_mint: ctx.accounts.asset_bank.load()?.mint,
+        liability_bank: ctx.accounts.liab_bank.key(),
+        liability_mint: ctx.accounts.liab_bank.load()?.mint,
+        asset_amount,
+        liability_amount: liab_spl_amount,
+        liquidatee_pre_health: pre_liquidation_health.to_num::<f64>(),
+        liquidatee_post_health: post_liquidation_health.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountLiquidateWithTokens<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = asset_bank.load()?.group == marginfi_group.key()
+    )]
+    pub asset_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        constraint = liab_bank.load()?.group == marginfi_group.key()
+    )]
+    pub liab_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        constraint = liquidatee_marginfi_account.load()?.group == marginfi_group.key()
+    )]
+    pub liquidatee_marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    pub signer: Signer<'info>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub signer_liab_token_account: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub destination_asset_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            asset_bank.key().as_ref(),
+        ],
+        bump = asset_bank.load()?.liquidity_vault_authority_bump
+    )]
+    pub asset_bank_liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_SEED.as_bytes(),
+            asset_bank.key().as_ref(),
+        ],
+        bump = asset_bank.load()?.liquidity_vault_bump
+    )]
+    pub asset_bank_liquidity_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            liab_bank.key().as_ref(),
+        ],
+        bump = liab_bank.load()?.liquidity_vault_authority_bump
+    )]
+    pub liab_bank_liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_SEED.as_bytes(),
+            liab_bank.key().as_ref(),
+        ],
+        bump = liab_bank.load()?.liquidity_vault_bump
+    )]
+    pub liab_bank_liquidity_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            liab_bank.key().as_ref(),
+        ],
+        bump = liab_bank.load()?.insurance_vault_bump
+    )]
+    pub liab_bank_insurance_vault: AccountInfo<'info>,
+
+    pub asset_token_program: Interface<'info, TokenInterface>,
+    pub liab_token_program: Interface<'info, TokenInterface>,
+}
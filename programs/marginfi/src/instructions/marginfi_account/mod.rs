@@ -2,9 +2,13 @@ mod borrow;
 mod close_balance;
 mod deposit;
 mod emissions;
+mod emode;
 mod flashloan;
 mod initialize;
 mod liquidate;
+mod liquidate_multiple;
+mod liquidate_with_tokens;
+mod receipt;
 mod repay;
 mod transfer_authority;
 mod withdraw;
@@ -13,9 +17,13 @@ pub use borrow::*;
 pub use close_balance::*;
 pub use deposit::*;
 pub use emissions::*;
+pub use emode::*;
 pub use flashloan::*;
 pub use initialize::*;
 pub use liquidate::*;
+pub use liquidate_multiple::*;
+pub use liquidate_with_tokens::*;
+pub use receipt::*;
 pub use repay::*;
 pub use transfer_authority::*;
 pub use withdraw::*;
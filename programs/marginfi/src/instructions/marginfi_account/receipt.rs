@@ -0,0 +1,260 @@
+use crate::{
+    check,
+    constants::RECEIPT_MINT_AUTHORITY_SEED,
+    events::{
+        AccountEventHeader, LendingAccountMintReceiptEvent, LendingAccountRedeemReceiptEvent,
+    },
+    prelude::*,
+    state::{
+        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG},
+        marginfi_group::Bank,
+    },
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{burn, mint_to, Burn, Mint, MintTo, Token};
+use fixed::types::I80F48;
+use solana_program::{clock::Clock, sysvar::Sysvar};
+
+/// 1. Accrue interest
+/// 2. Move `amount` asset shares out of the user's bank balance
+/// 3. Mint `amount` bank receipt tokens to the destination token account
+/// 4. Verify that the user account is in a healthy state
+///
+/// One receipt token is one asset share, redeemable for `amount * asset_share_value` of the bank
+/// asset, see `lending_account_redeem_receipt`. Tokenized shares keep earning interest but no
+/// emissions, and don't count as collateral.
+pub fn lending_account_mint_receipt(
+    ctx: Context<LendingAccountMintReceipt>,
+    amount: u64,
+) -> MarginfiResult {
+    let LendingAccountMintReceipt {
+        marginfi_group: marginfi_group_loader,
+        marginfi_account: marginfi_account_loader,
+        bank: bank_loader,
+        receipt_mint,
+        receipt_mint_authority,
+        destination_receipt_token_account,
+        token_program,
+        ..
+    } = ctx.accounts;
+
+    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    let mut bank = bank_loader.load_mut()?;
+
+    check_receipt_mint(&bank, &receipt_mint.key())?;
+
+    bank.accrue_interest(
+        Clock::get()?.unix_timestamp,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+
+    let shares = I80F48::from_num(amount);
+
+    BankAccountWrapper::find(
+        &bank_loader.key(),
+        &mut bank,
+        &mut marginfi_account.lending_account,
+    )?
+    .tokenize_asset_shares(shares)?;
+
+    mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: receipt_mint.to_account_info(),
+                to: destination_receipt_token_account.to_account_info(),
+                authority: receipt_mint_authority.to_account_info(),
+            },
+            &[&[
+                RECEIPT_MINT_AUTHORITY_SEED.as_bytes(),
+                &bank_loader.key().to_bytes(),
+                &[bank.receipt_mint_authority_bump],
+            ]],
+        ),
+        amount,
+    )?;
+
+    emit!(LendingAccountMintReceiptEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        bank: bank_loader.key(),
+        receipt_mint: receipt_mint.key(),
+        amount,
+        asset_amount: bank.get_asset_amount(shares)?.to_num::<f64>(),
+    });
+
+    drop(bank);
+
+    // Check account health, if below threshold fail transaction
+    RiskEngine::check_account_init_health(
+        &*marginfi_group_loader.load()?,
+        &marginfi_account,
+        ctx.remaining_accounts,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountMintReceipt<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub receipt_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Signs with the bank seeds, checked by the token program
+    pub receipt_mint_authority: AccountInfo<'info>,
+
+    /// CHECK: Token mint is checked at mint
+    #[account(mut)]
+    pub destination_receipt_token_account: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// 1. Accrue interest
+/// 2. Burn `amount` bank receipt tokens from the signer's token account
+/// 3. Move `amount` asset shares into the user's bank balance
+///
+/// Will error if the user's bank balance has a liability.
+pub fn lending_account_redeem_receipt(
+    ctx: Context<LendingAccountRedeemReceipt>,
+    amount: u64,
+) -> MarginfiResult {
+    let LendingAccountRedeemReceipt {
+        marginfi_account: marginfi_account_loader,
+        signer,
+        bank: bank_loader,
+        receipt_mint,
+        signer_receipt_token_account,
+        token_program,
+        ..
+    } = ctx.accounts;
+
+    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    let mut bank = bank_loader.load_mut()?;
+
+    check_receipt_mint(&bank, &receipt_mint.key())?;
+
+    bank.accrue_interest(
+        Clock::get()?.unix_timestamp,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+
+    burn(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Burn {
+                mint: receipt_mint.to_account_info(),
+                from: signer_receipt_token_account.to_account_info(),
+                authority: signer.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    let shares = I80F48::from_num(amount);
+
+    BankAccountWrapper::find_or_create(
+        &bank_loader.key(),
+        &mut bank,
+        &mut marginfi_account.lending_account,
+    )?
+    .redeem_asset_shares(shares)?;
+
+    emit!(LendingAccountRedeemReceiptEvent {
+        header: AccountEventHeader {
+            signer: Some(signer.key()),
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        bank: bank_loader.key(),
+        receipt_mint: receipt_mint.key(),
+        amount,
+        asset_amount: bank.get_asset_amount(shares)?.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountRedeemReceipt<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub receipt_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Token mint/authority are checked at burn
+    #[account(mut)]
+    pub signer_receipt_token_account: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+fn check_receipt_mint(bank: &Bank, receipt_mint: &Pubkey) -> MarginfiResult {
+    check!(
+        bank.receipt_mint.ne(&Pubkey::default()),
+        MarginfiError::ReceiptMintNotSetup
+    );
+
+    check!(
+        bank.receipt_mint.eq(receipt_mint),
+        MarginfiError::ReceiptMintNotSetup,
+        "Receipt mint does not match the bank"
+    );
+
+    Ok(())
+}
@@ -7,9 +7,10 @@ use crate::{
         marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
         marginfi_group::Bank,
     },
+    utils,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, Transfer};
+use anchor_spl::{token::Transfer, token_interface::TokenInterface};
 use fixed::types::I80F48;
 use solana_program::{clock::Clock, sysvar::Sysvar};
 
@@ -18,9 +19,12 @@ use solana_program::{clock::Clock, sysvar::Sysvar};
 /// 3. Record liability decrease in the bank account
 /// 4. Transfer funds from the signer's token account to the bank's liquidity vault
 ///
+/// Token22 banks expect the bank mint as the first remaining account,
+/// the liability is reduced by the amount received after the mint transfer fee.
+///
 /// Will error if there is no existing liability <=> depositing is not allowed.
-pub fn lending_account_repay(
-    ctx: Context<LendingAccountRepay>,
+pub fn lending_account_repay<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingAccountRepay<'info>>,
     amount: u64,
     repay_all: Option<bool>,
 ) -> MarginfiResult {
@@ -38,13 +42,19 @@ pub fn lending_account_repay(
     let mut bank = bank_loader.load_mut()?;
     let mut marginfi_account = marginfi_account_loader.load_mut()?;
 
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut remaining_accounts, &bank, token_program.key)?;
+
     check!(
         !marginfi_account.get_flag(DISABLED_FLAG),
         MarginfiError::AccountDisabled
     );
 
+    let clock = Clock::get()?;
+
     bank.accrue_interest(
-        Clock::get()?.unix_timestamp,
+        clock.unix_timestamp,
         #[cfg(not(feature = "client"))]
         bank_loader.key(),
     )?;
@@ -55,21 +65,35 @@ pub fn lending_account_repay(
         &mut marginfi_account.lending_account,
     )?;
 
-    let spl_deposit_amount = if repay_all {
-        bank_account.repay_all()?
+    let (spl_deposit_amount, spl_deposit_amount_pre_fee) = if repay_all {
+        let repay_amount = bank_account.repay_all()?;
+        let repay_amount_pre_fee = match maybe_bank_mint.as_ref() {
+            Some(mint) => {
+                utils::calculate_pre_fee_spl_deposit_amount(mint, repay_amount, clock.epoch)?
+            }
+            None => repay_amount,
+        };
+
+        (repay_amount, repay_amount_pre_fee)
     } else {
-        bank_account.repay(I80F48::from_num(amount))?;
+        let repay_amount = match maybe_bank_mint.as_ref() {
+            Some(mint) => utils::calculate_post_fee_spl_deposit_amount(mint, amount, clock.epoch)?,
+            None => amount,
+        };
+
+        bank_account.repay(I80F48::from_num(repay_amount))?;
 
-        amount
+        (repay_amount, amount)
     };
 
     bank_account.deposit_spl_transfer(
-        spl_deposit_amount,
+        spl_deposit_amount_pre_fee,
         Transfer {
             from: signer_token_account.to_account_info(),
             to: bank_liquidity_vault.to_account_info(),
             authority: signer.to_account_info(),
         },
+        maybe_bank_mint.as_ref(),
         token_program.to_account_info(),
     )?;
 
@@ -125,5 +149,5 @@ pub struct LendingAccountRepay<'info> {
     )]
     pub bank_liquidity_vault: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
@@ -7,9 +7,13 @@ use crate::{
         marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG},
         marginfi_group::{Bank, BankVaultType},
     },
+    utils,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Transfer};
+use anchor_spl::{
+    token::Transfer,
+    token_interface::{TokenAccount, TokenInterface},
+};
 use fixed::types::I80F48;
 use solana_program::{clock::Clock, sysvar::Sysvar};
 
@@ -19,13 +23,16 @@ use solana_program::{clock::Clock, sysvar::Sysvar};
 /// 4. Transfer funds from the bank's liquidity vault to the signer's token account
 /// 5. Verify that the user account is in a healthy state
 ///
+/// Token22 banks expect the bank mint as the first remaining account.
+///
 /// Will error if there is no existing asset <=> borrowing is not allowed.
-pub fn lending_account_withdraw(
-    ctx: Context<LendingAccountWithdraw>,
+pub fn lending_account_withdraw<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingAccountWithdraw<'info>>,
     amount: u64,
     withdraw_all: Option<bool>,
 ) -> MarginfiResult {
     let LendingAccountWithdraw {
+        marginfi_group: marginfi_group_loader,
         marginfi_account: marginfi_account_loader,
         destination_token_account,
         bank_liquidity_vault,
@@ -49,6 +56,13 @@ pub fn lending_account_withdraw(
         bank_loader.key(),
     )?;
 
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_bank_mint = utils::maybe_take_bank_mint(
+        &mut remaining_accounts,
+        &*bank_loader.load()?,
+        token_program.key,
+    )?;
+
     {
         let mut bank = bank_loader.load_mut()?;
         let liquidity_vault_authority_bump = bank.liquidity_vault_authority_bump;
@@ -74,6 +88,7 @@ pub fn lending_account_withdraw(
                 to: destination_token_account.to_account_info(),
                 authority: bank_liquidity_vault_authority.to_account_info(),
             },
+            maybe_bank_mint.as_ref(),
             token_program.to_account_info(),
             bank_signer!(
                 BankVaultType::Liquidity,
@@ -97,8 +112,12 @@ pub fn lending_account_withdraw(
     }
 
     // Check account health, if below threshold fail transaction
-    // Assuming `ctx.remaining_accounts` holds only oracle accounts
-    RiskEngine::check_account_init_health(&marginfi_account, ctx.remaining_accounts)?;
+    // Assuming `remaining_accounts` holds only oracle accounts
+    RiskEngine::check_account_init_health(
+        &*marginfi_group_loader.load()?,
+        &marginfi_account,
+        remaining_accounts,
+    )?;
 
     Ok(())
 }
@@ -125,7 +144,7 @@ pub struct LendingAccountWithdraw<'info> {
     pub bank: AccountLoader<'info, Bank>,
 
     #[account(mut)]
-    pub destination_token_account: Account<'info, TokenAccount>,
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: Seed constraint check
     #[account(
@@ -146,7 +165,7 @@ pub struct LendingAccountWithdraw<'info> {
         ],
         bump = bank.load()?.liquidity_vault_bump,
     )]
-    pub bank_liquidity_vault: Account<'info, TokenAccount>,
+    pub bank_liquidity_vault: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
@@ -1,9 +1,15 @@
 use crate::{
-    state::marginfi_group::{Bank, MarginfiGroup},
+    state::{
+        marginfi_group::{Bank, MarginfiGroup},
+        price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter},
+    },
     MarginfiResult,
 };
 use anchor_lang::prelude::*;
 
+/// Bank oracle accounts can be passed as remaining accounts to also refresh the bank price
+/// guard. A price tripping the guard emits `LendingPoolBankPriceGuardTripEvent` and is counted on
+/// the bank without failing, so anyone can record a trip.
 pub fn lending_pool_accrue_bank_interest(
     ctx: Context<LendingPoolAccrueBankInterest>,
 ) -> MarginfiResult {
@@ -16,6 +22,22 @@ pub fn lending_pool_accrue_bank_interest(
         ctx.accounts.bank.key(),
     )?;
 
+    if !ctx.remaining_accounts.is_empty() {
+        let price_feed = OraclePriceFeedAdapter::try_from_bank(
+            &bank,
+            ctx.remaining_accounts,
+            clock.unix_timestamp,
+        )?;
+
+        bank.update_oracle_price_guard(
+            price_feed.get_price_of_type(OraclePriceType::RealTime, None)?,
+            price_feed.get_price_source(),
+            &clock,
+            #[cfg(not(feature = "client"))]
+            ctx.accounts.bank.key(),
+        )?;
+    }
+
     Ok(())
 }
 
@@ -4,20 +4,23 @@ use crate::{
         INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED,
     },
     events::{GroupEventHeader, LendingPoolBankCreateEvent},
-    state::marginfi_group::{Bank, BankConfig, BankConfigCompact, MarginfiGroup},
-    MarginfiResult,
+    state::marginfi_group::{Bank, BankConfigCompact, BankVaultType, MarginfiGroup},
+    utils, MarginfiResult,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenInterface};
 
 /// Add a bank to the lending pool
 ///
 /// Admin only
 ///
+/// Mints owned by either the token or the token-2022 program are supported,
+/// token-2022 mints with extensions that are unsafe for the bank vaults are rejected.
+///
 /// TODO: Allow for different oracle configurations
 pub fn lending_pool_add_bank(
     ctx: Context<LendingPoolAddBank>,
-    bank_config: BankConfig,
+    bank_config: BankConfigCompact,
 ) -> MarginfiResult {
     let LendingPoolAddBank {
         bank_mint,
@@ -25,10 +28,17 @@ pub fn lending_pool_add_bank(
         insurance_vault,
         fee_vault,
         bank: bank_loader,
+        liquidity_vault_authority,
+        insurance_vault_authority,
+        fee_vault_authority,
+        fee_payer,
+        token_program,
+        system_program,
+        rent,
         ..
-    } = ctx.accounts;
+    } = &*ctx.accounts;
 
-    let mut bank = bank_loader.load_init()?;
+    utils::validate_bank_mint(&bank_mint.to_account_info())?;
 
     let liquidity_vault_bump = *ctx.bumps.get("liquidity_vault").unwrap();
     let liquidity_vault_authority_bump = *ctx.bumps.get("liquidity_vault_authority").unwrap();
@@ -37,6 +47,42 @@ pub fn lending_pool_add_bank(
     let fee_vault_bump = *ctx.bumps.get("fee_vault").unwrap();
     let fee_vault_authority_bump = *ctx.bumps.get("fee_vault_authority").unwrap();
 
+    for (vault_type, vault, vault_bump, vault_authority) in [
+        (
+            BankVaultType::Liquidity,
+            liquidity_vault,
+            liquidity_vault_bump,
+            liquidity_vault_authority,
+        ),
+        (
+            BankVaultType::Insurance,
+            insurance_vault,
+            insurance_vault_bump,
+            insurance_vault_authority,
+        ),
+        (
+            BankVaultType::Fee,
+            fee_vault,
+            fee_vault_bump,
+            fee_vault_authority,
+        ),
+    ] {
+        utils::create_bank_vault(
+            vault_type,
+            &bank_loader.key(),
+            vault_bump,
+            vault,
+            vault_authority,
+            &bank_mint.to_account_info(),
+            &fee_payer.to_account_info(),
+            &token_program.to_account_info(),
+            &system_program.to_account_info(),
+            rent,
+        )?;
+    }
+
+    let mut bank = bank_loader.load_init()?;
+
     *bank = Bank::new(
         ctx.accounts.marginfi_group.key(),
         bank_config,
@@ -54,8 +100,8 @@ pub fn lending_pool_add_bank(
         fee_vault_authority_bump,
     );
 
-    bank.config.validate()?;
-    bank.config.validate_oracle_setup(ctx.remaining_accounts)?;
+    bank.validate_config()?;
+    bank.validate_oracle_setup(ctx.remaining_accounts)?;
 
     emit!(LendingPoolBankCreateEvent {
         header: GroupEventHeader {
@@ -83,7 +129,7 @@ pub struct LendingPoolAddBank<'info> {
     #[account(mut)]
     pub fee_payer: Signer<'info>,
 
-    pub bank_mint: Box<Account<'info, Mint>>,
+    pub bank_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         init,
@@ -102,18 +148,16 @@ pub struct LendingPoolAddBank<'info> {
     )]
     pub liquidity_vault_authority: AccountInfo<'info>,
 
+    /// CHECK: Created and initialized in the instruction, see `utils::create_bank_vault`
     #[account(
-        init,
-        payer = fee_payer,
-        token::mint = bank_mint,
-        token::authority = liquidity_vault_authority,
+        mut,
         seeds = [
             LIQUIDITY_VAULT_SEED.as_bytes(),
             bank.key().as_ref(),
         ],
         bump,
     )]
-    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+    pub liquidity_vault: AccountInfo<'info>,
 
     /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
@@ -125,18 +169,16 @@ pub struct LendingPoolAddBank<'info> {
     )]
     pub insurance_vault_authority: AccountInfo<'info>,
 
+    /// CHECK: Created and initialized in the instruction, see `utils::create_bank_vault`
     #[account(
-        init,
-        payer = fee_payer,
-        token::mint = bank_mint,
-        token::authority = insurance_vault_authority,
+        mut,
         seeds = [
             INSURANCE_VAULT_SEED.as_bytes(),
             bank.key().as_ref(),
         ],
         bump,
     )]
-    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+    pub insurance_vault: AccountInfo<'info>,
 
     /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
@@ -148,21 +190,19 @@ pub struct LendingPoolAddBank<'info> {
     )]
     pub fee_vault_authority: AccountInfo<'info>,
 
+    /// CHECK: Created and initialized in the instruction, see `utils::create_bank_vault`
     #[account(
-        init,
-        payer = fee_payer,
-        token::mint = bank_mint,
-        token::authority = fee_vault_authority,
+        mut,
         seeds = [
             FEE_VAULT_SEED.as_bytes(),
             bank.key().as_ref(),
         ],
         bump,
     )]
-    pub fee_vault: Box<Account<'info, TokenAccount>>,
+    pub fee_vault: AccountInfo<'info>,
 
     pub rent: Sysvar<'info, Rent>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
@@ -172,7 +212,7 @@ pub struct LendingPoolAddBank<'info> {
 /// The previous lending_pool_add_bank is preserved for backwards-compatibility.
 pub fn lending_pool_add_bank_with_seed(
     ctx: Context<LendingPoolAddBankWithSeed>,
-    bank_config: BankConfig,
+    bank_config: BankConfigCompact,
     _bank_seed: u64,
 ) -> MarginfiResult {
     let LendingPoolAddBankWithSeed {
@@ -181,10 +221,17 @@ pub fn lending_pool_add_bank_with_seed(
         insurance_vault,
         fee_vault,
         bank: bank_loader,
+        liquidity_vault_authority,
+        insurance_vault_authority,
+        fee_vault_authority,
+        fee_payer,
+        token_program,
+        system_program,
+        rent,
         ..
-    } = ctx.accounts;
+    } = &*ctx.accounts;
 
-    let mut bank = bank_loader.load_init()?;
+    utils::validate_bank_mint(&bank_mint.to_account_info())?;
 
     let liquidity_vault_bump = *ctx.bumps.get("liquidity_vault").unwrap();
     let liquidity_vault_authority_bump = *ctx.bumps.get("liquidity_vault_authority").unwrap();
@@ -193,6 +240,42 @@ pub fn lending_pool_add_bank_with_seed(
     let fee_vault_bump = *ctx.bumps.get("fee_vault").unwrap();
     let fee_vault_authority_bump = *ctx.bumps.get("fee_vault_authority").unwrap();
 
+    for (vault_type, vault, vault_bump, vault_authority) in [
+        (
+            BankVaultType::Liquidity,
+            liquidity_vault,
+            liquidity_vault_bump,
+            liquidity_vault_authority,
+        ),
+        (
+            BankVaultType::Insurance,
+            insurance_vault,
+            insurance_vault_bump,
+            insurance_vault_authority,
+        ),
+        (
+            BankVaultType::Fee,
+            fee_vault,
+            fee_vault_bump,
+            fee_vault_authority,
+        ),
+    ] {
+        utils::create_bank_vault(
+            vault_type,
+            &bank_loader.key(),
+            vault_bump,
+            vault,
+            vault_authority,
+            &bank_mint.to_account_info(),
+            &fee_payer.to_account_info(),
+            &token_program.to_account_info(),
+            &system_program.to_account_info(),
+            rent,
+        )?;
+    }
+
+    let mut bank = bank_loader.load_init()?;
+
     *bank = Bank::new(
         ctx.accounts.marginfi_group.key(),
         bank_config,
@@ -210,8 +293,8 @@ pub fn lending_pool_add_bank_with_seed(
         fee_vault_authority_bump,
     );
 
-    bank.config.validate()?;
-    bank.config.validate_oracle_setup(ctx.remaining_accounts)?;
+    bank.validate_config()?;
+    bank.validate_oracle_setup(ctx.remaining_accounts)?;
 
     emit!(LendingPoolBankCreateEvent {
         header: GroupEventHeader {
@@ -243,7 +326,7 @@ pub struct LendingPoolAddBankWithSeed<'info> {
     #[account(mut)]
     pub fee_payer: Signer<'info>,
 
-    pub bank_mint: Box<Account<'info, Mint>>,
+    pub bank_mint: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         init,
@@ -268,18 +351,16 @@ pub struct LendingPoolAddBankWithSeed<'info> {
     )]
     pub liquidity_vault_authority: AccountInfo<'info>,
 
+    /// CHECK: Created and initialized in the instruction, see `utils::create_bank_vault`
     #[account(
-        init,
-        payer = fee_payer,
-        token::mint = bank_mint,
-        token::authority = liquidity_vault_authority,
+        mut,
         seeds = [
             LIQUIDITY_VAULT_SEED.as_bytes(),
             bank.key().as_ref(),
         ],
         bump,
     )]
-    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+    pub liquidity_vault: AccountInfo<'info>,
 
     /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
@@ -291,18 +372,16 @@ pub struct LendingPoolAddBankWithSeed<'info> {
     )]
     pub insurance_vault_authority: AccountInfo<'info>,
 
+    /// CHECK: Created and initialized in the instruction, see `utils::create_bank_vault`
     #[account(
-        init,
-        payer = fee_payer,
-        token::mint = bank_mint,
-        token::authority = insurance_vault_authority,
+        mut,
         seeds = [
             INSURANCE_VAULT_SEED.as_bytes(),
             bank.key().as_ref(),
         ],
         bump,
     )]
-    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+    pub insurance_vault: AccountInfo<'info>,
 
     /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
@@ -314,20 +393,18 @@ pub struct LendingPoolAddBankWithSeed<'info> {
     )]
     pub fee_vault_authority: AccountInfo<'info>,
 
+    /// CHECK: Created and initialized in the instruction, see `utils::create_bank_vault`
     #[account(
-        init,
-        payer = fee_payer,
-        token::mint = bank_mint,
-        token::authority = fee_vault_authority,
+        mut,
         seeds = [
             FEE_VAULT_SEED.as_bytes(),
             bank.key().as_ref(),
         ],
         bump,
     )]
-    pub fee_vault: Box<Account<'info, TokenAccount>>,
+    pub fee_vault: AccountInfo<'info>,
 
     pub rent: Sysvar<'info, Rent>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
@@ -7,14 +7,19 @@ use crate::{
     },
     math_error,
     state::marginfi_group::{Bank, BankVaultType, MarginfiGroup},
-    MarginfiResult,
+    utils, MarginfiResult,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Transfer};
+use anchor_spl::{
+    token::Transfer,
+    token_interface::{TokenAccount, TokenInterface},
+};
 use fixed::types::I80F48;
 use std::cmp::min;
 
-pub fn lending_pool_collect_bank_fees(ctx: Context<LendingPoolCollectBankFees>) -> MarginfiResult {
+pub fn lending_pool_collect_bank_fees<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingPoolCollectBankFees<'info>>,
+) -> MarginfiResult {
     let LendingPoolCollectBankFees {
         liquidity_vault_authority,
         insurance_vault,
@@ -26,6 +31,10 @@ pub fn lending_pool_collect_bank_fees(ctx: Context<LendingPoolCollectBankFees>)
 
     let mut bank = ctx.accounts.bank.load_mut()?;
 
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut remaining_accounts, &bank, token_program.key)?;
+
     let mut available_liquidity = I80F48::from_num(liquidity_vault.amount);
 
     let (insurance_fee_transfer_amount, new_outstanding_insurance_fees) = {
@@ -75,6 +84,7 @@ pub fn lending_pool_collect_bank_fees(ctx: Context<LendingPoolCollectBankFees>)
             to: fee_vault.to_account_info(),
             authority: liquidity_vault_authority.to_account_info(),
         },
+        maybe_bank_mint.as_ref(),
         token_program.to_account_info(),
         bank_signer!(
             BankVaultType::Liquidity,
@@ -92,6 +102,7 @@ pub fn lending_pool_collect_bank_fees(ctx: Context<LendingPoolCollectBankFees>)
             to: insurance_vault.to_account_info(),
             authority: liquidity_vault_authority.to_account_info(),
         },
+        maybe_bank_mint.as_ref(),
         token_program.to_account_info(),
         bank_signer!(
             BankVaultType::Liquidity,
@@ -145,7 +156,7 @@ pub struct LendingPoolCollectBankFees<'info> {
         ],
         bump = bank.load()?.liquidity_vault_bump
     )]
-    pub liquidity_vault: Account<'info, TokenAccount>,
+    pub liquidity_vault: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
@@ -169,11 +180,11 @@ pub struct LendingPoolCollectBankFees<'info> {
     )]
     pub fee_vault: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-pub fn lending_pool_withdraw_fees(
-    ctx: Context<LendingPoolWithdrawFees>,
+pub fn lending_pool_withdraw_fees<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingPoolWithdrawFees<'info>>,
     amount: u64,
 ) -> MarginfiResult {
     let LendingPoolWithdrawFees {
@@ -187,6 +198,10 @@ pub fn lending_pool_withdraw_fees(
 
     let bank = bank_loader.load()?;
 
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut remaining_accounts, &bank, token_program.key)?;
+
     bank.withdraw_spl_transfer(
         amount,
         Transfer {
@@ -194,6 +209,7 @@ pub fn lending_pool_withdraw_fees(
             to: dst_token_account.to_account_info(),
             authority: fee_vault_authority.to_account_info(),
         },
+        maybe_bank_mint.as_ref(),
         token_program.to_account_info(),
         bank_signer!(
             BankVaultType::Fee,
@@ -244,11 +260,11 @@ pub struct LendingPoolWithdrawFees<'info> {
     #[account(mut)]
     pub dst_token_account: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-pub fn lending_pool_withdraw_insurance(
-    ctx: Context<LendingPoolWithdrawInsurance>,
+pub fn lending_pool_withdraw_insurance<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingPoolWithdrawInsurance<'info>>,
     amount: u64,
 ) -> MarginfiResult {
     let LendingPoolWithdrawInsurance {
@@ -262,6 +278,10 @@ pub fn lending_pool_withdraw_insurance(
 
     let bank = bank_loader.load()?;
 
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut remaining_accounts, &bank, token_program.key)?;
+
     bank.withdraw_spl_transfer(
         amount,
         Transfer {
@@ -269,6 +289,7 @@ pub fn lending_pool_withdraw_insurance(
             to: dst_token_account.to_account_info(),
             authority: insurance_vault_authority.to_account_info(),
         },
+        maybe_bank_mint.as_ref(),
         token_program.to_account_info(),
         bank_signer!(
             BankVaultType::Insurance,
@@ -319,5 +340,5 @@ pub struct LendingPoolWithdrawInsurance<'info> {
     #[account(mut)]
     pub dst_token_account: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
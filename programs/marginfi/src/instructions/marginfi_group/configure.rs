@@ -1,14 +1,18 @@
 use crate::check;
-use crate::events::{GroupEventHeader, MarginfiGroupConfigureEvent};
+use crate::events::{
+    GroupEventHeader, MarginfiGroupConfigureEmodeCategoryEvent, MarginfiGroupConfigureEvent,
+    MarginfiGroupConfigureWeightOverrideEvent,
+};
 use crate::prelude::MarginfiError;
 use crate::state::marginfi_account::{
     MarginfiAccount, FLASHLOAN_ENABLED_FLAG, TRANSFER_AUTHORITY_ALLOWED_FLAG,
 };
 use crate::{
-    state::marginfi_group::{GroupConfig, MarginfiGroup},
+    state::marginfi_group::{Bank, EmodeCategory, GroupConfig, MarginfiGroup, WrappedI80F48},
     MarginfiResult,
 };
 use anchor_lang::prelude::*;
+use fixed::types::I80F48;
 
 /// Configure margin group
 ///
@@ -40,6 +44,94 @@ pub struct MarginfiGroupConfigure<'info> {
     pub admin: Signer<'info>,
 }
 
+/// Set the weights of e-mode category `emode_category`, a zero `asset_weight_maint` disables it.
+///
+/// Admin only
+pub fn configure_emode_category(
+    ctx: Context<MarginfiGroupConfigureEmodeCategory>,
+    emode_category: u8,
+    config: EmodeCategory,
+) -> MarginfiResult {
+    let marginfi_group = &mut ctx.accounts.marginfi_group.load_mut()?;
+
+    marginfi_group.configure_emode_category(emode_category, config)?;
+
+    emit!(MarginfiGroupConfigureEmodeCategoryEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        emode_category,
+        config,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MarginfiGroupConfigureEmodeCategory<'info> {
+    #[account(mut)]
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+}
+
+/// Set the asset weights of `asset_bank` deposits against `liability_bank` debt, a zero
+/// `asset_weight_maint` removes the override.
+///
+/// Admin only
+pub fn configure_weight_override(
+    ctx: Context<MarginfiGroupConfigureWeightOverride>,
+    asset_weight_init: WrappedI80F48,
+    asset_weight_maint: WrappedI80F48,
+) -> MarginfiResult {
+    let marginfi_group = &mut ctx.accounts.marginfi_group.load_mut()?;
+
+    marginfi_group.configure_weight_override(
+        ctx.accounts.asset_bank.key(),
+        ctx.accounts.liability_bank.key(),
+        asset_weight_init.into(),
+        asset_weight_maint.into(),
+    )?;
+
+    emit!(MarginfiGroupConfigureWeightOverrideEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        asset_bank: ctx.accounts.asset_bank.key(),
+        liability_bank: ctx.accounts.liability_bank.key(),
+        asset_weight_init: I80F48::from(asset_weight_init).to_num::<f64>(),
+        asset_weight_maint: I80F48::from(asset_weight_maint).to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MarginfiGroupConfigureWeightOverride<'info> {
+    #[account(mut)]
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        constraint = asset_bank.load()?.group == marginfi_group.key(),
+    )]
+    pub asset_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        constraint = liability_bank.load()?.group == marginfi_group.key(),
+    )]
+    pub liability_bank: AccountLoader<'info, Bank>,
+}
+
 /// Only these flags can be configured
 ///
 /// Example:
@@ -1,4 +1,7 @@
-use crate::constants::{EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED};
+use crate::constants::{
+    EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED, RECEIPT_MINT_AUTHORITY_SEED,
+    RECEIPT_MINT_SEED,
+};
 use crate::events::{GroupEventHeader, LendingPoolBankConfigureEvent};
 use crate::prelude::MarginfiError;
 use crate::{check, math_error};
@@ -19,7 +22,7 @@ pub fn lending_pool_configure_bank(
     bank.configure(&bank_config)?;
 
     if bank_config.oracle.is_some() {
-        bank.config.validate_oracle_setup(ctx.remaining_accounts)?;
+        bank.validate_oracle_setup(ctx.remaining_accounts)?;
     }
 
     emit!(LendingPoolBankConfigureEvent {
@@ -228,3 +231,66 @@ pub struct LendingPoolUpdateEmissionsParameters<'info> {
 
     pub token_program: Program<'info, Token>,
 }
+
+/// Create the bank receipt mint, letting depositors hold their asset shares as transferable tokens,
+/// see `lending_account_mint_receipt`. Receipt tokens share the bank mint decimals.
+pub fn lending_pool_setup_receipt_mint(
+    ctx: Context<LendingPoolSetupReceiptMint>,
+) -> MarginfiResult {
+    let mut bank = ctx.accounts.bank.load_mut()?;
+
+    check!(
+        bank.receipt_mint.eq(&Pubkey::default()),
+        MarginfiError::ReceiptMintAlreadySetup
+    );
+
+    bank.receipt_mint = ctx.accounts.receipt_mint.key();
+    bank.receipt_mint_bump = *ctx.bumps.get("receipt_mint").unwrap();
+    bank.receipt_mint_authority_bump = *ctx.bumps.get("receipt_mint_authority").unwrap();
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolSetupReceiptMint<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    /// CHECK: Asserted by PDA constraints
+    #[account(
+        seeds = [
+            RECEIPT_MINT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump
+    )]
+    pub receipt_mint_authority: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = bank.load()?.mint_decimals,
+        mint::authority = receipt_mint_authority,
+        seeds = [
+            RECEIPT_MINT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub receipt_mint: Box<Account<'info, Mint>>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
@@ -10,10 +10,13 @@ use crate::{
         marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine},
         marginfi_group::{Bank, BankVaultType, MarginfiGroup},
     },
-    MarginfiResult,
+    utils, MarginfiResult,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Transfer};
+use anchor_spl::{
+    token::Transfer,
+    token_interface::{TokenAccount, TokenInterface},
+};
 use fixed::types::I80F48;
 use std::cmp::{max, min};
 
@@ -23,7 +26,12 @@ use std::cmp::{max, min};
 /// 3. Cover the bad debt of the bankrupt account.
 /// 4. Transfer the insured amount from the insurance fund.
 /// 5. Socialize the loss between lenders if any.
-pub fn lending_pool_handle_bankruptcy(ctx: Context<LendingPoolHandleBankruptcy>) -> MarginfiResult {
+///
+/// Token22 banks expect the bank mint as the first remaining account,
+/// the insurance fund covers the mint transfer fee of the insured amount.
+pub fn lending_pool_handle_bankruptcy<'info>(
+    ctx: Context<'_, '_, '_, 'info, LendingPoolHandleBankruptcy<'info>>,
+) -> MarginfiResult {
     let LendingPoolHandleBankruptcy {
         marginfi_account: marginfi_account_loader,
         insurance_vault,
@@ -41,25 +49,82 @@ pub fn lending_pool_handle_bankruptcy(ctx: Context<LendingPoolHandleBankruptcy>)
         );
     }
 
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut remaining_accounts, &bank, token_program.key)?;
+
     drop(bank);
 
     let mut marginfi_account = marginfi_account_loader.load_mut()?;
 
-    RiskEngine::new(&marginfi_account, ctx.remaining_accounts)?.check_account_bankrupt()?;
+    RiskEngine::new(
+        &*marginfi_group_loader.load()?,
+        &marginfi_account,
+        remaining_accounts,
+    )?
+    .check_account_bankrupt()?;
 
     let mut bank = bank_loader.load_mut()?;
+    let clock = Clock::get()?;
+
+    let (bad_debt, covered_by_insurance, socialized_loss) = settle_bad_debt(
+        &mut bank,
+        bank_loader.key(),
+        &mut marginfi_account,
+        insurance_vault,
+        ctx.accounts.liquidity_vault.to_account_info(),
+        ctx.accounts.insurance_vault_authority.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        &clock,
+    )?;
+
+    marginfi_account.set_flag(DISABLED_FLAG);
+
+    emit!(LendingPoolBankHandleBankruptcyEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        bad_debt: bad_debt.to_num::<f64>(),
+        covered_amount: covered_by_insurance.to_num::<f64>(),
+        socialized_amount: socialized_loss.to_num::<f64>(),
+    });
+
+    Ok(())
+}
 
+/// Settles the bad debt of a bankrupt account in `bank`.
+/// The insurance fund covers as much as it holds, the rest is socialized between the bank depositors.
+///
+/// Returns (`bad_debt`, `covered_by_insurance`, `socialized_loss`).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn settle_bad_debt<'info>(
+    bank: &mut Bank,
+    bank_pk: Pubkey,
+    marginfi_account: &mut MarginfiAccount,
+    insurance_vault: &InterfaceAccount<'info, TokenAccount>,
+    liquidity_vault: AccountInfo<'info>,
+    insurance_vault_authority: AccountInfo<'info>,
+    maybe_bank_mint: Option<&AccountInfo<'info>>,
+    token_program: AccountInfo<'info>,
+    clock: &Clock,
+) -> MarginfiResult<(I80F48, I80F48, I80F48)> {
     bank.accrue_interest(
-        Clock::get()?.unix_timestamp,
+        clock.unix_timestamp,
         #[cfg(not(feature = "client"))]
-        bank_loader.key(),
+        bank_pk,
     )?;
 
     let lending_account_balance = marginfi_account
         .lending_account
         .balances
         .iter_mut()
-        .find(|balance| balance.active && balance.bank_pk == bank_loader.key());
+        .find(|balance| balance.active && balance.bank_pk == bank_pk);
 
     check!(
         lending_account_balance.is_some(),
@@ -76,28 +141,52 @@ pub fn lending_pool_handle_bankruptcy(ctx: Context<LendingPoolHandleBankruptcy>)
     );
 
     let (covered_by_insurance, socialized_loss) = {
-        let available_insurance_funds = I80F48::from_num(insurance_vault.amount);
+        let available_insurance_funds = match maybe_bank_mint {
+            Some(mint) => utils::calculate_post_fee_spl_deposit_amount(
+                mint,
+                insurance_vault.amount,
+                clock.epoch,
+            )?,
+            None => insurance_vault.amount,
+        };
 
-        let covered_by_insurance = min(bad_debt, available_insurance_funds);
+        let covered_by_insurance = min(bad_debt, I80F48::from_num(available_insurance_funds));
         let socialized_loss = max(bad_debt - covered_by_insurance, I80F48::ZERO);
 
         (covered_by_insurance, socialized_loss)
     };
 
+    let insurance_transfer_amount = {
+        let covered_by_insurance = covered_by_insurance
+            .checked_to_num()
+            .ok_or_else(math_error!())?;
+
+        match maybe_bank_mint {
+            Some(mint) => min(
+                utils::calculate_pre_fee_spl_deposit_amount(
+                    mint,
+                    covered_by_insurance,
+                    clock.epoch,
+                )?,
+                insurance_vault.amount,
+            ),
+            None => covered_by_insurance,
+        }
+    };
+
     // Cover bad debt with insurance funds.
     bank.withdraw_spl_transfer(
-        covered_by_insurance
-            .checked_to_num()
-            .ok_or_else(math_error!())?,
+        insurance_transfer_amount,
         Transfer {
-            from: ctx.accounts.insurance_vault.to_account_info(),
-            to: ctx.accounts.liquidity_vault.to_account_info(),
-            authority: ctx.accounts.insurance_vault_authority.to_account_info(),
+            from: insurance_vault.to_account_info(),
+            to: liquidity_vault,
+            authority: insurance_vault_authority,
         },
-        token_program.to_account_info(),
+        maybe_bank_mint,
+        token_program,
         bank_signer!(
             BankVaultType::Insurance,
-            bank_loader.key(),
+            bank_pk,
             bank.insurance_vault_authority_bump
         ),
     )?;
@@ -107,30 +196,10 @@ pub fn lending_pool_handle_bankruptcy(ctx: Context<LendingPoolHandleBankruptcy>)
 
     // Settle bad debt.
     // The liabilities of this account and global total liabilities are reduced by `bad_debt`
-    BankAccountWrapper::find_or_create(
-        &bank_loader.key(),
-        &mut bank,
-        &mut marginfi_account.lending_account,
-    )?
-    .repay(bad_debt)?;
+    BankAccountWrapper::find_or_create(&bank_pk, bank, &mut marginfi_account.lending_account)?
+        .repay(bad_debt)?;
 
-    marginfi_account.set_flag(DISABLED_FLAG);
-
-    emit!(LendingPoolBankHandleBankruptcyEvent {
-        header: AccountEventHeader {
-            signer: Some(ctx.accounts.signer.key()),
-            marginfi_account: marginfi_account_loader.key(),
-            marginfi_account_authority: marginfi_account.authority,
-            marginfi_group: marginfi_account.group,
-        },
-        bank: bank_loader.key(),
-        mint: bank.mint,
-        bad_debt: bad_debt.to_num::<f64>(),
-        covered_amount: covered_by_insurance.to_num::<f64>(),
-        socialized_amount: socialized_loss.to_num::<f64>(),
-    });
-
-    Ok(())
+    Ok((bad_debt, covered_by_insurance, socialized_loss))
 }
 
 #[derive(Accounts)]
@@ -171,7 +240,7 @@ pub struct LendingPoolHandleBankruptcy<'info> {
         ],
         bump = bank.load()?.insurance_vault_bump
     )]
-    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+    pub insurance_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// CHECK: Seed constraint
     #[account(
@@ -183,5 +252,5 @@ pub struct LendingPoolHandleBankruptcy<'info> {
     )]
     pub insurance_vault_authority: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
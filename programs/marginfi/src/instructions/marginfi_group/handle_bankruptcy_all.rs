@@ -0,0 +1,187 @@
+use super::settle_bad_debt;
+use crate::constants::PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG;
+use crate::events::{
+    AccountEventHeader, BankruptcySettlement, LendingPoolHandleBankruptcyAllEvent,
+};
+use crate::state::marginfi_account::{BalanceSide, DISABLED_FLAG};
+use crate::{
+    check,
+    prelude::MarginfiError,
+    state::{
+        marginfi_account::{MarginfiAccount, RiskEngine},
+        marginfi_group::{Bank, MarginfiGroup},
+    },
+    utils, MarginfiResult,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+/// Accounts of one bank a bankrupt account has bad debt in, as passed in the remaining accounts.
+struct BankruptcyBankAccounts<'info> {
+    bank: AccountLoader<'info, Bank>,
+    liquidity_vault: AccountInfo<'info>,
+    insurance_vault: InterfaceAccount<'info, TokenAccount>,
+    insurance_vault_authority: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    maybe_bank_mint: Option<AccountInfo<'info>>,
+}
+
+/// Handle a bankrupt marginfi account in every bank it has bad debt in, like
+/// [`super::lending_pool_handle_bankruptcy`] for each of its liability balances.
+///
+/// Remaining accounts:
+/// 1. For each liability balance, in balance order: bank (writable), liquidity vault (writable),
+///    insurance vault (writable), insurance vault authority, token program, and the bank mint
+///    for Token22 banks
+/// 2. The account's bank and oracle accounts
+pub fn lending_pool_handle_bankruptcy_all(
+    ctx: Context<LendingPoolHandleBankruptcyAll>,
+) -> MarginfiResult {
+    let LendingPoolHandleBankruptcyAll {
+        marginfi_account: marginfi_account_loader,
+        marginfi_group: marginfi_group_loader,
+        ..
+    } = ctx.accounts;
+
+    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+
+    let liability_bank_pks = marginfi_account
+        .lending_account
+        .balances
+        .iter()
+        .filter(|balance| balance.active && !balance.is_empty(BalanceSide::Liabilities))
+        .map(|balance| balance.bank_pk)
+        .collect::<Vec<_>>();
+
+    check!(
+        !liability_bank_pks.is_empty(),
+        MarginfiError::BalanceNotBadDebt
+    );
+
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let mut banks = Vec::with_capacity(liability_bank_pks.len());
+    let mut is_admin_required = false;
+
+    for bank_pk in liability_bank_pks.iter() {
+        check!(
+            remaining_accounts.len() >= 5,
+            MarginfiError::MissingPythOrBankAccount
+        );
+        let (bank_accounts, rest) = remaining_accounts.split_at(5);
+        let (
+            bank_ai,
+            liquidity_vault_ai,
+            insurance_vault_ai,
+            insurance_vault_authority_ai,
+            token_program_ai,
+        ) = (
+            &bank_accounts[0],
+            &bank_accounts[1],
+            &bank_accounts[2],
+            &bank_accounts[3],
+            &bank_accounts[4],
+        );
+        remaining_accounts = rest;
+
+        check!(bank_ai.key.eq(bank_pk), MarginfiError::InvalidBankAccount);
+
+        let bank_loader = AccountLoader::<Bank>::try_from(bank_ai)?;
+        let bank = bank_loader.load()?;
+
+        check!(
+            bank.group == marginfi_group_loader.key(),
+            MarginfiError::InvalidBankAccount
+        );
+
+        check!(
+            liquidity_vault_ai.key.eq(&bank.liquidity_vault)
+                && insurance_vault_ai.key.eq(&bank.insurance_vault),
+            MarginfiError::InvalidTransfer
+        );
+
+        is_admin_required |= !bank.get_flag(PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG);
+
+        let maybe_bank_mint =
+            utils::maybe_take_bank_mint(&mut remaining_accounts, &bank, token_program_ai.key)?;
+
+        drop(bank);
+
+        banks.push(BankruptcyBankAccounts {
+            bank: bank_loader,
+            liquidity_vault: liquidity_vault_ai.clone(),
+            insurance_vault: InterfaceAccount::<TokenAccount>::try_from(insurance_vault_ai)?,
+            insurance_vault_authority: insurance_vault_authority_ai.clone(),
+            token_program: token_program_ai.clone(),
+            maybe_bank_mint,
+        });
+    }
+
+    if is_admin_required {
+        check!(
+            ctx.accounts.signer.key() == marginfi_group_loader.load()?.admin,
+            MarginfiError::Unauthorized
+        );
+    }
+
+    RiskEngine::new(
+        &*marginfi_group_loader.load()?,
+        &marginfi_account,
+        remaining_accounts,
+    )?
+    .check_account_bankrupt()?;
+
+    let clock = Clock::get()?;
+    let mut settlements = Vec::with_capacity(banks.len());
+
+    for bank_accounts in banks.iter() {
+        let bank_pk = bank_accounts.bank.key();
+        let mut bank = bank_accounts.bank.load_mut()?;
+
+        let (bad_debt, covered_by_insurance, socialized_loss) = settle_bad_debt(
+            &mut bank,
+            bank_pk,
+            &mut marginfi_account,
+            &bank_accounts.insurance_vault,
+            bank_accounts.liquidity_vault.clone(),
+            bank_accounts.insurance_vault_authority.clone(),
+            bank_accounts.maybe_bank_mint.as_ref(),
+            bank_accounts.token_program.clone(),
+            &clock,
+        )?;
+
+        settlements.push(BankruptcySettlement {
+            bank: bank_pk,
+            mint: bank.mint,
+            bad_debt: bad_debt.to_num::<f64>(),
+            covered_amount: covered_by_insurance.to_num::<f64>(),
+            socialized_amount: socialized_loss.to_num::<f64>(),
+        });
+    }
+
+    marginfi_account.set_flag(DISABLED_FLAG);
+
+    emit!(LendingPoolHandleBankruptcyAllEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        settlements,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolHandleBankruptcyAll<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+}
@@ -4,6 +4,7 @@ mod collect_bank_fees;
 mod configure;
 mod configure_bank;
 mod handle_bankruptcy;
+mod handle_bankruptcy_all;
 mod initialize;
 
 pub use accrue_bank_interest::*;
@@ -12,4 +13,5 @@ pub use collect_bank_fees::*;
 pub use configure::*;
 pub use configure_bank::*;
 pub use handle_bankruptcy::*;
+pub use handle_bankruptcy_all::*;
 pub use initialize::*;
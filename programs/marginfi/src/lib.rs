@@ -10,7 +10,7 @@ pub mod utils;
 use anchor_lang::prelude::*;
 use instructions::*;
 use prelude::*;
-use state::marginfi_group::{BankConfigCompact, BankConfigOpt};
+use state::marginfi_group::{BankConfigCompact, BankConfigOpt, EmodeCategory, WrappedI80F48};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "mainnet-beta")] {
@@ -37,11 +37,27 @@ pub mod marginfi {
         marginfi_group::configure(ctx, config)
     }
 
+    pub fn marginfi_group_configure_emode_category(
+        ctx: Context<MarginfiGroupConfigureEmodeCategory>,
+        emode_category: u8,
+        config: EmodeCategory,
+    ) -> MarginfiResult {
+        marginfi_group::configure_emode_category(ctx, emode_category, config)
+    }
+
+    pub fn marginfi_group_configure_weight_override(
+        ctx: Context<MarginfiGroupConfigureWeightOverride>,
+        asset_weight_init: WrappedI80F48,
+        asset_weight_maint: WrappedI80F48,
+    ) -> MarginfiResult {
+        marginfi_group::configure_weight_override(ctx, asset_weight_init, asset_weight_maint)
+    }
+
     pub fn lending_pool_add_bank(
         ctx: Context<LendingPoolAddBank>,
         bank_config: BankConfigCompact,
     ) -> MarginfiResult {
-        marginfi_group::lending_pool_add_bank(ctx, bank_config.into())
+        marginfi_group::lending_pool_add_bank(ctx, bank_config)
     }
 
     /// A copy of lending_pool_add_bank with an additional bank seed.
@@ -52,7 +68,7 @@ pub mod marginfi {
         bank_config: BankConfigCompact,
         bank_seed: u64,
     ) -> MarginfiResult {
-        marginfi_group::lending_pool_add_bank_with_seed(ctx, bank_config.into(), bank_seed)
+        marginfi_group::lending_pool_add_bank_with_seed(ctx, bank_config, bank_seed)
     }
 
     pub fn lending_pool_configure_bank(
@@ -85,13 +101,27 @@ pub mod marginfi {
         )
     }
 
+    /// Create the receipt mint of a bank, tokenizing its asset shares
+    pub fn lending_pool_setup_receipt_mint(
+        ctx: Context<LendingPoolSetupReceiptMint>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_setup_receipt_mint(ctx)
+    }
+
     /// Handle bad debt of a bankrupt marginfi account for a given bank.
-    pub fn lending_pool_handle_bankruptcy(
-        ctx: Context<LendingPoolHandleBankruptcy>,
+    pub fn lending_pool_handle_bankruptcy<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingPoolHandleBankruptcy<'info>>,
     ) -> MarginfiResult {
         marginfi_group::lending_pool_handle_bankruptcy(ctx)
     }
 
+    /// Handle bad debt of a bankrupt marginfi account in every bank it has liabilities in.
+    pub fn lending_pool_handle_bankruptcy_all(
+        ctx: Context<LendingPoolHandleBankruptcyAll>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_handle_bankruptcy_all(ctx)
+    }
+
     // User instructions
 
     /// Initialize a marginfi account for a given group
@@ -99,31 +129,38 @@ pub mod marginfi {
         marginfi_account::initialize_account(ctx)
     }
 
-    pub fn lending_account_deposit(
-        ctx: Context<LendingAccountDeposit>,
+    pub fn marginfi_account_set_emode(
+        ctx: Context<MarginfiAccountSetEmode>,
+        emode_category: u8,
+    ) -> MarginfiResult {
+        marginfi_account::marginfi_account_set_emode(ctx, emode_category)
+    }
+
+    pub fn lending_account_deposit<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingAccountDeposit<'info>>,
         amount: u64,
     ) -> MarginfiResult {
         marginfi_account::lending_account_deposit(ctx, amount)
     }
 
-    pub fn lending_account_repay(
-        ctx: Context<LendingAccountRepay>,
+    pub fn lending_account_repay<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingAccountRepay<'info>>,
         amount: u64,
         repay_all: Option<bool>,
     ) -> MarginfiResult {
         marginfi_account::lending_account_repay(ctx, amount, repay_all)
     }
 
-    pub fn lending_account_withdraw(
-        ctx: Context<LendingAccountWithdraw>,
+    pub fn lending_account_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingAccountWithdraw<'info>>,
         amount: u64,
         withdraw_all: Option<bool>,
     ) -> MarginfiResult {
         marginfi_account::lending_account_withdraw(ctx, amount, withdraw_all)
     }
 
-    pub fn lending_account_borrow(
-        ctx: Context<LendingAccountBorrow>,
+    pub fn lending_account_borrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingAccountBorrow<'info>>,
         amount: u64,
     ) -> MarginfiResult {
         marginfi_account::lending_account_borrow(ctx, amount)
@@ -147,14 +184,48 @@ pub mod marginfi {
         marginfi_account::lending_account_settle_emissions(ctx)
     }
 
+    /// Move asset shares of a lending account balance into transferable bank receipt tokens
+    pub fn lending_account_mint_receipt(
+        ctx: Context<LendingAccountMintReceipt>,
+        amount: u64,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_mint_receipt(ctx, amount)
+    }
+
+    /// Burn bank receipt tokens, moving their asset shares into a lending account balance
+    pub fn lending_account_redeem_receipt(
+        ctx: Context<LendingAccountRedeemReceipt>,
+        amount: u64,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_redeem_receipt(ctx, amount)
+    }
+
     /// Liquidate a lending account balance of an unhealthy marginfi account
-    pub fn lending_account_liquidate(
-        ctx: Context<LendingAccountLiquidate>,
+    pub fn lending_account_liquidate<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingAccountLiquidate<'info>>,
         asset_amount: u64,
     ) -> MarginfiResult {
         marginfi_account::lending_account_liquidate(ctx, asset_amount)
     }
 
+    /// Liquidate several collateral balances of an unhealthy marginfi account against a single
+    /// liability
+    pub fn lending_account_liquidate_multiple<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingAccountLiquidateMultiple<'info>>,
+        asset_amounts: Vec<u64>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_liquidate_multiple(ctx, asset_amounts)
+    }
+
+    /// Liquidate a lending account balance of an unhealthy marginfi account, paying the liability
+    /// from and receiving the collateral into token accounts
+    pub fn lending_account_liquidate_with_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingAccountLiquidateWithTokens<'info>>,
+        asset_amount: u64,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_liquidate_with_tokens(ctx, asset_amount)
+    }
+
     pub fn lending_account_start_flashloan(
         ctx: Context<LendingAccountStartFlashloan>,
         end_index: u64,
@@ -162,12 +233,20 @@ pub mod marginfi {
         marginfi_account::lending_account_start_flashloan(ctx, end_index)
     }
 
+    /// Deprecated, only ends flashloans leaving the account without debt.
+    /// Use `lending_account_end_flashloan_v2`.
     pub fn lending_account_end_flashloan(
         ctx: Context<LendingAccountEndFlashloan>,
     ) -> MarginfiResult {
         marginfi_account::lending_account_end_flashloan(ctx)
     }
 
+    pub fn lending_account_end_flashloan_v2(
+        ctx: Context<LendingAccountEndFlashloanV2>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_end_flashloan_v2(ctx)
+    }
+
     // Operational instructions
     pub fn lending_pool_accrue_bank_interest(
         ctx: Context<LendingPoolAccrueBankInterest>,
@@ -175,21 +254,21 @@ pub mod marginfi {
         marginfi_group::lending_pool_accrue_bank_interest(ctx)
     }
 
-    pub fn lending_pool_collect_bank_fees(
-        ctx: Context<LendingPoolCollectBankFees>,
+    pub fn lending_pool_collect_bank_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingPoolCollectBankFees<'info>>,
     ) -> MarginfiResult {
         marginfi_group::lending_pool_collect_bank_fees(ctx)
     }
 
-    pub fn lending_pool_withdraw_fees(
-        ctx: Context<LendingPoolWithdrawFees>,
+    pub fn lending_pool_withdraw_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingPoolWithdrawFees<'info>>,
         amount: u64,
     ) -> MarginfiResult {
         marginfi_group::lending_pool_withdraw_fees(ctx, amount)
     }
 
-    pub fn lending_pool_withdraw_insurance(
-        ctx: Context<LendingPoolWithdrawInsurance>,
+    pub fn lending_pool_withdraw_insurance<'info>(
+        ctx: Context<'_, '_, '_, 'info, LendingPoolWithdrawInsurance<'info>>,
         amount: u64,
     ) -> MarginfiResult {
         marginfi_group::lending_pool_withdraw_insurance(ctx, amount)
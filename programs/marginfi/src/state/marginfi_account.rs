@@ -1,5 +1,5 @@
 use super::{
-    marginfi_group::{Bank, RiskTier, WrappedI80F48},
+    marginfi_group::{Bank, EmodeCategory, MarginfiGroup, RiskTier, WrappedI80F48},
     price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter, PriceBias},
 };
 use crate::{
@@ -17,8 +17,9 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::Transfer;
 use fixed::types::I80F48;
 use std::{
+    cell::Ref,
     cmp::{max, min},
-    ops::Not,
+    ops::{Deref, Not},
 };
 #[cfg(any(feature = "test", feature = "client"))]
 use type_layout::TypeLayout;
@@ -42,7 +43,11 @@ pub struct MarginfiAccount {
     /// - DISABLED_FLAG = 1 << 0 = 1 - This flag indicates that the account is disabled,
     /// and no further actions can be taken on it.
     pub account_flags: u64, // 8
-    pub _padding: [u64; 63],             // 8 * 63 = 512
+    /// E-mode category the account opted into, 0 for none.
+    /// See `MarginfiGroup::emode_categories`.
+    pub emode_category: u8, // 1
+    pub _pad0: [u8; 7],                  // 7
+    pub _padding: [u64; 62],             // 8 * 62 = 496
 }
 
 pub const DISABLED_FLAG: u64 = 1 << 0;
@@ -57,13 +62,26 @@ impl MarginfiAccount {
         self.group = group;
     }
 
-    pub fn get_remaining_accounts_len(&self) -> usize {
+    /// Number of remaining accounts the account's active balances take, a bank followed by its
+    /// oracles. Banks are read from `remaining_ais`.
+    pub fn get_remaining_accounts_len(
+        &self,
+        remaining_ais: &[AccountInfo],
+    ) -> MarginfiResult<usize> {
         self.lending_account
             .balances
             .iter()
             .filter(|b| b.active)
-            .count()
-            * 2 // TODO: Make account count oracle setup specific
+            .try_fold(0, |len, balance| {
+                let bank_ai = remaining_ais
+                    .iter()
+                    .find(|ai| ai.key.eq(&balance.bank_pk))
+                    .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+                let bank_al = AccountLoader::<Bank>::try_from(bank_ai)?;
+                let oracles_len = bank_al.load()?.config.get_oracle_keys().len();
+
+                Ok(len + 1 + oracles_len)
+            })
     }
 
     pub fn set_flag(&mut self, flag: u64) {
@@ -139,10 +157,49 @@ impl RequirementType {
     }
 }
 
-pub struct BankAccountWithPriceFeed<'a, 'b> {
-    bank: AccountInfo<'b>,
+/// Bank and oracle price feed backing a balance in risk calculations.
+///
+/// On-chain this is read from the `(bank, oracle)` remaining accounts, off-chain it can be backed
+/// by cached state, so both run the same risk math.
+pub trait BankPriceSource {
+    type BankRef<'s>: Deref<Target = Bank>
+    where
+        Self: 's;
+
+    fn load_bank(&self) -> MarginfiResult<Self::BankRef<'_>>;
+
+    fn price_feed(&self) -> MarginfiResult<&OraclePriceFeedAdapter>;
+}
+
+/// Bank and price feed loaded from remaining accounts.
+pub struct AccountInfoBankPriceSource<'b> {
+    bank: AccountLoader<'b, Bank>,
     price_feed: Box<MarginfiResult<OraclePriceFeedAdapter>>,
+}
+
+impl<'b> BankPriceSource for AccountInfoBankPriceSource<'b> {
+    type BankRef<'s>
+        = Ref<'s, Bank>
+    where
+        Self: 's;
+
+    fn load_bank(&self) -> MarginfiResult<Ref<'_, Bank>> {
+        self.bank.load()
+    }
+
+    fn price_feed(&self) -> MarginfiResult<&OraclePriceFeedAdapter> {
+        match self.price_feed.as_ref() {
+            Ok(a) => Ok(a),
+            Err(_) => Err(MarginfiError::StaleOracle)?,
+        }
+    }
+}
+
+pub struct BankAccountWithPriceFeed<'a, S: BankPriceSource> {
+    source: S,
     balance: &'a Balance,
+    /// (init, maint) asset weights from the group weight overrides, set by the `RiskEngine`.
+    asset_weight_override: Option<(I80F48, I80F48)>,
 }
 
 pub enum BalanceSide {
@@ -150,60 +207,86 @@ pub enum BalanceSide {
     Liabilities,
 }
 
-impl<'a, 'b> BankAccountWithPriceFeed<'a, 'b> {
+impl<'a, 'b> BankAccountWithPriceFeed<'a, AccountInfoBankPriceSource<'b>> {
     pub fn load(
         lending_account: &'a LendingAccount,
         remaining_ais: &[AccountInfo<'b>],
-    ) -> MarginfiResult<Vec<BankAccountWithPriceFeed<'a, 'b>>> {
+    ) -> MarginfiResult<Vec<Self>> {
         let active_balances = lending_account
             .balances
             .iter()
             .filter(|balance| balance.active)
             .collect::<Vec<_>>();
 
-        debug!("Expecting {} remaining accounts", active_balances.len() * 2);
         debug!("Got {} remaining accounts", remaining_ais.len());
 
-        check!(
-            active_balances.len() * 2 == remaining_ais.len(),
-            MarginfiError::MissingPythOrBankAccount
-        );
-
         let current_timestamp = Clock::get()?.unix_timestamp;
 
-        active_balances
-            .iter()
-            .enumerate()
-            .map(|(i, balance)| {
-                let bank_index = i * 2;
-                let oracle_ai_idx = bank_index + 1;
+        // Each balance is followed by the oracle accounts its bank config reads from.
+        let mut ai_idx = 0;
 
-                let bank_ai = remaining_ais.get(bank_index).unwrap();
+        let bank_accounts_with_price = active_balances
+            .iter()
+            .map(|balance| {
+                let bank_ai = remaining_ais
+                    .get(ai_idx)
+                    .ok_or(MarginfiError::MissingPythOrBankAccount)?;
 
                 check!(
                     balance.bank_pk.eq(bank_ai.key),
                     MarginfiError::InvalidBankAccount
                 );
 
+                let bank_al = AccountLoader::<Bank>::try_from(bank_ai)?;
+
                 let price_adapter = {
-                    let oracle_ais = &remaining_ais[oracle_ai_idx..oracle_ai_idx + 1];
-                    let bank_al = AccountLoader::<Bank>::try_from(bank_ai)?;
                     let bank = bank_al.load()?;
 
-                    Box::new(OraclePriceFeedAdapter::try_from_bank_config(
-                        &bank.config,
+                    let oracle_ai_idx = ai_idx + 1;
+                    let oracle_ais_end = oracle_ai_idx + bank.config.get_oracle_keys().len();
+                    check!(
+                        oracle_ais_end <= remaining_ais.len(),
+                        MarginfiError::MissingPythOrBankAccount
+                    );
+                    let oracle_ais = &remaining_ais[oracle_ai_idx..oracle_ais_end];
+                    ai_idx = oracle_ais_end;
+
+                    Box::new(OraclePriceFeedAdapter::try_from_bank(
+                        &bank,
                         oracle_ais,
                         current_timestamp,
                     ))
                 };
 
                 Ok(BankAccountWithPriceFeed {
-                    bank: bank_ai.clone(),
-                    price_feed: price_adapter,
+                    source: AccountInfoBankPriceSource {
+                        bank: bank_al,
+                        price_feed: price_adapter,
+                    },
                     balance,
+                    asset_weight_override: None,
                 })
             })
-            .collect::<Result<Vec<_>>>()
+            .collect::<Result<Vec<_>>>()?;
+
+        debug!("Expected {} remaining accounts", ai_idx);
+
+        check!(
+            ai_idx == remaining_ais.len(),
+            MarginfiError::MissingPythOrBankAccount
+        );
+
+        Ok(bank_accounts_with_price)
+    }
+}
+
+impl<'a, S: BankPriceSource> BankAccountWithPriceFeed<'a, S> {
+    pub fn new(source: S, balance: &'a Balance) -> Self {
+        Self {
+            source,
+            balance,
+            asset_weight_override: None,
+        }
     }
 
     #[inline(always)]
@@ -215,22 +298,24 @@ impl<'a, 'b> BankAccountWithPriceFeed<'a, 'b> {
     /// 3. Initial requirement is discounted by the initial discount, if enabled and the usd limit is exceeded.
     /// 4. Assets are only calculated for collateral risk tier.
     /// 5. Oracle errors are ignored for deposits in isolated risk tier.
+    /// 6. E-mode category weights, if given, replace the bank weights.
+    /// 7. Group weight overrides, if resolved by the risk engine, replace the asset weights.
     pub fn calc_weighted_assets_and_liabilities_values(
         &self,
         requirement_type: RequirementType,
+        emode: Option<&EmodeCategory>,
     ) -> MarginfiResult<(I80F48, I80F48)> {
         match self.balance.get_side() {
             Some(side) => {
-                let bank_al = AccountLoader::<Bank>::try_from(&self.bank)?;
-                let bank = bank_al.load()?;
+                let bank = self.source.load_bank()?;
                 match side {
                     BalanceSide::Assets => Ok((
-                        self.calc_weighted_assets(requirement_type, &bank)?,
+                        self.calc_weighted_assets(requirement_type, &bank, emode)?,
                         I80F48::ZERO,
                     )),
                     BalanceSide::Liabilities => Ok((
                         I80F48::ZERO,
-                        self.calc_weighted_liabs(requirement_type, &bank)?,
+                        self.calc_weighted_liabs(requirement_type, &bank, emode)?,
                     )),
                 }
             }
@@ -238,18 +323,38 @@ impl<'a, 'b> BankAccountWithPriceFeed<'a, 'b> {
         }
     }
 
+    #[inline(always)]
+    fn get_weight(
+        &self,
+        requirement_type: RequirementType,
+        balance_side: BalanceSide,
+        bank: &Bank,
+        emode: Option<&EmodeCategory>,
+    ) -> I80F48 {
+        let is_assets = matches!(balance_side, BalanceSide::Assets);
+
+        match (requirement_type, self.asset_weight_override) {
+            (RequirementType::Initial, Some((init, _))) if is_assets => init,
+            (RequirementType::Maintenance, Some((_, maint))) if is_assets => maint,
+            _ => match emode {
+                Some(emode) => emode.get_weight(requirement_type, balance_side),
+                None => bank.config.get_weight(requirement_type, balance_side),
+            },
+        }
+    }
+
     #[inline(always)]
     fn calc_weighted_assets(
         &self,
         requirement_type: RequirementType,
         bank: &Bank,
+        emode: Option<&EmodeCategory>,
     ) -> MarginfiResult<I80F48> {
         match bank.config.risk_tier {
             RiskTier::Collateral => {
-                let price_feed = self.try_get_price_feed()?;
-                let mut asset_weight = bank
-                    .config
-                    .get_weight(requirement_type, BalanceSide::Assets);
+                let price_feed = self.source.price_feed()?;
+                let mut asset_weight =
+                    self.get_weight(requirement_type, BalanceSide::Assets, bank, emode);
 
                 let lower_price = price_feed.get_price_of_type(
                     requirement_type.get_oracle_price_type(),
@@ -282,11 +387,11 @@ impl<'a, 'b> BankAccountWithPriceFeed<'a, 'b> {
         &self,
         requirement_type: RequirementType,
         bank: &Bank,
+        emode: Option<&EmodeCategory>,
     ) -> MarginfiResult<I80F48> {
-        let price_feed = self.try_get_price_feed()?;
-        let liability_weight = bank
-            .config
-            .get_weight(requirement_type, BalanceSide::Liabilities);
+        let price_feed = self.source.price_feed()?;
+        let liability_weight =
+            self.get_weight(requirement_type, BalanceSide::Liabilities, bank, emode);
 
         let higher_price = price_feed.get_price_of_type(
             requirement_type.get_oracle_price_type(),
@@ -301,13 +406,6 @@ impl<'a, 'b> BankAccountWithPriceFeed<'a, 'b> {
         )
     }
 
-    fn try_get_price_feed(&self) -> MarginfiResult<&OraclePriceFeedAdapter> {
-        match self.price_feed.as_ref() {
-            Ok(a) => Ok(a),
-            Err(_) => Err(MarginfiError::StaleOracle)?,
-        }
-    }
-
     #[inline]
     pub fn is_empty(&self, side: BalanceSide) -> bool {
         self.balance.is_empty(side)
@@ -378,13 +476,17 @@ impl RiskRequirementType {
     }
 }
 
-pub struct RiskEngine<'a, 'b> {
+pub struct RiskEngine<'a, S: BankPriceSource> {
     marginfi_account: &'a MarginfiAccount,
-    bank_accounts_with_price: Vec<BankAccountWithPriceFeed<'a, 'b>>,
+    bank_accounts_with_price: Vec<BankAccountWithPriceFeed<'a, S>>,
+    /// Weights of the account's e-mode category, set while every balance of the account is in a
+    /// bank of that category.
+    emode: Option<EmodeCategory>,
 }
 
-impl<'a, 'b> RiskEngine<'a, 'b> {
+impl<'a, 'b> RiskEngine<'a, AccountInfoBankPriceSource<'b>> {
     pub fn new(
+        marginfi_group: &MarginfiGroup,
         marginfi_account: &'a MarginfiAccount,
         remaining_ais: &[AccountInfo<'b>],
     ) -> MarginfiResult<Self> {
@@ -393,30 +495,56 @@ impl<'a, 'b> RiskEngine<'a, 'b> {
             MarginfiError::AccountInFlashloan
         );
 
-        Self::new_no_flashloan_check(marginfi_account, remaining_ais)
+        Self::new_no_flashloan_check(marginfi_group, marginfi_account, remaining_ais)
     }
 
     /// Internal constructor used either after manually checking account is not in a flashloan,
     /// or explicity checking health for flashloan enabled actions.
-    fn new_no_flashloan_check(
+    pub(crate) fn new_no_flashloan_check(
+        marginfi_group: &MarginfiGroup,
         marginfi_account: &'a MarginfiAccount,
         remaining_ais: &[AccountInfo<'b>],
     ) -> MarginfiResult<Self> {
         let bank_accounts_with_price =
             BankAccountWithPriceFeed::load(&marginfi_account.lending_account, remaining_ais)?;
 
-        Ok(Self {
-            marginfi_account,
-            bank_accounts_with_price,
-        })
+        Self::new_with_bank_accounts(marginfi_group, marginfi_account, bank_accounts_with_price)
     }
 
     /// Checks account is healty after performing actions that increase risk (removing liquidity).
+    /// The action is refused while a bank price trips its price guard.
     ///
     /// `IN_FLASHLOAN_FLAG` behaviour.
     /// - Health check is skipped.
-    /// - `remaining_ais` can be an empty vec.
+    /// - `remaining_ais` can be an empty vec. Borrows from a bank with an active liability value
+    ///   limit still need the bank and its oracles, see `lending_account_borrow`.
     pub fn check_account_init_health(
+        marginfi_group: &MarginfiGroup,
+        marginfi_account: &'a MarginfiAccount,
+        remaining_ais: &[AccountInfo<'b>],
+    ) -> MarginfiResult<()> {
+        if marginfi_account.get_flag(IN_FLASHLOAN_FLAG) {
+            return Ok(());
+        }
+
+        Self::new_no_flashloan_check(marginfi_group, marginfi_account, remaining_ais)?
+            .check_init_health()
+    }
+
+    /// Checks of [`RiskEngine::check_account_init_health`] on an already loaded risk engine,
+    /// for instructions that also need the bank prices it loaded.
+    pub fn check_init_health(&self) -> MarginfiResult {
+        self.check_account_health(RiskRequirementType::Initial)?;
+        self.check_price_guards()?;
+
+        Ok(())
+    }
+
+    /// Checks the liquidator account is healthy after a liquidation. Unlike
+    /// [`RiskEngine::check_account_init_health`], price guards aren't enforced, liquidations go on
+    /// while a price moves fast.
+    pub fn check_liquidator_init_health(
+        marginfi_group: &MarginfiGroup,
         marginfi_account: &'a MarginfiAccount,
         remaining_ais: &[AccountInfo<'b>],
     ) -> MarginfiResult<()> {
@@ -424,12 +552,196 @@ impl<'a, 'b> RiskEngine<'a, 'b> {
             return Ok(());
         }
 
-        Self::new_no_flashloan_check(marginfi_account, remaining_ais)?
+        Self::new_no_flashloan_check(marginfi_group, marginfi_account, remaining_ais)?
             .check_account_health(RiskRequirementType::Initial)?;
 
         Ok(())
     }
 
+    /// Fails with `OraclePriceGuardTripped` if a bank price moved faster than the bank's
+    /// `oracle_max_price_change` allows. Banks writable in this instruction, e.g. the bank borrowed
+    /// from, record their price as the new reference. Read-only banks, e.g. collateral banks, are
+    /// checked against the reference left by the crank, see `Bank::last_oracle_price`.
+    fn check_price_guards(&self) -> MarginfiResult {
+        let clock = Clock::get()?;
+
+        for bank_account in &self.bank_accounts_with_price {
+            // Stale oracles are left to the health check
+            let Ok(price_feed) = bank_account.source.price_feed() else {
+                continue;
+            };
+            let price = price_feed.get_price_of_type(OraclePriceType::RealTime, None)?;
+            let bank_al = &bank_account.source.bank;
+
+            let within_guard = if bank_al.to_account_info().is_writable {
+                bank_al.load_mut()?.update_oracle_price_guard(
+                    price,
+                    price_feed.get_price_source(),
+                    &clock,
+                    #[cfg(not(feature = "client"))]
+                    bank_al.key(),
+                )?
+            } else {
+                bank_al.load()?.check_oracle_price_guard(
+                    price,
+                    clock.unix_timestamp,
+                    #[cfg(not(feature = "client"))]
+                    bank_al.key(),
+                )?
+            };
+
+            check!(within_guard, MarginfiError::OraclePriceGuardTripped);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, S: BankPriceSource> RiskEngine<'a, S> {
+    /// Build a risk engine from bank and price sources resolved by the caller, e.g. off-chain
+    /// from cached state. `sources` holds one source per active balance, in balance order.
+    ///
+    /// Unlike [`RiskEngine::new`], accounts in a flashloan are accepted.
+    pub fn new_with_sources(
+        marginfi_group: &MarginfiGroup,
+        marginfi_account: &'a MarginfiAccount,
+        sources: Vec<S>,
+    ) -> MarginfiResult<Self> {
+        let active_balances = marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .filter(|balance| balance.active)
+            .collect::<Vec<_>>();
+
+        check!(
+            active_balances.len() == sources.len(),
+            MarginfiError::MissingPythOrBankAccount
+        );
+
+        Self::new_with_bank_accounts(
+            marginfi_group,
+            marginfi_account,
+            active_balances
+                .into_iter()
+                .zip(sources)
+                .map(|(balance, source)| BankAccountWithPriceFeed::new(source, balance))
+                .collect(),
+        )
+    }
+
+    fn new_with_bank_accounts(
+        marginfi_group: &MarginfiGroup,
+        marginfi_account: &'a MarginfiAccount,
+        mut bank_accounts_with_price: Vec<BankAccountWithPriceFeed<'a, S>>,
+    ) -> MarginfiResult<Self> {
+        let emode = match marginfi_group.get_emode_category(marginfi_account.emode_category) {
+            Some(emode) => {
+                let mut in_category = true;
+
+                for a in bank_accounts_with_price.iter() {
+                    if a.balance.get_side().is_some() {
+                        in_category &= a.source.load_bank()?.config.emode_category
+                            == marginfi_account.emode_category;
+                    }
+                }
+
+                in_category.then_some(*emode)
+            }
+            None => None,
+        };
+
+        let liability_bank_pks = bank_accounts_with_price
+            .iter()
+            .filter(|a| matches!(a.balance.get_side(), Some(BalanceSide::Liabilities)))
+            .map(|a| a.balance.bank_pk)
+            .collect::<Vec<_>>();
+
+        for a in bank_accounts_with_price.iter_mut() {
+            if matches!(a.balance.get_side(), Some(BalanceSide::Assets)) {
+                a.asset_weight_override = Self::resolve_asset_weight_override(
+                    marginfi_group,
+                    &a.balance.bank_pk,
+                    &liability_bank_pks,
+                );
+            }
+        }
+
+        Ok(Self {
+            marginfi_account,
+            bank_accounts_with_price,
+            emode,
+        })
+    }
+
+    /// Lowest (init, maint) override weights of `asset_bank_pk` deposits across the account's
+    /// liability banks. `None` unless every liability bank has an override for the asset bank.
+    fn resolve_asset_weight_override(
+        marginfi_group: &MarginfiGroup,
+        asset_bank_pk: &Pubkey,
+        liability_bank_pks: &[Pubkey],
+    ) -> Option<(I80F48, I80F48)> {
+        let mut weights: Option<(I80F48, I80F48)> = None;
+
+        for liability_bank_pk in liability_bank_pks {
+            let weight_override =
+                marginfi_group.get_weight_override(asset_bank_pk, liability_bank_pk)?;
+            let init = I80F48::from(weight_override.asset_weight_init);
+            let maint = I80F48::from(weight_override.asset_weight_maint);
+
+            weights = Some(match weights {
+                Some((min_init, min_maint)) => (min_init.min(init), min_maint.min(maint)),
+                None => (init, maint),
+            });
+        }
+
+        weights
+    }
+
+    /// Price of `bank_pk` from the feed loaded for the account's balance in it, of the oracle
+    /// price type used for `requirement_type`.
+    pub fn get_bank_price(
+        &self,
+        bank_pk: &Pubkey,
+        requirement_type: RequirementType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let bank_account = self
+            .bank_accounts_with_price
+            .iter()
+            .find(|a| a.balance.bank_pk.eq(bank_pk))
+            .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+
+        bank_account
+            .source
+            .price_feed()?
+            .get_price_of_type(requirement_type.get_oracle_price_type(), bias)
+    }
+
+    /// Weights of the account's e-mode category, if they apply to the account.
+    pub fn get_emode(&self) -> Option<&EmodeCategory> {
+        self.emode.as_ref()
+    }
+
+    /// Returns the weighted assets and liabilities of each active balance in the form of
+    /// (bank, assets, liabilities), in balance order.
+    pub fn get_balance_health_components(
+        &self,
+        requirement_type: RiskRequirementType,
+    ) -> MarginfiResult<Vec<(Pubkey, I80F48, I80F48)>> {
+        self.bank_accounts_with_price
+            .iter()
+            .map(|a| {
+                let (assets, liabilities) = a.calc_weighted_assets_and_liabilities_values(
+                    requirement_type.to_weight_type(),
+                    self.emode.as_ref(),
+                )?;
+
+                Ok((a.balance.bank_pk, assets, liabilities))
+            })
+            .collect()
+    }
+
     /// Returns the total assets and liabilities of the account in the form of (assets, liabilities)
     pub fn get_account_health_components(
         &self,
@@ -439,8 +751,10 @@ impl<'a, 'b> RiskEngine<'a, 'b> {
         let mut total_liabilities = I80F48::ZERO;
 
         for a in &self.bank_accounts_with_price {
-            let (assets, liabilities) =
-                a.calc_weighted_assets_and_liabilities_values(requirement_type.to_weight_type())?;
+            let (assets, liabilities) = a.calc_weighted_assets_and_liabilities_values(
+                requirement_type.to_weight_type(),
+                self.emode.as_ref(),
+            )?;
 
             total_assets = total_assets.checked_add(assets).ok_or_else(math_error!())?;
             total_liabilities = total_liabilities
@@ -463,7 +777,7 @@ impl<'a, 'b> RiskEngine<'a, 'b> {
             .ok_or_else(math_error!())?)
     }
 
-    fn check_account_health(&self, requirement_type: RiskRequirementType) -> MarginfiResult {
+    pub fn check_account_health(&self, requirement_type: RiskRequirementType) -> MarginfiResult {
         let (total_weighted_assets, total_weighted_liabilities) =
             self.get_account_health_components(requirement_type)?;
 
@@ -531,6 +845,22 @@ impl<'a, 'b> RiskEngine<'a, 'b> {
         Ok(account_health)
     }
 
+    /// Share of the maintenance weighted liabilities not covered by maintenance weighted assets,
+    /// zero for an account above maintenance. Scales the liquidator fee, see
+    /// `Bank::get_liquidation_liquidator_fee`.
+    pub fn get_maintenance_shortfall_ratio(&self) -> MarginfiResult<I80F48> {
+        let (assets, liabs) =
+            self.get_account_health_components(RiskRequirementType::Maintenance)?;
+
+        if liabs == I80F48::ZERO || assets >= liabs {
+            return Ok(I80F48::ZERO);
+        }
+
+        Ok((liabs - assets)
+            .checked_div(liabs)
+            .ok_or_else(math_error!())?)
+    }
+
     /// Check that the account is at most at the maintenance requirement level post liquidation.
     /// This check is used to ensure two things in the liquidation process:
     /// 1. We check that the liquidatee's remaining liability is not empty
@@ -625,7 +955,7 @@ impl<'a, 'b> RiskEngine<'a, 'b> {
         Ok(())
     }
 
-    fn check_account_risk_tiers(&self) -> MarginfiResult {
+    pub fn check_account_risk_tiers(&self) -> MarginfiResult {
         let balances_with_liablities = self
             .bank_accounts_with_price
             .iter()
@@ -633,15 +963,9 @@ impl<'a, 'b> RiskEngine<'a, 'b> {
 
         let n_balances_with_liablities = balances_with_liablities.clone().count();
 
-        let is_in_isolated_risk_tier = balances_with_liablities.clone().any(|a| {
-            AccountLoader::<Bank>::try_from(&a.bank)
-                .unwrap()
-                .load()
-                .unwrap()
-                .config
-                .risk_tier
-                == RiskTier::Isolated
-        });
+        let is_in_isolated_risk_tier = balances_with_liablities
+            .clone()
+            .any(|a| a.source.load_bank().unwrap().config.risk_tier == RiskTier::Isolated);
 
         check!(
             !is_in_isolated_risk_tier || n_balances_with_liablities == 1,
@@ -1013,19 +1337,103 @@ impl<'a> BankAccountWrapper<'a> {
         Ok(())
     }
 
+    // ------------ Share tokenization
+
+    /// Move `shares` asset shares out of the balance, to be held as bank receipt tokens.
+    /// The bank keeps counting them in its total asset shares.
+    ///
+    /// Like `withdraw_all`, the balance is closed once only dust is left in it, the value of the
+    /// dust goes to the insurance fund.
+    pub fn tokenize_asset_shares(&mut self, shares: I80F48) -> MarginfiResult {
+        self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
+
+        let balance = &mut self.balance;
+        let bank = &mut self.bank;
+
+        bank.assert_operational_mode(None)?;
+
+        let current_asset_shares: I80F48 = balance.asset_shares.into();
+
+        debug!("Tokenizing asset shares: {}", shares);
+
+        check!(
+            shares <= current_asset_shares,
+            MarginfiError::IllegalBalanceState,
+            "Not enough asset shares"
+        );
+
+        balance.change_asset_shares(-shares)?;
+
+        let remaining_asset_shares: I80F48 = balance.asset_shares.into();
+
+        if remaining_asset_shares.is_zero_with_tolerance(ZERO_AMOUNT_THRESHOLD)
+            && balance.is_empty(BalanceSide::Liabilities)
+        {
+            let remaining_asset_amount = bank.get_asset_amount(remaining_asset_shares)?;
+
+            balance.close()?;
+            bank.change_asset_shares(-remaining_asset_shares, false)?;
+
+            bank.collected_insurance_fees_outstanding = {
+                remaining_asset_amount
+                    .checked_add(bank.collected_insurance_fees_outstanding.into())
+                    .ok_or_else(math_error!())?
+                    .into()
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Move `shares` asset shares, redeemed from bank receipt tokens, into the balance.
+    /// Will error if the balance has a liability.
+    pub fn redeem_asset_shares(&mut self, shares: I80F48) -> MarginfiResult {
+        self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
+
+        let balance = &mut self.balance;
+        let bank = &mut self.bank;
+
+        bank.assert_operational_mode(None)?;
+
+        debug!("Redeeming asset shares: {}", shares);
+
+        check!(
+            balance.is_empty(BalanceSide::Liabilities),
+            MarginfiError::IllegalBalanceState,
+            "Balance has existing debt"
+        );
+
+        balance.change_asset_shares(shares)?;
+
+        Ok(())
+    }
+
     // ------------ Internal accounting logic
 
     fn increase_balance_internal(
         &mut self,
         balance_delta: I80F48,
         operation_type: BalanceIncreaseType,
+    ) -> MarginfiResult {
+        self.increase_balance_internal_at(
+            balance_delta,
+            operation_type,
+            Clock::get()?.unix_timestamp as u64,
+        )
+    }
+
+    fn increase_balance_internal_at(
+        &mut self,
+        balance_delta: I80F48,
+        operation_type: BalanceIncreaseType,
+        current_timestamp: u64,
     ) -> MarginfiResult {
         debug!(
             "Balance increase: {} (type: {:?})",
             balance_delta, operation_type
         );
 
-        self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
+        self.claim_emissions(current_timestamp)?;
 
         let balance = &mut self.balance;
         let bank = &mut self.bank;
@@ -1084,13 +1492,26 @@ impl<'a> BankAccountWrapper<'a> {
         &mut self,
         balance_delta: I80F48,
         operation_type: BalanceDecreaseType,
+    ) -> MarginfiResult {
+        self.decrease_balance_internal_at(
+            balance_delta,
+            operation_type,
+            Clock::get()?.unix_timestamp as u64,
+        )
+    }
+
+    fn decrease_balance_internal_at(
+        &mut self,
+        balance_delta: I80F48,
+        operation_type: BalanceDecreaseType,
+        current_timestamp: u64,
     ) -> MarginfiResult {
         debug!(
             "Balance decrease: {} of (type: {:?})",
             balance_delta, operation_type
         );
 
-        self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
+        self.claim_emissions(current_timestamp)?;
 
         let balance = &mut self.balance;
         let bank = &mut self.bank;
@@ -1240,20 +1661,72 @@ impl<'a> BankAccountWrapper<'a> {
         &self,
         amount: u64,
         accounts: Transfer<'b>,
+        maybe_mint: Option<&AccountInfo<'b>>,
         program: AccountInfo<'c>,
     ) -> MarginfiResult {
-        self.bank.deposit_spl_transfer(amount, accounts, program)
+        self.bank
+            .deposit_spl_transfer(amount, accounts, maybe_mint, program)
     }
 
     pub fn withdraw_spl_transfer<'b: 'c, 'c: 'b>(
         &self,
         amount: u64,
         accounts: Transfer<'b>,
+        maybe_mint: Option<&AccountInfo<'b>>,
         program: AccountInfo<'c>,
         signer_seeds: &[&[&[u8]]],
     ) -> MarginfiResult {
         self.bank
-            .withdraw_spl_transfer(amount, accounts, program, signer_seeds)
+            .withdraw_spl_transfer(amount, accounts, maybe_mint, program, signer_seeds)
+    }
+}
+
+/// Borrow / lend primitives taking the current time explicitly instead of reading the clock
+/// sysvar, so balance changes can be simulated off-chain.
+#[cfg(any(feature = "test", feature = "client"))]
+impl<'a> BankAccountWrapper<'a> {
+    pub fn deposit_at(&mut self, amount: I80F48, current_timestamp: u64) -> MarginfiResult {
+        self.increase_balance_internal_at(amount, BalanceIncreaseType::Any, current_timestamp)
+    }
+
+    pub fn repay_at(&mut self, amount: I80F48, current_timestamp: u64) -> MarginfiResult {
+        self.increase_balance_internal_at(amount, BalanceIncreaseType::RepayOnly, current_timestamp)
+    }
+
+    pub fn withdraw_at(&mut self, amount: I80F48, current_timestamp: u64) -> MarginfiResult {
+        self.decrease_balance_internal_at(
+            amount,
+            BalanceDecreaseType::WithdrawOnly,
+            current_timestamp,
+        )
+    }
+
+    pub fn borrow_at(&mut self, amount: I80F48, current_timestamp: u64) -> MarginfiResult {
+        self.decrease_balance_internal_at(amount, BalanceDecreaseType::Any, current_timestamp)
+    }
+
+    pub fn increase_balance_in_liquidation_at(
+        &mut self,
+        amount: I80F48,
+        current_timestamp: u64,
+    ) -> MarginfiResult {
+        self.increase_balance_internal_at(
+            amount,
+            BalanceIncreaseType::BypassDepositLimit,
+            current_timestamp,
+        )
+    }
+
+    pub fn decrease_balance_in_liquidation_at(
+        &mut self,
+        amount: I80F48,
+        current_timestamp: u64,
+    ) -> MarginfiResult {
+        self.decrease_balance_internal_at(
+            amount,
+            BalanceDecreaseType::BypassBorrowLimit,
+            current_timestamp,
+        )
     }
 }
 
@@ -1341,7 +1814,9 @@ mod test {
                 _padding: [0; 8],
             },
             account_flags: TRANSFER_AUTHORITY_ALLOWED_FLAG,
-            _padding: [0; 63],
+            emode_category: 0,
+            _pad0: [0; 7],
+            _padding: [0; 62],
         };
 
         assert!(acc.get_flag(TRANSFER_AUTHORITY_ALLOWED_FLAG));
@@ -1,17 +1,25 @@
 use super::{
     marginfi_account::{BalanceSide, RequirementType},
-    price::{OraclePriceFeedAdapter, OracleSetup},
+    price::{
+        OracleConfidenceConfig, OracleConfidencePolicy, OraclePriceFeedAdapter, OraclePriceSource,
+        OracleSetup,
+    },
 };
 #[cfg(not(feature = "client"))]
-use crate::events::{GroupEventHeader, LendingPoolBankAccrueInterestEvent};
+use crate::events::{
+    GroupEventHeader, LendingPoolBankAccrueInterestEvent, LendingPoolBankPriceGuardTripEvent,
+};
 use crate::{
     assert_struct_align, assert_struct_size, check,
     constants::{
-        EMISSION_FLAGS, FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED, GROUP_FLAGS,
-        INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED,
-        LIQUIDITY_VAULT_SEED, MAX_ORACLE_KEYS, MAX_PRICE_AGE_SEC,
+        EMISSION_FLAGS, EMODE_CATEGORY_NONE, FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED, GROUP_FLAGS,
+        INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED, INTEREST_COMPOUNDING_SERIES_TERMS,
+        LIQUIDATION_INSURANCE_FEE, LIQUIDATION_LIQUIDATOR_FEE, LIQUIDITY_VAULT_AUTHORITY_SEED,
+        LIQUIDITY_VAULT_SEED, MAX_CONF_INTERVAL, MAX_EMODE_CATEGORIES,
+        MAX_EXTRA_INTEREST_RATE_KINKS, MAX_ORACLE_KEYS, MAX_PRICE_AGE_SEC, MAX_WEIGHT_OVERRIDES,
+        MIN_ADAPTIVE_PLATEAU_INTEREST_RATE, ORACLE_PRICE_GUARD_WINDOW_SEC,
         PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG, PYTH_ID, SECONDS_PER_YEAR,
-        TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
+        TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE, TOTAL_LIABILITY_VALUE_LIMIT_INACTIVE,
     },
     debug, math_error,
     prelude::MarginfiError,
@@ -20,7 +28,10 @@ use crate::{
     MarginfiResult,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{transfer, Transfer};
+use anchor_spl::{
+    token::{transfer, Transfer},
+    token_interface::{transfer_checked, TransferChecked},
+};
 use fixed::types::I80F48;
 use pyth_sdk_solana::{load_price_feed_from_account_info, PriceFeed};
 #[cfg(feature = "client")]
@@ -41,8 +52,11 @@ use type_layout::TypeLayout;
 #[derive(Default)]
 pub struct MarginfiGroup {
     pub admin: Pubkey,
-    pub _padding_0: [[u64; 2]; 32],
-    pub _padding_1: [[u64; 2]; 32],
+    /// E-mode categories, category `n` is stored at index `n - 1`.
+    pub emode_categories: [EmodeCategory; MAX_EMODE_CATEGORIES],
+    /// Asset weights of (asset bank, liability bank) pairs, unused entries are zeroed.
+    pub weight_overrides: [WeightOverride; MAX_WEIGHT_OVERRIDES],
+    pub _padding_0: [[u64; 2]; 12],
 }
 
 impl MarginfiGroup {
@@ -55,6 +69,91 @@ impl MarginfiGroup {
         Ok(())
     }
 
+    /// Set the weights of an e-mode category, a zero `asset_weight_maint` disables the category.
+    pub fn configure_emode_category(
+        &mut self,
+        emode_category: u8,
+        config: EmodeCategory,
+    ) -> MarginfiResult {
+        check!(
+            emode_category != EMODE_CATEGORY_NONE
+                && emode_category as usize <= MAX_EMODE_CATEGORIES,
+            MarginfiError::InvalidEmodeCategory
+        );
+
+        self.emode_categories[emode_category as usize - 1] = if config.is_active() {
+            config.validate()?;
+            config
+        } else {
+            EmodeCategory::default()
+        };
+
+        Ok(())
+    }
+
+    /// Active e-mode category `emode_category`, `None` for no e-mode or a disabled category.
+    pub fn get_emode_category(&self, emode_category: u8) -> Option<&EmodeCategory> {
+        if emode_category == EMODE_CATEGORY_NONE {
+            return None;
+        }
+
+        self.emode_categories
+            .get(emode_category as usize - 1)
+            .filter(|category| category.is_active())
+    }
+
+    /// Set the asset weights of `asset_bank` deposits against `liability_bank` debt, a zero
+    /// `asset_weight_maint` removes the override.
+    pub fn configure_weight_override(
+        &mut self,
+        asset_bank: Pubkey,
+        liability_bank: Pubkey,
+        asset_weight_init: I80F48,
+        asset_weight_maint: I80F48,
+    ) -> MarginfiResult {
+        check!(asset_bank != liability_bank, MarginfiError::InvalidConfig);
+
+        let existing = self
+            .weight_overrides
+            .iter()
+            .position(|o| o.is_for(&asset_bank, &liability_bank));
+
+        if asset_weight_maint == I80F48::ZERO {
+            if let Some(index) = existing {
+                self.weight_overrides[index] = WeightOverride::default();
+            }
+
+            return Ok(());
+        }
+
+        let weight_override = WeightOverride {
+            asset_bank,
+            liability_bank,
+            asset_weight_init: asset_weight_init.into(),
+            asset_weight_maint: asset_weight_maint.into(),
+        };
+        weight_override.validate()?;
+
+        let index = existing
+            .or_else(|| self.weight_overrides.iter().position(|o| !o.is_active()))
+            .ok_or(MarginfiError::WeightOverrideTableFull)?;
+
+        self.weight_overrides[index] = weight_override;
+
+        Ok(())
+    }
+
+    /// Override of the asset weights of `asset_bank` deposits against `liability_bank` debt.
+    pub fn get_weight_override(
+        &self,
+        asset_bank: &Pubkey,
+        liability_bank: &Pubkey,
+    ) -> Option<&WeightOverride> {
+        self.weight_overrides
+            .iter()
+            .find(|o| o.is_for(asset_bank, liability_bank))
+    }
+
     /// Set the group parameters when initializing a group.
     /// This should be called only when the group is first initialized.
     /// Both margin requirements are initially set to 100% and should be configured before use.
@@ -70,6 +169,119 @@ pub struct GroupConfig {
     pub admin: Option<Pubkey>,
 }
 
+/// Weights replacing the bank weights of an account in e-mode, while all of its balances are in
+/// banks of the account's category. Disabled while `asset_weight_maint` is zero.
+#[zero_copy]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default, Debug, AnchorDeserialize, AnchorSerialize)]
+pub struct EmodeCategory {
+    pub asset_weight_init: WrappedI80F48,
+    pub asset_weight_maint: WrappedI80F48,
+
+    pub liability_weight_init: WrappedI80F48,
+    pub liability_weight_maint: WrappedI80F48,
+}
+
+impl EmodeCategory {
+    pub fn is_active(&self) -> bool {
+        I80F48::from(self.asset_weight_maint) != I80F48::ZERO
+    }
+
+    /// Same bounds as the weights of `BankConfig`.
+    pub fn validate(&self) -> MarginfiResult {
+        let asset_init_w = I80F48::from(self.asset_weight_init);
+        let asset_maint_w = I80F48::from(self.asset_weight_maint);
+
+        check!(
+            asset_init_w >= I80F48::ZERO && asset_init_w <= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+        check!(
+            asset_maint_w >= asset_init_w && asset_maint_w <= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+
+        let liab_init_w = I80F48::from(self.liability_weight_init);
+        let liab_maint_w = I80F48::from(self.liability_weight_maint);
+
+        check!(liab_init_w >= I80F48::ONE, MarginfiError::InvalidConfig);
+        check!(
+            liab_maint_w <= liab_init_w && liab_maint_w >= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn get_weight(
+        &self,
+        requirement_type: RequirementType,
+        balance_side: BalanceSide,
+    ) -> I80F48 {
+        match (requirement_type, balance_side) {
+            (RequirementType::Initial, BalanceSide::Assets) => self.asset_weight_init.into(),
+            (RequirementType::Initial, BalanceSide::Liabilities) => {
+                self.liability_weight_init.into()
+            }
+            (RequirementType::Maintenance, BalanceSide::Assets) => self.asset_weight_maint.into(),
+            (RequirementType::Maintenance, BalanceSide::Liabilities) => {
+                self.liability_weight_maint.into()
+            }
+            (RequirementType::Equity, _) => I80F48::ONE,
+        }
+    }
+}
+
+/// Asset weights of `asset_bank` deposits held by an account borrowing from `liability_bank`.
+/// Replace the bank and e-mode weights of the deposits while every liability of the account has
+/// an override for the asset bank, see `RiskEngine`. Unused while `asset_bank` is the default key.
+#[zero_copy]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default, Debug, AnchorDeserialize, AnchorSerialize)]
+pub struct WeightOverride {
+    pub asset_bank: Pubkey,
+    pub liability_bank: Pubkey,
+
+    pub asset_weight_init: WrappedI80F48,
+    pub asset_weight_maint: WrappedI80F48,
+}
+
+impl WeightOverride {
+    pub fn is_active(&self) -> bool {
+        self.asset_bank != Pubkey::default()
+    }
+
+    fn is_for(&self, asset_bank: &Pubkey, liability_bank: &Pubkey) -> bool {
+        self.is_active() && self.asset_bank == *asset_bank && self.liability_bank == *liability_bank
+    }
+
+    /// Same bounds as the asset weights of `BankConfig`.
+    pub fn validate(&self) -> MarginfiResult {
+        let asset_init_w = I80F48::from(self.asset_weight_init);
+        let asset_maint_w = I80F48::from(self.asset_weight_maint);
+
+        check!(
+            asset_init_w >= I80F48::ZERO && asset_init_w <= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+        check!(
+            asset_maint_w >= asset_init_w && asset_maint_w <= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+
+        Ok(())
+    }
+}
+
 /// Load and validate a pyth price feed account.
 pub fn load_pyth_price_feed(ai: &AccountInfo) -> MarginfiResult<PriceFeed> {
     check!(ai.owner.eq(&PYTH_ID), MarginfiError::InvalidOracleAccount);
@@ -78,6 +290,32 @@ pub fn load_pyth_price_feed(ai: &AccountInfo) -> MarginfiResult<PriceFeed> {
     Ok(price_feed)
 }
 
+#[repr(u8)]
+#[cfg_attr(any(feature = "test", feature = "client"), derive(PartialEq, Eq))]
+#[derive(Copy, Clone, Debug, Default, AnchorSerialize, AnchorDeserialize)]
+pub enum InterestRateCurve {
+    /// Single kink at `optimal_utilization_rate`.
+    #[default]
+    Linear,
+    /// Kink at `optimal_utilization_rate` followed by the `extra_kinks`.
+    MultiKink,
+    /// Single kink whose plateau rate moves up while utilization is above
+    /// `optimal_utilization_rate` and down while it is below, at `adaptive_speed`.
+    Adaptive,
+}
+
+#[cfg(feature = "client")]
+impl Display for InterestRateCurve {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterestRateCurve::Linear => write!(f, "Linear"),
+            InterestRateCurve::MultiKink => write!(f, "MultiKink"),
+            InterestRateCurve::Adaptive => write!(f, "Adaptive"),
+        }
+    }
+}
+
+/// Point of a `InterestRateCurve::MultiKink` curve, unused while `utilization_rate` is zero.
 #[zero_copy]
 #[repr(C)]
 #[cfg_attr(
@@ -85,6 +323,24 @@ pub fn load_pyth_price_feed(ai: &AccountInfo) -> MarginfiResult<PriceFeed> {
     derive(PartialEq, Eq, TypeLayout)
 )]
 #[derive(Default, Debug, AnchorDeserialize, AnchorSerialize)]
+pub struct InterestRateKink {
+    pub utilization_rate: WrappedI80F48,
+    pub interest_rate: WrappedI80F48,
+}
+
+impl InterestRateKink {
+    pub fn is_active(&self) -> bool {
+        I80F48::from(self.utilization_rate) != I80F48::ZERO
+    }
+}
+
+#[zero_copy(unsafe)]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default, Debug, AnchorDeserialize, AnchorSerialize)]
 pub struct InterestRateConfigCompact {
     // Curve Params
     pub optimal_utilization_rate: WrappedI80F48,
@@ -96,6 +352,10 @@ pub struct InterestRateConfigCompact {
     pub insurance_ir_fee: WrappedI80F48,
     pub protocol_fixed_fee_apr: WrappedI80F48,
     pub protocol_ir_fee: WrappedI80F48,
+
+    pub curve_type: InterestRateCurve,
+    pub extra_kinks: [InterestRateKink; MAX_EXTRA_INTEREST_RATE_KINKS],
+    pub adaptive_speed: WrappedI80F48,
 }
 
 impl From<InterestRateConfigCompact> for InterestRateConfig {
@@ -108,7 +368,11 @@ impl From<InterestRateConfigCompact> for InterestRateConfig {
             insurance_ir_fee: ir_config.insurance_ir_fee,
             protocol_fixed_fee_apr: ir_config.protocol_fixed_fee_apr,
             protocol_ir_fee: ir_config.protocol_ir_fee,
-            _padding: [[0; 2]; 8],
+            curve_type: ir_config.curve_type,
+            _pad0: [0; 7],
+            extra_kinks: ir_config.extra_kinks,
+            adaptive_speed: ir_config.adaptive_speed,
+            _padding: [0; 5],
         }
     }
 }
@@ -123,11 +387,14 @@ impl From<InterestRateConfig> for InterestRateConfigCompact {
             insurance_ir_fee: ir_config.insurance_ir_fee,
             protocol_fixed_fee_apr: ir_config.protocol_fixed_fee_apr,
             protocol_ir_fee: ir_config.protocol_ir_fee,
+            curve_type: ir_config.curve_type,
+            extra_kinks: ir_config.extra_kinks,
+            adaptive_speed: ir_config.adaptive_speed,
         }
     }
 }
 
-#[zero_copy]
+#[zero_copy(unsafe)]
 #[repr(C)]
 #[cfg_attr(
     any(feature = "test", feature = "client"),
@@ -146,7 +413,14 @@ pub struct InterestRateConfig {
     pub protocol_fixed_fee_apr: WrappedI80F48,
     pub protocol_ir_fee: WrappedI80F48,
 
-    pub _padding: [[u64; 2]; 8], // 16 * 8 = 128 bytes
+    pub curve_type: InterestRateCurve,
+    pub _pad0: [u8; 7],
+    pub extra_kinks: [InterestRateKink; MAX_EXTRA_INTEREST_RATE_KINKS],
+    /// Share of the plateau rate the `InterestRateCurve::Adaptive` curve moves by per year while
+    /// utilization is fully off target.
+    pub adaptive_speed: WrappedI80F48,
+
+    pub _padding: [u64; 5], // 8 + 64 + 16 + 40 = 128 bytes
 }
 
 impl InterestRateConfig {
@@ -213,9 +487,17 @@ impl InterestRateConfig {
     #[inline]
     fn interest_rate_curve(&self, ur: I80F48) -> Option<I80F48> {
         let optimal_ur = self.optimal_utilization_rate.into();
-        let plateau_ir = self.plateau_interest_rate.into();
         let max_ir: I80F48 = self.max_interest_rate.into();
 
+        // The bank passes its current adaptive plateau as `plateau_interest_rate`,
+        // see `Bank::get_interest_rate_config`
+        let plateau_ir: I80F48 = match self.curve_type {
+            InterestRateCurve::Linear | InterestRateCurve::Adaptive => {
+                self.plateau_interest_rate.into()
+            }
+            InterestRateCurve::MultiKink => return self.multi_kink_interest_rate_curve(ur),
+        };
+
         if ur <= optimal_ur {
             ur.checked_div(optimal_ur)?.checked_mul(plateau_ir)
         } else {
@@ -226,6 +508,38 @@ impl InterestRateConfig {
         }
     }
 
+    /// Linear interpolation between (0, 0), (`optimal_utilization_rate`, `plateau_interest_rate`),
+    /// the active `extra_kinks` and (1, `max_interest_rate`).
+    fn multi_kink_interest_rate_curve(&self, ur: I80F48) -> Option<I80F48> {
+        let mut start = (I80F48::ZERO, I80F48::ZERO);
+
+        let kinks = [(
+            I80F48::from(self.optimal_utilization_rate),
+            I80F48::from(self.plateau_interest_rate),
+        )]
+        .into_iter()
+        .chain(
+            self.extra_kinks
+                .iter()
+                .filter(|kink| kink.is_active())
+                .map(|kink| {
+                    (
+                        I80F48::from(kink.utilization_rate),
+                        I80F48::from(kink.interest_rate),
+                    )
+                }),
+        );
+
+        for end in kinks {
+            if ur <= end.0 {
+                return interpolate_interest_rate(start, end, ur);
+            }
+            start = end;
+        }
+
+        interpolate_interest_rate(start, (I80F48::ONE, self.max_interest_rate.into()), ur)
+    }
+
     pub fn validate(&self) -> MarginfiResult {
         let optimal_ur: I80F48 = self.optimal_utilization_rate.into();
         let plateau_ir: I80F48 = self.plateau_interest_rate.into();
@@ -239,6 +553,48 @@ impl InterestRateConfig {
         check!(max_ir > I80F48::ZERO, MarginfiError::InvalidConfig);
         check!(plateau_ir < max_ir, MarginfiError::InvalidConfig);
 
+        match self.curve_type {
+            InterestRateCurve::Linear => {}
+            InterestRateCurve::MultiKink => {
+                // Active kinks come first and keep both rates increasing up to (1, `max_ir`)
+                let mut previous = (optimal_ur, plateau_ir);
+                let mut active = true;
+
+                for kink in self.extra_kinks.iter() {
+                    if !kink.is_active() {
+                        active = false;
+                        continue;
+                    }
+
+                    check!(active, MarginfiError::InvalidConfig);
+
+                    let kink_ur: I80F48 = kink.utilization_rate.into();
+                    let kink_ir: I80F48 = kink.interest_rate.into();
+
+                    check!(
+                        kink_ur > previous.0 && kink_ur < I80F48::ONE,
+                        MarginfiError::InvalidConfig
+                    );
+                    check!(
+                        kink_ir >= previous.1 && kink_ir <= max_ir,
+                        MarginfiError::InvalidConfig
+                    );
+
+                    previous = (kink_ur, kink_ir);
+                }
+            }
+            InterestRateCurve::Adaptive => {
+                check!(
+                    I80F48::from(self.adaptive_speed) > I80F48::ZERO,
+                    MarginfiError::InvalidConfig
+                );
+                check!(
+                    plateau_ir >= MIN_ADAPTIVE_PLATEAU_INTEREST_RATE,
+                    MarginfiError::InvalidConfig
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -259,9 +615,25 @@ impl InterestRateConfig {
             ir_config.protocol_fixed_fee_apr
         );
         set_if_some!(self.protocol_ir_fee, ir_config.protocol_ir_fee);
+        set_if_some!(self.curve_type, ir_config.curve_type);
+        set_if_some!(self.extra_kinks, ir_config.extra_kinks);
+        set_if_some!(self.adaptive_speed, ir_config.adaptive_speed);
     }
 }
 
+/// Interest rate at `ur` on the segment from `start` to `end`, both (utilization, rate) points.
+#[inline]
+fn interpolate_interest_rate(
+    start: (I80F48, I80F48),
+    end: (I80F48, I80F48),
+    ur: I80F48,
+) -> Option<I80F48> {
+    (ur - start.0)
+        .checked_div(end.0 - start.0)?
+        .checked_mul(end.1 - start.1)?
+        .checked_add(start.1)
+}
+
 #[cfg_attr(
     any(feature = "test", feature = "client"),
     derive(Debug, PartialEq, Eq, TypeLayout)
@@ -276,6 +648,10 @@ pub struct InterestRateConfigOpt {
     pub insurance_ir_fee: Option<WrappedI80F48>,
     pub protocol_fixed_fee_apr: Option<WrappedI80F48>,
     pub protocol_ir_fee: Option<WrappedI80F48>,
+
+    pub curve_type: Option<InterestRateCurve>,
+    pub extra_kinks: Option<[InterestRateKink; MAX_EXTRA_INTEREST_RATE_KINKS]>,
+    pub adaptive_speed: Option<WrappedI80F48>,
 }
 
 assert_struct_size!(Bank, 1856);
@@ -330,7 +706,92 @@ pub struct Bank {
     pub emissions_remaining: WrappedI80F48,
     pub emissions_mint: Pubkey,
 
-    pub _padding_0: [[u64; 2]; 28],
+    /// Price of `OracleSetup::Fixed` banks, peg of `OracleSetup::Pegged` banks.
+    pub fixed_price: WrappedI80F48,
+
+    /// Largest oracle confidence interval, as a share of the price.
+    /// If set to 0, `MAX_CONF_INTERVAL` is used.
+    pub oracle_max_confidence: WrappedI80F48,
+    /// Multiple applied to the published oracle confidence.
+    /// If set to 0, `CONF_INTERVAL_MULTIPLE` is used for Pyth and `STD_DEV_MULTIPLE` for
+    /// Switchboard.
+    pub oracle_confidence_multiple: WrappedI80F48,
+    /// Whether prices with a confidence interval wider than `oracle_max_confidence` are clamped
+    /// or rejected.
+    pub oracle_confidence_policy: OracleConfidencePolicy,
+    pub _oracle_confidence_padding: [u8; 15],
+
+    /// Largest oracle price change per second, as a share of the last accepted price. Borrows and
+    /// withdraws are refused while a price moves faster, see `Bank::last_oracle_price`.
+    /// If set to 0, the price guard is disabled.
+    pub oracle_max_price_change: WrappedI80F48,
+    /// Last oracle price accepted by the price guard. Refreshed by the crank, liquidations, and
+    /// borrows and withdraws from the bank. Moves are measured over at most
+    /// `ORACLE_PRICE_GUARD_WINDOW_SEC`, so the crank has to run more often than that for collateral
+    /// banks only read by borrows and withdraws elsewhere to keep a recent reference.
+    pub last_oracle_price: WrappedI80F48,
+    pub last_oracle_price_slot: u64,
+    pub last_oracle_price_timestamp: i64,
+
+    /// Share of the liquidated collateral value paid to the liquidator. Unused until
+    /// `liquidation_fees_configured` is set.
+    pub liquidation_liquidator_fee: WrappedI80F48,
+    /// Share of the liquidated collateral value paid into the insurance fund. Unused until
+    /// `liquidation_fees_configured` is set.
+    pub liquidation_insurance_fee: WrappedI80F48,
+    /// Largest share of the liquidated collateral value paid to the liquidator. The liquidator fee
+    /// grows from `liquidation_liquidator_fee` with how far the liquidatee is below maintenance,
+    /// up to this value, see `Bank::get_liquidation_liquidator_fee`.
+    /// If set to 0, the liquidator fee is fixed.
+    pub liquidation_max_liquidator_fee: WrappedI80F48,
+    /// Largest share of a liability one liquidation instruction may repay, see
+    /// `Bank::check_liquidation_close_factor`.
+    /// If set to 0, the whole liability may be repaid.
+    pub liquidation_close_factor: WrappedI80F48,
+    /// Liabilities up to this amount, in native tokens, may be repaid in full by one liquidation
+    /// regardless of `liquidation_close_factor`, so small positions aren't left as dust.
+    pub liquidation_full_close_threshold: u64,
+    /// 1 once `liquidation_liquidator_fee` and `liquidation_insurance_fee` hold the bank fees, 0
+    /// on banks created before per bank fees, which charge `LIQUIDATION_LIQUIDATOR_FEE` and
+    /// `LIQUIDATION_INSURANCE_FEE` until their fees are first configured.
+    pub liquidation_fees_configured: u8,
+    pub _liquidation_padding: [u8; 7],
+
+    /// Mint of the receipt tokens representing asset shares of this bank, one token per share.
+    /// Set up with `lending_pool_setup_receipt_mint`, `Pubkey::default()` if the bank has none.
+    pub receipt_mint: Pubkey,
+    pub receipt_mint_bump: u8,
+    pub receipt_mint_authority_bump: u8,
+    pub _receipt_mint_padding: [u8; 14],
+
+    /// USD denominated limit on the value of the bank's liabilities, checked on borrow at the
+    /// oracle price. Value is UI USD value, for example value 100 -> $100.
+    /// If set to 0, borrows are only limited by `BankConfig::borrow_limit`.
+    ///
+    /// Liability side counterpart of `BankConfig::total_asset_value_init_limit`: instead of
+    /// discounting the bank's value as collateral, borrows over the limit are refused. Kept on
+    /// the bank since the `BankConfig` layout has no room left, set through `BankConfigOpt`.
+    pub total_liability_value_limit: u64,
+    pub _liability_value_limit_padding: [u8; 8],
+
+    /// Current plateau rate of the `InterestRateCurve::Adaptive` interest rate curve, moved on
+    /// every interest accrual. Zero until the first accrual, or after the curve type or plateau
+    /// rate is reconfigured, in which case `InterestRateConfig::plateau_interest_rate` is used.
+    pub adaptive_plateau_interest_rate: WrappedI80F48,
+
+    /// Price guard trips recorded by the instructions going on despite a trip, the crank and
+    /// liquidations. Borrows and withdraws refused by the guard fail, leaving no record.
+    pub oracle_price_guard_trip_count: u64,
+    /// Unix timestamp of the last recorded price guard trip, 0 if none.
+    pub last_oracle_price_guard_trip_timestamp: i64,
+
+    /// Feed of the last price read by the crank, a liquidation, or a borrow or withdraw from the
+    /// bank, `Fallback` while the primary feed was stale. Read-only banks don't record the feed
+    /// their price came from.
+    pub last_oracle_price_source: OraclePriceSource,
+    pub _oracle_price_source_padding: [u8; 15],
+
+    pub _padding_0: [[u64; 2]; 9],
     pub _padding_1: [[u64; 2]; 32], // 16 * 2 * 32 = 1024B
 }
 
@@ -338,7 +799,7 @@ impl Bank {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         marginfi_group_pk: Pubkey,
-        config: BankConfig,
+        config: BankConfigCompact,
         mint: Pubkey,
         mint_decimals: u8,
         liquidity_vault: Pubkey,
@@ -372,12 +833,39 @@ impl Bank {
             total_liability_shares: I80F48::ZERO.into(),
             total_asset_shares: I80F48::ZERO.into(),
             last_update: current_timestamp,
-            config,
+            config: config.into(),
             flags: 0,
             emissions_rate: 0,
             emissions_remaining: I80F48::ZERO.into(),
             emissions_mint: Pubkey::default(),
-            _padding_0: [[0; 2]; 28],
+            fixed_price: config.fixed_price,
+            oracle_max_confidence: config.oracle_max_confidence,
+            oracle_confidence_multiple: config.oracle_confidence_multiple,
+            oracle_confidence_policy: config.oracle_confidence_policy,
+            _oracle_confidence_padding: [0; 15],
+            oracle_max_price_change: config.oracle_max_price_change,
+            last_oracle_price: I80F48::ZERO.into(),
+            last_oracle_price_slot: 0,
+            last_oracle_price_timestamp: 0,
+            liquidation_liquidator_fee: config.liquidation_liquidator_fee,
+            liquidation_insurance_fee: config.liquidation_insurance_fee,
+            liquidation_max_liquidator_fee: I80F48::ZERO.into(),
+            liquidation_close_factor: I80F48::ZERO.into(),
+            liquidation_full_close_threshold: 0,
+            liquidation_fees_configured: 1,
+            _liquidation_padding: [0; 7],
+            receipt_mint: Pubkey::default(),
+            receipt_mint_bump: 0,
+            receipt_mint_authority_bump: 0,
+            _receipt_mint_padding: [0; 14],
+            total_liability_value_limit: TOTAL_LIABILITY_VALUE_LIMIT_INACTIVE,
+            _liability_value_limit_padding: [0; 8],
+            adaptive_plateau_interest_rate: I80F48::ZERO.into(),
+            oracle_price_guard_trip_count: 0,
+            last_oracle_price_guard_trip_timestamp: 0,
+            last_oracle_price_source: OraclePriceSource::Primary,
+            _oracle_price_source_padding: [0; 15],
+            _padding_0: [[0; 2]; 9],
             _padding_1: [[0; 2]; 32],
         }
     }
@@ -497,6 +985,40 @@ impl Bank {
         Ok(())
     }
 
+    pub fn liability_value_limit_active(&self) -> bool {
+        self.total_liability_value_limit != TOTAL_LIABILITY_VALUE_LIMIT_INACTIVE
+    }
+
+    /// Check the value of the bank's liabilities at `price` against
+    /// `total_liability_value_limit`.
+    pub fn check_total_liability_value_limit(&self, price: I80F48) -> MarginfiResult {
+        if !self.liability_value_limit_active() {
+            return Ok(());
+        }
+
+        let bank_total_liabilities_value = calc_value(
+            self.get_liability_amount(self.total_liability_shares.into())?,
+            price,
+            self.mint_decimals,
+            None,
+        )?;
+
+        let total_liability_value_limit = I80F48::from_num(self.total_liability_value_limit);
+
+        #[cfg(target_os = "solana")]
+        debug!(
+            "Liability value limit active, limit: {}, total_liabilities: {}",
+            total_liability_value_limit, bank_total_liabilities_value
+        );
+
+        check!(
+            bank_total_liabilities_value <= total_liability_value_limit,
+            MarginfiError::BankLiabilityValueCapacityExceeded
+        );
+
+        Ok(())
+    }
+
     pub fn check_utilization_ratio(&self) -> MarginfiResult {
         let total_assets = self.get_asset_amount(self.total_asset_shares.into())?;
         let total_liabilities = self.get_liability_amount(self.total_liability_shares.into())?;
@@ -530,8 +1052,18 @@ impl Bank {
 
         set_if_some!(self.config.oracle_keys, config.oracle.map(|o| o.keys));
 
+        set_if_some!(
+            self.config.pyth_push_feed_id,
+            config.oracle.map(|o| o.pyth_push_feed_id)
+        );
+
         if let Some(ir_config) = &config.interest_rate_config {
             self.config.interest_rate_config.update(ir_config);
+
+            // Restart the adaptive curve from the configured plateau rate
+            if ir_config.curve_type.is_some() || ir_config.plateau_interest_rate.is_some() {
+                self.adaptive_plateau_interest_rate = I80F48::ZERO.into();
+            }
         }
 
         set_if_some!(self.config.risk_tier, config.risk_tier);
@@ -543,15 +1075,211 @@ impl Bank {
 
         set_if_some!(self.config.oracle_max_age, config.oracle_max_age);
 
+        set_if_some!(
+            self.config.oracle_max_deviation_bps,
+            config.oracle_max_deviation_bps
+        );
+
+        set_if_some!(self.fixed_price, config.fixed_price);
+
+        set_if_some!(self.oracle_max_confidence, config.oracle_max_confidence);
+
+        set_if_some!(
+            self.oracle_confidence_multiple,
+            config.oracle_confidence_multiple
+        );
+
+        set_if_some!(
+            self.oracle_confidence_policy,
+            config.oracle_confidence_policy
+        );
+
+        set_if_some!(self.oracle_max_price_change, config.oracle_max_price_change);
+
+        // Banks created before per bank fees start from the default fees, so configuring one fee
+        // leaves the other one in place
+        if self.liquidation_fees_configured == 0 {
+            self.liquidation_liquidator_fee = LIQUIDATION_LIQUIDATOR_FEE.into();
+            self.liquidation_insurance_fee = LIQUIDATION_INSURANCE_FEE.into();
+            self.liquidation_fees_configured = 1;
+        }
+
+        set_if_some!(
+            self.liquidation_liquidator_fee,
+            config.liquidation_liquidator_fee
+        );
+
+        set_if_some!(
+            self.liquidation_insurance_fee,
+            config.liquidation_insurance_fee
+        );
+
+        set_if_some!(
+            self.liquidation_max_liquidator_fee,
+            config.liquidation_max_liquidator_fee
+        );
+
+        set_if_some!(
+            self.liquidation_close_factor,
+            config.liquidation_close_factor
+        );
+
+        set_if_some!(
+            self.liquidation_full_close_threshold,
+            config.liquidation_full_close_threshold
+        );
+
+        set_if_some!(self.config.emode_category, config.emode_category);
+
+        set_if_some!(
+            self.total_liability_value_limit,
+            config.total_liability_value_limit
+        );
+
         if let Some(flag) = config.permissionless_bad_debt_settlement {
             self.update_flag(flag, PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG);
         }
 
+        self.validate_config()?;
+
+        Ok(())
+    }
+
+    /// Validate the bank config, including the parts of it stored on the bank itself.
+    pub fn validate_config(&self) -> MarginfiResult {
         self.config.validate()?;
 
+        check!(
+            I80F48::from(self.oracle_max_confidence) >= I80F48::ZERO
+                && I80F48::from(self.oracle_confidence_multiple) >= I80F48::ZERO
+                && I80F48::from(self.oracle_max_price_change) >= I80F48::ZERO,
+            MarginfiError::InvalidConfig
+        );
+
+        if matches!(
+            self.config.oracle_setup,
+            OracleSetup::Fixed | OracleSetup::Pegged
+        ) {
+            check!(
+                I80F48::from(self.fixed_price) > I80F48::ZERO,
+                MarginfiError::InvalidConfig
+            );
+        }
+
+        let liquidator_fee = I80F48::from(self.liquidation_liquidator_fee);
+        let insurance_fee = I80F48::from(self.liquidation_insurance_fee);
+        let max_liquidator_fee = I80F48::from(self.liquidation_max_liquidator_fee);
+        let close_factor = I80F48::from(self.liquidation_close_factor);
+
+        check!(
+            liquidator_fee >= I80F48::ZERO
+                && insurance_fee >= I80F48::ZERO
+                && max_liquidator_fee >= I80F48::ZERO,
+            MarginfiError::InvalidConfig
+        );
+        check!(
+            self.get_liquidation_base_liquidator_fee()
+                .max(max_liquidator_fee)
+                .checked_add(self.get_liquidation_insurance_fee())
+                .ok_or_else(math_error!())?
+                < I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+        check!(
+            close_factor >= I80F48::ZERO && close_factor <= I80F48::ONE,
+            MarginfiError::InvalidConfig
+        );
+
         Ok(())
     }
 
+    pub fn validate_oracle_setup(&self, ais: &[AccountInfo]) -> MarginfiResult {
+        OraclePriceFeedAdapter::validate_bank_config(&self.config, &self.mint, ais)?;
+        Ok(())
+    }
+
+    pub fn get_oracle_confidence_config(&self) -> OracleConfidenceConfig {
+        let max_confidence = I80F48::from(self.oracle_max_confidence);
+        let interval_multiple = I80F48::from(self.oracle_confidence_multiple);
+
+        OracleConfidenceConfig {
+            max_confidence: if max_confidence == I80F48::ZERO {
+                MAX_CONF_INTERVAL
+            } else {
+                max_confidence
+            },
+            interval_multiple: Some(interval_multiple).filter(|m| *m != I80F48::ZERO),
+            policy: self.oracle_confidence_policy,
+        }
+    }
+
+    /// Plateau rate the `InterestRateCurve::Adaptive` curve currently uses, the configured
+    /// `plateau_interest_rate` until the first accrual.
+    pub fn get_adaptive_plateau_interest_rate(&self) -> I80F48 {
+        let adaptive_plateau_ir: I80F48 = self.adaptive_plateau_interest_rate.into();
+
+        if adaptive_plateau_ir == I80F48::ZERO {
+            self.config
+                .interest_rate_config
+                .plateau_interest_rate
+                .into()
+        } else {
+            adaptive_plateau_ir
+        }
+    }
+
+    /// Interest rate config the bank currently accrues with, for adaptive curves the plateau rate
+    /// is the current adaptive one.
+    pub fn get_interest_rate_config(&self) -> InterestRateConfig {
+        let mut ir_config = self.config.interest_rate_config;
+
+        if matches!(ir_config.curve_type, InterestRateCurve::Adaptive) {
+            ir_config.plateau_interest_rate = self.get_adaptive_plateau_interest_rate().into();
+        }
+
+        ir_config
+    }
+
+    /// Move the `InterestRateCurve::Adaptive` plateau rate for `time_delta` seconds spent at
+    /// utilization `ur`. The plateau compounds continuously at `adaptive_speed` per year, scaled
+    /// by how far utilization is from `optimal_utilization_rate` relative to the room on that side,
+    /// so the result doesn't depend on how often interest is accrued. It stays between
+    /// `MIN_ADAPTIVE_PLATEAU_INTEREST_RATE` and `max_interest_rate`.
+    pub fn update_adaptive_plateau_interest_rate(
+        &mut self,
+        ur: I80F48,
+        time_delta: u64,
+    ) -> Option<()> {
+        let ir_config = &self.config.interest_rate_config;
+
+        if !matches!(ir_config.curve_type, InterestRateCurve::Adaptive) {
+            return Some(());
+        }
+
+        let optimal_ur: I80F48 = ir_config.optimal_utilization_rate.into();
+        let ur = ur.min(I80F48::ONE);
+
+        let error = if ur > optimal_ur {
+            (ur - optimal_ur).checked_div(I80F48::ONE - optimal_ur)?
+        } else {
+            (ur - optimal_ur).checked_div(optimal_ur)?
+        };
+
+        let speed = I80F48::from(ir_config.adaptive_speed).checked_mul(error)?;
+
+        let plateau_ir = self
+            .get_adaptive_plateau_interest_rate()
+            .checked_mul(calc_compounded_interest_factor(speed, time_delta)?)?
+            .clamp(
+                MIN_ADAPTIVE_PLATEAU_INTEREST_RATE,
+                self.config.interest_rate_config.max_interest_rate.into(),
+            );
+
+        self.adaptive_plateau_interest_rate = plateau_ir.into();
+
+        Some(())
+    }
+
     /// Calculate the interest rate accrual state changes for a given time period
     ///
     /// Collected protocol and insurance fees are stored in state.
@@ -575,6 +1303,18 @@ impl Bank {
 
         self.last_update = current_timestamp;
 
+        // Idle banks still move the adaptive plateau, towards lower rates
+        let utilization_rate = if total_assets == I80F48::ZERO {
+            I80F48::ZERO
+        } else {
+            total_liabilities
+                .checked_div(total_assets)
+                .ok_or_else(math_error!())?
+        };
+        let interest_rate_config = self.get_interest_rate_config();
+        self.update_adaptive_plateau_interest_rate(utilization_rate, time_delta)
+            .ok_or_else(math_error!())?;
+
         if (total_assets == I80F48::ZERO) || (total_liabilities == I80F48::ZERO) {
             #[cfg(not(feature = "client"))]
             emit!(LendingPoolBankAccrueInterestEvent {
@@ -597,7 +1337,7 @@ impl Bank {
                 time_delta,
                 total_assets,
                 total_liabilities,
-                &self.config.interest_rate_config,
+                &interest_rate_config,
                 self.asset_share_value.into(),
                 self.liability_share_value.into(),
             )
@@ -644,10 +1384,187 @@ impl Bank {
         Ok(())
     }
 
+    #[inline]
+    pub fn get_liquidation_base_liquidator_fee(&self) -> I80F48 {
+        if self.liquidation_fees_configured == 0 {
+            LIQUIDATION_LIQUIDATOR_FEE
+        } else {
+            self.liquidation_liquidator_fee.into()
+        }
+    }
+
+    #[inline]
+    pub fn get_liquidation_insurance_fee(&self) -> I80F48 {
+        if self.liquidation_fees_configured == 0 {
+            LIQUIDATION_INSURANCE_FEE
+        } else {
+            self.liquidation_insurance_fee.into()
+        }
+    }
+
+    /// Liquidator fee charged on this bank's collateral. Starts at `liquidation_liquidator_fee`
+    /// and grows, Dutch auction style, by the liquidatee's maintenance shortfall ratio, up to
+    /// `liquidation_max_liquidator_fee`.
+    pub fn get_liquidation_liquidator_fee(&self, shortfall_ratio: I80F48) -> I80F48 {
+        let base_fee = self.get_liquidation_base_liquidator_fee();
+        let max_fee = I80F48::from(self.liquidation_max_liquidator_fee);
+
+        if max_fee <= base_fee {
+            return base_fee;
+        }
+
+        base_fee
+            .saturating_add(shortfall_ratio.max(I80F48::ZERO))
+            .min(max_fee)
+    }
+
+    /// Checks a liquidation repaying `repaid_amount` of a `liability_amount` liability of this bank
+    /// stays within `liquidation_close_factor`. Liabilities up to
+    /// `liquidation_full_close_threshold` may be repaid in full.
+    ///
+    /// The limit applies to each liquidation instruction, not to what is repaid per slot. Repeated
+    /// liquidations in one transaction each repay at most the close factor of the liability left,
+    /// and each one has to find the liquidatee below maintenance and leave it there, which is what
+    /// bounds the total repaid.
+    pub fn check_liquidation_close_factor(
+        &self,
+        repaid_amount: I80F48,
+        liability_amount: I80F48,
+    ) -> MarginfiResult {
+        let close_factor = I80F48::from(self.liquidation_close_factor);
+
+        if close_factor == I80F48::ZERO
+            || liability_amount <= I80F48::from_num(self.liquidation_full_close_threshold)
+        {
+            return Ok(());
+        }
+
+        let max_repaid_amount = liability_amount
+            .checked_mul(close_factor)
+            .ok_or_else(math_error!())?;
+
+        check!(
+            repaid_amount <= max_repaid_amount,
+            MarginfiError::IllegalLiquidation,
+            "Liquidation exceeds close factor"
+        );
+
+        Ok(())
+    }
+
+    /// Whether `price` moved from the last accepted oracle price by no more than
+    /// `oracle_max_price_change` per second elapsed since, counting at most
+    /// `ORACLE_PRICE_GUARD_WINDOW_SEC`. Always true while the guard is disabled or no price was
+    /// accepted yet.
+    pub fn is_oracle_price_within_guard(
+        &self,
+        price: I80F48,
+        current_timestamp: i64,
+    ) -> MarginfiResult<bool> {
+        let max_change = I80F48::from(self.oracle_max_price_change);
+        let last_price = I80F48::from(self.last_oracle_price);
+
+        if max_change == I80F48::ZERO || last_price == I80F48::ZERO {
+            return Ok(true);
+        }
+
+        // Updates landing in the same second get a second worth of movement, and an old reference
+        // doesn't open the guard to any move
+        let elapsed = current_timestamp
+            .saturating_sub(self.last_oracle_price_timestamp)
+            .clamp(1, ORACLE_PRICE_GUARD_WINDOW_SEC);
+        let max_move = last_price
+            .saturating_mul(max_change)
+            .saturating_mul(I80F48::from_num(elapsed));
+        let price_move = price
+            .checked_sub(last_price)
+            .ok_or_else(math_error!())?
+            .abs();
+
+        Ok(price_move <= max_move)
+    }
+
+    /// Check `price` against the price guard, emitting `LendingPoolBankPriceGuardTripEvent` if it
+    /// moved too fast. The event is only kept in the logs of the failed transaction when the
+    /// caller reverts on the trip, see `Bank::oracle_price_guard_trip_count` for a durable record.
+    pub fn check_oracle_price_guard(
+        &self,
+        price: I80F48,
+        current_timestamp: i64,
+        #[cfg(not(feature = "client"))] bank: Pubkey,
+    ) -> MarginfiResult<bool> {
+        let within_guard = self.is_oracle_price_within_guard(price, current_timestamp)?;
+
+        #[cfg(not(feature = "client"))]
+        {
+            if !within_guard {
+                msg!("Oracle price guard tripped for bank {}", bank);
+
+                emit!(LendingPoolBankPriceGuardTripEvent {
+                    header: GroupEventHeader {
+                        marginfi_group: self.group,
+                        signer: None
+                    },
+                    bank,
+                    mint: self.mint,
+                    last_price: I80F48::from(self.last_oracle_price).to_num::<f64>(),
+                    last_price_slot: self.last_oracle_price_slot,
+                    price: price.to_num::<f64>(),
+                });
+            }
+        }
+
+        Ok(within_guard)
+    }
+
+    /// Record `price` as the last accepted oracle price if it passes the price guard. A price
+    /// tripping the guard is left out, the previous one stays the reference, unless that reference
+    /// is older than `ORACLE_PRICE_GUARD_WINDOW_SEC` and can't tell a sudden move from a drift.
+    ///
+    /// A trip is counted in `oracle_price_guard_trip_count`. Instructions failing on a trip,
+    /// borrows and withdraws, roll the update back, only the crank and liquidations record trips
+    /// and replace an old reference. The feed the price was read from is recorded either way.
+    pub fn update_oracle_price_guard(
+        &mut self,
+        price: I80F48,
+        source: OraclePriceSource,
+        clock: &Clock,
+        #[cfg(not(feature = "client"))] bank: Pubkey,
+    ) -> MarginfiResult<bool> {
+        let within_guard = self.check_oracle_price_guard(
+            price,
+            clock.unix_timestamp,
+            #[cfg(not(feature = "client"))]
+            bank,
+        )?;
+
+        let reference_expired = clock
+            .unix_timestamp
+            .saturating_sub(self.last_oracle_price_timestamp)
+            > ORACLE_PRICE_GUARD_WINDOW_SEC;
+
+        if !within_guard {
+            self.oracle_price_guard_trip_count =
+                self.oracle_price_guard_trip_count.saturating_add(1);
+            self.last_oracle_price_guard_trip_timestamp = clock.unix_timestamp;
+        }
+
+        self.last_oracle_price_source = source;
+
+        if within_guard || reference_expired {
+            self.last_oracle_price = price.into();
+            self.last_oracle_price_slot = clock.slot;
+            self.last_oracle_price_timestamp = clock.unix_timestamp;
+        }
+
+        Ok(within_guard)
+    }
+
     pub fn deposit_spl_transfer<'b: 'c, 'c: 'b>(
         &self,
         amount: u64,
         accounts: Transfer<'b>,
+        maybe_mint: Option<&AccountInfo<'b>>,
         program: AccountInfo<'c>,
     ) -> MarginfiResult {
         check!(
@@ -660,13 +1577,30 @@ impl Bank {
             amount, accounts.from.key, accounts.to.key, accounts.authority.key
         );
 
-        transfer(CpiContext::new(program, accounts), amount)
+        if let Some(mint) = maybe_mint {
+            transfer_checked(
+                CpiContext::new(
+                    program,
+                    TransferChecked {
+                        from: accounts.from,
+                        mint: mint.clone(),
+                        to: accounts.to,
+                        authority: accounts.authority,
+                    },
+                ),
+                amount,
+                self.mint_decimals,
+            )
+        } else {
+            transfer(CpiContext::new(program, accounts), amount)
+        }
     }
 
     pub fn withdraw_spl_transfer<'b: 'c, 'c: 'b>(
         &self,
         amount: u64,
         accounts: Transfer<'b>,
+        maybe_mint: Option<&AccountInfo<'b>>,
         program: AccountInfo<'c>,
         signer_seeds: &[&[&[u8]]],
     ) -> MarginfiResult {
@@ -675,10 +1609,27 @@ impl Bank {
             amount, accounts.from.key, accounts.to.key, accounts.authority.key
         );
 
-        transfer(
-            CpiContext::new_with_signer(program, accounts, signer_seeds),
-            amount,
-        )
+        if let Some(mint) = maybe_mint {
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    program,
+                    TransferChecked {
+                        from: accounts.from,
+                        mint: mint.clone(),
+                        to: accounts.to,
+                        authority: accounts.authority,
+                    },
+                    signer_seeds,
+                ),
+                amount,
+                self.mint_decimals,
+            )
+        } else {
+            transfer(
+                CpiContext::new_with_signer(program, accounts, signer_seeds),
+                amount,
+            )
+        }
     }
 
     /// Socialize a loss `loss_amount` among depositors,
@@ -751,15 +1702,18 @@ impl Bank {
     }
 }
 
-/// We use a simple interest rate model that auto settles the accrued interest into the lending account balances.
-/// The plan is to move to a compound interest model in the future.
+/// Interest compounds continuously and is settled into the lending account balances through the
+/// asset and liability share values.
 ///
-/// Simple interest rate model:
+/// Continuously compounded interest:
 /// - `P` - principal
-/// - `i` - interest rate (per second)
-/// - `t` - time (in seconds)
+/// - `i` - interest rate (APR)
+/// - `t` - time (in years)
+///
+/// `P_t = P_0 * e^(i * t)`
 ///
-/// `P_t = P_0 * (1 + i) * t`
+/// so at a constant rate, accruing a period in one call or split over several calls yields the same
+/// share values, see [`calc_compounded_interest_factor`].
 ///
 /// We use two interest rates, one for lending and one for borrowing.
 ///
@@ -778,6 +1732,8 @@ impl Bank {
 ///
 /// `i_b = i * (1 + f_i) + f_f`
 ///
+/// The interest paid by borrowers in excess of the interest earned by lenders is split between the
+/// group and the insurance fund in proportion to their fee rates, banks without fees collect none.
 fn calc_interest_rate_accrual_state_changes(
     time_delta: u64,
     total_assets_amount: I80F48,
@@ -800,11 +1756,37 @@ fn calc_interest_rate_accrual_state_changes(
         insurance_fee_apr
     );
 
+    // Borrowers pay and lenders earn compounded interest, the spread between the two is split
+    // between the group and the insurance fund in proportion to their fee rates.
+    let interest_paid =
+        calc_interest_payment_for_period(borrowing_apr, time_delta, total_liabilities_amount)?;
+    let interest_earned =
+        calc_interest_payment_for_period(lending_apr, time_delta, total_assets_amount)?;
+    let fees_collected = interest_paid
+        .checked_sub(interest_earned)?
+        .max(I80F48::ZERO);
+
+    let total_fee_apr = group_fee_apr.checked_add(insurance_fee_apr)?;
+    let (group_fees_collected, insurance_fees_collected) = if total_fee_apr == I80F48::ZERO {
+        // Without fees, the small spread compounding leaves between the two stays in the
+        // liquidity vault
+        (I80F48::ZERO, I80F48::ZERO)
+    } else {
+        let group_fees_collected = fees_collected
+            .checked_mul(group_fee_apr)?
+            .checked_div(total_fee_apr)?;
+
+        (
+            group_fees_collected,
+            fees_collected.checked_sub(group_fees_collected)?,
+        )
+    };
+
     Some((
         calc_accrued_interest_payment_per_period(lending_apr, time_delta, asset_share_value)?,
         calc_accrued_interest_payment_per_period(borrowing_apr, time_delta, liability_share_value)?,
-        calc_interest_payment_for_period(group_fee_apr, time_delta, total_liabilities_amount)?,
-        calc_interest_payment_for_period(insurance_fee_apr, time_delta, total_liabilities_amount)?,
+        group_fees_collected,
+        insurance_fees_collected,
     ))
 }
 
@@ -816,6 +1798,43 @@ fn calc_fee_rate(base_rate: I80F48, rate_fees: I80F48, fixed_fees: I80F48) -> Op
     base_rate.checked_mul(rate_fees)?.checked_add(fixed_fees)
 }
 
+/// Calculates the growth factor of a principal compounding at interest rate (in APR) `apr` over period `time_delta`.
+///
+/// Interest compounds continuously, `e^(apr * time_delta / SECONDS_PER_YEAR)`, which per-second compounding converges to,
+/// so that accruing a period in one call or in many yields the same result.
+/// The exponential is approximated by scaling and squaring, `e^x = (e^(x / 2^k))^(2^k)`, with the exponent halved
+/// until it is at most 1 in magnitude, where the Taylor series converges quickly.
+fn calc_compounded_interest_factor(apr: I80F48, time_delta: u64) -> Option<I80F48> {
+    let mut exponent = apr
+        .checked_mul(time_delta.into())?
+        .checked_div(SECONDS_PER_YEAR)?;
+
+    let mut squarings = 0;
+    while exponent.abs() > I80F48::ONE {
+        exponent = exponent.checked_div_int(2)?;
+        squarings += 1;
+    }
+
+    let mut term = I80F48::ONE;
+    let mut factor = I80F48::ONE;
+
+    for n in 1..=INTEREST_COMPOUNDING_SERIES_TERMS {
+        term = term.checked_mul(exponent)?.checked_div(n.into())?;
+
+        if term == I80F48::ZERO {
+            break;
+        }
+
+        factor = factor.checked_add(term)?;
+    }
+
+    for _ in 0..squarings {
+        factor = factor.checked_mul(factor)?;
+    }
+
+    Some(factor)
+}
+
 /// Calculates the accrued interest payment per period `time_delta` in a principal value `value` for interest rate (in APR) `arp`.
 /// Result is the new principal value.
 fn calc_accrued_interest_payment_per_period(
@@ -823,11 +1842,7 @@ fn calc_accrued_interest_payment_per_period(
     time_delta: u64,
     value: I80F48,
 ) -> Option<I80F48> {
-    let ir_per_period = apr
-        .checked_mul(time_delta.into())?
-        .checked_div(SECONDS_PER_YEAR)?;
-
-    let new_value = value.checked_mul(I80F48::ONE.checked_add(ir_per_period)?)?;
+    let new_value = value.checked_mul(calc_compounded_interest_factor(apr, time_delta)?)?;
 
     Some(new_value)
 }
@@ -836,9 +1851,7 @@ fn calc_accrued_interest_payment_per_period(
 /// Result is the interest payment.
 fn calc_interest_payment_for_period(apr: I80F48, time_delta: u64, value: I80F48) -> Option<I80F48> {
     let interest_payment = value
-        .checked_mul(apr)?
-        .checked_mul(time_delta.into())?
-        .checked_div(SECONDS_PER_YEAR)?;
+        .checked_mul(calc_compounded_interest_factor(apr, time_delta)?.checked_sub(I80F48::ONE)?)?;
 
     Some(interest_payment)
 }
@@ -898,6 +1911,8 @@ pub struct BankConfigCompact {
 
     pub oracle_setup: OracleSetup,
     pub oracle_key: Pubkey,
+    /// Feed id of the Pyth `PriceUpdateV2` oracle, unused by other oracle setups.
+    pub pyth_push_feed_id: [u8; 32],
 
     pub borrow_limit: u64,
 
@@ -911,10 +1926,41 @@ pub struct BankConfigCompact {
     /// This is useful for limiting the damage of orcale attacks.
     ///
     /// Value is UI USD value, for example value 100 -> $100
+    ///
+    /// The liability side counterpart is `Bank::total_liability_value_limit`.
     pub total_asset_value_init_limit: u64,
 
     /// Time window in seconds for the oracle price feed to be considered live.
     pub oracle_max_age: u16,
+
+    /// Largest deviation, in basis points, tolerated by aggregated, fallback and pegged oracles.
+    pub oracle_max_deviation_bps: u16,
+
+    /// Share of the liquidated collateral value paid to the liquidator, `LIQUIDATION_LIQUIDATOR_FEE`
+    /// by default.
+    pub liquidation_liquidator_fee: WrappedI80F48,
+    /// Share of the liquidated collateral value paid into the insurance fund,
+    /// `LIQUIDATION_INSURANCE_FEE` by default. 0 for no insurance fee.
+    pub liquidation_insurance_fee: WrappedI80F48,
+
+    /// Price of `OracleSetup::Fixed` banks, peg of `OracleSetup::Pegged` banks.
+    pub fixed_price: WrappedI80F48,
+
+    /// Largest oracle confidence interval, as a share of the price.
+    /// If set to 0, `MAX_CONF_INTERVAL` is used.
+    pub oracle_max_confidence: WrappedI80F48,
+    /// Multiple applied to the published oracle confidence.
+    /// If set to 0, `CONF_INTERVAL_MULTIPLE` is used for Pyth and `STD_DEV_MULTIPLE` for
+    /// Switchboard.
+    pub oracle_confidence_multiple: WrappedI80F48,
+    pub oracle_confidence_policy: OracleConfidencePolicy,
+
+    /// Largest oracle price change per second, as a share of the last accepted price.
+    /// If set to 0, the price guard is disabled.
+    pub oracle_max_price_change: WrappedI80F48,
+
+    /// E-mode category of the bank. 0 for none.
+    pub emode_category: u8,
 }
 
 impl From<BankConfigCompact> for BankConfig {
@@ -940,11 +1986,16 @@ impl From<BankConfigCompact> for BankConfig {
             risk_tier: config.risk_tier,
             total_asset_value_init_limit: config.total_asset_value_init_limit,
             oracle_max_age: config.oracle_max_age,
-            _padding: [0; 19],
+            oracle_max_deviation_bps: config.oracle_max_deviation_bps,
+            emode_category: config.emode_category,
+            _pad0: [0; 3],
+            pyth_push_feed_id: config.pyth_push_feed_id,
         }
     }
 }
 
+/// Compact config of a bank with `config`, the bank level fields kept on `Bank` at their
+/// defaults. Use `From<&Bank>` to carry over the bank level fields of an existing bank.
 impl From<BankConfig> for BankConfigCompact {
     fn from(config: BankConfig) -> Self {
         Self {
@@ -957,10 +2008,35 @@ impl From<BankConfig> for BankConfigCompact {
             operational_state: config.operational_state,
             oracle_setup: config.oracle_setup,
             oracle_key: config.oracle_keys[0],
+            pyth_push_feed_id: config.pyth_push_feed_id,
             borrow_limit: config.borrow_limit,
             risk_tier: config.risk_tier,
             total_asset_value_init_limit: config.total_asset_value_init_limit,
             oracle_max_age: config.oracle_max_age,
+            oracle_max_deviation_bps: config.oracle_max_deviation_bps,
+            liquidation_liquidator_fee: LIQUIDATION_LIQUIDATOR_FEE.into(),
+            liquidation_insurance_fee: LIQUIDATION_INSURANCE_FEE.into(),
+            fixed_price: I80F48::ZERO.into(),
+            oracle_max_confidence: MAX_CONF_INTERVAL.into(),
+            oracle_confidence_multiple: I80F48::ZERO.into(),
+            oracle_confidence_policy: OracleConfidencePolicy::default(),
+            oracle_max_price_change: I80F48::ZERO.into(),
+            emode_category: config.emode_category,
+        }
+    }
+}
+
+impl From<&Bank> for BankConfigCompact {
+    fn from(bank: &Bank) -> Self {
+        Self {
+            fixed_price: bank.fixed_price,
+            oracle_max_confidence: bank.oracle_max_confidence,
+            oracle_confidence_multiple: bank.oracle_confidence_multiple,
+            oracle_confidence_policy: bank.oracle_confidence_policy,
+            oracle_max_price_change: bank.oracle_max_price_change,
+            liquidation_liquidator_fee: bank.get_liquidation_base_liquidator_fee().into(),
+            liquidation_insurance_fee: bank.get_liquidation_insurance_fee().into(),
+            ..bank.config.into()
         }
     }
 }
@@ -1002,12 +2078,26 @@ pub struct BankConfig {
     /// This is useful for limiting the damage of orcale attacks.
     ///
     /// Value is UI USD value, for example value 100 -> $100
+    ///
+    /// The liability side counterpart is `Bank::total_liability_value_limit`.
     pub total_asset_value_init_limit: u64,
 
     /// Time window in seconds for the oracle price feed to be considered live.
     pub oracle_max_age: u16,
 
-    pub _padding: [u16; 19], // 16 * 4 = 64 bytes
+    /// Largest deviation, in basis points, tolerated between the highest and lowest sources of an
+    /// aggregated oracle, between a fallback oracle and the last price of the primary one, or
+    /// between a pegged oracle and its peg.
+    pub oracle_max_deviation_bps: u16,
+
+    /// E-mode category of the bank, see `MarginfiGroup::emode_categories`. 0 for none.
+    pub emode_category: u8,
+
+    pub _pad0: [u8; 3],
+
+    /// Feed id expected in the Pyth `PriceUpdateV2` accounts read by the bank, every Pyth pull
+    /// source of the bank has to publish this feed.
+    pub pyth_push_feed_id: [u8; 32],
 }
 
 impl Default for BankConfig {
@@ -1026,7 +2116,10 @@ impl Default for BankConfig {
             risk_tier: RiskTier::Isolated,
             total_asset_value_init_limit: TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
             oracle_max_age: 0,
-            _padding: [0; 19],
+            oracle_max_deviation_bps: 0,
+            emode_category: EMODE_CATEGORY_NONE,
+            _pad0: [0; 3],
+            pyth_push_feed_id: [0; 32],
         }
     }
 }
@@ -1092,6 +2185,22 @@ impl BankConfig {
             check!(asset_maint_w == I80F48::ZERO, MarginfiError::InvalidConfig);
         }
 
+        if matches!(
+            self.oracle_setup,
+            OracleSetup::Aggregated | OracleSetup::Pegged
+        ) || self.get_fallback_oracle_key().is_some()
+        {
+            check!(
+                self.oracle_max_deviation_bps > 0 && self.oracle_max_deviation_bps <= 10_000,
+                MarginfiError::InvalidConfig
+            );
+        }
+
+        check!(
+            self.emode_category as usize <= MAX_EMODE_CATEGORIES,
+            MarginfiError::InvalidEmodeCategory
+        );
+
         Ok(())
     }
 
@@ -1105,9 +2214,40 @@ impl BankConfig {
         self.borrow_limit != u64::MAX
     }
 
-    pub fn validate_oracle_setup(&self, ais: &[AccountInfo]) -> MarginfiResult {
-        OraclePriceFeedAdapter::validate_bank_config(self, ais)?;
-        Ok(())
+    /// Oracle accounts the bank price is read from, in the order they're passed to the program.
+    pub fn get_oracle_keys(&self) -> &[Pubkey] {
+        match self.oracle_setup {
+            OracleSetup::Aggregated => {
+                let len = self
+                    .oracle_keys
+                    .iter()
+                    .take_while(|key| **key != Pubkey::default())
+                    .count();
+
+                &self.oracle_keys[..len]
+            }
+            OracleSetup::None | OracleSetup::Pegged => &self.oracle_keys[..1],
+            OracleSetup::StakePool => &self.oracle_keys[..2],
+            OracleSetup::Fixed => &[],
+            _ => match self.get_fallback_oracle_key() {
+                Some(_) => &self.oracle_keys[..2],
+                None => &self.oracle_keys[..1],
+            },
+        }
+    }
+
+    /// Oracle used when the primary oracle of a single feed setup is stale, set in `oracle_keys[1]`.
+    /// Only used for up to `FALLBACK_ORACLE_MAX_PRIMARY_STALENESS_SEC` past the primary max age,
+    /// see `OraclePriceFeedAdapter::try_from_bank_with_max_age`.
+    pub fn get_fallback_oracle_key(&self) -> Option<Pubkey> {
+        match self.oracle_setup {
+            OracleSetup::None
+            | OracleSetup::Aggregated
+            | OracleSetup::StakePool
+            | OracleSetup::Fixed
+            | OracleSetup::Pegged => None,
+            _ => Some(self.oracle_keys[1]).filter(|key| *key != Pubkey::default()),
+        }
     }
 
     pub fn usd_init_limit_active(&self) -> bool {
@@ -1181,7 +2321,33 @@ pub struct BankConfigOpt {
 
     pub oracle_max_age: Option<u16>,
 
+    pub oracle_max_deviation_bps: Option<u16>,
+
     pub permissionless_bad_debt_settlement: Option<bool>,
+
+    pub fixed_price: Option<WrappedI80F48>,
+
+    pub oracle_max_confidence: Option<WrappedI80F48>,
+
+    pub oracle_confidence_multiple: Option<WrappedI80F48>,
+
+    pub oracle_confidence_policy: Option<OracleConfidencePolicy>,
+
+    pub oracle_max_price_change: Option<WrappedI80F48>,
+
+    pub liquidation_liquidator_fee: Option<WrappedI80F48>,
+
+    pub liquidation_insurance_fee: Option<WrappedI80F48>,
+
+    pub liquidation_max_liquidator_fee: Option<WrappedI80F48>,
+
+    pub liquidation_close_factor: Option<WrappedI80F48>,
+
+    pub liquidation_full_close_threshold: Option<u64>,
+
+    pub emode_category: Option<u8>,
+
+    pub total_liability_value_limit: Option<u64>,
 }
 
 #[cfg_attr(
@@ -1192,6 +2358,8 @@ pub struct BankConfigOpt {
 pub struct OracleConfig {
     pub setup: OracleSetup,
     pub keys: [Pubkey; MAX_ORACLE_KEYS],
+    /// Feed id of the Pyth pull sources, zeroed if the oracle has none.
+    pub pyth_push_feed_id: [u8; 32],
 }
 
 #[derive(Debug, Clone)]
@@ -1240,7 +2408,7 @@ mod tests {
     use fixed_macro::types::I80F48;
 
     #[test]
-    /// Tests that the interest payment for a 1 year period with 100% APR is 1.
+    /// Tests that the interest payment for a 1 year period with 100% APR is e - 1.
     fn interest_payment_100apr_1year() {
         let apr = I80F48::ONE;
         let time_delta = 31_536_000; // 1 year
@@ -1248,12 +2416,12 @@ mod tests {
 
         assert_eq_with_tolerance!(
             calc_interest_payment_for_period(apr, time_delta, value).unwrap(),
-            I80F48::ONE,
+            I80F48!(1.71828),
             I80F48!(0.001)
         );
     }
 
-    /// Tests that the interest payment for a 1 year period with 50% APR is 0.5.
+    /// Tests that the interest payment for a 1 year period with 50% APR is e^0.5 - 1.
     #[test]
     fn interest_payment_50apr_1year() {
         let apr = I80F48::from_num(0.5);
@@ -1262,7 +2430,7 @@ mod tests {
 
         assert_eq_with_tolerance!(
             calc_interest_payment_for_period(apr, time_delta, value).unwrap(),
-            I80F48::from_num(0.5),
+            I80F48!(0.64872),
             I80F48!(0.001)
         );
     }
@@ -1286,11 +2454,11 @@ mod tests {
     /// apr: 100%
     /// time: 1 year
     /// principal: 2
-    /// expected: 4
+    /// expected: 2e
     fn accrued_interest_apr100_year1() {
         assert_eq_with_tolerance!(
             calc_accrued_interest_payment_per_period(I80F48!(1), 31_536_000, I80F48!(2)).unwrap(),
-            I80F48!(4),
+            I80F48!(5.43656),
             I80F48!(0.001)
         );
     }
@@ -1299,11 +2467,11 @@ mod tests {
     /// apr: 50%
     /// time: 1 year
     /// principal: 2
-    /// expected: 3
+    /// expected: 2e^0.5
     fn accrued_interest_apr50_year1() {
         assert_eq_with_tolerance!(
             calc_accrued_interest_payment_per_period(I80F48!(0.5), 31_536_000, I80F48!(2)).unwrap(),
-            I80F48!(3),
+            I80F48!(3.29744),
             I80F48!(0.001)
         );
     }
@@ -1321,6 +2489,41 @@ mod tests {
         );
     }
 
+    #[test]
+    /// Accruing a day at once or hour by hour compounds to the same value.
+    fn accrued_interest_single_vs_hourly_calls() {
+        let apr = I80F48!(0.35);
+        let principal = I80F48!(1_000_000);
+
+        let single = calc_accrued_interest_payment_per_period(apr, 86_400, principal).unwrap();
+
+        let mut hourly = principal;
+        for _ in 0..24 {
+            hourly = calc_accrued_interest_payment_per_period(apr, 3_600, hourly).unwrap();
+        }
+
+        assert_eq_with_tolerance!(single, hourly, I80F48!(0.000001));
+        assert!(single > principal * (I80F48::ONE + apr * I80F48!(86_400) / SECONDS_PER_YEAR));
+    }
+
+    #[test]
+    /// apr: 1000%
+    /// time: 3 years
+    /// principal: 1
+    /// expected: e^30
+    fn accrued_interest_large_exponent() {
+        assert_eq_with_tolerance!(
+            calc_compounded_interest_factor(I80F48!(10), 3 * 31_536_000).unwrap(),
+            I80F48!(10_686_474_581_524.46),
+            I80F48!(100)
+        );
+        assert_eq_with_tolerance!(
+            calc_compounded_interest_factor(I80F48!(-10), 31_536_000).unwrap(),
+            I80F48!(0.0000453999),
+            I80F48!(0.000000001)
+        );
+    }
+
     #[test]
     /// ur: 0
     /// protocol_fixed_fee: 0.01
@@ -1551,4 +2754,659 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn ir_config_multi_kink_curve() {
+        let mut config = InterestRateConfig {
+            optimal_utilization_rate: I80F48!(0.5).into(),
+            plateau_interest_rate: I80F48!(0.1).into(),
+            max_interest_rate: I80F48!(2).into(),
+            curve_type: InterestRateCurve::MultiKink,
+            extra_kinks: [
+                InterestRateKink {
+                    utilization_rate: I80F48!(0.8).into(),
+                    interest_rate: I80F48!(0.2).into(),
+                },
+                InterestRateKink {
+                    utilization_rate: I80F48!(0.9).into(),
+                    interest_rate: I80F48!(0.5).into(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert!(config.validate().is_ok());
+
+        for (ur, ir) in [
+            (I80F48!(0.25), I80F48!(0.05)),
+            (I80F48!(0.65), I80F48!(0.15)),
+            (I80F48!(0.85), I80F48!(0.35)),
+            (I80F48!(0.95), I80F48!(1.25)),
+        ] {
+            assert_eq_with_tolerance!(config.interest_rate_curve(ur).unwrap(), ir, I80F48!(0.001));
+        }
+
+        // Kinks have to keep increasing
+        config.extra_kinks[1].utilization_rate = I80F48!(0.7).into();
+        assert!(config.validate().is_err());
+
+        // And unused kinks can only come last
+        config.extra_kinks[0] = InterestRateKink::default();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn bank_adaptive_interest_rate_curve() -> anyhow::Result<()> {
+        let mut bank = Bank {
+            asset_share_value: I80F48::ONE.into(),
+            liability_share_value: I80F48::ONE.into(),
+            total_asset_shares: I80F48!(1_000_000).into(),
+            config: BankConfig {
+                interest_rate_config: InterestRateConfig {
+                    optimal_utilization_rate: I80F48!(0.8).into(),
+                    plateau_interest_rate: I80F48!(0.04).into(),
+                    max_interest_rate: I80F48!(1).into(),
+                    curve_type: InterestRateCurve::Adaptive,
+                    adaptive_speed: I80F48!(50).into(),
+                    ..Default::default()
+                },
+                liability_weight_init: I80F48::ONE.into(),
+                liability_weight_maint: I80F48::ONE.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(bank.validate_config().is_ok());
+
+        // Halfway between target and full utilization for a day raises the plateau
+        bank.update_adaptive_plateau_interest_rate(I80F48!(0.9), 86_400)
+            .unwrap();
+        assert_eq_with_tolerance!(
+            bank.get_adaptive_plateau_interest_rate(),
+            I80F48!(0.042836),
+            I80F48!(0.000001)
+        );
+        assert_eq_with_tolerance!(
+            bank.get_interest_rate_config()
+                .interest_rate_curve(I80F48!(0.4))
+                .unwrap(),
+            I80F48!(0.021418),
+            I80F48!(0.000001)
+        );
+
+        // As far below target for as long lowers it back
+        bank.update_adaptive_plateau_interest_rate(I80F48!(0.4), 86_400)
+            .unwrap();
+        assert_eq_with_tolerance!(
+            bank.get_adaptive_plateau_interest_rate(),
+            I80F48!(0.04),
+            I80F48!(0.000001)
+        );
+
+        // A day at once or second by second moves the plateau the same
+        let mut by_second = bank;
+        bank.update_adaptive_plateau_interest_rate(I80F48!(1), 86_400)
+            .unwrap();
+        for _ in 0..86_400 {
+            by_second
+                .update_adaptive_plateau_interest_rate(I80F48!(1), 1)
+                .unwrap();
+        }
+        assert_eq_with_tolerance!(
+            bank.get_adaptive_plateau_interest_rate(),
+            by_second.get_adaptive_plateau_interest_rate(),
+            I80F48!(0.000001)
+        );
+
+        // Idle banks accrue no interest but still lower the plateau
+        let plateau_ir = bank.get_adaptive_plateau_interest_rate();
+        bank.accrue_interest(
+            86_400,
+            #[cfg(not(feature = "client"))]
+            Pubkey::default(),
+        )?;
+        assert!(bank.get_adaptive_plateau_interest_rate() < plateau_ir);
+
+        // Never below the floor
+        bank.update_adaptive_plateau_interest_rate(I80F48!(0), 31_536_000)
+            .unwrap();
+        assert_eq!(
+            bank.get_adaptive_plateau_interest_rate(),
+            MIN_ADAPTIVE_PLATEAU_INTEREST_RATE
+        );
+
+        // Reconfiguring the plateau restarts the curve
+        bank.configure(&BankConfigOpt {
+            interest_rate_config: Some(InterestRateConfigOpt {
+                plateau_interest_rate: Some(I80F48!(0.05).into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })?;
+        assert_eq!(bank.get_adaptive_plateau_interest_rate(), I80F48!(0.05));
+
+        // While other config changes leave it as is
+        bank.update_adaptive_plateau_interest_rate(I80F48!(0.9), 86_400)
+            .unwrap();
+        let plateau_ir = bank.get_adaptive_plateau_interest_rate();
+        bank.configure(&BankConfigOpt {
+            deposit_limit: Some(1_000),
+            ..Default::default()
+        })?;
+        assert_eq!(bank.get_adaptive_plateau_interest_rate(), plateau_ir);
+
+        Ok(())
+    }
+
+    #[test]
+    /// A bank accrued once over a day or hour by hour ends up with the same share values and
+    /// collected fees, and no value is created or lost. Hourly accruals follow the utilization
+    /// rate as liabilities grow faster than assets, so the fees only match to within 0.05%.
+    fn bank_accrue_interest_single_vs_hourly_calls() -> anyhow::Result<()> {
+        let new_bank = || Bank {
+            asset_share_value: I80F48::ONE.into(),
+            liability_share_value: I80F48::ONE.into(),
+            total_liability_shares: I80F48!(600_000_000).into(),
+            total_asset_shares: I80F48!(1_000_000_000).into(),
+            last_update: 0,
+            config: BankConfig {
+                interest_rate_config: InterestRateConfig {
+                    optimal_utilization_rate: I80F48!(0.8).into(),
+                    plateau_interest_rate: I80F48!(0.1).into(),
+                    max_interest_rate: I80F48!(3).into(),
+                    protocol_fixed_fee_apr: I80F48!(0.01).into(),
+                    protocol_ir_fee: I80F48!(0.05).into(),
+                    insurance_fee_fixed_apr: I80F48!(0.005).into(),
+                    insurance_ir_fee: I80F48!(0.1).into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let net_assets = |bank: &Bank| -> anyhow::Result<I80F48> {
+            Ok(bank.get_asset_amount(bank.total_asset_shares.into())?
+                + I80F48::from(bank.collected_group_fees_outstanding)
+                + I80F48::from(bank.collected_insurance_fees_outstanding)
+                - bank.get_liability_amount(bank.total_liability_shares.into())?)
+        };
+
+        let mut single = new_bank();
+        let pre_net_assets = net_assets(&single)?;
+        single.accrue_interest(
+            86_400,
+            #[cfg(not(feature = "client"))]
+            Pubkey::default(),
+        )?;
+
+        let mut hourly = new_bank();
+        for hour in 1..=24 {
+            hourly.accrue_interest(
+                hour * 3_600,
+                #[cfg(not(feature = "client"))]
+                Pubkey::default(),
+            )?;
+        }
+
+        assert_eq_with_tolerance!(
+            I80F48::from(single.asset_share_value),
+            I80F48::from(hourly.asset_share_value),
+            I80F48!(0.000001)
+        );
+        assert_eq_with_tolerance!(
+            I80F48::from(single.liability_share_value),
+            I80F48::from(hourly.liability_share_value),
+            I80F48!(0.000001)
+        );
+        let group_fees = I80F48::from(single.collected_group_fees_outstanding);
+        assert_eq_with_tolerance!(
+            group_fees,
+            I80F48::from(hourly.collected_group_fees_outstanding),
+            group_fees * I80F48!(0.0005)
+        );
+        let insurance_fees = I80F48::from(single.collected_insurance_fees_outstanding);
+        assert_eq_with_tolerance!(
+            insurance_fees,
+            I80F48::from(hourly.collected_insurance_fees_outstanding),
+            insurance_fees * I80F48!(0.0005)
+        );
+
+        assert_eq_with_tolerance!(pre_net_assets, net_assets(&single)?, I80F48!(1));
+        assert_eq_with_tolerance!(pre_net_assets, net_assets(&hourly)?, I80F48!(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bank_accrue_interest_without_fees() -> anyhow::Result<()> {
+        let mut bank = Bank {
+            asset_share_value: I80F48::ONE.into(),
+            liability_share_value: I80F48::ONE.into(),
+            total_liability_shares: I80F48!(600_000_000).into(),
+            total_asset_shares: I80F48!(1_000_000_000).into(),
+            last_update: 0,
+            config: BankConfig {
+                interest_rate_config: InterestRateConfig {
+                    optimal_utilization_rate: I80F48!(0.8).into(),
+                    plateau_interest_rate: I80F48!(0.1).into(),
+                    max_interest_rate: I80F48!(3).into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        bank.accrue_interest(
+            SECONDS_PER_YEAR.to_num(),
+            #[cfg(not(feature = "client"))]
+            Pubkey::default(),
+        )?;
+
+        // Lenders and borrowers accrue interest, neither the group nor the insurance fund is paid
+        assert!(I80F48::from(bank.asset_share_value) > I80F48::ONE);
+        assert!(I80F48::from(bank.liability_share_value) > I80F48::from(bank.asset_share_value));
+        assert_eq!(
+            I80F48::from(bank.collected_group_fees_outstanding),
+            I80F48::ZERO
+        );
+        assert_eq!(
+            I80F48::from(bank.collected_insurance_fees_outstanding),
+            I80F48::ZERO
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn group_weight_overrides() {
+        let mut group = MarginfiGroup::default();
+        let (jitosol, sol, usdc) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        group
+            .configure_weight_override(jitosol, sol, I80F48!(0.9), I80F48!(0.95))
+            .unwrap();
+        group
+            .configure_weight_override(jitosol, usdc, I80F48!(0.7), I80F48!(0.8))
+            .unwrap();
+
+        assert_eq!(
+            I80F48::from(
+                group
+                    .get_weight_override(&jitosol, &sol)
+                    .unwrap()
+                    .asset_weight_init
+            ),
+            I80F48!(0.9)
+        );
+        assert!(group.get_weight_override(&sol, &jitosol).is_none());
+
+        // Updating a pair reuses its entry
+        group
+            .configure_weight_override(jitosol, usdc, I80F48!(0.6), I80F48!(0.8))
+            .unwrap();
+        assert_eq!(
+            group
+                .weight_overrides
+                .iter()
+                .filter(|o| o.is_active())
+                .count(),
+            2
+        );
+
+        // Zero maintenance weight removes the override
+        group
+            .configure_weight_override(jitosol, sol, I80F48::ZERO, I80F48::ZERO)
+            .unwrap();
+        assert!(group.get_weight_override(&jitosol, &sol).is_none());
+
+        assert!(group
+            .configure_weight_override(jitosol, sol, I80F48!(0.9), I80F48!(0.8))
+            .is_err());
+        assert!(group
+            .configure_weight_override(sol, sol, I80F48!(0.9), I80F48!(0.95))
+            .is_err());
+
+        for _ in 1..MAX_WEIGHT_OVERRIDES {
+            group
+                .configure_weight_override(Pubkey::new_unique(), usdc, I80F48!(0.5), I80F48!(0.5))
+                .unwrap();
+        }
+        assert!(group
+            .configure_weight_override(jitosol, sol, I80F48!(0.9), I80F48!(0.95))
+            .is_err());
+    }
+
+    #[test]
+    fn liquidation_close_factor_full_close_threshold() {
+        let mut bank = Bank {
+            liquidation_close_factor: I80F48!(0.5).into(),
+            ..Default::default()
+        };
+
+        assert!(bank
+            .check_liquidation_close_factor(I80F48!(50), I80F48!(100))
+            .is_ok());
+        assert!(bank
+            .check_liquidation_close_factor(I80F48!(51), I80F48!(100))
+            .is_err());
+
+        // Liabilities up to the threshold can be closed at once
+        bank.liquidation_full_close_threshold = 100;
+        assert!(bank
+            .check_liquidation_close_factor(I80F48!(100), I80F48!(100))
+            .is_ok());
+        assert!(bank
+            .check_liquidation_close_factor(I80F48!(51), I80F48!(101))
+            .is_err());
+    }
+
+    #[test]
+    fn oracle_price_guard_window() {
+        // 0.01% per second, 6% over the window
+        let mut bank = Bank {
+            oracle_max_price_change: I80F48!(0.0001).into(),
+            last_oracle_price: I80F48!(10).into(),
+            ..Default::default()
+        };
+        let clock = |unix_timestamp| Clock {
+            unix_timestamp,
+            ..Default::default()
+        };
+
+        // 1% after 100 seconds
+        assert!(bank
+            .is_oracle_price_within_guard(I80F48!(10.09), 100)
+            .unwrap());
+        assert!(!bank
+            .is_oracle_price_within_guard(I80F48!(9.8), 100)
+            .unwrap());
+
+        // An old reference still only allows a window's worth of movement
+        let now = 10 * ORACLE_PRICE_GUARD_WINDOW_SEC;
+        assert!(bank
+            .is_oracle_price_within_guard(I80F48!(10.59), now)
+            .unwrap());
+        assert!(!bank.is_oracle_price_within_guard(I80F48!(8), now).unwrap());
+
+        // A tripping price replaces an old reference, but not a recent one
+        let within_guard = bank
+            .update_oracle_price_guard(
+                I80F48!(8),
+                OraclePriceSource::Fallback,
+                &clock(now),
+                #[cfg(not(feature = "client"))]
+                Pubkey::default(),
+            )
+            .unwrap();
+        assert!(!within_guard);
+        assert_eq!(I80F48::from(bank.last_oracle_price), I80F48!(8));
+        assert_eq!(bank.last_oracle_price_timestamp, now);
+        assert_eq!(bank.oracle_price_guard_trip_count, 1);
+        assert_eq!(bank.last_oracle_price_guard_trip_timestamp, now);
+        assert_eq!(bank.last_oracle_price_source, OraclePriceSource::Fallback);
+
+        bank.update_oracle_price_guard(
+            I80F48!(10),
+            OraclePriceSource::Primary,
+            &clock(now + 1),
+            #[cfg(not(feature = "client"))]
+            Pubkey::default(),
+        )
+        .unwrap();
+        assert_eq!(I80F48::from(bank.last_oracle_price), I80F48!(8));
+        assert_eq!(bank.last_oracle_price_source, OraclePriceSource::Primary);
+    }
+
+    #[test]
+    fn liquidation_fees_can_be_zero() {
+        let bank_with_fees = |liquidator_fee: I80F48, insurance_fee: I80F48| {
+            Bank::new(
+                Pubkey::default(),
+                BankConfigCompact {
+                    liquidation_liquidator_fee: liquidator_fee.into(),
+                    liquidation_insurance_fee: insurance_fee.into(),
+                    ..BankConfig::default().into()
+                },
+                Pubkey::default(),
+                6,
+                Pubkey::default(),
+                Pubkey::default(),
+                Pubkey::default(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            )
+        };
+
+        let bank = bank_with_fees(I80F48!(0.05), I80F48::ZERO);
+        assert_eq!(bank.get_liquidation_base_liquidator_fee(), I80F48!(0.05));
+        assert_eq!(bank.get_liquidation_insurance_fee(), I80F48::ZERO);
+
+        // Banks created before per bank fees charge the defaults, configuring one fee keeps the
+        // default of the other one
+        let mut bank = Bank {
+            config: BankConfig {
+                interest_rate_config: InterestRateConfig {
+                    optimal_utilization_rate: I80F48!(0.8).into(),
+                    plateau_interest_rate: I80F48!(0.1).into(),
+                    max_interest_rate: I80F48!(1).into(),
+                    ..Default::default()
+                },
+                liability_weight_init: I80F48::ONE.into(),
+                liability_weight_maint: I80F48::ONE.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            bank.get_liquidation_base_liquidator_fee(),
+            LIQUIDATION_LIQUIDATOR_FEE
+        );
+        assert_eq!(
+            bank.get_liquidation_insurance_fee(),
+            LIQUIDATION_INSURANCE_FEE
+        );
+
+        bank.configure(&BankConfigOpt {
+            liquidation_insurance_fee: Some(I80F48::ZERO.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            bank.get_liquidation_base_liquidator_fee(),
+            LIQUIDATION_LIQUIDATOR_FEE
+        );
+        assert_eq!(bank.get_liquidation_insurance_fee(), I80F48::ZERO);
+    }
+
+    #[test]
+    fn bank_config_compact_keeps_bank_fields() {
+        let bank = Bank {
+            config: BankConfig {
+                oracle_setup: OracleSetup::Fixed,
+                oracle_max_deviation_bps: 100,
+                ..Default::default()
+            },
+            fixed_price: I80F48!(1.5).into(),
+            oracle_max_confidence: I80F48!(0.02).into(),
+            oracle_confidence_multiple: I80F48!(3).into(),
+            oracle_confidence_policy: OracleConfidencePolicy::Reject,
+            oracle_max_price_change: I80F48!(0.001).into(),
+            liquidation_liquidator_fee: I80F48!(0.04).into(),
+            liquidation_insurance_fee: I80F48!(0.01).into(),
+            liquidation_fees_configured: 1,
+            ..Default::default()
+        };
+
+        let config = BankConfigCompact::from(&bank);
+        let new_bank = Bank::new(
+            Pubkey::default(),
+            config,
+            Pubkey::default(),
+            6,
+            Pubkey::default(),
+            Pubkey::default(),
+            Pubkey::default(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        );
+
+        assert!(matches!(new_bank.config.oracle_setup, OracleSetup::Fixed));
+        assert_eq!(new_bank.config.oracle_max_deviation_bps, 100);
+        assert_eq!(I80F48::from(new_bank.fixed_price), I80F48!(1.5));
+        assert_eq!(I80F48::from(new_bank.oracle_max_confidence), I80F48!(0.02));
+        assert_eq!(
+            I80F48::from(new_bank.oracle_confidence_multiple),
+            I80F48!(3)
+        );
+        assert!(matches!(
+            new_bank.oracle_confidence_policy,
+            OracleConfidencePolicy::Reject
+        ));
+        assert_eq!(
+            I80F48::from(new_bank.oracle_max_price_change),
+            I80F48!(0.001)
+        );
+        assert_eq!(
+            I80F48::from(new_bank.liquidation_liquidator_fee),
+            I80F48!(0.04)
+        );
+        assert_eq!(
+            I80F48::from(new_bank.liquidation_insurance_fee),
+            I80F48!(0.01)
+        );
+
+        // Without a bank, the bank level fields take their defaults
+        let config = BankConfigCompact::from(bank.config);
+        assert_eq!(
+            I80F48::from(config.liquidation_liquidator_fee),
+            LIQUIDATION_LIQUIDATOR_FEE
+        );
+        assert_eq!(
+            I80F48::from(config.liquidation_insurance_fee),
+            LIQUIDATION_INSURANCE_FEE
+        );
+    }
+
+    #[test]
+    /// Banks created before the fields added in the bank padding must keep their values and read
+    /// the new fields as unset.
+    fn bank_baseline_layout() {
+        use crate::constants::EMISSIONS_FLAG_LENDING_ACTIVE;
+        use anchor_lang::{AccountDeserialize, Discriminator};
+
+        fn write(data: &mut [u8], offset: usize, bytes: &[u8]) {
+            // Account data starts with the discriminator
+            data[8 + offset..8 + offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        let mint = Pubkey::new_unique();
+        let oracle = Pubkey::new_unique();
+        let emissions_mint = Pubkey::new_unique();
+        let plateau_interest_rate = WrappedI80F48::from(I80F48!(0.1));
+        let emissions_remaining = WrappedI80F48::from(I80F48!(1000));
+
+        let mut data = vec![0u8; 8 + std::mem::size_of::<Bank>()];
+        data[..8].copy_from_slice(&Bank::DISCRIMINATOR);
+
+        // Offsets of the original bank layout
+        write(&mut data, 0, mint.as_ref());
+        write(&mut data, 32, &[6]);
+        write(&mut data, 288 + 64, &1_000u64.to_le_bytes());
+        write(&mut data, 288 + 72 + 16, &plateau_interest_rate.value);
+        write(
+            &mut data,
+            288 + 312,
+            &[BankOperationalState::Operational as u8],
+        );
+        write(&mut data, 288 + 313, &[OracleSetup::PythEma as u8]);
+        write(&mut data, 288 + 314, oracle.as_ref());
+        write(&mut data, 288 + 480, &500u64.to_le_bytes());
+        write(
+            &mut data,
+            288 + 488,
+            &(RiskTier::Collateral as u64).to_le_bytes(),
+        );
+        write(&mut data, 288 + 496, &10_000u64.to_le_bytes());
+        write(&mut data, 288 + 504, &60u16.to_le_bytes());
+        write(&mut data, 832, &EMISSIONS_FLAG_LENDING_ACTIVE.to_le_bytes());
+        write(&mut data, 840, &7u64.to_le_bytes());
+        write(&mut data, 848, &emissions_remaining.value);
+        write(&mut data, 864, emissions_mint.as_ref());
+
+        let bank = Bank::try_deserialize(&mut data.as_slice()).unwrap();
+
+        assert_eq!(bank.mint, mint);
+        assert_eq!(bank.mint_decimals, 6);
+        assert_eq!(bank.config.deposit_limit, 1_000);
+        assert_eq!(
+            I80F48::from(bank.config.interest_rate_config.plateau_interest_rate),
+            I80F48!(0.1)
+        );
+        assert!(matches!(
+            bank.config.operational_state,
+            BankOperationalState::Operational
+        ));
+        assert!(matches!(bank.config.oracle_setup, OracleSetup::PythEma));
+        assert_eq!(bank.config.oracle_keys[0], oracle);
+        assert_eq!(bank.config.borrow_limit, 500);
+        assert_eq!(bank.config.risk_tier, RiskTier::Collateral);
+        assert_eq!(bank.config.total_asset_value_init_limit, 10_000);
+        assert_eq!(bank.config.oracle_max_age, 60);
+        assert_eq!(bank.flags, EMISSIONS_FLAG_LENDING_ACTIVE);
+        assert_eq!(bank.emissions_rate, 7);
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48!(1000));
+        assert_eq!(bank.emissions_mint, emissions_mint);
+
+        assert_eq!(bank.config.oracle_max_deviation_bps, 0);
+        assert_eq!(bank.config.emode_category, EMODE_CATEGORY_NONE);
+        assert_eq!(I80F48::from(bank.fixed_price), I80F48::ZERO);
+        assert_eq!(
+            bank.get_oracle_confidence_config().max_confidence,
+            MAX_CONF_INTERVAL
+        );
+        assert!(matches!(
+            bank.oracle_confidence_policy,
+            OracleConfidencePolicy::Clamp
+        ));
+        assert_eq!(I80F48::from(bank.oracle_max_price_change), I80F48::ZERO);
+        assert_eq!(
+            bank.get_liquidation_base_liquidator_fee(),
+            LIQUIDATION_LIQUIDATOR_FEE
+        );
+        assert_eq!(
+            bank.get_liquidation_insurance_fee(),
+            LIQUIDATION_INSURANCE_FEE
+        );
+        assert_eq!(bank.liquidation_fees_configured, 0);
+        assert_eq!(bank.liquidation_full_close_threshold, 0);
+        assert_eq!(bank.receipt_mint, Pubkey::default());
+        assert_eq!(
+            bank.total_liability_value_limit,
+            TOTAL_LIABILITY_VALUE_LIMIT_INACTIVE
+        );
+        assert_eq!(
+            I80F48::from(bank.adaptive_plateau_interest_rate),
+            I80F48::ZERO
+        );
+        assert_eq!(bank.oracle_price_guard_trip_count, 0);
+        assert_eq!(bank.last_oracle_price_guard_trip_timestamp, 0);
+        assert_eq!(bank.last_oracle_price_source, OraclePriceSource::Primary);
+    }
 }
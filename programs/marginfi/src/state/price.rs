@@ -4,6 +4,7 @@ use anchor_lang::prelude::*;
 
 use enum_dispatch::enum_dispatch;
 use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
 use pyth_sdk_solana::{load_price_feed_from_account_info, Price, PriceFeed};
 use switchboard_v2::{
     AggregatorAccountData, AggregatorResolutionMode, SwitchboardDecimal, SWITCHBOARD_PROGRAM_ID,
@@ -12,13 +13,16 @@ use switchboard_v2::{
 use crate::{
     check,
     constants::{
-        CONF_INTERVAL_MULTIPLE, EXP_10, EXP_10_I80F48, MAX_CONF_INTERVAL, PYTH_ID, STD_DEV_MULTIPLE,
+        BPS_DENOMINATOR, CONF_INTERVAL_MULTIPLE, EXP_10, EXP_10_I80F48,
+        FALLBACK_ORACLE_MAX_PRIMARY_STALENESS_SEC, MARINADE_ID, MAX_CONF_INTERVAL,
+        MIN_AGGREGATED_ORACLE_KEYS, PYTH_ID, PYTH_PUSH_ORACLE_ID, SPL_STAKE_POOL_ID,
+        STD_DEV_MULTIPLE, SWITCHBOARD_PULL_ID,
     },
     debug, math_error,
     prelude::*,
 };
 
-use super::marginfi_group::BankConfig;
+use super::marginfi_group::{Bank, BankConfig};
 
 #[repr(u8)]
 #[cfg_attr(any(feature = "test", feature = "client"), derive(PartialEq, Eq))]
@@ -27,6 +31,52 @@ pub enum OracleSetup {
     None,
     PythEma,
     SwitchboardV2,
+    /// Median of all configured `oracle_keys`, feeds of any other setup can be mixed.
+    Aggregated,
+    /// Pyth pull oracle `PriceUpdateV2` account.
+    PythPushOracle,
+    /// Switchboard on-demand pull feed.
+    SwitchboardPull,
+    /// Liquid staking token priced as SOL times the stake pool exchange rate. `oracle_keys[0]` is
+    /// the SPL stake pool or Marinade state account, `oracle_keys[1]` the SOL price feed.
+    StakePool,
+    /// Constant `Bank::fixed_price`, no oracle account.
+    Fixed,
+    /// Feed in `oracle_keys[0]` held to the `Bank::fixed_price` peg, within
+    /// `oracle_max_deviation_bps`.
+    Pegged,
+}
+
+/// What to do with a price whose confidence interval is wider than the bank allows.
+#[repr(u8)]
+#[cfg_attr(any(feature = "test", feature = "client"), derive(PartialEq, Eq))]
+#[derive(Copy, Clone, Debug, Default, AnchorSerialize, AnchorDeserialize)]
+pub enum OracleConfidencePolicy {
+    /// Cap the confidence interval at the bank maximum.
+    #[default]
+    Clamp,
+    /// Fail with `OracleConfidenceTooWide`.
+    Reject,
+}
+
+/// Confidence interval rules of a bank, applied to every feed the bank price is read from.
+#[derive(Copy, Clone, Debug)]
+pub struct OracleConfidenceConfig {
+    /// Largest confidence interval, as a share of the price.
+    pub max_confidence: I80F48,
+    /// Multiple applied to the published confidence, `None` for the feed default.
+    pub interval_multiple: Option<I80F48>,
+    pub policy: OracleConfidencePolicy,
+}
+
+impl Default for OracleConfidenceConfig {
+    fn default() -> Self {
+        Self {
+            max_confidence: MAX_CONF_INTERVAL,
+            interval_multiple: None,
+            policy: OracleConfidencePolicy::Clamp,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -58,33 +108,115 @@ pub trait PriceAdapter {
 pub enum OraclePriceFeedAdapter {
     PythEma(PythEmaPriceFeed),
     SwitchboardV2(SwitchboardV2PriceFeed),
+    Aggregated(AggregatedPriceFeed),
+    PythPushOracle(PythPushOraclePriceFeed),
+    SwitchboardPull(SwitchboardPullPriceFeed),
+    Fallback(FallbackPriceFeed),
+    StakePool(StakePoolPriceFeed),
+    Fixed(FixedPriceFeed),
+    Pegged(PeggedPriceFeed),
+}
+
+/// Feed a bank price was read from, see `Bank::last_oracle_price_source`.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
+pub enum OraclePriceSource {
+    #[default]
+    Primary,
+    /// The primary feed was stale, the price comes from `oracle_keys[1]`.
+    Fallback,
 }
 
 impl OraclePriceFeedAdapter {
-    pub fn try_from_bank_config(
-        bank_config: &BankConfig,
+    pub fn try_from_bank(
+        bank: &Bank,
         ais: &[AccountInfo],
         current_timestamp: i64,
     ) -> MarginfiResult<Self> {
-        Self::try_from_bank_config_with_max_age(
-            bank_config,
+        Self::try_from_bank_with_max_age(
+            bank,
             ais,
             current_timestamp,
-            bank_config.get_oracle_max_age(),
+            bank.config.get_oracle_max_age(),
         )
     }
 
-    pub fn try_from_bank_config_with_max_age(
-        bank_config: &BankConfig,
+    /// Load the bank price feed, switching to the fallback feed if the primary one is stale and a
+    /// fallback is configured.
+    ///
+    /// The fallback price has to be within `oracle_max_deviation_bps` of the last primary price,
+    /// and the primary price no older than `max_age + FALLBACK_ORACLE_MAX_PRIMARY_STALENESS_SEC`.
+    /// Feeds don't keep past prices to compare at a shared publish time, so a primary price older
+    /// than that can't vouch for the fallback and the bank price is stale.
+    pub fn try_from_bank_with_max_age(
+        bank: &Bank,
         ais: &[AccountInfo],
         current_timestamp: i64,
         max_age: u64,
     ) -> MarginfiResult<Self> {
         debug!("Max age: {}", max_age);
+        let price_feed = match Self::load_checked(bank, ais, current_timestamp, max_age) {
+            Err(err) if err == MarginfiError::StaleOracle.into() => {
+                match bank.config.get_fallback_oracle_key() {
+                    Some(fallback_key) => Self::load_fallback_checked(
+                        bank,
+                        ais,
+                        &fallback_key,
+                        current_timestamp,
+                        max_age,
+                    ),
+                    None => Err(err),
+                }
+            }
+            price_feed => price_feed,
+        };
+
+        price_feed.map(|mut price_feed| {
+            price_feed.set_confidence_config(bank.get_oracle_confidence_config());
+            price_feed
+        })
+    }
+
+    /// Apply the bank confidence interval rules to this feed and the feeds it's built from.
+    pub fn set_confidence_config(&mut self, config: OracleConfidenceConfig) {
+        match self {
+            OraclePriceFeedAdapter::PythEma(feed) => feed.confidence = config,
+            OraclePriceFeedAdapter::SwitchboardV2(feed) => feed.confidence = config,
+            OraclePriceFeedAdapter::PythPushOracle(feed) => feed.confidence = config,
+            OraclePriceFeedAdapter::SwitchboardPull(feed) => feed.confidence = config,
+            OraclePriceFeedAdapter::Aggregated(feed) => feed
+                .sources
+                .iter_mut()
+                .for_each(|source| source.set_confidence_config(config)),
+            OraclePriceFeedAdapter::Fallback(feed) => feed.feed.set_confidence_config(config),
+            OraclePriceFeedAdapter::StakePool(feed) => feed.sol_feed.set_confidence_config(config),
+            OraclePriceFeedAdapter::Pegged(feed) => feed.feed.set_confidence_config(config),
+            OraclePriceFeedAdapter::Fixed(_) => {}
+        }
+    }
+
+    /// Where the price of this feed comes from.
+    pub fn get_price_source(&self) -> OraclePriceSource {
+        match self {
+            OraclePriceFeedAdapter::Fallback(_) => OraclePriceSource::Fallback,
+            _ => OraclePriceSource::Primary,
+        }
+    }
+
+    fn load_checked(
+        bank: &Bank,
+        ais: &[AccountInfo],
+        current_timestamp: i64,
+        max_age: u64,
+    ) -> MarginfiResult<Self> {
+        let bank_config = &bank.config;
         match bank_config.oracle_setup {
             OracleSetup::None => Err(MarginfiError::OracleNotSetup.into()),
             OracleSetup::PythEma => {
-                check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
+                check!(
+                    ais.len() == bank_config.get_oracle_keys().len(),
+                    MarginfiError::InvalidOracleAccount
+                );
                 check!(
                     ais[0].key == &bank_config.oracle_keys[0],
                     MarginfiError::InvalidOracleAccount
@@ -97,7 +229,10 @@ impl OraclePriceFeedAdapter {
                 ))
             }
             OracleSetup::SwitchboardV2 => {
-                check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
+                check!(
+                    ais.len() == bank_config.get_oracle_keys().len(),
+                    MarginfiError::InvalidOracleAccount
+                );
                 check!(
                     ais[0].key == &bank_config.oracle_keys[0],
                     MarginfiError::InvalidOracleAccount
@@ -107,17 +242,132 @@ impl OraclePriceFeedAdapter {
                     SwitchboardV2PriceFeed::load_checked(&ais[0], current_timestamp, max_age)?,
                 ))
             }
+            OracleSetup::Aggregated => {
+                let oracle_keys = bank_config.get_oracle_keys();
+                check!(
+                    ais.len() == oracle_keys.len(),
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                let mut sources = Vec::with_capacity(ais.len());
+                for (ai, oracle_key) in ais.iter().zip(oracle_keys) {
+                    check!(ai.key == oracle_key, MarginfiError::InvalidOracleAccount);
+
+                    if let Some(source) = AggregatedPriceFeed::load_source_checked(
+                        ai,
+                        &bank_config.pyth_push_feed_id,
+                        current_timestamp,
+                        max_age,
+                    )? {
+                        sources.push(source);
+                    }
+                }
+
+                Ok(OraclePriceFeedAdapter::Aggregated(
+                    AggregatedPriceFeed::try_new(sources, bank_config.oracle_max_deviation_bps)?,
+                ))
+            }
+            OracleSetup::PythPushOracle => {
+                check!(
+                    ais.len() == bank_config.get_oracle_keys().len(),
+                    MarginfiError::InvalidOracleAccount
+                );
+                check!(
+                    ais[0].key == &bank_config.oracle_keys[0],
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                Ok(OraclePriceFeedAdapter::PythPushOracle(
+                    PythPushOraclePriceFeed::load_checked(
+                        &ais[0],
+                        &bank_config.pyth_push_feed_id,
+                        current_timestamp,
+                        max_age,
+                    )?,
+                ))
+            }
+            OracleSetup::SwitchboardPull => {
+                check!(
+                    ais.len() == bank_config.get_oracle_keys().len(),
+                    MarginfiError::InvalidOracleAccount
+                );
+                check!(
+                    ais[0].key == &bank_config.oracle_keys[0],
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                Ok(OraclePriceFeedAdapter::SwitchboardPull(
+                    SwitchboardPullPriceFeed::load_checked(&ais[0], current_timestamp, max_age)?,
+                ))
+            }
+            OracleSetup::StakePool => {
+                let oracle_keys = bank_config.get_oracle_keys();
+                check!(
+                    ais.len() == oracle_keys.len(),
+                    MarginfiError::InvalidOracleAccount
+                );
+                check!(
+                    ais.iter().zip(oracle_keys).all(|(ai, key)| ai.key == key),
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                Ok(OraclePriceFeedAdapter::StakePool(
+                    StakePoolPriceFeed::load_checked(
+                        &ais[0],
+                        &ais[1],
+                        &bank_config.pyth_push_feed_id,
+                        current_timestamp,
+                        max_age,
+                    )?,
+                ))
+            }
+            OracleSetup::Fixed => {
+                check!(ais.is_empty(), MarginfiError::InvalidOracleAccount);
+
+                Ok(OraclePriceFeedAdapter::Fixed(FixedPriceFeed::new(
+                    bank.fixed_price.into(),
+                )))
+            }
+            OracleSetup::Pegged => {
+                check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
+                check!(
+                    ais[0].key == &bank_config.oracle_keys[0],
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                let feed = Self::load_by_owner_checked(
+                    &ais[0],
+                    &bank_config.pyth_push_feed_id,
+                    current_timestamp,
+                    max_age,
+                )?;
+                let price_feed = PeggedPriceFeed::try_new(
+                    feed,
+                    bank.fixed_price.into(),
+                    bank_config.oracle_max_deviation_bps,
+                )?;
+
+                if price_feed.is_depegged() {
+                    msg!("Oracle {} is off its peg", ais[0].key);
+                }
+
+                Ok(OraclePriceFeedAdapter::Pegged(price_feed))
+            }
         }
     }
 
     pub fn validate_bank_config(
         bank_config: &BankConfig,
+        bank_mint: &Pubkey,
         oracle_ais: &[AccountInfo],
     ) -> MarginfiResult {
         match bank_config.oracle_setup {
             OracleSetup::None => Err(MarginfiError::OracleNotSetup.into()),
             OracleSetup::PythEma => {
-                check!(oracle_ais.len() == 1, MarginfiError::InvalidOracleAccount);
+                check!(
+                    oracle_ais.len() == bank_config.get_oracle_keys().len(),
+                    MarginfiError::InvalidOracleAccount
+                );
                 check!(
                     oracle_ais[0].key == &bank_config.oracle_keys[0],
                     MarginfiError::InvalidOracleAccount
@@ -125,10 +375,13 @@ impl OraclePriceFeedAdapter {
 
                 PythEmaPriceFeed::check_ais(&oracle_ais[0])?;
 
-                Ok(())
+                Self::validate_fallback(bank_config, oracle_ais)
             }
             OracleSetup::SwitchboardV2 => {
-                check!(oracle_ais.len() == 1, MarginfiError::InvalidOracleAccount);
+                check!(
+                    oracle_ais.len() == bank_config.get_oracle_keys().len(),
+                    MarginfiError::InvalidOracleAccount
+                );
                 check!(
                     oracle_ais[0].key == &bank_config.oracle_keys[0],
                     MarginfiError::InvalidOracleAccount
@@ -136,8 +389,184 @@ impl OraclePriceFeedAdapter {
 
                 SwitchboardV2PriceFeed::check_ais(&oracle_ais[0])?;
 
+                Self::validate_fallback(bank_config, oracle_ais)
+            }
+            OracleSetup::Aggregated => {
+                let oracle_keys = bank_config.get_oracle_keys();
+                check!(
+                    oracle_keys.len() >= MIN_AGGREGATED_ORACLE_KEYS,
+                    MarginfiError::InvalidOracleAccount
+                );
+                check!(
+                    oracle_ais.len() == oracle_keys.len(),
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                for (ai, oracle_key) in oracle_ais.iter().zip(oracle_keys) {
+                    check!(ai.key == oracle_key, MarginfiError::InvalidOracleAccount);
+                    OraclePriceFeedAdapter::check_ais_by_owner(ai, &bank_config.pyth_push_feed_id)?;
+                }
+
+                Ok(())
+            }
+            OracleSetup::PythPushOracle => {
+                check!(
+                    oracle_ais.len() == bank_config.get_oracle_keys().len(),
+                    MarginfiError::InvalidOracleAccount
+                );
+                check!(
+                    oracle_ais[0].key == &bank_config.oracle_keys[0],
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                PythPushOraclePriceFeed::check_ais(&oracle_ais[0], &bank_config.pyth_push_feed_id)?;
+
+                Self::validate_fallback(bank_config, oracle_ais)
+            }
+            OracleSetup::SwitchboardPull => {
+                check!(
+                    oracle_ais.len() == bank_config.get_oracle_keys().len(),
+                    MarginfiError::InvalidOracleAccount
+                );
+                check!(
+                    oracle_ais[0].key == &bank_config.oracle_keys[0],
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                SwitchboardPullPriceFeed::check_ais(&oracle_ais[0])?;
+
+                Self::validate_fallback(bank_config, oracle_ais)
+            }
+            OracleSetup::StakePool => {
+                let oracle_keys = bank_config.get_oracle_keys();
+                check!(
+                    oracle_ais.len() == oracle_keys.len(),
+                    MarginfiError::InvalidOracleAccount
+                );
+                check!(
+                    oracle_ais
+                        .iter()
+                        .zip(oracle_keys)
+                        .all(|(ai, key)| ai.key == key),
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                StakePoolPriceFeed::check_ais(&oracle_ais[0], bank_mint)?;
+                Self::check_ais_by_owner(&oracle_ais[1], &bank_config.pyth_push_feed_id)?;
+
                 Ok(())
             }
+            OracleSetup::Fixed => {
+                check!(oracle_ais.is_empty(), MarginfiError::InvalidOracleAccount);
+
+                Ok(())
+            }
+            OracleSetup::Pegged => {
+                check!(oracle_ais.len() == 1, MarginfiError::InvalidOracleAccount);
+                check!(
+                    oracle_ais[0].key == &bank_config.oracle_keys[0],
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                Self::check_ais_by_owner(&oracle_ais[0], &bank_config.pyth_push_feed_id)
+            }
+        }
+    }
+}
+
+impl OraclePriceFeedAdapter {
+    /// Load the fallback feed of a bank whose primary feed is stale. The fallback is only trusted
+    /// if it agrees with the last price published by the primary feed, within the bank's
+    /// `oracle_max_deviation_bps`.
+    fn load_fallback_checked(
+        bank: &Bank,
+        ais: &[AccountInfo],
+        fallback_key: &Pubkey,
+        current_timestamp: i64,
+        max_age: u64,
+    ) -> MarginfiResult<Self> {
+        check!(
+            ais.len() == 2 && ais[1].key == fallback_key,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        let last_primary = Self::load_checked(
+            bank,
+            ais,
+            current_timestamp,
+            max_age.saturating_add(FALLBACK_ORACLE_MAX_PRIMARY_STALENESS_SEC),
+        )?;
+        let fallback = Self::load_by_owner_checked(
+            &ais[1],
+            &bank.config.pyth_push_feed_id,
+            current_timestamp,
+            max_age,
+        )?;
+
+        check_price_deviation(
+            fallback.get_price_of_type(OraclePriceType::RealTime, None)?,
+            last_primary.get_price_of_type(OraclePriceType::RealTime, None)?,
+            bank.config.oracle_max_deviation_bps,
+        )?;
+
+        msg!(
+            "Oracle {} is stale, using fallback oracle {}",
+            ais[0].key,
+            fallback_key
+        );
+
+        Ok(OraclePriceFeedAdapter::Fallback(FallbackPriceFeed {
+            feed: Box::new(fallback),
+        }))
+    }
+
+    fn validate_fallback(bank_config: &BankConfig, oracle_ais: &[AccountInfo]) -> MarginfiResult {
+        if let Some(fallback_key) = bank_config.get_fallback_oracle_key() {
+            check!(
+                oracle_ais[1].key == &fallback_key,
+                MarginfiError::InvalidOracleAccount
+            );
+
+            Self::check_ais_by_owner(&oracle_ais[1], &bank_config.pyth_push_feed_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load a feed of any supported type, picked by the account owner. Pyth pull feeds have to
+    /// publish `pyth_feed_id`.
+    fn load_by_owner_checked(
+        ai: &AccountInfo,
+        pyth_feed_id: &[u8; 32],
+        current_timestamp: i64,
+        max_age: u64,
+    ) -> MarginfiResult<Self> {
+        if ai.owner.eq(&PYTH_ID) {
+            PythEmaPriceFeed::load_checked(ai, current_timestamp, max_age)
+                .map(OraclePriceFeedAdapter::PythEma)
+        } else if ai.owner.eq(&SWITCHBOARD_PROGRAM_ID) {
+            SwitchboardV2PriceFeed::load_checked(ai, current_timestamp, max_age)
+                .map(OraclePriceFeedAdapter::SwitchboardV2)
+        } else if ai.owner.eq(&PYTH_PUSH_ORACLE_ID) {
+            PythPushOraclePriceFeed::load_checked(ai, pyth_feed_id, current_timestamp, max_age)
+                .map(OraclePriceFeedAdapter::PythPushOracle)
+        } else if ai.owner.eq(&SWITCHBOARD_PULL_ID) {
+            SwitchboardPullPriceFeed::load_checked(ai, current_timestamp, max_age)
+                .map(OraclePriceFeedAdapter::SwitchboardPull)
+        } else {
+            Err(MarginfiError::InvalidOracleAccount.into())
+        }
+    }
+
+    fn check_ais_by_owner(ai: &AccountInfo, pyth_feed_id: &[u8; 32]) -> MarginfiResult {
+        if ai.owner.eq(&PYTH_ID) {
+            PythEmaPriceFeed::check_ais(ai)
+        } else if ai.owner.eq(&PYTH_PUSH_ORACLE_ID) {
+            PythPushOraclePriceFeed::check_ais(ai, pyth_feed_id)
+        } else if ai.owner.eq(&SWITCHBOARD_PULL_ID) {
+            SwitchboardPullPriceFeed::check_ais(ai)
+        } else {
+            SwitchboardV2PriceFeed::check_ais(ai)
         }
     }
 }
@@ -146,9 +575,20 @@ impl OraclePriceFeedAdapter {
 pub struct PythEmaPriceFeed {
     ema_price: Box<Price>,
     price: Box<Price>,
+    confidence: OracleConfidenceConfig,
 }
 
 impl PythEmaPriceFeed {
+    /// Build a feed from prices already read off-chain.
+    #[cfg(any(feature = "test", feature = "client"))]
+    pub fn new(ema_price: Price, price: Price) -> Self {
+        Self {
+            ema_price: Box::new(ema_price),
+            price: Box::new(price),
+            confidence: OracleConfidenceConfig::default(),
+        }
+    }
+
     pub fn load_checked(ai: &AccountInfo, current_time: i64, max_age: u64) -> MarginfiResult<Self> {
         let price_feed = load_pyth_price_feed(ai)?;
         let ema_price = price_feed
@@ -162,6 +602,7 @@ impl PythEmaPriceFeed {
         Ok(Self {
             ema_price: Box::new(ema_price),
             price: Box::new(price),
+            confidence: OracleConfidenceConfig::default(),
         })
     }
 
@@ -177,29 +618,7 @@ impl PythEmaPriceFeed {
             &self.price
         };
 
-        let conf_interval =
-            pyth_price_components_to_i80f48(I80F48::from_num(price.conf), price.expo)?
-                .checked_mul(CONF_INTERVAL_MULTIPLE)
-                .ok_or_else(math_error!())?;
-
-        // Cap confidence interval to 5% of price
-        let price = pyth_price_components_to_i80f48(I80F48::from_num(price.price), price.expo)?;
-
-        let max_conf_interval = price
-            .checked_mul(MAX_CONF_INTERVAL)
-            .ok_or_else(math_error!())?;
-
-        assert!(
-            max_conf_interval >= I80F48::ZERO,
-            "Negative max confidence interval"
-        );
-
-        assert!(
-            conf_interval >= I80F48::ZERO,
-            "Negative confidence interval"
-        );
-
-        Ok(min(conf_interval, max_conf_interval))
+        pyth_price_confidence_interval(price, &self.confidence)
     }
 
     #[inline(always)]
@@ -246,6 +665,7 @@ impl PriceAdapter for PythEmaPriceFeed {
 #[cfg_attr(feature = "client", derive(Clone, Debug))]
 pub struct SwitchboardV2PriceFeed {
     aggregator_account: Box<LiteAggregatorAccountData>,
+    confidence: OracleConfidenceConfig,
 }
 
 impl SwitchboardV2PriceFeed {
@@ -270,6 +690,7 @@ impl SwitchboardV2PriceFeed {
 
         Ok(Self {
             aggregator_account: Box::new(aggregator_account.into()),
+            confidence: OracleConfidenceConfig::default(),
         })
     }
 
@@ -303,26 +724,14 @@ impl SwitchboardV2PriceFeed {
             .ok_or(MarginfiError::InvalidSwitchboardDecimalConversion)?;
 
         let conf_interval = std_div
-            .checked_mul(STD_DEV_MULTIPLE)
-            .ok_or_else(math_error!())?;
-
-        let price = self.get_price()?;
-
-        let max_conf_interval = price
-            .checked_mul(MAX_CONF_INTERVAL)
+            .checked_mul(
+                self.confidence
+                    .interval_multiple
+                    .unwrap_or(STD_DEV_MULTIPLE),
+            )
             .ok_or_else(math_error!())?;
 
-        assert!(
-            max_conf_interval >= I80F48::ZERO,
-            "Negative max confidence interval"
-        );
-
-        assert!(
-            conf_interval >= I80F48::ZERO,
-            "Negative confidence interval"
-        );
-
-        Ok(min(conf_interval, max_conf_interval))
+        cap_confidence_interval(conf_interval, self.get_price()?, &self.confidence)
     }
 }
 
@@ -352,46 +761,552 @@ impl PriceAdapter for SwitchboardV2PriceFeed {
     }
 }
 
-/// A slimmed down version of the AggregatorAccountData struct copied from the switchboard-v2/src/aggregator.rs
+/// Pyth pull oracle price, read from a `PriceUpdateV2` account posted through the Pyth receiver
+/// program. Only fully verified updates of the feed configured on the bank are accepted.
 #[cfg_attr(feature = "client", derive(Clone, Debug))]
-struct LiteAggregatorAccountData {
-    /// Use sliding windoe or round based resolution
-    /// NOTE: This changes result propogation in latest_round_result
-    pub resolution_mode: AggregatorResolutionMode,
-    /// Latest confirmed update request result that has been accepted as valid.
-    pub latest_confirmed_round_result: SwitchboardDecimal,
-    pub latest_confirmed_round_num_success: u32,
-    pub latest_confirmed_round_std_deviation: SwitchboardDecimal,
-    /// Minimum number of oracle responses required before a round is validated.
-    pub min_oracle_results: u32,
+pub struct PythPushOraclePriceFeed {
+    ema_price: Box<Price>,
+    price: Box<Price>,
+    confidence: OracleConfidenceConfig,
 }
 
-impl From<&AggregatorAccountData> for LiteAggregatorAccountData {
-    fn from(agg: &AggregatorAccountData) -> Self {
-        Self {
-            resolution_mode: agg.resolution_mode,
-            latest_confirmed_round_result: agg.latest_confirmed_round.result,
-            latest_confirmed_round_num_success: agg.latest_confirmed_round.num_success,
-            latest_confirmed_round_std_deviation: agg.latest_confirmed_round.std_deviation,
-            min_oracle_results: agg.min_oracle_results,
-        }
+impl PythPushOraclePriceFeed {
+    pub fn load_checked(
+        ai: &AccountInfo,
+        feed_id: &[u8; 32],
+        current_time: i64,
+        max_age: u64,
+    ) -> MarginfiResult<Self> {
+        let price_update = load_price_update_v2(ai, feed_id)?;
+        let message = &price_update.price_message;
+
+        let age = current_time.saturating_sub(message.publish_time);
+        check!(
+            age <= i64::try_from(max_age).unwrap_or(i64::MAX),
+            MarginfiError::StaleOracle
+        );
+
+        Ok(Self {
+            ema_price: Box::new(Price {
+                price: message.ema_price,
+                conf: message.ema_conf,
+                expo: message.exponent,
+                publish_time: message.publish_time,
+            }),
+            price: Box::new(Price {
+                price: message.price,
+                conf: message.conf,
+                expo: message.exponent,
+                publish_time: message.publish_time,
+            }),
+            confidence: OracleConfidenceConfig::default(),
+        })
     }
-}
 
-impl LiteAggregatorAccountData {
-    /// If sufficient oracle responses, returns the latest on-chain result in SwitchboardDecimal format
-    ///
-    /// # Examples
-    ///
-    /// ```ignore
-    /// use switchboard_v2::AggregatorAccountData;
-    /// use std::convert::TryInto;
-    ///
-    /// let feed_result = AggregatorAccountData::new(feed_account_info)?.get_result()?;
-    /// let decimal: f64 = feed_result.try_into()?;
-    /// ```
-    pub fn get_result(&self) -> anchor_lang::Result<SwitchboardDecimal> {
-        if self.resolution_mode == AggregatorResolutionMode::ModeSlidingResolution {
+    fn check_ais(ai: &AccountInfo, feed_id: &[u8; 32]) -> MarginfiResult {
+        load_price_update_v2(ai, feed_id)?;
+        Ok(())
+    }
+
+    pub fn get_publish_time(&self) -> i64 {
+        self.price.publish_time
+    }
+
+    fn get_confidence_interval(&self, use_ema: bool) -> MarginfiResult<I80F48> {
+        let price = if use_ema {
+            &self.ema_price
+        } else {
+            &self.price
+        };
+
+        pyth_price_confidence_interval(price, &self.confidence)
+    }
+
+    #[inline(always)]
+    fn get_ema_price(&self) -> MarginfiResult<I80F48> {
+        pyth_price_components_to_i80f48(I80F48::from_num(self.ema_price.price), self.ema_price.expo)
+    }
+
+    #[inline(always)]
+    fn get_unweighted_price(&self) -> MarginfiResult<I80F48> {
+        pyth_price_components_to_i80f48(I80F48::from_num(self.price.price), self.price.expo)
+    }
+}
+
+impl PriceAdapter for PythPushOraclePriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let price = match price_type {
+            OraclePriceType::TimeWeighted => self.get_ema_price()?,
+            OraclePriceType::RealTime => self.get_unweighted_price()?,
+        };
+
+        match bias {
+            None => Ok(price),
+            Some(price_bias) => {
+                let confidence_interval = self
+                    .get_confidence_interval(matches!(price_type, OraclePriceType::TimeWeighted))?;
+
+                match price_bias {
+                    PriceBias::Low => Ok(price
+                        .checked_sub(confidence_interval)
+                        .ok_or_else(math_error!())?),
+                    PriceBias::High => Ok(price
+                        .checked_add(confidence_interval)
+                        .ok_or_else(math_error!())?),
+                }
+            }
+        }
+    }
+}
+
+/// Switchboard on-demand pull feed price. The feed has no time weighted price, the latest result
+/// is used for both price types.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+pub struct SwitchboardPullPriceFeed {
+    feed: Box<LitePullFeedAccountData>,
+    confidence: OracleConfidenceConfig,
+}
+
+impl SwitchboardPullPriceFeed {
+    pub fn load_checked(
+        ai: &AccountInfo,
+        current_timestamp: i64,
+        max_age: u64,
+    ) -> MarginfiResult<Self> {
+        check!(
+            ai.owner.eq(&SWITCHBOARD_PULL_ID),
+            MarginfiError::InvalidOracleAccount
+        );
+
+        let feed = LitePullFeedAccountData::try_from_bytes(&ai.data.borrow())?;
+
+        let age = current_timestamp.saturating_sub(feed.last_update_timestamp);
+        check!(
+            age <= i64::try_from(max_age).unwrap_or(i64::MAX),
+            MarginfiError::StaleOracle
+        );
+
+        Ok(Self {
+            feed: Box::new(feed),
+            confidence: OracleConfidenceConfig::default(),
+        })
+    }
+
+    fn check_ais(ai: &AccountInfo) -> MarginfiResult {
+        check!(
+            ai.owner.eq(&SWITCHBOARD_PULL_ID),
+            MarginfiError::InvalidOracleAccount
+        );
+
+        LitePullFeedAccountData::try_from_bytes(&ai.data.borrow())?;
+
+        Ok(())
+    }
+
+    pub fn get_publish_time(&self) -> i64 {
+        self.feed.last_update_timestamp
+    }
+
+    fn get_price(&self) -> MarginfiResult<I80F48> {
+        let value = self.feed.get_result()?;
+
+        Ok(switchboard_pull_value_to_i80f48(value)
+            .ok_or(MarginfiError::InvalidSwitchboardDecimalConversion)?)
+    }
+
+    fn get_confidence_interval(&self) -> MarginfiResult<I80F48> {
+        let std_dev = switchboard_pull_value_to_i80f48(self.feed.result_std_dev)
+            .ok_or(MarginfiError::InvalidSwitchboardDecimalConversion)?;
+
+        let conf_interval = std_dev
+            .checked_mul(
+                self.confidence
+                    .interval_multiple
+                    .unwrap_or(STD_DEV_MULTIPLE),
+            )
+            .ok_or_else(math_error!())?;
+
+        cap_confidence_interval(conf_interval, self.get_price()?, &self.confidence)
+    }
+}
+
+impl PriceAdapter for SwitchboardPullPriceFeed {
+    fn get_price_of_type(
+        &self,
+        _price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let price = self.get_price()?;
+
+        match bias {
+            Some(price_bias) => {
+                let confidence_interval = self.get_confidence_interval()?;
+
+                match price_bias {
+                    PriceBias::Low => Ok(price
+                        .checked_sub(confidence_interval)
+                        .ok_or_else(math_error!())?),
+                    PriceBias::High => Ok(price
+                        .checked_add(confidence_interval)
+                        .ok_or_else(math_error!())?),
+                }
+            }
+            None => Ok(price),
+        }
+    }
+}
+
+/// Liquid staking token price, the SOL price times the stake pool exchange rate. The confidence
+/// interval of the SOL feed carries over to the biased prices.
+///
+/// Pools only update their exchange rate once per epoch and it only grows outside of slashing, a
+/// pool not yet updated this epoch slightly underprices the token.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct StakePoolPriceFeed {
+    sol_feed: Box<OraclePriceFeedAdapter>,
+    exchange_rate: I80F48,
+}
+
+impl StakePoolPriceFeed {
+    pub fn new(sol_feed: OraclePriceFeedAdapter, exchange_rate: I80F48) -> Self {
+        Self {
+            sol_feed: Box::new(sol_feed),
+            exchange_rate,
+        }
+    }
+
+    pub fn load_checked(
+        pool_ai: &AccountInfo,
+        sol_feed_ai: &AccountInfo,
+        pyth_feed_id: &[u8; 32],
+        current_timestamp: i64,
+        max_age: u64,
+    ) -> MarginfiResult<Self> {
+        let exchange_rate = load_stake_pool_exchange_rate(pool_ai)?;
+        let sol_feed = OraclePriceFeedAdapter::load_by_owner_checked(
+            sol_feed_ai,
+            pyth_feed_id,
+            current_timestamp,
+            max_age,
+        )?;
+
+        Ok(Self::new(sol_feed, exchange_rate))
+    }
+
+    /// The pool has to be the one issuing the bank mint, any other pool would price the bank
+    /// asset at an unrelated exchange rate.
+    fn check_ais(ai: &AccountInfo, bank_mint: &Pubkey) -> MarginfiResult {
+        load_stake_pool_exchange_rate(ai)?;
+
+        check!(
+            load_stake_pool_mint(ai)? == *bank_mint,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        Ok(())
+    }
+
+    /// SOL per liquid staking token.
+    pub fn get_exchange_rate(&self) -> I80F48 {
+        self.exchange_rate
+    }
+}
+
+impl PriceAdapter for StakePoolPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let sol_price = self.sol_feed.get_price_of_type(price_type, bias)?;
+
+        Ok(sol_price
+            .checked_mul(self.exchange_rate)
+            .ok_or_else(math_error!())?)
+    }
+}
+
+/// SOL per liquid staking token of an SPL stake pool or Marinade state account.
+pub fn load_stake_pool_exchange_rate(ai: &AccountInfo) -> MarginfiResult<I80F48> {
+    let data = ai.data.borrow();
+
+    if ai.owner.eq(&SPL_STAKE_POOL_ID) {
+        LiteStakePool::try_from_bytes(&data)?.get_exchange_rate()
+    } else if ai.owner.eq(&MARINADE_ID) {
+        LiteMarinadeState::try_from_bytes(&data)?.get_exchange_rate()
+    } else {
+        Err(MarginfiError::InvalidOracleAccount.into())
+    }
+}
+
+/// Liquid staking token mint of an SPL stake pool or Marinade state account.
+fn load_stake_pool_mint(ai: &AccountInfo) -> MarginfiResult<Pubkey> {
+    let data = ai.data.borrow();
+
+    if ai.owner.eq(&SPL_STAKE_POOL_ID) {
+        Ok(LiteStakePool::try_from_bytes(&data)?.pool_mint)
+    } else if ai.owner.eq(&MARINADE_ID) {
+        Ok(LiteMarinadeState::try_from_bytes(&data)?.msol_mint)
+    } else {
+        Err(MarginfiError::InvalidOracleAccount.into())
+    }
+}
+
+/// Constant price, for stablecoins priced at exactly their peg or localnet test assets.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+pub struct FixedPriceFeed {
+    price: I80F48,
+}
+
+impl FixedPriceFeed {
+    pub fn new(price: I80F48) -> Self {
+        Self { price }
+    }
+}
+
+impl PriceAdapter for FixedPriceFeed {
+    fn get_price_of_type(
+        &self,
+        _price_type: OraclePriceType,
+        _bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        Ok(self.price)
+    }
+}
+
+/// Feed price clamped to a band around its peg, hiding noise around the peg.
+///
+/// A feed outside the band is depegged: the clamp is lifted and the feed price is used as is, so
+/// a real depeg isn't masked.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct PeggedPriceFeed {
+    feed: Box<OraclePriceFeedAdapter>,
+    peg: I80F48,
+    max_deviation: I80F48,
+    depegged: bool,
+}
+
+impl PeggedPriceFeed {
+    pub fn try_new(
+        feed: OraclePriceFeedAdapter,
+        peg: I80F48,
+        max_deviation_bps: u16,
+    ) -> MarginfiResult<Self> {
+        let max_deviation = peg
+            .checked_mul(I80F48::from_num(max_deviation_bps))
+            .ok_or_else(math_error!())?
+            .checked_div(BPS_DENOMINATOR)
+            .ok_or_else(math_error!())?;
+
+        let price = feed.get_price_of_type(OraclePriceType::RealTime, None)?;
+        let deviation = price.checked_sub(peg).ok_or_else(math_error!())?.abs();
+
+        Ok(Self {
+            feed: Box::new(feed),
+            peg,
+            max_deviation,
+            depegged: deviation > max_deviation,
+        })
+    }
+
+    /// Whether the feed strayed from the peg by more than the tolerated deviation.
+    pub fn is_depegged(&self) -> bool {
+        self.depegged
+    }
+}
+
+impl PriceAdapter for PeggedPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let price = self.feed.get_price_of_type(price_type, bias)?;
+
+        if self.depegged {
+            return Ok(price);
+        }
+
+        Ok(price.clamp(self.peg - self.max_deviation, self.peg + self.max_deviation))
+    }
+}
+
+/// Fallback feed standing in for a stale primary feed.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct FallbackPriceFeed {
+    feed: Box<OraclePriceFeedAdapter>,
+}
+
+impl PriceAdapter for FallbackPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        self.feed.get_price_of_type(price_type, bias)
+    }
+}
+
+/// Median of several Pyth and Switchboard feeds. Stale sources are left out, the price is stale
+/// with fewer than `MIN_AGGREGATED_ORACLE_KEYS` fresh sources, and rejected when the highest and
+/// lowest fresh source prices are further apart than the bank's `oracle_max_deviation_bps` of the
+/// lowest one.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct AggregatedPriceFeed {
+    sources: Vec<OraclePriceFeedAdapter>,
+}
+
+impl AggregatedPriceFeed {
+    pub fn try_new(
+        sources: Vec<OraclePriceFeedAdapter>,
+        max_deviation_bps: u16,
+    ) -> MarginfiResult<Self> {
+        check!(
+            sources.len() >= MIN_AGGREGATED_ORACLE_KEYS,
+            MarginfiError::StaleOracle
+        );
+
+        let feed = Self { sources };
+
+        let prices = feed.get_source_prices(OraclePriceType::RealTime, None)?;
+        let (min_price, max_price) = prices
+            .iter()
+            .skip(1)
+            .fold((prices[0], prices[0]), |(min_price, max_price), price| {
+                (min_price.min(*price), max_price.max(*price))
+            });
+
+        check_price_deviation(max_price, min_price, max_deviation_bps)?;
+
+        Ok(feed)
+    }
+
+    /// Load a source by its owner, `None` if it's too old to be used.
+    fn load_source_checked(
+        ai: &AccountInfo,
+        pyth_feed_id: &[u8; 32],
+        current_timestamp: i64,
+        max_age: u64,
+    ) -> MarginfiResult<Option<OraclePriceFeedAdapter>> {
+        match OraclePriceFeedAdapter::load_by_owner_checked(
+            ai,
+            pyth_feed_id,
+            current_timestamp,
+            max_age,
+        ) {
+            Ok(source) => Ok(Some(source)),
+            Err(err) if err == MarginfiError::StaleOracle.into() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn get_source_prices(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<Vec<I80F48>> {
+        self.sources
+            .iter()
+            .map(|source| source.get_price_of_type(price_type, bias))
+            .collect()
+    }
+}
+
+impl PriceAdapter for AggregatedPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        median(self.get_source_prices(price_type, bias)?)
+    }
+}
+
+/// Fail with `OracleDeviationExceeded` if `price` strays from `reference` by more than
+/// `max_deviation_bps`.
+fn check_price_deviation(
+    price: I80F48,
+    reference: I80F48,
+    max_deviation_bps: u16,
+) -> MarginfiResult {
+    let max_deviation = reference
+        .checked_mul(I80F48::from_num(max_deviation_bps))
+        .ok_or_else(math_error!())?
+        .checked_div(BPS_DENOMINATOR)
+        .ok_or_else(math_error!())?;
+    let deviation = price
+        .checked_sub(reference)
+        .ok_or_else(math_error!())?
+        .abs();
+
+    check!(
+        deviation <= max_deviation,
+        MarginfiError::OracleDeviationExceeded
+    );
+
+    Ok(())
+}
+
+fn median(mut prices: Vec<I80F48>) -> MarginfiResult<I80F48> {
+    check!(!prices.is_empty(), MarginfiError::InvalidPrice);
+
+    prices.sort();
+    let mid = prices.len() / 2;
+
+    if prices.len() % 2 == 1 {
+        Ok(prices[mid])
+    } else {
+        Ok(prices[mid - 1]
+            .checked_add(prices[mid])
+            .ok_or_else(math_error!())?
+            / 2)
+    }
+}
+
+/// A slimmed down version of the AggregatorAccountData struct copied from the switchboard-v2/src/aggregator.rs
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+struct LiteAggregatorAccountData {
+    /// Use sliding windoe or round based resolution
+    /// NOTE: This changes result propogation in latest_round_result
+    pub resolution_mode: AggregatorResolutionMode,
+    /// Latest confirmed update request result that has been accepted as valid.
+    pub latest_confirmed_round_result: SwitchboardDecimal,
+    pub latest_confirmed_round_num_success: u32,
+    pub latest_confirmed_round_std_deviation: SwitchboardDecimal,
+    /// Minimum number of oracle responses required before a round is validated.
+    pub min_oracle_results: u32,
+}
+
+impl From<&AggregatorAccountData> for LiteAggregatorAccountData {
+    fn from(agg: &AggregatorAccountData) -> Self {
+        Self {
+            resolution_mode: agg.resolution_mode,
+            latest_confirmed_round_result: agg.latest_confirmed_round.result,
+            latest_confirmed_round_num_success: agg.latest_confirmed_round.num_success,
+            latest_confirmed_round_std_deviation: agg.latest_confirmed_round.std_deviation,
+            min_oracle_results: agg.min_oracle_results,
+        }
+    }
+}
+
+impl LiteAggregatorAccountData {
+    /// If sufficient oracle responses, returns the latest on-chain result in SwitchboardDecimal format
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use switchboard_v2::AggregatorAccountData;
+    /// use std::convert::TryInto;
+    ///
+    /// let feed_result = AggregatorAccountData::new(feed_account_info)?.get_result()?;
+    /// let decimal: f64 = feed_result.try_into()?;
+    /// ```
+    pub fn get_result(&self) -> anchor_lang::Result<SwitchboardDecimal> {
+        if self.resolution_mode == AggregatorResolutionMode::ModeSlidingResolution {
             return Ok(self.latest_confirmed_round_result);
         }
         let min_oracle_results = self.min_oracle_results;
@@ -403,6 +1318,280 @@ impl LiteAggregatorAccountData {
     }
 }
 
+/// A slimmed down version of the PriceUpdateV2 account copied from pyth-solana-receiver-sdk
+#[derive(AnchorDeserialize)]
+struct LitePriceUpdateV2 {
+    pub _write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub _posted_slot: u64,
+}
+
+impl LitePriceUpdateV2 {
+    const DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+}
+
+/// Number of guardian signatures checked when the update was posted.
+#[derive(AnchorDeserialize, PartialEq, Eq)]
+enum VerificationLevel {
+    Partial { _num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorDeserialize)]
+struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub _prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+/// Load and validate a fully verified Pyth `PriceUpdateV2` account of the `feed_id` feed.
+fn load_price_update_v2(ai: &AccountInfo, feed_id: &[u8; 32]) -> MarginfiResult<LitePriceUpdateV2> {
+    check!(
+        ai.owner.eq(&PYTH_PUSH_ORACLE_ID),
+        MarginfiError::InvalidOracleAccount
+    );
+
+    let data = ai.data.borrow();
+    check!(
+        data.len() >= 8 && data[..8] == LitePriceUpdateV2::DISCRIMINATOR,
+        MarginfiError::InvalidOracleAccount
+    );
+
+    let price_update = LitePriceUpdateV2::deserialize(&mut &data[8..])
+        .map_err(|_| MarginfiError::InvalidOracleAccount)?;
+    check!(
+        price_update.verification_level == VerificationLevel::Full,
+        MarginfiError::InvalidOracleAccount
+    );
+    check!(
+        price_update.price_message.feed_id == *feed_id,
+        MarginfiError::PythPushFeedIdMismatch
+    );
+
+    Ok(price_update)
+}
+
+/// The fields of the PullFeedAccountData account of switchboard-on-demand the adapter reads,
+/// picked out of the account data by offset.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+struct LitePullFeedAccountData {
+    /// Median of the latest oracle submissions, scaled by 10^18.
+    pub result_value: i128,
+    /// Standard deviation of the latest oracle submissions, scaled by 10^18.
+    pub result_std_dev: i128,
+    pub result_num_samples: u8,
+    /// Minimum number of submissions required for the result to be valid.
+    pub min_sample_size: u8,
+    pub last_update_timestamp: i64,
+}
+
+impl LitePullFeedAccountData {
+    const DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+    const LEN: usize = 8 + 3200;
+
+    // Offsets from the start of the account data, discriminator included.
+    const MIN_SAMPLE_SIZE_OFFSET: usize = 8 + 2207;
+    const LAST_UPDATE_TIMESTAMP_OFFSET: usize = 8 + 2208;
+    const RESULT_VALUE_OFFSET: usize = 8 + 2256;
+    const RESULT_STD_DEV_OFFSET: usize = Self::RESULT_VALUE_OFFSET + 16;
+    const RESULT_NUM_SAMPLES_OFFSET: usize = Self::RESULT_VALUE_OFFSET + 96;
+
+    fn try_from_bytes(data: &[u8]) -> MarginfiResult<Self> {
+        check!(
+            data.len() >= Self::LEN && data[..8] == Self::DISCRIMINATOR,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        let read_i128 = |offset: usize| {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&data[offset..offset + 16]);
+            i128::from_le_bytes(bytes)
+        };
+        let mut timestamp_bytes = [0u8; 8];
+        timestamp_bytes.copy_from_slice(
+            &data[Self::LAST_UPDATE_TIMESTAMP_OFFSET..Self::LAST_UPDATE_TIMESTAMP_OFFSET + 8],
+        );
+
+        Ok(Self {
+            result_value: read_i128(Self::RESULT_VALUE_OFFSET),
+            result_std_dev: read_i128(Self::RESULT_STD_DEV_OFFSET),
+            result_num_samples: data[Self::RESULT_NUM_SAMPLES_OFFSET],
+            min_sample_size: data[Self::MIN_SAMPLE_SIZE_OFFSET],
+            last_update_timestamp: i64::from_le_bytes(timestamp_bytes),
+        })
+    }
+
+    /// The latest result, if enough oracles responded.
+    fn get_result(&self) -> MarginfiResult<i128> {
+        check!(
+            self.result_num_samples >= self.min_sample_size,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        Ok(self.result_value)
+    }
+}
+
+#[inline(always)]
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[inline(always)]
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&data[offset..offset + 32]);
+    Pubkey::new_from_array(bytes)
+}
+
+/// Fields of an SPL stake pool `StakePool` account needed for its exchange rate.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+struct LiteStakePool {
+    pub pool_mint: Pubkey,
+    /// Lamports under management, as of the last pool update.
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+}
+
+impl LiteStakePool {
+    const ACCOUNT_TYPE_STAKE_POOL: u8 = 1;
+
+    // Offsets from the start of the account data, the pool mint follows the account type, 5
+    // pubkeys and a bump.
+    const POOL_MINT_OFFSET: usize = 162;
+    const TOTAL_LAMPORTS_OFFSET: usize = 258;
+    const POOL_TOKEN_SUPPLY_OFFSET: usize = Self::TOTAL_LAMPORTS_OFFSET + 8;
+
+    fn try_from_bytes(data: &[u8]) -> MarginfiResult<Self> {
+        check!(
+            data.len() >= Self::POOL_TOKEN_SUPPLY_OFFSET + 8
+                && data[0] == Self::ACCOUNT_TYPE_STAKE_POOL,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        Ok(Self {
+            pool_mint: read_pubkey(data, Self::POOL_MINT_OFFSET),
+            total_lamports: read_u64(data, Self::TOTAL_LAMPORTS_OFFSET),
+            pool_token_supply: read_u64(data, Self::POOL_TOKEN_SUPPLY_OFFSET),
+        })
+    }
+
+    /// Lamports per pool token, 1:1 for an empty pool like the stake pool program does.
+    fn get_exchange_rate(&self) -> MarginfiResult<I80F48> {
+        if self.pool_token_supply == 0 {
+            return Ok(I80F48::ONE);
+        }
+
+        Ok(I80F48::from_num(self.total_lamports)
+            .checked_div(I80F48::from_num(self.pool_token_supply))
+            .ok_or_else(math_error!())?)
+    }
+}
+
+/// Fields of the Marinade `State` account needed for the mSOL exchange rate.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+struct LiteMarinadeState {
+    pub msol_mint: Pubkey,
+    /// Lamports per mSOL, scaled by 2^32.
+    pub msol_price: u64,
+}
+
+impl LiteMarinadeState {
+    const DISCRIMINATOR: [u8; 8] = [216, 146, 107, 94, 104, 75, 182, 177];
+    const MSOL_MINT_OFFSET: usize = 8;
+    const MSOL_PRICE_OFFSET: usize = 8 + 504;
+    const PRICE_DENOMINATOR: I80F48 = I80F48!(4294967296);
+
+    fn try_from_bytes(data: &[u8]) -> MarginfiResult<Self> {
+        check!(
+            data.len() >= Self::MSOL_PRICE_OFFSET + 8 && data[..8] == Self::DISCRIMINATOR,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        Ok(Self {
+            msol_mint: read_pubkey(data, Self::MSOL_MINT_OFFSET),
+            msol_price: read_u64(data, Self::MSOL_PRICE_OFFSET),
+        })
+    }
+
+    fn get_exchange_rate(&self) -> MarginfiResult<I80F48> {
+        check!(self.msol_price > 0, MarginfiError::InvalidOracleAccount);
+
+        Ok(I80F48::from_num(self.msol_price)
+            .checked_div(Self::PRICE_DENOMINATOR)
+            .ok_or_else(math_error!())?)
+    }
+}
+
+/// Scale of the switchboard on-demand fixed point values.
+const SWITCHBOARD_PULL_SCALE: usize = 18;
+
+/// Convert a switchboard on-demand value, splitting off the integer part first so large prices
+/// don't overflow.
+#[inline(always)]
+fn switchboard_pull_value_to_i80f48(value: i128) -> Option<I80F48> {
+    let scale = EXP_10[SWITCHBOARD_PULL_SCALE];
+    let integer = I80F48::checked_from_num(value.checked_div(scale)?)?;
+    let fraction = I80F48::checked_from_num(value.checked_rem(scale)?)?
+        .checked_div(EXP_10_I80F48[SWITCHBOARD_PULL_SCALE])?;
+
+    integer.checked_add(fraction)
+}
+
+/// Pyth confidence interval widened to the 95% range, capped at the bank maximum share of the
+/// price.
+fn pyth_price_confidence_interval(
+    price: &Price,
+    config: &OracleConfidenceConfig,
+) -> MarginfiResult<I80F48> {
+    let conf_interval = pyth_price_components_to_i80f48(I80F48::from_num(price.conf), price.expo)?
+        .checked_mul(config.interval_multiple.unwrap_or(CONF_INTERVAL_MULTIPLE))
+        .ok_or_else(math_error!())?;
+
+    let price = pyth_price_components_to_i80f48(I80F48::from_num(price.price), price.expo)?;
+
+    cap_confidence_interval(conf_interval, price, config)
+}
+
+/// Cap a confidence interval at `max_confidence` of the price, or reject the price if the bank
+/// policy says so.
+fn cap_confidence_interval(
+    conf_interval: I80F48,
+    price: I80F48,
+    config: &OracleConfidenceConfig,
+) -> MarginfiResult<I80F48> {
+    let max_conf_interval = price
+        .checked_mul(config.max_confidence)
+        .ok_or_else(math_error!())?;
+
+    assert!(
+        max_conf_interval >= I80F48::ZERO,
+        "Negative max confidence interval"
+    );
+
+    assert!(
+        conf_interval >= I80F48::ZERO,
+        "Negative confidence interval"
+    );
+
+    if matches!(config.policy, OracleConfidencePolicy::Reject) {
+        check!(
+            conf_interval <= max_conf_interval,
+            MarginfiError::OracleConfidenceTooWide
+        );
+    }
+
+    Ok(min(conf_interval, max_conf_interval))
+}
+
 #[inline(always)]
 fn pyth_price_components_to_i80f48(price: I80F48, exponent: i32) -> MarginfiResult<I80F48> {
     let scaling_factor = EXP_10_I80F48[exponent.unsigned_abs() as usize];
@@ -464,6 +1653,31 @@ mod tests {
     use rust_decimal::Decimal;
 
     use super::*;
+
+    #[test]
+    fn aggregated_price_feed_checks() {
+        let sources = |prices: &[I80F48]| {
+            prices
+                .iter()
+                .map(|price| OraclePriceFeedAdapter::Fixed(FixedPriceFeed::new(*price)))
+                .collect::<Vec<_>>()
+        };
+
+        let feed = AggregatedPriceFeed::try_new(sources(&[I80F48!(100), I80F48!(100.9)]), 100);
+        assert!(feed.is_ok());
+
+        // A single fresh source can't be cross checked
+        let feed = AggregatedPriceFeed::try_new(sources(&[I80F48!(100)]), 100);
+        assert!(matches!(feed, Err(err) if err == MarginfiError::StaleOracle.into()));
+
+        // Each source is within 1% of the median, but the outer ones are 1.8% apart
+        let feed = AggregatedPriceFeed::try_new(
+            sources(&[I80F48!(101.8), I80F48!(100), I80F48!(100.9)]),
+            100,
+        );
+        assert!(matches!(feed, Err(err) if err == MarginfiError::OracleDeviationExceeded.into()));
+    }
+
     #[test]
     fn swb_decimal_test_18() {
         let decimal = SwitchboardDecimal {
@@ -514,6 +1728,7 @@ mod tests {
         let pyth_adapter = PythEmaPriceFeed {
             ema_price: high_confidence_price,
             price: low_confidence_price,
+            confidence: OracleConfidenceConfig::default(),
         };
 
         // Test confidence interval when using EMA price (high confidence)
@@ -539,6 +1754,7 @@ mod tests {
                 latest_confirmed_round_std_deviation: SwitchboardDecimal::from_f64(10.0),
                 min_oracle_results: 1,
             }),
+            confidence: OracleConfidenceConfig::default(),
         };
 
         let swb_adapter_low_confidence = SwitchboardV2PriceFeed {
@@ -549,6 +1765,7 @@ mod tests {
                 latest_confirmed_round_std_deviation: SwitchboardDecimal::from_f64(1.0),
                 min_oracle_results: 1,
             }),
+            confidence: OracleConfidenceConfig::default(),
         };
 
         // Test confidence interval
@@ -566,4 +1783,330 @@ mod tests {
 
         assert_eq!(low_conf_interval, I80F48!(1.96));
     }
+
+    #[test]
+    fn pyth_push_conf_interval_cap() {
+        let price = |conf: u64| {
+            Box::new(Price {
+                price: 100i64 * EXP_10[6] as i64,
+                conf: conf * EXP_10[6] as u64,
+                expo: -6,
+                publish_time: 0,
+            })
+        };
+
+        let pyth_push_adapter = PythPushOraclePriceFeed {
+            ema_price: price(10),
+            price: price(1),
+            confidence: OracleConfidenceConfig::default(),
+        };
+
+        // The confidence interval should be capped at 5%
+        let high_conf_interval = pyth_push_adapter.get_confidence_interval(true).unwrap();
+        assert_eq!(high_conf_interval, I80F48!(5.00000000000007));
+
+        let low_conf_interval = pyth_push_adapter.get_confidence_interval(false).unwrap();
+        assert_eq!(low_conf_interval, I80F48!(2.12));
+    }
+
+    #[test]
+    fn switchboard_pull_value_conversion() {
+        let value = 123_500_000_000_000_000_000i128;
+        assert_eq!(
+            switchboard_pull_value_to_i80f48(value).unwrap(),
+            I80F48!(123.5)
+        );
+
+        // 10^25 overflows I80F48 before scaling
+        let value = 10_000_000 * EXP_10[18];
+        assert_eq!(
+            switchboard_pull_value_to_i80f48(value).unwrap(),
+            I80F48!(10_000_000)
+        );
+    }
+
+    #[test]
+    fn switchboard_pull_conf_interval_cap() {
+        let mut data = vec![0u8; LitePullFeedAccountData::LEN];
+        data[..8].copy_from_slice(&LitePullFeedAccountData::DISCRIMINATOR);
+
+        let offset = LitePullFeedAccountData::RESULT_VALUE_OFFSET;
+        data[offset..offset + 16].copy_from_slice(&(100 * EXP_10[18]).to_le_bytes());
+        let offset = LitePullFeedAccountData::RESULT_STD_DEV_OFFSET;
+        data[offset..offset + 16].copy_from_slice(&(10 * EXP_10[18]).to_le_bytes());
+        data[LitePullFeedAccountData::RESULT_NUM_SAMPLES_OFFSET] = 1;
+        data[LitePullFeedAccountData::MIN_SAMPLE_SIZE_OFFSET] = 1;
+
+        let swb_pull_adapter = SwitchboardPullPriceFeed {
+            feed: Box::new(LitePullFeedAccountData::try_from_bytes(&data).unwrap()),
+            confidence: OracleConfidenceConfig::default(),
+        };
+
+        assert_eq!(swb_pull_adapter.get_price().unwrap(), I80F48!(100));
+        // The confidence interval should be capped at 5%
+        assert_eq!(
+            swb_pull_adapter.get_confidence_interval().unwrap(),
+            I80F48!(5.00000000000007)
+        );
+
+        // Not enough samples
+        data[LitePullFeedAccountData::MIN_SAMPLE_SIZE_OFFSET] = 2;
+        let swb_pull_adapter = SwitchboardPullPriceFeed {
+            feed: Box::new(LitePullFeedAccountData::try_from_bytes(&data).unwrap()),
+            confidence: OracleConfidenceConfig::default(),
+        };
+        assert!(swb_pull_adapter.get_price().is_err());
+    }
+
+    fn spl_stake_pool_data(total_lamports: u64, pool_token_supply: u64) -> Vec<u8> {
+        let mut data = vec![0u8; 611];
+        data[0] = LiteStakePool::ACCOUNT_TYPE_STAKE_POOL;
+
+        let offset = LiteStakePool::TOTAL_LAMPORTS_OFFSET;
+        data[offset..offset + 8].copy_from_slice(&total_lamports.to_le_bytes());
+        let offset = LiteStakePool::POOL_TOKEN_SUPPLY_OFFSET;
+        data[offset..offset + 8].copy_from_slice(&pool_token_supply.to_le_bytes());
+
+        data
+    }
+
+    #[test]
+    fn spl_stake_pool_exchange_rate() {
+        let data = spl_stake_pool_data(1_100 * EXP_10[9] as u64, 1_000 * EXP_10[9] as u64);
+        let rate = LiteStakePool::try_from_bytes(&data)
+            .unwrap()
+            .get_exchange_rate()
+            .unwrap();
+        assert_eq!(rate, I80F48!(1_100) / I80F48!(1_000));
+
+        // An empty pool trades 1:1
+        let data = spl_stake_pool_data(0, 0);
+        let rate = LiteStakePool::try_from_bytes(&data)
+            .unwrap()
+            .get_exchange_rate()
+            .unwrap();
+        assert_eq!(rate, I80F48::ONE);
+
+        // Not a stake pool account, e.g. a validator list
+        let mut data = spl_stake_pool_data(1, 1);
+        data[0] = 2;
+        assert!(LiteStakePool::try_from_bytes(&data).is_err());
+    }
+
+    #[test]
+    fn stake_pool_mint_must_match_bank_mint() {
+        let pool_mint = Pubkey::new_unique();
+        let mut data = spl_stake_pool_data(1, 1);
+        let offset = LiteStakePool::POOL_MINT_OFFSET;
+        data[offset..offset + 32].copy_from_slice(pool_mint.as_ref());
+
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let ai = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &SPL_STAKE_POOL_ID,
+            false,
+            0,
+        );
+
+        assert!(StakePoolPriceFeed::check_ais(&ai, &pool_mint).is_ok());
+        assert!(StakePoolPriceFeed::check_ais(&ai, &Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn pyth_push_feed_id_must_match_bank_config() {
+        let feed_id = [7u8; 32];
+
+        let mut data = LitePriceUpdateV2::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        // VerificationLevel::Full
+        data.push(1);
+        data.extend_from_slice(&feed_id);
+        data.extend_from_slice(&(100i64 * EXP_10[6] as i64).to_le_bytes());
+        data.extend_from_slice(&(EXP_10[6] as u64).to_le_bytes());
+        data.extend_from_slice(&(-6i32).to_le_bytes());
+        data.extend_from_slice(&0i64.to_le_bytes());
+        data.extend_from_slice(&0i64.to_le_bytes());
+        data.extend_from_slice(&(100i64 * EXP_10[6] as i64).to_le_bytes());
+        data.extend_from_slice(&(EXP_10[6] as u64).to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let ai = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &PYTH_PUSH_ORACLE_ID,
+            false,
+            0,
+        );
+
+        assert!(PythPushOraclePriceFeed::check_ais(&ai, &feed_id).is_ok());
+        assert!(PythPushOraclePriceFeed::load_checked(&ai, &feed_id, 0, 60).is_ok());
+
+        // An update of another feed, e.g. a cheaper asset, is rejected
+        let other_feed_id = [8u8; 32];
+        assert!(PythPushOraclePriceFeed::check_ais(&ai, &other_feed_id).is_err());
+        assert!(PythPushOraclePriceFeed::load_checked(&ai, &other_feed_id, 0, 60).is_err());
+    }
+
+    #[test]
+    fn marinade_exchange_rate() {
+        let mut data = vec![0u8; 8 + 992];
+        data[..8].copy_from_slice(&LiteMarinadeState::DISCRIMINATOR);
+
+        // 1.25 SOL per mSOL
+        let msol_price = 5u64 << 30;
+        let offset = LiteMarinadeState::MSOL_PRICE_OFFSET;
+        data[offset..offset + 8].copy_from_slice(&msol_price.to_le_bytes());
+
+        let rate = LiteMarinadeState::try_from_bytes(&data)
+            .unwrap()
+            .get_exchange_rate()
+            .unwrap();
+        assert_eq!(rate, I80F48!(1.25));
+
+        data[..8].copy_from_slice(&LitePullFeedAccountData::DISCRIMINATOR);
+        assert!(LiteMarinadeState::try_from_bytes(&data).is_err());
+    }
+
+    #[test]
+    fn stake_pool_price_bias() {
+        let sol_price = Box::new(Price {
+            price: 100i64 * EXP_10[6] as i64,
+            conf: EXP_10[6] as u64,
+            expo: -6,
+            publish_time: 0,
+        });
+        let sol_feed = OraclePriceFeedAdapter::PythEma(PythEmaPriceFeed {
+            ema_price: sol_price.clone(),
+            price: sol_price,
+            confidence: OracleConfidenceConfig::default(),
+        });
+
+        let stake_pool_adapter = StakePoolPriceFeed::new(sol_feed, I80F48!(1.25));
+
+        let price = |bias| {
+            stake_pool_adapter
+                .get_price_of_type(OraclePriceType::RealTime, bias)
+                .unwrap()
+        };
+
+        assert_eq!(price(None), I80F48!(125));
+        // The 2.12 SOL confidence interval scales with the exchange rate
+        assert_eq!(
+            price(Some(PriceBias::Low)),
+            (I80F48!(100) - I80F48!(2.12)) * I80F48!(1.25)
+        );
+        assert_eq!(
+            price(Some(PriceBias::High)),
+            (I80F48!(100) + I80F48!(2.12)) * I80F48!(1.25)
+        );
+    }
+
+    #[test]
+    fn pegged_price_clamp() {
+        let feed = |price: i64| {
+            let price = Box::new(Price {
+                price,
+                conf: EXP_10[4] as u64,
+                expo: -6,
+                publish_time: 0,
+            });
+            OraclePriceFeedAdapter::PythEma(PythEmaPriceFeed {
+                ema_price: price.clone(),
+                price,
+                confidence: OracleConfidenceConfig::default(),
+            })
+        };
+
+        let feed_price = |price: i64| {
+            feed(price)
+                .get_price_of_type(OraclePriceType::RealTime, None)
+                .unwrap()
+        };
+        let band = I80F48!(100) / BPS_DENOMINATOR;
+
+        // 0.999 with a 1% band, the biased prices are held within 0.99..=1.01
+        let pegged_adapter = PeggedPriceFeed::try_new(feed(999_000), I80F48!(1), 100).unwrap();
+        assert!(!pegged_adapter.is_depegged());
+
+        let price = |bias| {
+            pegged_adapter
+                .get_price_of_type(OraclePriceType::RealTime, bias)
+                .unwrap()
+        };
+        assert_eq!(price(None), feed_price(999_000));
+        assert_eq!(price(Some(PriceBias::Low)), I80F48!(1) - band);
+        assert_eq!(price(Some(PriceBias::High)), I80F48!(1) + band);
+
+        // 0.95 is off the peg, the feed price is used as is
+        let pegged_adapter = PeggedPriceFeed::try_new(feed(950_000), I80F48!(1), 100).unwrap();
+        assert!(pegged_adapter.is_depegged());
+        assert_eq!(
+            pegged_adapter
+                .get_price_of_type(OraclePriceType::RealTime, None)
+                .unwrap(),
+            feed_price(950_000)
+        );
+    }
+
+    #[test]
+    fn bank_conf_interval_policy() {
+        // 2.12% confidence interval with the default multiple
+        let sol_price = Box::new(Price {
+            price: 100i64 * EXP_10[6] as i64,
+            conf: EXP_10[6] as u64,
+            expo: -6,
+            publish_time: 0,
+        });
+        let mut pyth_adapter = OraclePriceFeedAdapter::PythEma(PythEmaPriceFeed {
+            ema_price: sol_price.clone(),
+            price: sol_price,
+            confidence: OracleConfidenceConfig::default(),
+        });
+
+        let price = |adapter: &OraclePriceFeedAdapter| {
+            adapter.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))
+        };
+        assert_eq!(price(&pyth_adapter).unwrap(), I80F48!(100) - I80F48!(2.12));
+
+        // Capped at 1% of the price
+        let mut config = OracleConfidenceConfig {
+            max_confidence: I80F48!(0.01),
+            interval_multiple: None,
+            policy: OracleConfidencePolicy::Clamp,
+        };
+        pyth_adapter.set_confidence_config(config);
+        assert_eq!(
+            price(&pyth_adapter).unwrap(),
+            I80F48!(100) - I80F48!(100) * I80F48!(0.01)
+        );
+
+        // Rejected when wider than 1% of the price
+        config.policy = OracleConfidencePolicy::Reject;
+        pyth_adapter.set_confidence_config(config);
+        assert_eq!(
+            price(&pyth_adapter).unwrap_err(),
+            MarginfiError::OracleConfidenceTooWide.into()
+        );
+        assert_eq!(
+            pyth_adapter
+                .get_price_of_type(OraclePriceType::RealTime, None)
+                .unwrap(),
+            I80F48!(100)
+        );
+
+        // A 0.5x multiple brings the interval back under the cap
+        config.interval_multiple = Some(I80F48!(0.5));
+        pyth_adapter.set_confidence_config(config);
+        assert_eq!(price(&pyth_adapter).unwrap(), I80F48!(100) - I80F48!(0.5));
+    }
 }
@@ -1,5 +1,19 @@
-use crate::{bank_authority_seed, bank_seed, state::marginfi_group::BankVaultType};
-use anchor_lang::prelude::Pubkey;
+use crate::{
+    bank_authority_seed, bank_seed, check, math_error,
+    prelude::{MarginfiError, MarginfiResult},
+    state::marginfi_group::{Bank, BankVaultType},
+};
+use anchor_lang::{prelude::*, solana_program::program_pack::Pack, system_program};
+use anchor_spl::{
+    token_2022::spl_token_2022::{
+        extension::{
+            transfer_fee::{TransferFee, TransferFeeConfig, MAX_FEE_BASIS_POINTS},
+            BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        },
+        state::{Account, Mint},
+    },
+    token_interface,
+};
 use fixed::types::I80F48;
 
 pub fn find_bank_vault_pda(bank_pk: &Pubkey, vault_type: BankVaultType) -> (Pubkey, u8) {
@@ -10,6 +24,284 @@ pub fn find_bank_vault_authority_pda(bank_pk: &Pubkey, vault_type: BankVaultType
     Pubkey::find_program_address(bank_authority_seed!(vault_type, bank_pk), &crate::id())
 }
 
+/// `ExtensionType::TransferHook`, newer than the token-2022 version the program is built with.
+const TRANSFER_HOOK_EXTENSION_TYPE: u16 = 14;
+
+/// Token22 extensions that break the vault accounting assumptions, banks can't be created for
+/// mints using them.
+const UNSUPPORTED_MINT_EXTENSIONS: [u16; 3] = [
+    // Lets a third party move tokens out of the bank vaults
+    ExtensionType::PermanentDelegate as u16,
+    // Tokens can't be moved in and out of the vaults
+    ExtensionType::NonTransferable as u16,
+    // Transfers call into a program of the mint authority's choosing, with extra accounts the
+    // vault transfers don't pass
+    TRANSFER_HOOK_EXTENSION_TYPE,
+];
+
+/// Token22 banks need the bank mint for `transfer_checked`,
+/// it is expected as the first remaining account.
+///
+/// Returns `None` for banks using the legacy token program and leaves `remaining_accounts` as is.
+pub fn maybe_take_bank_mint<'info>(
+    remaining_accounts: &mut &[AccountInfo<'info>],
+    bank: &Bank,
+    token_program: &Pubkey,
+) -> MarginfiResult<Option<AccountInfo<'info>>> {
+    match *token_program {
+        anchor_spl::token::ID => Ok(None),
+        anchor_spl::token_2022::ID => {
+            let (maybe_mint, remaining) = remaining_accounts
+                .split_first()
+                .ok_or(MarginfiError::T22MintRequired)?;
+
+            check!(
+                maybe_mint.key.eq(&bank.mint),
+                MarginfiError::T22MintRequired
+            );
+
+            *remaining_accounts = remaining;
+
+            Ok(Some(maybe_mint.clone()))
+        }
+        _ => Err(MarginfiError::InvalidTransfer.into()),
+    }
+}
+
+/// Rejects mints with extensions the program can't support safely.
+pub fn validate_bank_mint(mint_ai: &AccountInfo) -> MarginfiResult {
+    if mint_ai.owner.eq(&anchor_spl::token::ID) {
+        return Ok(());
+    }
+
+    let mint_data = mint_ai.try_borrow_data()?;
+    StateWithExtensions::<Mint>::unpack(&mint_data)?;
+
+    for extension in get_mint_extension_types(&mint_data)? {
+        check!(
+            !UNSUPPORTED_MINT_EXTENSIONS.contains(&extension),
+            MarginfiError::UnsupportedMintExtension
+        );
+    }
+
+    Ok(())
+}
+
+/// Extension types of a Token22 mint, read from the raw TLV entries following the account type
+/// byte, so extensions unknown to the token-2022 version the program is built with are listed too.
+fn get_mint_extension_types(mint_data: &[u8]) -> MarginfiResult<Vec<u16>> {
+    let mut extension_types = vec![];
+
+    if mint_data.len() <= Mint::LEN {
+        return Ok(extension_types);
+    }
+
+    let tlv_data = mint_data
+        .get(Account::LEN + 1..)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let mut offset = 0;
+
+    while offset + 4 <= tlv_data.len() {
+        let extension_type = u16::from_le_bytes([tlv_data[offset], tlv_data[offset + 1]]);
+        if extension_type == ExtensionType::Uninitialized as u16 {
+            break;
+        }
+
+        let length = u16::from_le_bytes([tlv_data[offset + 2], tlv_data[offset + 3]]);
+        extension_types.push(extension_type);
+        offset += 4 + length as usize;
+    }
+
+    Ok(extension_types)
+}
+
+/// Size of a token account for `mint_ai`, including the account extensions
+/// required by the Token22 extensions of the mint.
+fn get_token_account_len(mint_ai: &AccountInfo) -> MarginfiResult<usize> {
+    if mint_ai.owner.eq(&anchor_spl::token::ID) {
+        return Ok(Account::LEN);
+    }
+
+    let mint_data = mint_ai.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+    let account_extensions =
+        ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?);
+
+    Ok(ExtensionType::get_account_len::<Account>(
+        &account_extensions,
+    ))
+}
+
+/// Create and initialize a bank vault at its PDA.
+///
+/// Anchor's `init` constraint allocates token accounts without room for account extensions,
+/// which Token22 mints with e.g. a transfer fee require, so vaults are created manually.
+#[allow(clippy::too_many_arguments)]
+pub fn create_bank_vault<'info>(
+    vault_type: BankVaultType,
+    bank_pk: &Pubkey,
+    vault_bump: u8,
+    vault: &AccountInfo<'info>,
+    vault_authority: &AccountInfo<'info>,
+    bank_mint: &AccountInfo<'info>,
+    fee_payer: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &Rent,
+) -> MarginfiResult {
+    let space = get_token_account_len(bank_mint)?;
+    let required_lamports = rent.minimum_balance(space);
+    let bank_pk_bytes = bank_pk.to_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[vault_type.get_seed(), &bank_pk_bytes, &[vault_bump]]];
+
+    // Mirrors anchor's `init`, the vault address may have been funded before the bank was created
+    if vault.lamports() == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: fee_payer.clone(),
+                    to: vault.clone(),
+                },
+                signer_seeds,
+            ),
+            required_lamports,
+            space as u64,
+            token_program.key,
+        )?;
+    } else {
+        let missing_lamports = required_lamports.saturating_sub(vault.lamports());
+        if missing_lamports > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    system_program::Transfer {
+                        from: fee_payer.clone(),
+                        to: vault.clone(),
+                    },
+                ),
+                missing_lamports,
+            )?;
+        }
+
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Allocate {
+                    account_to_allocate: vault.clone(),
+                },
+                signer_seeds,
+            ),
+            space as u64,
+        )?;
+
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Assign {
+                    account_to_assign: vault.clone(),
+                },
+                signer_seeds,
+            ),
+            token_program.key,
+        )?;
+    }
+
+    token_interface::initialize_account3(CpiContext::new(
+        token_program.clone(),
+        token_interface::InitializeAccount3 {
+            account: vault.clone(),
+            mint: bank_mint.clone(),
+            authority: vault_authority.clone(),
+        },
+    ))?;
+
+    Ok(())
+}
+
+fn get_epoch_transfer_fee(
+    mint_ai: &AccountInfo,
+    epoch: u64,
+) -> MarginfiResult<Option<TransferFee>> {
+    let mint_data = mint_ai.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+
+    Ok(mint
+        .get_extension::<TransferFeeConfig>()
+        .ok()
+        .map(|transfer_fee_config| *transfer_fee_config.get_epoch_fee(epoch)))
+}
+
+/// Amount received by the destination when `amount` is sent,
+/// taking the Token22 transfer fee of the mint into account.
+pub fn calculate_post_fee_spl_deposit_amount(
+    mint_ai: &AccountInfo,
+    amount: u64,
+    epoch: u64,
+) -> MarginfiResult<u64> {
+    let fee = match get_epoch_transfer_fee(mint_ai, epoch)? {
+        Some(transfer_fee) => transfer_fee
+            .calculate_fee(amount)
+            .ok_or_else(math_error!())?,
+        None => 0,
+    };
+
+    Ok(amount.checked_sub(fee).ok_or_else(math_error!())?)
+}
+
+/// Amount that has to be sent for the destination to receive `amount`,
+/// taking the Token22 transfer fee of the mint into account.
+pub fn calculate_pre_fee_spl_deposit_amount(
+    mint_ai: &AccountInfo,
+    amount: u64,
+    epoch: u64,
+) -> MarginfiResult<u64> {
+    match get_epoch_transfer_fee(mint_ai, epoch)? {
+        Some(transfer_fee) => {
+            Ok(calculate_pre_fee_amount(&transfer_fee, amount).ok_or_else(math_error!())?)
+        }
+        None => Ok(amount),
+    }
+}
+
+fn calculate_pre_fee_amount(transfer_fee: &TransferFee, amount: u64) -> Option<u64> {
+    let fee_bps = u16::from(transfer_fee.transfer_fee_basis_points);
+    let max_fee = u64::from(transfer_fee.maximum_fee);
+
+    if fee_bps == 0 || amount == 0 {
+        return Some(amount);
+    }
+
+    let with_max_fee = amount.checked_add(max_fee);
+
+    if fee_bps >= MAX_FEE_BASIS_POINTS {
+        return with_max_fee;
+    }
+
+    // amount / (1 - fee_bps / 10_000), rounded up
+    let denominator = (MAX_FEE_BASIS_POINTS - fee_bps) as u128;
+    let pre_fee_amount = (amount as u128)
+        .checked_mul(MAX_FEE_BASIS_POINTS as u128)?
+        .checked_add(denominator - 1)?
+        / denominator;
+    let pre_fee_amount = u64::try_from(pre_fee_amount).ok()?;
+
+    if let Some(with_max_fee) = with_max_fee {
+        if pre_fee_amount >= with_max_fee {
+            return Some(with_max_fee);
+        }
+    }
+
+    // The fee itself is rounded up, cover a possible off by one
+    let fee = transfer_fee.calculate_fee(pre_fee_amount)?;
+
+    if pre_fee_amount - fee < amount {
+        pre_fee_amount.checked_add(1)
+    } else {
+        Some(pre_fee_amount)
+    }
+}
+
 pub trait NumTraitsWithTolerance<T> {
     fn is_zero_with_tolerance(&self, t: T) -> bool;
     fn is_positive_with_tolerance(&self, t: T) -> bool;
@@ -27,3 +319,106 @@ where
         self.gt(&t)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Token22 mint data with the given (extension type, length) TLV entries, zero filled.
+    fn mint_data_with_extensions(extensions: &[(u16, u16)]) -> Vec<u8> {
+        let mut data = vec![0u8; Account::LEN];
+        Mint::pack(
+            Mint {
+                decimals: 6,
+                is_initialized: true,
+                ..Default::default()
+            },
+            &mut data[..Mint::LEN],
+        )
+        .unwrap();
+
+        // AccountType::Mint
+        data.push(1);
+        for (extension_type, length) in extensions {
+            data.extend_from_slice(&extension_type.to_le_bytes());
+            data.extend_from_slice(&length.to_le_bytes());
+            data.extend(std::iter::repeat(0).take(*length as usize));
+        }
+
+        data
+    }
+
+    #[test]
+    fn transfer_hook_mint_unsupported() {
+        let validate = |mut data: Vec<u8>| {
+            let key = Pubkey::new_unique();
+            let mut lamports = 0;
+            let ai = AccountInfo::new(
+                &key,
+                false,
+                false,
+                &mut lamports,
+                &mut data,
+                &anchor_spl::token_2022::ID,
+                false,
+                0,
+            );
+
+            validate_bank_mint(&ai)
+        };
+
+        // Mint close authority
+        assert!(validate(mint_data_with_extensions(&[(3, 32)])).is_ok());
+
+        // Transfer hook authority and program id, after a mint close authority
+        assert_eq!(
+            validate(mint_data_with_extensions(&[
+                (3, 32),
+                (TRANSFER_HOOK_EXTENSION_TYPE, 64)
+            ])),
+            Err(MarginfiError::UnsupportedMintExtension.into())
+        );
+    }
+
+    fn transfer_fee(fee_bps: u16, max_fee: u64) -> TransferFee {
+        TransferFee {
+            epoch: 0.into(),
+            maximum_fee: max_fee.into(),
+            transfer_fee_basis_points: fee_bps.into(),
+        }
+    }
+
+    #[test]
+    fn pre_fee_amount_covers_transfer_fee() {
+        for (fee_bps, max_fee) in [
+            (0, 0),
+            (1, u64::MAX),
+            (50, 1_000),
+            (100, u64::MAX),
+            (9_999, 10),
+        ] {
+            let transfer_fee = transfer_fee(fee_bps, max_fee);
+
+            for amount in [1, 7, 100, 12_345, 1_000_000, 999_999_999_999] {
+                let pre_fee_amount = calculate_pre_fee_amount(&transfer_fee, amount).unwrap();
+                let received = pre_fee_amount - transfer_fee.calculate_fee(pre_fee_amount).unwrap();
+
+                assert!(received >= amount);
+                // Sending one token less must not be enough
+                let received =
+                    pre_fee_amount - 1 - transfer_fee.calculate_fee(pre_fee_amount - 1).unwrap();
+                assert!(received < amount);
+            }
+        }
+    }
+
+    #[test]
+    fn pre_fee_amount_capped_by_max_fee() {
+        let transfer_fee = transfer_fee(500, 10);
+
+        assert_eq!(
+            calculate_pre_fee_amount(&transfer_fee, 1_000_000),
+            Some(1_000_010)
+        );
+    }
+}
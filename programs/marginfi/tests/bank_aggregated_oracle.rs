@@ -0,0 +1,159 @@
+use fixtures::{
+    assert_custom_error,
+    test::{
+        BankMint, TestFixture, TestSettings, PYTH_SOL_EQUIVALENT_FEED, PYTH_SOL_FEED,
+        PYTH_USDC_FEED, SWITCHBOARD_SOL_FEED,
+    },
+};
+use marginfi::{
+    constants::MAX_ORACLE_KEYS,
+    prelude::MarginfiError,
+    state::{
+        marginfi_group::{BankConfigOpt, OracleConfig},
+        price::OracleSetup,
+    },
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+
+fn aggregated_oracle_config(sources: &[Pubkey]) -> OracleConfig {
+    let mut keys = [Pubkey::default(); MAX_ORACLE_KEYS];
+    keys[..sources.len()].copy_from_slice(sources);
+
+    OracleConfig {
+        setup: OracleSetup::Aggregated,
+        keys,
+        pyth_push_feed_id: [0; 32],
+    }
+}
+
+#[tokio::test]
+/// Borrowing against an aggregated oracle should price off the fresh sources only
+async fn bank_aggregated_oracle_ignores_stale_sources() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    sol_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(aggregated_oracle_config(&[
+                PYTH_SOL_FEED,
+                PYTH_SOL_EQUIVALENT_FEED,
+                SWITCHBOARD_SOL_FEED,
+            ])),
+            oracle_max_deviation_bps: Some(100),
+            ..Default::default()
+        })
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 1_000)
+        .await?;
+
+    // Fund SOL borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // Borrow SOL with all sources fresh
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await?;
+
+    // Only the SOL Pyth feed goes stale
+    test_f
+        .set_pyth_oracle_timestamp(PYTH_SOL_EQUIVALENT_FEED, 120)
+        .await;
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.advance_time(120).await;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await;
+
+    assert!(res.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+/// Borrowing against an aggregated oracle whose sources disagree should error
+async fn bank_aggregated_oracle_deviation_should_error() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    // A zero deviation band is rejected
+    let res = sol_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(aggregated_oracle_config(&[
+                PYTH_SOL_FEED,
+                PYTH_SOL_EQUIVALENT_FEED,
+            ])),
+            ..Default::default()
+        })
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidConfig);
+
+    // The USDC feed is an order of magnitude off the SOL feeds
+    sol_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(aggregated_oracle_config(&[
+                PYTH_SOL_FEED,
+                PYTH_SOL_EQUIVALENT_FEED,
+                PYTH_USDC_FEED,
+            ])),
+            oracle_max_deviation_bps: Some(100),
+            ..Default::default()
+        })
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let res = lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 1_000)
+        .await;
+
+    assert!(res.is_ok());
+
+    // Fund SOL borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // Borrow SOL
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::OracleDeviationExceeded);
+
+    Ok(())
+}
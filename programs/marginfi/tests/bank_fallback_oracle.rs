@@ -0,0 +1,228 @@
+use fixtures::{
+    assert_custom_error,
+    test::{
+        BankMint, TestFixture, TestSettings, PYTH_SOL_EQUIVALENT_FEED, PYTH_SOL_FEED,
+        PYTH_USDC_FEED,
+    },
+};
+use marginfi::{
+    constants::{FALLBACK_ORACLE_MAX_PRIMARY_STALENESS_SEC, MAX_ORACLE_KEYS},
+    prelude::MarginfiError,
+    state::{
+        marginfi_group::{BankConfigOpt, OracleConfig},
+        price::{OraclePriceSource, OracleSetup},
+    },
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+
+fn fallback_oracle_config(primary: Pubkey, fallback: Pubkey) -> OracleConfig {
+    let mut keys = [Pubkey::default(); MAX_ORACLE_KEYS];
+    keys[0] = primary;
+    keys[1] = fallback;
+
+    OracleConfig {
+        setup: OracleSetup::PythEma,
+        keys,
+        pyth_push_feed_id: [0; 32],
+    }
+}
+
+#[tokio::test]
+/// Borrowing against a bank with a stale primary oracle should price off the fallback oracle
+async fn bank_fallback_oracle_used_when_primary_stale() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    // A fallback without a deviation band is rejected
+    let res = sol_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(fallback_oracle_config(
+                PYTH_SOL_FEED,
+                PYTH_SOL_EQUIVALENT_FEED,
+            )),
+            ..Default::default()
+        })
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidConfig);
+
+    sol_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(fallback_oracle_config(
+                PYTH_SOL_FEED,
+                PYTH_SOL_EQUIVALENT_FEED,
+            )),
+            oracle_max_deviation_bps: Some(100),
+            ..Default::default()
+        })
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 1_000)
+        .await?;
+
+    // Fund SOL borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // Borrow SOL with the primary oracle fresh
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await?;
+
+    assert_eq!(
+        sol_bank.load().await.last_oracle_price_source,
+        OraclePriceSource::Primary
+    );
+
+    // Only the primary SOL oracle goes stale
+    test_f
+        .set_pyth_oracle_timestamp(PYTH_SOL_EQUIVALENT_FEED, 120)
+        .await;
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.advance_time(120).await;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await;
+
+    assert!(res.is_ok());
+
+    // The borrowed bank records the feed its price came from
+    assert_eq!(
+        sol_bank.load().await.last_oracle_price_source,
+        OraclePriceSource::Fallback
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+/// A fallback oracle that disagrees with the last primary price should not be used
+async fn bank_fallback_oracle_deviation_should_error() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    // The USDC feed is an order of magnitude off the SOL feed
+    sol_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(fallback_oracle_config(PYTH_SOL_FEED, PYTH_USDC_FEED)),
+            oracle_max_deviation_bps: Some(100),
+            ..Default::default()
+        })
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 1_000)
+        .await?;
+
+    // Fund SOL borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // The primary SOL oracle goes stale
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 120).await;
+    test_f.advance_time(120).await;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::OracleDeviationExceeded);
+
+    Ok(())
+}
+
+#[tokio::test]
+/// A fallback oracle can't be checked against a primary price that is too old, the bank price is
+/// stale then
+async fn bank_fallback_oracle_primary_too_old_should_error() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    sol_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(fallback_oracle_config(
+                PYTH_SOL_FEED,
+                PYTH_SOL_EQUIVALENT_FEED,
+            )),
+            oracle_max_deviation_bps: Some(100),
+            ..Default::default()
+        })
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 1_000)
+        .await?;
+
+    // Fund SOL borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // The primary SOL oracle is stale well past the bank max age
+    let stale_for = 2 * FALLBACK_ORACLE_MAX_PRIMARY_STALENESS_SEC as i64;
+    test_f
+        .set_pyth_oracle_timestamp(PYTH_SOL_EQUIVALENT_FEED, stale_for)
+        .await;
+    test_f
+        .set_pyth_oracle_timestamp(PYTH_USDC_FEED, stale_for)
+        .await;
+    test_f.advance_time(stale_for).await;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::StaleOracle);
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::{
+    assert_custom_error,
+    test::{BankMint, TestFixture, TestSettings, PYTH_SOL_FEED},
+};
+use marginfi::{
+    constants::MAX_ORACLE_KEYS,
+    prelude::MarginfiError,
+    state::{
+        marginfi_group::{BankConfigOpt, OracleConfig},
+        price::OracleSetup,
+    },
+};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+
+#[tokio::test]
+/// Borrowing against a fixed price bank should not need an oracle account
+async fn bank_fixed_oracle_borrow_without_oracle() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    let fixed_oracle = OracleConfig {
+        setup: OracleSetup::Fixed,
+        keys: [Pubkey::default(); MAX_ORACLE_KEYS],
+        pyth_push_feed_id: [0; 32],
+    };
+
+    // A fixed price bank needs a price
+    let res = usdc_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(fixed_oracle),
+            ..Default::default()
+        })
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidConfig);
+
+    usdc_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(fixed_oracle),
+            fixed_price: Some(I80F48!(1).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 1_000)
+        .await?;
+
+    // Fund SOL borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // Only the SOL feed is kept fresh, the USDC bank doesn't read any feed
+    test_f.set_pyth_oracle_timestamp(PYTH_SOL_FEED, 120).await;
+    test_f.advance_time(120).await;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await;
+
+    assert!(res.is_ok());
+
+    let usdc_bank_state = usdc_bank.load().await;
+    assert!(usdc_bank_state.config.get_oracle_keys().is_empty());
+    assert_eq!(I80F48::from(usdc_bank_state.fixed_price), I80F48!(1));
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::{
+    assert_custom_error,
+    test::{create_oracle_key_array, BankMint, TestFixture, TestSettings, PYTH_USDC_FEED},
+};
+use marginfi::{
+    prelude::MarginfiError,
+    state::{
+        marginfi_group::{BankConfigOpt, OracleConfig},
+        price::OracleSetup,
+    },
+};
+use solana_program_test::tokio;
+
+#[tokio::test]
+/// A price moving faster than the bank allows should block borrows and withdraws, not repays and
+/// deposits, until enough time passed for the move
+async fn bank_price_guard_blocks_risk_increasing_actions() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    // SOL may move by 1% per second
+    sol_bank
+        .update_config(BankConfigOpt {
+            oracle_max_price_change: Some(I80F48!(0.01).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 1_000)
+        .await?;
+
+    // Fund SOL borrower
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // The borrow records the SOL price as the reference
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 10)
+        .await?;
+
+    let sol_bank_state = sol_bank.load().await;
+    assert_eq!(I80F48::from(sol_bank_state.last_oracle_price), I80F48!(10));
+
+    // The SOL oracle suddenly reports 1
+    sol_bank
+        .update_config(BankConfigOpt {
+            oracle: Some(OracleConfig {
+                setup: OracleSetup::PythEma,
+                keys: create_oracle_key_array(PYTH_USDC_FEED),
+                pyth_push_feed_id: [0; 32],
+            }),
+            ..Default::default()
+        })
+        .await?;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 1)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::OraclePriceGuardTripped);
+
+    let res = borrower_mfi_account_f
+        .try_bank_withdraw(borrower_token_account_f_usdc.key, usdc_bank, 100, None)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::OraclePriceGuardTripped);
+
+    // Reducing risk still works
+    borrower_mfi_account_f
+        .try_bank_repay(borrower_token_account_f_sol.key, sol_bank, 5, None)
+        .await?;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 100)
+        .await?;
+
+    // After 100s a 90% move is within the guard
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, 100).await;
+    test_f.advance_time(100).await;
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 2)
+        .await?;
+
+    let sol_bank_state = sol_bank.load().await;
+    assert_eq!(I80F48::from(sol_bank_state.last_oracle_price), I80F48!(1));
+
+    Ok(())
+}
@@ -21,7 +21,7 @@ use marginfi::state::marginfi_account::{
 };
 use marginfi::state::{
     marginfi_account::MarginfiAccount,
-    marginfi_group::{Bank, BankConfig, BankConfigOpt, BankVaultType},
+    marginfi_group::{Bank, BankConfig, BankConfigOpt, BankVaultType, EmodeCategory},
 };
 use marginfi::{assert_eq_with_tolerance, prelude::*};
 use pretty_assertions::assert_eq;
@@ -542,6 +542,161 @@ async fn marginfi_account_repay_all_success() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn marginfi_account_token_22_transfer_fee_success() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![TestBankSetting {
+            mint: BankMint::SOL,
+            ..TestBankSetting::default()
+        }],
+        ..TestSettings::default()
+    }))
+    .await;
+
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+
+    // 1% transfer fee, uncapped
+    let t22_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[SupportedExtension::TransferFee {
+            basis_points: 100,
+            maximum_fee: u64::MAX,
+        }],
+    )
+    .await;
+    let t22_bank_f = test_f
+        .marginfi_group
+        .try_lending_pool_add_bank(&t22_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+
+    // Lender deposits 1_000, the bank is credited with the amount received by the vault
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_f = t22_mint_f.create_token_account_and_mint_to(1_000).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f.key, &t22_bank_f, 1_000)
+        .await?;
+
+    let liquidity_vault = t22_bank_f
+        .get_vault_token_account(BankVaultType::Liquidity)
+        .await;
+    assert_eq!(liquidity_vault.balance().await, native!(990, "USDC"));
+
+    // Borrower receives the borrowed amount minus the fee, owes the full amount
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_f_t22 = t22_mint_f.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_sol.key, sol_bank_f, 100)
+        .await?;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_t22.key, &t22_bank_f, 100)
+        .await?;
+
+    assert_eq!(
+        borrower_token_account_f_t22.balance().await,
+        native!(99, "USDC")
+    );
+
+    // Repaying 50 only pays down the amount received by the vault
+    borrower_mfi_account_f
+        .try_bank_repay(borrower_token_account_f_t22.key, &t22_bank_f, 50, None)
+        .await?;
+
+    assert_eq!(
+        borrower_token_account_f_t22.balance().await,
+        native!(49, "USDC")
+    );
+
+    let t22_bank = t22_bank_f.load().await;
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    let borrower_liability = t22_bank.get_liability_amount(
+        borrower_mfi_account
+            .lending_account
+            .get_balance(&t22_bank_f.key)
+            .unwrap()
+            .liability_shares
+            .into(),
+    )?;
+    assert_eq_noise!(
+        borrower_liability,
+        I80F48::from(native!(50.5, "USDC", f64)),
+        I80F48::from(native!(0.01, "USDC", f64))
+    );
+
+    // Lender withdraws 500 and receives the amount minus the fee
+    lender_mfi_account_f
+        .try_bank_withdraw(lender_token_account_f.key, &t22_bank_f, 500, None)
+        .await?;
+
+    assert_eq!(lender_token_account_f.balance().await, native!(495, "USDC"));
+
+    let t22_bank = t22_bank_f.load().await;
+    let lender_mfi_account = lender_mfi_account_f.load().await;
+    let lender_deposit = t22_bank.get_asset_amount(
+        lender_mfi_account
+            .lending_account
+            .get_balance(&t22_bank_f.key)
+            .unwrap()
+            .asset_shares
+            .into(),
+    )?;
+    assert_eq_noise!(
+        lender_deposit,
+        I80F48::from(native!(490, "USDC")),
+        I80F48::from(native!(0.01, "USDC", f64))
+    );
+
+    assert_eq!(liquidity_vault.balance().await, native!(439.5, "USDC", f64));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_token_22_deposit_failure_mint_required() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+
+    let t22_mint_f = MintFixture::new_token_22(test_f.context.clone(), None, None, &[]).await;
+    let t22_bank_f = test_f
+        .marginfi_group
+        .try_lending_pool_add_bank(&t22_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+
+    let marginfi_account_f = test_f.create_marginfi_account().await;
+    let token_account_f = t22_mint_f.create_token_account_and_mint_to(1_000).await;
+
+    let deposit_ix = marginfi_account_f
+        .make_bank_deposit_ix(token_account_f.key, &t22_bank_f, 1_000)
+        .await;
+
+    // Bank mint missing from the remaining accounts
+    let mut missing_mint_ix = deposit_ix.clone();
+    missing_mint_ix.accounts.pop();
+
+    // Another mint in place of the bank mint
+    let mut mismatched_mint_ix = deposit_ix;
+    mismatched_mint_ix.accounts.last_mut().unwrap().pubkey = test_f.usdc_mint.key;
+
+    for ix in [missing_mint_ix, mismatched_mint_ix] {
+        let res = {
+            let mut ctx = test_f.context.borrow_mut();
+            let tx = Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&ctx.payer.pubkey()),
+                &[&ctx.payer],
+                ctx.last_blockhash,
+            );
+
+            ctx.banks_client.process_transaction(tx).await
+        };
+
+        assert_custom_error!(res.unwrap_err(), MarginfiError::T22MintRequired);
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn marginfi_account_borrow_success() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
@@ -680,17 +835,799 @@ async fn marginfi_account_borrow_failure_not_enough_collateral() -> anyhow::Resu
 
     assert_custom_error!(res.unwrap_err(), MarginfiError::BadAccountHealth);
 
-    let res = borrower_mfi_account_f
-        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 100)
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_f_sol.key, sol_bank, 100)
+        .await;
+
+    assert!(res.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_borrow_failure_liability_value_limit() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    usdc_bank
+        .update_config(BankConfigOpt {
+            total_liability_value_limit: Some(1_000),
+            ..Default::default()
+        })
+        .await?;
+
+    let marginfi_account_f = test_f.create_marginfi_account().await;
+    let depositor_usdc_account = usdc_bank
+        .mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+    marginfi_account_f
+        .try_bank_deposit(depositor_usdc_account.key, usdc_bank, 10_000)
+        .await?;
+
+    let borrower = test_f.create_marginfi_account().await;
+    let borrower_sol_account = sol_bank.mint.create_token_account_and_mint_to(1_000).await;
+    let borrower_usdc_account = usdc_bank.mint.create_token_account_and_mint_to(0).await;
+    borrower
+        .try_bank_deposit(borrower_sol_account.key, sol_bank, 1_000)
+        .await?;
+
+    // Healthy, but $1,100 of liabilities is over the $1,000 limit
+    let res = borrower
+        .try_bank_borrow(borrower_usdc_account.key, usdc_bank, 1_100)
+        .await;
+
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::BankLiabilityValueCapacityExceeded
+    );
+
+    let res = borrower
+        .try_bank_borrow(borrower_usdc_account.key, usdc_bank, 900)
+        .await;
+
+    assert!(res.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_borrow_success_emode() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(0.5).into(),
+                    asset_weight_maint: I80F48!(0.6).into(),
+                    emode_category: 1,
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+            TestBankSetting {
+                mint: BankMint::SolEquivalent,
+                config: Some(BankConfig {
+                    emode_category: 1,
+                    ..*DEFAULT_SOL_EQUIVALENT_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        ..TestSettings::default()
+    }))
+    .await;
+
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol_eq.key, sol_eq_bank_f, 100)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    let borrower_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(0)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    // Category 1 is not configured yet
+    let res = borrower_mfi_account_f.try_set_emode(1).await;
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidEmodeCategory);
+
+    // $50 of weighted collateral can't back $80 of debt
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_sol_eq.key, sol_eq_bank_f, 8)
+        .await;
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BadAccountHealth);
+
+    test_f
+        .marginfi_group
+        .try_configure_emode_category(
+            1,
+            EmodeCategory {
+                asset_weight_init: I80F48!(0.9).into(),
+                asset_weight_maint: I80F48!(0.95).into(),
+                liability_weight_init: I80F48!(1).into(),
+                liability_weight_maint: I80F48!(1).into(),
+            },
+        )
+        .await?;
+
+    borrower_mfi_account_f.try_set_emode(1).await?;
+    assert_eq!(borrower_mfi_account_f.load().await.emode_category, 1);
+
+    // $90 of weighted collateral with the category weights
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_sol_eq.key, sol_eq_bank_f, 8)
+        .await?;
+
+    // Leaving e-mode would put the account below the initial requirement
+    let res = borrower_mfi_account_f.try_set_emode(0).await;
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BadAccountHealth);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_borrow_success_weight_override() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::USDC,
+                ..TestBankSetting::default()
+            },
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(0.5).into(),
+                    asset_weight_maint: I80F48!(0.6).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        ..TestSettings::default()
+    }))
+    .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 10)
+        .await?;
+
+    // $50 of weighted collateral can't back $80 of debt
+    let res = borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 80)
+        .await;
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BadAccountHealth);
+
+    test_f
+        .marginfi_group
+        .try_configure_weight_override(sol_bank_f, usdc_bank_f, I80F48!(0.9), I80F48!(0.95))
+        .await?;
+
+    let marginfi_group = test_f.marginfi_group.load().await;
+    let weight_override = marginfi_group
+        .get_weight_override(&sol_bank_f.key, &usdc_bank_f.key)
+        .unwrap();
+    assert_eq!(
+        I80F48::from(weight_override.asset_weight_init),
+        I80F48!(0.9)
+    );
+
+    // $90 of weighted collateral against USDC debt
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 80)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_liquidation_success() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::USDC,
+                ..TestBankSetting::default()
+            },
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    asset_weight_maint: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        group_config: Some(GroupConfig { admin: None }),
+    }))
+    .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+
+    // Borrower deposits 100 SOL worth of $1000
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    // Borrower borrows $999
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
+        .await?;
+
+    // Synthetically bring down the borrower account health by reducing the asset weights of the SOL bank
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.25).into()),
+            asset_weight_maint: Some(I80F48!(0.5).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    lender_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 1, usdc_bank_f)
+        .await?;
+
+    // Checks
+    let sol_bank: Bank = sol_bank_f.load().await;
+    let usdc_bank: Bank = usdc_bank_f.load().await;
+
+    let depositor_ma = lender_mfi_account_f.load().await;
+    let borrower_ma = borrower_mfi_account_f.load().await;
+
+    // Depositors should have 1 SOL
+    assert_eq!(
+        sol_bank
+            .get_asset_amount(depositor_ma.lending_account.balances[1].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(1, "SOL"))
+    );
+
+    // Depositors should have 1990.25 USDC
+    assert_eq_noise!(
+        usdc_bank
+            .get_asset_amount(depositor_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(1990.25, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    // Borrower should have 99 SOL
+    assert_eq!(
+        sol_bank
+            .get_asset_amount(borrower_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(99, "SOL"))
+    );
+
+    // Borrower should have 989.50 USDC
+    assert_eq_noise!(
+        usdc_bank
+            .get_liability_amount(
+                borrower_ma.lending_account.balances[1]
+                    .liability_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(989.50, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    // Check insurance fund fee
+    let insurance_fund_usdc = usdc_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+
+    assert_eq_noise!(
+        insurance_fund_usdc.balance().await as i64,
+        native!(0.25, "USDC", f64) as i64,
+        1
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_liquidation_with_tokens_success() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::USDC,
+                ..TestBankSetting::default()
+            },
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    asset_weight_maint: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        group_config: Some(GroupConfig { admin: None }),
+    }))
+    .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+
+    // Borrower deposits 100 SOL worth of $1000
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    // Borrower borrows $999
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
+        .await?;
+
+    // Synthetically bring down the borrower account health by reducing the asset weights of the SOL bank
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.25).into()),
+            asset_weight_maint: Some(I80F48!(0.5).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    // Liquidator without a marginfi account
+    let liquidator_token_account_usdc =
+        test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    let liquidator_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+
+    borrower_mfi_account_f
+        .try_liquidate_with_tokens(
+            sol_bank_f,
+            1,
+            usdc_bank_f,
+            liquidator_token_account_usdc.key,
+            liquidator_token_account_sol.key,
+        )
+        .await?;
+
+    // Checks
+    let sol_bank: Bank = sol_bank_f.load().await;
+    let usdc_bank: Bank = usdc_bank_f.load().await;
+
+    let borrower_ma = borrower_mfi_account_f.load().await;
+
+    // Liquidator receives 1 SOL
+    assert_eq!(
+        liquidator_token_account_sol.balance().await,
+        native!(1, "SOL")
+    );
+
+    // Liquidator pays 9.75 USDC
+    assert_eq_noise!(
+        liquidator_token_account_usdc.balance().await as i64,
+        native!(90.25, "USDC", f64) as i64,
+        1
+    );
+
+    // Borrower should have 99 SOL
+    assert_eq!(
+        sol_bank
+            .get_asset_amount(borrower_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(99, "SOL"))
+    );
+
+    // Borrower should have 989.50 USDC
+    assert_eq_noise!(
+        usdc_bank
+            .get_liability_amount(
+                borrower_ma.lending_account.balances[1]
+                    .liability_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(989.50, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    // Check insurance fund fee
+    let insurance_fund_usdc = usdc_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+
+    assert_eq_noise!(
+        insurance_fund_usdc.balance().await as i64,
+        native!(0.25, "USDC", f64) as i64,
+        1
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_liquidation_multiple_success() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::USDC,
+                ..TestBankSetting::default()
+            },
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    asset_weight_maint: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+            TestBankSetting {
+                mint: BankMint::SolEquivalent,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    asset_weight_maint: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_EQUIVALENT_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        group_config: Some(GroupConfig { admin: None }),
+    }))
+    .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+
+    // Borrower deposits 100 SOL and 100 SOL_EQ worth of $2000
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol_eq.key, sol_eq_bank_f, 100)
+        .await?;
+
+    // Borrower borrows $1999
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 1_999)
+        .await?;
+
+    // Synthetically bring down the borrower account health by reducing the asset weights of both collateral banks
+    for bank_f in [sol_bank_f, sol_eq_bank_f] {
+        bank_f
+            .update_config(BankConfigOpt {
+                asset_weight_init: Some(I80F48!(0.25).into()),
+                asset_weight_maint: Some(I80F48!(0.5).into()),
+                ..Default::default()
+            })
+            .await?;
+    }
+
+    lender_mfi_account_f
+        .try_liquidate_multiple(
+            &borrower_mfi_account_f,
+            vec![(sol_bank_f, 1.), (sol_eq_bank_f, 1.)],
+            usdc_bank_f,
+        )
+        .await?;
+
+    // Checks
+    let sol_bank: Bank = sol_bank_f.load().await;
+    let sol_eq_bank: Bank = sol_eq_bank_f.load().await;
+    let usdc_bank: Bank = usdc_bank_f.load().await;
+
+    let depositor_ma = lender_mfi_account_f.load().await;
+    let borrower_ma = borrower_mfi_account_f.load().await;
+
+    // Depositors should have 1 SOL and 1 SOL_EQ
+    assert_eq!(
+        sol_bank
+            .get_asset_amount(depositor_ma.lending_account.balances[1].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(1, "SOL"))
+    );
+    assert_eq!(
+        sol_eq_bank
+            .get_asset_amount(depositor_ma.lending_account.balances[2].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(1, "SOL_EQ"))
+    );
+
+    // Depositors should have 1980.50 USDC
+    assert_eq_noise!(
+        usdc_bank
+            .get_asset_amount(depositor_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(1980.5, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    // Borrower should have 99 SOL and 99 SOL_EQ
+    assert_eq!(
+        sol_bank
+            .get_asset_amount(borrower_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(99, "SOL"))
+    );
+    assert_eq!(
+        sol_eq_bank
+            .get_asset_amount(borrower_ma.lending_account.balances[1].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(99, "SOL_EQ"))
+    );
+
+    // Borrower should have 1980 USDC of debt
+    assert_eq_noise!(
+        usdc_bank
+            .get_liability_amount(
+                borrower_ma.lending_account.balances[2]
+                    .liability_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(1980, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    // Check insurance fund fee, charged on both seizures
+    let insurance_fund_usdc = usdc_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+
+    assert_eq_noise!(
+        insurance_fund_usdc.balance().await as i64,
+        native!(0.5, "USDC", f64) as i64,
+        1
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_liquidation_multiple_repeated_bank_fails() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::USDC,
+                ..TestBankSetting::default()
+            },
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    asset_weight_maint: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        group_config: Some(GroupConfig { admin: None }),
+    }))
+    .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
+        .await?;
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.25).into()),
+            asset_weight_maint: Some(I80F48!(0.5).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    let res = lender_mfi_account_f
+        .try_liquidate_multiple(
+            &borrower_mfi_account_f,
+            vec![(sol_bank_f, 1.), (sol_bank_f, 1.)],
+            usdc_bank_f,
+        )
+        .await;
+
+    assert_custom_error!(res.unwrap_err(), MarginfiError::IllegalLiquidation);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn marginfi_account_liquidation_success_custom_fees() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::USDC,
+                ..TestBankSetting::default()
+            },
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    asset_weight_maint: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        group_config: Some(GroupConfig { admin: None }),
+    }))
+    .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
+        .await?;
+
+    // The SOL collateral bank charges a 5% liquidator fee and a 1% insurance fee instead of the
+    // 2.5% defaults
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.25).into()),
+            asset_weight_maint: Some(I80F48!(0.5).into()),
+            liquidation_liquidator_fee: Some(I80F48!(0.05).into()),
+            liquidation_insurance_fee: Some(I80F48!(0.01).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    let sol_bank: Bank = sol_bank_f.load().await;
+    assert_eq!(
+        sol_bank.get_liquidation_base_liquidator_fee(),
+        I80F48!(0.05)
+    );
+    assert_eq!(sol_bank.get_liquidation_insurance_fee(), I80F48!(0.01));
+
+    lender_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 1, usdc_bank_f)
+        .await?;
+
+    let usdc_bank: Bank = usdc_bank_f.load().await;
+
+    let depositor_ma = lender_mfi_account_f.load().await;
+    let borrower_ma = borrower_mfi_account_f.load().await;
+
+    // Liquidatee loses the 1 SOL of collateral
+    assert_eq_noise!(
+        sol_bank
+            .get_asset_amount(borrower_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(99, "SOL")),
+        native!(0.00001, "SOL", f64)
+    );
+
+    // Liquidator pays $10 discounted by the 5% liquidator fee -> 1990.50 USDC left
+    assert_eq_noise!(
+        usdc_bank
+            .get_asset_amount(depositor_ma.lending_account.balances[0].asset_shares.into())
+            .unwrap(),
+        I80F48::from(native!(1990.5, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    // Liquidatee receives $10 discounted by both fees (6%) -> 989.60 USDC of debt left
+    assert_eq_noise!(
+        usdc_bank
+            .get_liability_amount(
+                borrower_ma.lending_account.balances[1]
+                    .liability_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(989.6, "USDC", f64)),
+        native!(0.00001, "USDC", f64)
+    );
+
+    let insurance_fund_usdc = usdc_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
         .await;
 
-    assert!(res.is_ok());
+    assert_eq_noise!(
+        insurance_fund_usdc.balance().await as i64,
+        native!(0.1, "USDC", f64) as i64,
+        1
+    );
 
     Ok(())
 }
 
 #[tokio::test]
-async fn marginfi_account_liquidation_success() -> anyhow::Result<()> {
+async fn marginfi_account_liquidation_dutch_auction_fee_and_close_factor() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings {
         banks: vec![
             TestBankSetting {
@@ -726,62 +1663,61 @@ async fn marginfi_account_liquidation_success() -> anyhow::Result<()> {
     let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
     let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
 
-    // Borrower deposits 100 SOL worth of $1000
     borrower_mfi_account_f
         .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
         .await?;
 
-    // Borrower borrows $999
     borrower_mfi_account_f
         .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
         .await?;
 
-    // Synthetically bring down the borrower account health by reducing the asset weights of the SOL bank
+    // The borrower ends up about 50% below maintenance, the liquidator fee grows to its 10% cap
     sol_bank_f
         .update_config(BankConfigOpt {
             asset_weight_init: Some(I80F48!(0.25).into()),
             asset_weight_maint: Some(I80F48!(0.5).into()),
+            liquidation_liquidator_fee: Some(I80F48!(0.05).into()),
+            liquidation_insurance_fee: Some(I80F48!(0.01).into()),
+            liquidation_max_liquidator_fee: Some(I80F48!(0.1).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    // At most 5% of the USDC debt may be repaid at once
+    usdc_bank_f
+        .update_config(BankConfigOpt {
+            liquidation_close_factor: Some(I80F48!(0.05).into()),
             ..Default::default()
         })
         .await?;
 
+    // $60 of SOL would repay $52.50 of the $999 debt, over the $49.95 close factor
+    let res = lender_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 6, usdc_bank_f)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::IllegalLiquidation);
+
     lender_mfi_account_f
-        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 1, usdc_bank_f)
+        .try_liquidate(&borrower_mfi_account_f, sol_bank_f, 5, usdc_bank_f)
         .await?;
 
-    // Checks
-    let sol_bank: Bank = sol_bank_f.load().await;
     let usdc_bank: Bank = usdc_bank_f.load().await;
 
     let depositor_ma = lender_mfi_account_f.load().await;
     let borrower_ma = borrower_mfi_account_f.load().await;
 
-    // Depositors should have 1 SOL
-    assert_eq!(
-        sol_bank
-            .get_asset_amount(depositor_ma.lending_account.balances[1].asset_shares.into())
-            .unwrap(),
-        I80F48::from(native!(1, "SOL"))
-    );
-
-    // Depositors should have 1990.25 USDC
+    // Liquidator pays $50 discounted by the 10% liquidator fee -> 1955 USDC left
     assert_eq_noise!(
         usdc_bank
             .get_asset_amount(depositor_ma.lending_account.balances[0].asset_shares.into())
             .unwrap(),
-        I80F48::from(native!(1990.25, "USDC", f64)),
+        I80F48::from(native!(1955, "USDC")),
         native!(0.00001, "USDC", f64)
     );
 
-    // Borrower should have 99 SOL
-    assert_eq!(
-        sol_bank
-            .get_asset_amount(borrower_ma.lending_account.balances[0].asset_shares.into())
-            .unwrap(),
-        I80F48::from(native!(99, "SOL"))
-    );
-
-    // Borrower should have 989.50 USDC
+    // Liquidatee receives $50 discounted by both fees (12.5%) -> 955.25 USDC of debt left
     assert_eq_noise!(
         usdc_bank
             .get_liability_amount(
@@ -790,21 +1726,10 @@ async fn marginfi_account_liquidation_success() -> anyhow::Result<()> {
                     .into()
             )
             .unwrap(),
-        I80F48::from(native!(989.50, "USDC", f64)),
+        I80F48::from(native!(955.25, "USDC", f64)),
         native!(0.00001, "USDC", f64)
     );
 
-    // Check insurance fund fee
-    let insurance_fund_usdc = usdc_bank_f
-        .get_vault_token_account(BankVaultType::Insurance)
-        .await;
-
-    assert_eq_noise!(
-        insurance_fund_usdc.balance().await as i64,
-        native!(0.25, "USDC", f64) as i64,
-        1
-    );
-
     Ok(())
 }
 
@@ -1747,6 +2672,174 @@ async fn emissions_test_2() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn receipt_mint_and_redeem_success() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+
+    usdc_bank_f.try_setup_receipt_mint().await?;
+
+    let receipt_mint = get_receipt_mint_address(usdc_bank_f.key).0;
+    assert_eq!(usdc_bank_f.load().await.receipt_mint, receipt_mint);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    let receipt_token_account_f =
+        TokenAccountFixture::new(test_f.context.clone(), &receipt_mint, &test_f.payer()).await;
+
+    lender_mfi_account_f
+        .try_mint_receipt(
+            usdc_bank_f,
+            receipt_token_account_f.key,
+            native!(400, "USDC"),
+        )
+        .await?;
+
+    // One receipt token per tokenized asset share, bank totals are unchanged
+    assert_eq!(
+        receipt_token_account_f.balance().await,
+        native!(400, "USDC")
+    );
+
+    let lender_ma = lender_mfi_account_f.load().await;
+    assert_eq!(
+        I80F48::from(lender_ma.lending_account.balances[0].asset_shares),
+        I80F48::from_num(native!(600, "USDC"))
+    );
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(usdc_bank.total_asset_shares),
+        I80F48::from_num(native!(1_000, "USDC"))
+    );
+
+    // Receipt holder redeems the shares into another account
+    let holder_mfi_account_f = test_f.create_marginfi_account().await;
+    holder_mfi_account_f
+        .try_redeem_receipt(
+            usdc_bank_f,
+            receipt_token_account_f.key,
+            native!(400, "USDC"),
+        )
+        .await?;
+
+    assert_eq!(receipt_token_account_f.balance().await, 0);
+
+    let holder_ma = holder_mfi_account_f.load().await;
+    assert_eq!(
+        holder_ma.lending_account.balances[0].bank_pk,
+        usdc_bank_f.key
+    );
+    assert_eq!(
+        I80F48::from(holder_ma.lending_account.balances[0].asset_shares),
+        I80F48::from_num(native!(400, "USDC"))
+    );
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(usdc_bank.total_asset_shares),
+        I80F48::from_num(native!(1_000, "USDC"))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn receipt_mint_all_closes_balance() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+
+    usdc_bank_f.try_setup_receipt_mint().await?;
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    let receipt_token_account_f = TokenAccountFixture::new(
+        test_f.context.clone(),
+        &get_receipt_mint_address(usdc_bank_f.key).0,
+        &test_f.payer(),
+    )
+    .await;
+
+    lender_mfi_account_f
+        .try_mint_receipt(
+            usdc_bank_f,
+            receipt_token_account_f.key,
+            native!(1_000, "USDC"),
+        )
+        .await?;
+
+    // Nothing is left in the balance, it is closed like a full withdrawal
+    let lender_ma = lender_mfi_account_f.load().await;
+    assert!(!lender_ma.lending_account.balances[0].active);
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(usdc_bank.total_asset_shares),
+        I80F48::from_num(native!(1_000, "USDC"))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn receipt_mint_failure_not_enough_shares() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+
+    usdc_bank_f.try_setup_receipt_mint().await?;
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    let receipt_token_account_f = TokenAccountFixture::new(
+        test_f.context.clone(),
+        &get_receipt_mint_address(usdc_bank_f.key).0,
+        &test_f.payer(),
+    )
+    .await;
+
+    let res = lender_mfi_account_f
+        .try_mint_receipt(
+            usdc_bank_f,
+            receipt_token_account_f.key,
+            native!(1_001, "USDC"),
+        )
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::IllegalBalanceState);
+
+    // Receipt mint can only be set up once
+    let res = usdc_bank_f.try_setup_receipt_mint().await;
+
+    assert!(res.is_err());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn account_flags() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
@@ -1790,6 +2883,7 @@ async fn account_flags() -> anyhow::Result<()> {
 // 7. Flashloan fails because of invalid `end_flashloan` ix order
 // 8. Flashloan fails because `end_flashloan` ix is for another account
 // 9. Flashloan fails because account is already in a flashloan
+// 10. Legacy `end_flashloan` ix only ends flashloans leaving no debt
 
 #[tokio::test]
 async fn flashloan_success_1op() -> anyhow::Result<()> {
@@ -1840,6 +2934,85 @@ async fn flashloan_success_1op() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+/// A borrow in a flashloan checks the bank liability value limit, loading only the borrowed bank's
+/// price
+async fn flashloan_borrow_liability_value_limit() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    // $5,000 of SOL liabilities at most
+    sol_bank
+        .update_config(BankConfigOpt {
+            total_liability_value_limit: Some(5_000),
+            ..Default::default()
+        })
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000)
+        .await?;
+
+    // The borrower has a USDC balance left out of the borrow remaining accounts
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    borrower_mfi_account_f
+        .try_set_flag(FLASHLOAN_ENABLED_FLAG)
+        .await?;
+
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    let usdc_oracle = usdc_bank.load().await.config.oracle_keys[0];
+
+    // $10,000 of SOL is over the limit, $4,000 is within
+    for (ui_amount, within_limit) in [(1_000., false), (400., true)] {
+        let mut borrow_ix = borrower_mfi_account_f
+            .make_bank_borrow_ix(borrower_token_account_f_sol.key, sol_bank, ui_amount)
+            .await;
+        borrow_ix
+            .accounts
+            .retain(|meta| meta.pubkey != usdc_bank.key && meta.pubkey != usdc_oracle);
+
+        let repay_ix = borrower_mfi_account_f
+            .make_bank_repay_ix(
+                borrower_token_account_f_sol.key,
+                sol_bank,
+                ui_amount,
+                Some(true),
+            )
+            .await;
+
+        let res = borrower_mfi_account_f
+            .try_flashloan(vec![borrow_ix, repay_ix], vec![], vec![])
+            .await;
+
+        if within_limit {
+            assert!(res.is_ok());
+        } else {
+            assert_custom_error!(
+                res.unwrap_err(),
+                MarginfiError::BankLiabilityValueCapacityExceeded
+            );
+        }
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn flashloan_success_3op() -> anyhow::Result<()> {
     // Setup test executor with non-admin payer
@@ -1938,6 +3111,71 @@ async fn flashloan_fail_account_health() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn flashloan_legacy_end_ix_requires_no_debt() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+
+    let borrow_ix = borrower_mfi_account_f
+        .make_bank_borrow_ix(borrower_token_account_f_sol.key, sol_bank, 1_000)
+        .await;
+    let repay_ix = borrower_mfi_account_f
+        .make_bank_repay_ix(
+            borrower_token_account_f_sol.key,
+            sol_bank,
+            1_000,
+            Some(true),
+        )
+        .await;
+
+    // Borrowing without repaying leaves debt, the group is needed for the health check. Repaid
+    // within the flashloan, the legacy instruction can end it.
+    for (mut ixs, leaves_debt) in [
+        (vec![borrow_ix.clone()], true),
+        (vec![borrow_ix, repay_ix], false),
+    ] {
+        let start_ix = borrower_mfi_account_f
+            .make_lending_account_start_flashloan_ix(ixs.len() as u64 + 1)
+            .await;
+        let end_ix = borrower_mfi_account_f.make_lending_account_end_flashloan_v1_ix();
+
+        ixs.insert(0, start_ix);
+        ixs.push(end_ix);
+
+        let mut ctx = test_f.context.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &ixs,
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        let res = ctx.banks_client.process_transaction(tx).await;
+
+        if leaves_debt {
+            assert_custom_error!(res.unwrap_err(), MarginfiError::IllegalFlashloan);
+        } else {
+            assert!(res.is_ok());
+        }
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 // Note: The flashloan flag is now deprecated
 async fn flashloan_ok_missing_flag() -> anyhow::Result<()> {
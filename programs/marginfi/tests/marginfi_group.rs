@@ -9,8 +9,13 @@ use fixtures::{assert_custom_error, assert_eq_noise, native, prelude::*};
 use marginfi::{
     constants::TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
     prelude::{GroupConfig, MarginfiError, MarginfiGroup},
-    state::marginfi_group::{
-        Bank, BankConfig, BankConfigOpt, BankOperationalState, BankVaultType, InterestRateConfig,
+    state::{
+        marginfi_account::DISABLED_FLAG,
+        marginfi_group::{
+            Bank, BankConfig, BankConfigOpt, BankOperationalState, BankVaultType,
+            InterestRateConfig,
+        },
+        price::OracleConfidencePolicy,
     },
 };
 use pretty_assertions::assert_eq;
@@ -110,6 +115,7 @@ async fn marginfi_group_config_check() -> anyhow::Result<()> {
                 insurance_ir_fee: Some(I80F48::from_num(0.11).into()),
                 protocol_fixed_fee_apr: Some(I80F48::from_num(0.51).into()),
                 protocol_ir_fee: Some(I80F48::from_num(0.011).into()),
+                ..Default::default()
             }),
             ..BankConfigOpt::default()
         })
@@ -231,6 +237,29 @@ async fn marginfi_group_add_bank_failure_fake_pyth_feed() -> anyhow::Result<()>
     Ok(())
 }
 
+#[tokio::test]
+async fn marginfi_group_add_bank_failure_unsupported_mint_extension() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+
+    for extension in [
+        SupportedExtension::PermanentDelegate,
+        SupportedExtension::NonTransferable,
+    ] {
+        let bank_asset_mint_fixture =
+            MintFixture::new_token_22(test_f.context.clone(), None, None, &[extension]).await;
+
+        let res = test_f
+            .marginfi_group
+            .try_lending_pool_add_bank(&bank_asset_mint_fixture, *DEFAULT_USDC_TEST_BANK_CONFIG)
+            .await;
+
+        assert!(res.is_err());
+        assert_custom_error!(res.unwrap_err(), MarginfiError::UnsupportedMintExtension);
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn marginfi_group_accrue_interest_rates_success_1() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings {
@@ -804,6 +833,136 @@ async fn marginfi_group_handle_bankruptcy_success_partially_insured() -> anyhow:
     Ok(())
 }
 
+#[tokio::test]
+async fn marginfi_group_handle_bankruptcy_all_success() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::USDC,
+                config: None,
+            },
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+            TestBankSetting {
+                mint: BankMint::SolEquivalent,
+                config: None,
+            },
+        ],
+        group_config: Some(GroupConfig { admin: None }),
+    }))
+    .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+    let sol_eq_bank_f = test_f.get_bank(&BankMint::SolEquivalent);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(100_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 100_000)
+        .await?;
+    let lender_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol_eq.key, sol_eq_bank_f, 1_000)
+        .await?;
+
+    let borrower_account = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_001)
+        .await;
+    borrower_account
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 1_001)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_account
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 5_000)
+        .await?;
+    let borrower_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(0)
+        .await;
+    borrower_account
+        .try_bank_borrow(borrower_token_account_sol_eq.key, sol_eq_bank_f, 100)
+        .await?;
+
+    let mut borrower_mfi_account = borrower_account.load().await;
+    borrower_mfi_account.lending_account.balances[0]
+        .asset_shares
+        .value = 0_i128.to_le_bytes();
+    borrower_account.set_account(&borrower_mfi_account).await?;
+
+    // USDC bad debt is fully insured, SOL_EQ bad debt is socialized
+    test_f
+        .usdc_mint
+        .mint_to(&usdc_bank_f.load().await.insurance_vault, 5_000)
+        .await;
+
+    test_f
+        .marginfi_group
+        .try_handle_bankruptcy_all(vec![usdc_bank_f, sol_eq_bank_f], &borrower_account)
+        .await?;
+
+    let borrower_mfi_account = borrower_account.load().await;
+
+    assert_eq!(
+        I80F48::from(borrower_mfi_account.lending_account.balances[1].liability_shares),
+        I80F48::ZERO
+    );
+    assert_eq!(
+        I80F48::from(borrower_mfi_account.lending_account.balances[2].liability_shares),
+        I80F48::ZERO
+    );
+    assert!(borrower_mfi_account.get_flag(DISABLED_FLAG));
+
+    let lender_mfi_account = lender_mfi_account_f.load().await;
+    let usdc_bank = usdc_bank_f.load().await;
+    let sol_eq_bank = sol_eq_bank_f.load().await;
+
+    let lender_usdc_value = usdc_bank.get_asset_amount(
+        lender_mfi_account.lending_account.balances[0]
+            .asset_shares
+            .into(),
+    )?;
+
+    assert_eq_noise!(
+        lender_usdc_value,
+        I80F48::from(native!(100_000, "USDC")),
+        I80F48::ONE
+    );
+
+    let lender_sol_eq_value = sol_eq_bank.get_asset_amount(
+        lender_mfi_account.lending_account.balances[1]
+            .asset_shares
+            .into(),
+    )?;
+
+    assert_eq_noise!(
+        lender_sol_eq_value,
+        I80F48::from(native!(900, "SOL_EQ")),
+        I80F48::ONE
+    );
+
+    let insurance_amount = usdc_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+
+    assert_eq!(insurance_amount.balance().await, 0);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn marginfi_group_handle_bankruptcy_success_not_insured() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings {
@@ -1291,6 +1450,71 @@ async fn marginfi_group_init_limit_0() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn marginfi_group_bank_oracle_confidence_config() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::USDC);
+    let sol_bank = test_f.get_bank(&BankMint::SOL);
+
+    // Negative confidence caps are rejected
+    let res = usdc_bank
+        .update_config(BankConfigOpt {
+            oracle_max_confidence: Some(I80F48!(-0.01).into()),
+            ..BankConfigOpt::default()
+        })
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidConfig);
+
+    usdc_bank
+        .update_config(BankConfigOpt {
+            oracle_max_confidence: Some(I80F48!(0.01).into()),
+            oracle_confidence_multiple: Some(I80F48!(3).into()),
+            oracle_confidence_policy: Some(OracleConfidencePolicy::Reject),
+            ..BankConfigOpt::default()
+        })
+        .await?;
+
+    let usdc_bank_state = usdc_bank.load().await;
+    assert_eq!(
+        I80F48::from(usdc_bank_state.oracle_max_confidence),
+        I80F48!(0.01)
+    );
+    assert_eq!(
+        I80F48::from(usdc_bank_state.oracle_confidence_multiple),
+        I80F48!(3)
+    );
+    assert_eq!(
+        usdc_bank_state.oracle_confidence_policy,
+        OracleConfidencePolicy::Reject
+    );
+
+    // The USDC feed is within the cap, the bank can still back a borrow
+    let lender = test_f.create_marginfi_account().await;
+    let lender_sol_account = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lender
+        .try_bank_deposit(lender_sol_account.key, sol_bank, 100)
+        .await?;
+
+    let borrower = test_f.create_marginfi_account().await;
+    let borrower_usdc_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    borrower
+        .try_bank_deposit(borrower_usdc_account.key, usdc_bank, 1_000)
+        .await?;
+
+    let borrower_sol_account = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    borrower
+        .try_bank_borrow(borrower_sol_account.key, sol_bank, 10)
+        .await?;
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn bank_field_values_reg() -> anyhow::Result<()> {
     let bank_fixtures_path = "tests/fixtures/bank";
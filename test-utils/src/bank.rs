@@ -1,13 +1,13 @@
 use super::utils::load_and_deserialize;
 use crate::prelude::{
-    get_emissions_authority_address, get_emissions_token_account_address, MintFixture,
-    TokenAccountFixture,
+    get_emissions_authority_address, get_emissions_token_account_address, get_receipt_mint_address,
+    get_receipt_mint_authority_address, MintFixture, TokenAccountFixture,
 };
 use anchor_lang::{
     prelude::{AccountMeta, Pubkey},
     InstructionData, ToAccountMetas,
 };
-use anchor_spl::token;
+use anchor_spl::{token, token_2022};
 use fixed::types::I80F48;
 use marginfi::{
     bank_authority_seed,
@@ -50,6 +50,16 @@ impl BankFixture {
         find_bank_vault_authority_pda(&self.key, vault_type)
     }
 
+    pub fn get_token_program(&self) -> Pubkey {
+        self.mint.token_program
+    }
+
+    /// Token22 banks expect the bank mint as the first remaining account.
+    pub fn maybe_mint_account_meta(&self) -> Option<AccountMeta> {
+        (self.mint.token_program == token_2022::ID)
+            .then(|| AccountMeta::new_readonly(self.mint.key, false))
+    }
+
     pub async fn load(&self) -> Bank {
         load_and_deserialize::<Bank>(self.ctx.clone(), &self.key).await
     }
@@ -67,6 +77,7 @@ impl BankFixture {
                 oracle_config
                     .keys
                     .iter()
+                    .filter(|k| **k != Pubkey::default())
                     .map(|k| AccountMeta::new_readonly(*k, false)),
             );
         }
@@ -212,6 +223,43 @@ impl BankFixture {
         Ok(())
     }
 
+    pub async fn try_setup_receipt_mint(&self) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingPoolSetupReceiptMint {
+                marginfi_group: self.load().await.group,
+                admin: self.ctx.borrow().payer.pubkey(),
+                bank: self.key,
+                receipt_mint_authority: get_receipt_mint_authority_address(self.key).0,
+                receipt_mint: get_receipt_mint_address(self.key).0,
+                rent: solana_program::sysvar::rent::id(),
+                token_program: anchor_spl::token::ID,
+                system_program: solana_program::system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingPoolSetupReceiptMint {}.data(),
+        };
+
+        let tx = {
+            let ctx = self.ctx.borrow_mut();
+
+            Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&ctx.payer.pubkey()),
+                &[&ctx.payer],
+                ctx.last_blockhash,
+            )
+        };
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn try_update_emissions(
         &self,
         emissions_flags: Option<u64>,
@@ -70,7 +70,7 @@ impl MarginfiAccountFixture {
         let marginfi_account = self.load().await;
         let ctx = self.ctx.borrow_mut();
 
-        Instruction {
+        let mut ix = Instruction {
             program_id: marginfi::id(),
             accounts: marginfi::accounts::LendingAccountDeposit {
                 marginfi_group: marginfi_account.group,
@@ -79,14 +79,18 @@ impl MarginfiAccountFixture {
                 bank: bank.key,
                 signer_token_account: funding_account,
                 bank_liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
-                token_program: token::ID,
+                token_program: bank.get_token_program(),
             }
             .to_account_metas(Some(true)),
             data: marginfi::instruction::LendingAccountDeposit {
                 amount: ui_to_native!(ui_amount.into(), bank.mint.mint.decimals),
             }
             .data(),
-        }
+        };
+
+        ix.accounts.extend(bank.maybe_mint_account_meta());
+
+        ix
     }
 
     pub async fn try_bank_deposit<T: Into<f64>>(
@@ -133,7 +137,7 @@ impl MarginfiAccountFixture {
                 bank_liquidity_vault_authority: bank
                     .get_vault_authority(BankVaultType::Liquidity)
                     .0,
-                token_program: token::ID,
+                token_program: bank.get_token_program(),
             }
             .to_account_metas(Some(true)),
             data: marginfi::instruction::LendingAccountWithdraw {
@@ -143,6 +147,8 @@ impl MarginfiAccountFixture {
             .data(),
         };
 
+        ix.accounts.extend(bank.maybe_mint_account_meta());
+
         let exclude_vec = match withdraw_all.unwrap_or(false) {
             true => vec![bank.key],
             false => vec![],
@@ -199,7 +205,7 @@ impl MarginfiAccountFixture {
                 bank_liquidity_vault_authority: bank
                     .get_vault_authority(BankVaultType::Liquidity)
                     .0,
-                token_program: token::ID,
+                token_program: bank.get_token_program(),
             }
             .to_account_metas(Some(true)),
             data: marginfi::instruction::LendingAccountBorrow {
@@ -208,6 +214,8 @@ impl MarginfiAccountFixture {
             .data(),
         };
 
+        ix.accounts.extend(bank.maybe_mint_account_meta());
+
         ix.accounts.extend_from_slice(
             &self
                 .load_observation_account_metas(vec![bank.key], vec![])
@@ -252,7 +260,7 @@ impl MarginfiAccountFixture {
         let marginfi_account = self.load().await;
         let ctx = self.ctx.borrow_mut();
 
-        Instruction {
+        let mut ix = Instruction {
             program_id: marginfi::id(),
             accounts: marginfi::accounts::LendingAccountRepay {
                 marginfi_group: marginfi_account.group,
@@ -261,7 +269,7 @@ impl MarginfiAccountFixture {
                 bank: bank.key,
                 signer_token_account: funding_account,
                 bank_liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
-                token_program: token::ID,
+                token_program: bank.get_token_program(),
             }
             .to_account_metas(Some(true)),
             data: marginfi::instruction::LendingAccountRepay {
@@ -269,7 +277,11 @@ impl MarginfiAccountFixture {
                 repay_all,
             }
             .data(),
-        }
+        };
+
+        ix.accounts.extend(bank.maybe_mint_account_meta());
+
+        ix
     }
 
     pub async fn try_bank_repay<T: Into<f64>>(
@@ -354,10 +366,14 @@ impl MarginfiAccountFixture {
         }
         .to_account_metas(Some(true));
 
-        accounts.extend(vec![
-            AccountMeta::new_readonly(asset_bank.config.oracle_keys[0], false),
-            AccountMeta::new_readonly(liab_bank.config.oracle_keys[0], false),
-        ]);
+        accounts.extend(
+            asset_bank
+                .config
+                .get_oracle_keys()
+                .iter()
+                .chain(liab_bank.config.get_oracle_keys())
+                .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+        );
 
         let mut ix = Instruction {
             program_id: marginfi::id(),
@@ -399,6 +415,166 @@ impl MarginfiAccountFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    pub async fn try_liquidate_multiple(
+        &self,
+        liquidatee: &MarginfiAccountFixture,
+        assets: Vec<(&BankFixture, f64)>,
+        liab_bank_fixture: &BankFixture,
+    ) -> std::result::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+
+        let liab_bank = liab_bank_fixture.load().await;
+
+        let mut accounts = marginfi::accounts::LendingAccountLiquidateMultiple {
+            marginfi_group: marginfi_account.group,
+            liab_bank: liab_bank_fixture.key,
+            liquidator_marginfi_account: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            liquidatee_marginfi_account: liquidatee.key,
+            bank_liquidity_vault_authority: liab_bank_fixture
+                .get_vault_authority(BankVaultType::Liquidity)
+                .0,
+            bank_liquidity_vault: liab_bank_fixture.get_vault(BankVaultType::Liquidity).0,
+            bank_insurance_vault: liab_bank_fixture.get_vault(BankVaultType::Insurance).0,
+            token_program: token::ID,
+        }
+        .to_account_metas(Some(true));
+
+        let mut asset_amounts = vec![];
+        let mut asset_bank_keys = vec![];
+
+        for (asset_bank_fixture, asset_ui_amount) in assets {
+            let asset_bank = asset_bank_fixture.load().await;
+
+            accounts.push(AccountMeta::new(asset_bank_fixture.key, false));
+            accounts.extend(
+                asset_bank
+                    .config
+                    .get_oracle_keys()
+                    .iter()
+                    .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+            );
+
+            asset_amounts.push(ui_to_native!(
+                asset_ui_amount,
+                asset_bank_fixture.mint.mint.decimals
+            ));
+            asset_bank_keys.push(asset_bank_fixture.key);
+        }
+
+        accounts.extend(
+            liab_bank
+                .config
+                .get_oracle_keys()
+                .iter()
+                .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+        );
+
+        asset_bank_keys.push(liab_bank_fixture.key);
+
+        accounts.extend_from_slice(
+            &self
+                .load_observation_account_metas(asset_bank_keys, vec![])
+                .await,
+        );
+
+        accounts.extend_from_slice(
+            &liquidatee
+                .load_observation_account_metas(vec![], vec![])
+                .await,
+        );
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountLiquidateMultiple { asset_amounts }.data(),
+        };
+
+        let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[compute_budget_ix, ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Liquidates this account on behalf of the payer, who has no marginfi account
+    pub async fn try_liquidate_with_tokens<T: Into<f64>>(
+        &self,
+        asset_bank_fixture: &BankFixture,
+        asset_ui_amount: T,
+        liab_bank_fixture: &BankFixture,
+        signer_liab_token_account: Pubkey,
+        destination_asset_token_account: Pubkey,
+    ) -> std::result::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+
+        let asset_bank = asset_bank_fixture.load().await;
+        let liab_bank = liab_bank_fixture.load().await;
+
+        let mut accounts = marginfi::accounts::LendingAccountLiquidateWithTokens {
+            marginfi_group: marginfi_account.group,
+            asset_bank: asset_bank_fixture.key,
+            liab_bank: liab_bank_fixture.key,
+            liquidatee_marginfi_account: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            signer_liab_token_account,
+            destination_asset_token_account,
+            asset_bank_liquidity_vault_authority: asset_bank_fixture
+                .get_vault_authority(BankVaultType::Liquidity)
+                .0,
+            asset_bank_liquidity_vault: asset_bank_fixture.get_vault(BankVaultType::Liquidity).0,
+            liab_bank_liquidity_vault_authority: liab_bank_fixture
+                .get_vault_authority(BankVaultType::Liquidity)
+                .0,
+            liab_bank_liquidity_vault: liab_bank_fixture.get_vault(BankVaultType::Liquidity).0,
+            liab_bank_insurance_vault: liab_bank_fixture.get_vault(BankVaultType::Insurance).0,
+            asset_token_program: token::ID,
+            liab_token_program: token::ID,
+        }
+        .to_account_metas(Some(true));
+
+        accounts.extend(
+            asset_bank
+                .config
+                .get_oracle_keys()
+                .iter()
+                .chain(liab_bank.config.get_oracle_keys())
+                .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+        );
+
+        accounts.extend_from_slice(&self.load_observation_account_metas(vec![], vec![]).await);
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountLiquidateWithTokens {
+                asset_amount: ui_to_native!(
+                    asset_ui_amount.into(),
+                    asset_bank_fixture.mint.mint.decimals
+                ),
+            }
+            .data(),
+        };
+
+        let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[compute_budget_ix, ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn try_withdraw_emissions(
         &self,
         bank: &BankFixture,
@@ -433,6 +609,74 @@ impl MarginfiAccountFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    pub async fn try_mint_receipt(
+        &self,
+        bank: &BankFixture,
+        destination_account: Pubkey,
+        amount: u64,
+    ) -> std::result::Result<(), BanksClientError> {
+        let mut ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountMintReceipt {
+                marginfi_group: self.load().await.group,
+                marginfi_account: self.key,
+                signer: self.ctx.borrow().payer.pubkey(),
+                bank: bank.key,
+                receipt_mint: get_receipt_mint_address(bank.key).0,
+                receipt_mint_authority: get_receipt_mint_authority_address(bank.key).0,
+                destination_receipt_token_account: destination_account,
+                token_program: token::ID,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingAccountMintReceipt { amount }.data(),
+        };
+
+        ix.accounts
+            .extend_from_slice(&self.load_observation_account_metas(vec![], vec![]).await);
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_redeem_receipt(
+        &self,
+        bank: &BankFixture,
+        source_account: Pubkey,
+        amount: u64,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountRedeemReceipt {
+                marginfi_group: self.load().await.group,
+                marginfi_account: self.key,
+                signer: self.ctx.borrow().payer.pubkey(),
+                bank: bank.key,
+                receipt_mint: get_receipt_mint_address(bank.key).0,
+                signer_receipt_token_account: source_account,
+                token_program: token::ID,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingAccountRedeemReceipt { amount }.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     /// Set a flag on the account
     ///
     /// Function assumes signer is group admin
@@ -485,6 +729,35 @@ impl MarginfiAccountFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    pub async fn try_set_emode(
+        &self,
+        emode_category: u8,
+    ) -> std::result::Result<(), BanksClientError> {
+        let mut ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::MarginfiAccountSetEmode {
+                marginfi_group: self.load().await.group,
+                marginfi_account: self.key,
+                signer: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::MarginfiAccountSetEmode { emode_category }.data(),
+        };
+
+        ix.accounts
+            .extend_from_slice(&self.load_observation_account_metas(vec![], vec![]).await);
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn make_lending_account_start_flashloan_ix(&self, end_index: u64) -> Instruction {
         Instruction {
             program_id: marginfi::id(),
@@ -503,9 +776,10 @@ impl MarginfiAccountFixture {
         include_banks: Vec<Pubkey>,
         exclude_banks: Vec<Pubkey>,
     ) -> Instruction {
-        let mut account_metas = marginfi::accounts::LendingAccountEndFlashloan {
+        let mut account_metas = marginfi::accounts::LendingAccountEndFlashloanV2 {
             marginfi_account: self.key,
             signer: self.ctx.borrow().payer.pubkey(),
+            marginfi_group: self.load().await.group,
         }
         .to_account_metas(Some(true));
 
@@ -517,6 +791,19 @@ impl MarginfiAccountFixture {
         Instruction {
             program_id: marginfi::id(),
             accounts: account_metas,
+            data: marginfi::instruction::LendingAccountEndFlashloanV2 {}.data(),
+        }
+    }
+
+    /// Legacy end flashloan instruction, without the group account.
+    pub fn make_lending_account_end_flashloan_v1_ix(&self) -> Instruction {
+        Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountEndFlashloan {
+                marginfi_account: self.key,
+                signer: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
             data: marginfi::instruction::LendingAccountEndFlashloan {}.data(),
         }
     }
@@ -588,18 +875,18 @@ impl MarginfiAccountFixture {
             .iter()
             .zip(bank_pks.iter())
             .flat_map(|(bank, bank_pk)| {
-                vec![
-                    AccountMeta {
-                        pubkey: *bank_pk,
-                        is_signer: false,
-                        is_writable: false,
-                    },
-                    AccountMeta {
-                        pubkey: bank.config.oracle_keys[0],
-                        is_signer: false,
-                        is_writable: false,
-                    },
-                ]
+                let mut metas = vec![AccountMeta {
+                    pubkey: *bank_pk,
+                    is_signer: false,
+                    is_writable: false,
+                }];
+                metas.extend(
+                    bank.config
+                        .get_oracle_keys()
+                        .iter()
+                        .map(|oracle_key| AccountMeta::new_readonly(*oracle_key, false)),
+                );
+                metas
             })
             .collect::<Vec<_>>();
         account_metas
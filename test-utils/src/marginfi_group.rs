@@ -4,9 +4,10 @@ use crate::utils::*;
 use anchor_lang::{prelude::*, solana_program::system_program, InstructionData};
 use anchor_spl::token;
 use anyhow::Result;
+use fixed::types::I80F48;
 use marginfi::{
     prelude::MarginfiGroup,
-    state::marginfi_group::{BankConfig, BankConfigOpt, BankVaultType, GroupConfig},
+    state::marginfi_group::{BankConfig, BankConfigOpt, BankVaultType, EmodeCategory, GroupConfig},
 };
 use solana_program::sysvar;
 use solana_program_test::*;
@@ -91,12 +92,17 @@ impl MarginfiGroupFixture {
             fee_vault_authority: bank_fixture.get_vault_authority(BankVaultType::Fee).0,
             fee_vault: bank_fixture.get_vault(BankVaultType::Fee).0,
             rent: sysvar::rent::id(),
-            token_program: token::ID,
+            token_program: bank_asset_mint_fixture.token_program,
             system_program: system_program::id(),
         }
         .to_account_metas(Some(true));
 
-        accounts.push(AccountMeta::new_readonly(bank_config.oracle_keys[0], false));
+        accounts.extend(
+            bank_config
+                .get_oracle_keys()
+                .iter()
+                .map(|k| AccountMeta::new_readonly(*k, false)),
+        );
 
         let ix = Instruction {
             program_id: marginfi::id(),
@@ -158,12 +164,17 @@ impl MarginfiGroupFixture {
             fee_vault_authority: bank_fixture.get_vault_authority(BankVaultType::Fee).0,
             fee_vault: bank_fixture.get_vault(BankVaultType::Fee).0,
             rent: sysvar::rent::id(),
-            token_program: token::ID,
+            token_program: bank_asset_mint_fixture.token_program,
             system_program: system_program::id(),
         }
         .to_account_metas(Some(true));
 
-        accounts.push(AccountMeta::new_readonly(bank_config.oracle_keys[0], false));
+        accounts.extend(
+            bank_config
+                .get_oracle_keys()
+                .iter()
+                .map(|k| AccountMeta::new_readonly(*k, false)),
+        );
 
         let ix = Instruction {
             program_id: marginfi::id(),
@@ -293,6 +304,80 @@ impl MarginfiGroupFixture {
         Ok(())
     }
 
+    pub async fn try_configure_emode_category(
+        &self,
+        emode_category: u8,
+        config: EmodeCategory,
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::MarginfiGroupConfigureEmodeCategory {
+                marginfi_group: self.key,
+                admin: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::MarginfiGroupConfigureEmodeCategory {
+                emode_category,
+                config,
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey().clone()),
+            &[&self.ctx.borrow().payer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn try_configure_weight_override(
+        &self,
+        asset_bank: &BankFixture,
+        liability_bank: &BankFixture,
+        asset_weight_init: I80F48,
+        asset_weight_maint: I80F48,
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::MarginfiGroupConfigureWeightOverride {
+                marginfi_group: self.key,
+                admin: self.ctx.borrow().payer.pubkey(),
+                asset_bank: asset_bank.key,
+                liability_bank: liability_bank.key,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::MarginfiGroupConfigureWeightOverride {
+                asset_weight_init: asset_weight_init.into(),
+                asset_weight_maint: asset_weight_maint.into(),
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey().clone()),
+            &[&self.ctx.borrow().payer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn try_collect_fees(&self, bank: &BankFixture) -> Result<()> {
         let mut ctx = self.ctx.borrow_mut();
 
@@ -364,6 +449,55 @@ impl MarginfiGroupFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    pub async fn try_handle_bankruptcy_all(
+        &self,
+        banks: Vec<&BankFixture>,
+        marginfi_account: &MarginfiAccountFixture,
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = marginfi::accounts::LendingPoolHandleBankruptcyAll {
+            marginfi_group: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            marginfi_account: marginfi_account.key,
+        }
+        .to_account_metas(Some(true));
+
+        for bank in banks {
+            accounts.extend([
+                AccountMeta::new(bank.key, false),
+                AccountMeta::new(bank.get_vault(BankVaultType::Liquidity).0, false),
+                AccountMeta::new(bank.get_vault(BankVaultType::Insurance).0, false),
+                AccountMeta::new_readonly(
+                    bank.get_vault_authority(BankVaultType::Insurance).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(token::ID, false),
+            ]);
+        }
+
+        accounts.append(
+            &mut marginfi_account
+                .load_observation_account_metas(vec![], vec![])
+                .await,
+        );
+
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolHandleBankruptcyAll {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub fn get_size() -> usize {
         8 + mem::size_of::<MarginfiGroup>()
     }
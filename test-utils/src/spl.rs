@@ -1,11 +1,15 @@
 use crate::ui_to_native;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{
-    spl_token::{
+use anchor_spl::{
+    token::{spl_token, Mint, TokenAccount},
+    token_2022::spl_token_2022::{
         self,
-        instruction::{initialize_mint, mint_to},
+        extension::{transfer_fee, ExtensionType, StateWithExtensions},
+        instruction::{
+            initialize_account, initialize_mint, initialize_non_transferable_mint,
+            initialize_permanent_delegate, mint_to,
+        },
     },
-    Mint, TokenAccount,
 };
 use solana_program_test::ProgramTestContext;
 use solana_sdk::{
@@ -14,11 +18,52 @@ use solana_sdk::{
 };
 use std::{cell::RefCell, rc::Rc};
 
+/// Token22 mint extensions the mint fixture can be created with.
+#[derive(Clone, Copy, Debug)]
+pub enum SupportedExtension {
+    TransferFee { basis_points: u16, maximum_fee: u64 },
+    PermanentDelegate,
+    NonTransferable,
+}
+
+impl SupportedExtension {
+    fn extension_type(&self) -> ExtensionType {
+        match self {
+            Self::TransferFee { .. } => ExtensionType::TransferFeeConfig,
+            Self::PermanentDelegate => ExtensionType::PermanentDelegate,
+            Self::NonTransferable => ExtensionType::NonTransferable,
+        }
+    }
+
+    /// Extensions have to be initialized before the mint itself.
+    fn init_instruction(&self, mint: &Pubkey, authority: &Pubkey) -> Instruction {
+        match self {
+            Self::TransferFee {
+                basis_points,
+                maximum_fee,
+            } => transfer_fee::instruction::initialize_transfer_fee_config(
+                &spl_token_2022::id(),
+                mint,
+                Some(authority),
+                Some(authority),
+                *basis_points,
+                *maximum_fee,
+            ),
+            Self::PermanentDelegate => {
+                initialize_permanent_delegate(&spl_token_2022::id(), mint, authority)
+            }
+            Self::NonTransferable => initialize_non_transferable_mint(&spl_token_2022::id(), mint),
+        }
+        .unwrap()
+    }
+}
+
 #[derive(Clone)]
 pub struct MintFixture {
     pub ctx: Rc<RefCell<ProgramTestContext>>,
     pub key: Pubkey,
     pub mint: Mint,
+    pub token_program: Pubkey,
 }
 
 impl MintFixture {
@@ -26,6 +71,32 @@ impl MintFixture {
         ctx: Rc<RefCell<ProgramTestContext>>,
         mint_keypair: Option<Keypair>,
         mint_decimals: Option<u8>,
+    ) -> MintFixture {
+        Self::create(ctx, mint_keypair, mint_decimals, spl_token::id(), &[]).await
+    }
+
+    pub async fn new_token_22(
+        ctx: Rc<RefCell<ProgramTestContext>>,
+        mint_keypair: Option<Keypair>,
+        mint_decimals: Option<u8>,
+        extensions: &[SupportedExtension],
+    ) -> MintFixture {
+        Self::create(
+            ctx,
+            mint_keypair,
+            mint_decimals,
+            spl_token_2022::id(),
+            extensions,
+        )
+        .await
+    }
+
+    async fn create(
+        ctx: Rc<RefCell<ProgramTestContext>>,
+        mint_keypair: Option<Keypair>,
+        mint_decimals: Option<u8>,
+        token_program: Pubkey,
+        extensions: &[SupportedExtension],
     ) -> MintFixture {
         let ctx_ref = Rc::clone(&ctx);
         let keypair = mint_keypair.unwrap_or_else(Keypair::new);
@@ -34,24 +105,40 @@ impl MintFixture {
 
             let rent = ctx.banks_client.get_rent().await.unwrap();
 
-            let init_account_ix = create_account(
+            let mint_len = if extensions.is_empty() {
+                Mint::LEN
+            } else {
+                ExtensionType::get_account_len::<spl_token_2022::state::Mint>(
+                    &extensions
+                        .iter()
+                        .map(SupportedExtension::extension_type)
+                        .collect::<Vec<_>>(),
+                )
+            };
+
+            let mut ixs = vec![create_account(
                 &ctx.payer.pubkey(),
                 &keypair.pubkey(),
-                rent.minimum_balance(Mint::LEN),
-                Mint::LEN as u64,
-                &spl_token::id(),
+                rent.minimum_balance(mint_len),
+                mint_len as u64,
+                &token_program,
+            )];
+            ixs.extend(extensions.iter().map(|extension| {
+                extension.init_instruction(&keypair.pubkey(), &ctx.payer.pubkey())
+            }));
+            ixs.push(
+                initialize_mint(
+                    &token_program,
+                    &keypair.pubkey(),
+                    &ctx.payer.pubkey(),
+                    None,
+                    mint_decimals.unwrap_or(6),
+                )
+                .unwrap(),
             );
-            let init_mint_ix = initialize_mint(
-                &spl_token::id(),
-                &keypair.pubkey(),
-                &ctx.payer.pubkey(),
-                None,
-                mint_decimals.unwrap_or(6),
-            )
-            .unwrap();
 
             let tx = Transaction::new_signed_with_payer(
-                &[init_account_ix, init_mint_ix],
+                &ixs,
                 Some(&ctx.payer.pubkey()),
                 &[&ctx.payer, &keypair],
                 ctx.last_blockhash,
@@ -66,13 +153,14 @@ impl MintFixture {
                 .unwrap()
                 .unwrap();
 
-            Mint::try_deserialize(&mut mint_account.data.as_slice()).unwrap()
+            deserialize_mint(&mint_account.data)
         };
 
         MintFixture {
             ctx: ctx_ref,
             key: keypair.pubkey(),
             mint,
+            token_program,
         }
     }
 
@@ -86,7 +174,7 @@ impl MintFixture {
             .await
             .unwrap()
             .unwrap();
-        self.mint = Mint::try_deserialize(&mut mint_account.data.as_slice()).unwrap();
+        self.mint = deserialize_mint(&mint_account.data);
     }
 
     pub async fn mint_to<T: Into<f64>>(&mut self, dest: &Pubkey, ui_amount: T) {
@@ -115,7 +203,7 @@ impl MintFixture {
     pub fn make_mint_to_ix(&self, dest: &Pubkey, amount: u64) -> Instruction {
         let ctx = self.ctx.borrow();
         mint_to(
-            &spl_token::id(),
+            &self.token_program,
             &self.key,
             dest,
             &ctx.payer.pubkey(),
@@ -165,36 +253,63 @@ impl TokenAccountFixture {
         payer_pk: &Pubkey,
         owner_pk: &Pubkey,
         keypair: &Keypair,
+        token_program: &Pubkey,
+        account_len: usize,
     ) -> [Instruction; 2] {
         let init_account_ix = create_account(
             payer_pk,
             &keypair.pubkey(),
-            rent.minimum_balance(TokenAccount::LEN),
-            TokenAccount::LEN as u64,
-            &spl_token::id(),
+            rent.minimum_balance(account_len),
+            account_len as u64,
+            token_program,
         );
 
-        let init_token_ix = spl_token::instruction::initialize_account(
-            &spl_token::id(),
-            &keypair.pubkey(),
-            mint_pk,
-            owner_pk,
-        )
-        .unwrap();
+        let init_token_ix =
+            initialize_account(token_program, &keypair.pubkey(), mint_pk, owner_pk).unwrap();
 
         [init_account_ix, init_token_ix]
     }
 
+    /// Token program of the mint and the size of its token accounts, which includes the account
+    /// extensions required by the Token22 extensions of the mint.
+    async fn get_account_params(ctx: &mut ProgramTestContext, mint_pk: &Pubkey) -> (Pubkey, usize) {
+        let mint_account = ctx
+            .banks_client
+            .get_account(*mint_pk)
+            .await
+            .unwrap()
+            .unwrap();
+
+        if mint_account.owner == spl_token::id() {
+            return (spl_token::id(), TokenAccount::LEN);
+        }
+
+        let mint =
+            StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data).unwrap();
+        let account_extensions = ExtensionType::get_required_init_account_extensions(
+            &mint.get_extension_types().unwrap(),
+        );
+
+        (
+            mint_account.owner,
+            ExtensionType::get_account_len::<spl_token_2022::state::Account>(&account_extensions),
+        )
+    }
+
     pub async fn new_account(&self) -> Pubkey {
         let keypair = Keypair::new();
         let mut ctx = self.ctx.borrow_mut();
 
+        let (token_program, account_len) =
+            Self::get_account_params(&mut ctx, &self.token.mint).await;
         let ixs = Self::create_ixs(
             ctx.banks_client.get_rent().await.unwrap(),
             &self.token.mint,
             &ctx.payer.pubkey(),
             &ctx.payer.pubkey(),
             &keypair,
+            &token_program,
+            account_len,
         )
         .await;
 
@@ -223,8 +338,17 @@ impl TokenAccountFixture {
             let mut ctx = ctx.borrow_mut();
 
             let rent = ctx.banks_client.get_rent().await.unwrap();
-            let instructions =
-                Self::create_ixs(rent, mint_pk, &ctx.payer.pubkey(), owner_pk, keypair).await;
+            let (token_program, account_len) = Self::get_account_params(&mut ctx, mint_pk).await;
+            let instructions = Self::create_ixs(
+                rent,
+                mint_pk,
+                &ctx.payer.pubkey(),
+                owner_pk,
+                keypair,
+                &token_program,
+                account_len,
+            )
+            .await;
 
             let tx = Transaction::new_signed_with_payer(
                 &instructions,
@@ -239,7 +363,7 @@ impl TokenAccountFixture {
         Self {
             ctx: ctx_ref.clone(),
             key: keypair.pubkey(),
-            token: get_and_deserialize(ctx_ref.clone(), keypair.pubkey()).await,
+            token: get_token_account(ctx_ref.clone(), keypair.pubkey()).await,
         }
     }
 
@@ -256,7 +380,7 @@ impl TokenAccountFixture {
         ctx: Rc<RefCell<ProgramTestContext>>,
         address: Pubkey,
     ) -> TokenAccountFixture {
-        let token = get_and_deserialize(ctx.clone(), address).await;
+        let token = get_token_account(ctx.clone(), address).await;
 
         Self {
             ctx: ctx.clone(),
@@ -266,7 +390,7 @@ impl TokenAccountFixture {
     }
 
     pub async fn balance(&self) -> u64 {
-        let token_account: TokenAccount = get_and_deserialize(self.ctx.clone(), self.key).await;
+        let token_account = get_token_account(self.ctx.clone(), self.key).await;
 
         token_account.amount
     }
@@ -282,7 +406,22 @@ pub async fn get_and_deserialize<T: AccountDeserialize>(
 }
 
 pub async fn balance_of(ctx: Rc<RefCell<ProgramTestContext>>, pubkey: Pubkey) -> u64 {
-    let token_account: TokenAccount = get_and_deserialize(ctx, pubkey).await;
+    let token_account = get_token_account(ctx, pubkey).await;
 
     token_account.amount
 }
+
+/// Token22 accounts store their extensions after the base account state,
+/// only the base state is deserialized.
+pub async fn get_token_account(
+    ctx: Rc<RefCell<ProgramTestContext>>,
+    pubkey: Pubkey,
+) -> TokenAccount {
+    let mut ctx = ctx.borrow_mut();
+    let account = ctx.banks_client.get_account(pubkey).await.unwrap().unwrap();
+    TokenAccount::try_deserialize(&mut &account.data[..TokenAccount::LEN]).unwrap()
+}
+
+fn deserialize_mint(data: &[u8]) -> Mint {
+    Mint::try_deserialize(&mut &data[..Mint::LEN]).unwrap()
+}
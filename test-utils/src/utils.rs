@@ -546,6 +546,26 @@ pub fn get_emissions_token_account_address(
     )
 }
 
+pub fn get_receipt_mint_address(bank_pk: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi::constants::RECEIPT_MINT_SEED.as_bytes(),
+            bank_pk.as_ref(),
+        ],
+        &marginfi::id(),
+    )
+}
+
+pub fn get_receipt_mint_authority_address(bank_pk: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi::constants::RECEIPT_MINT_AUTHORITY_SEED.as_bytes(),
+            bank_pk.as_ref(),
+        ],
+        &marginfi::id(),
+    )
+}
+
 #[cfg(feature = "lip")]
 pub mod lip {
     use super::*;
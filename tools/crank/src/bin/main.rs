@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use marginfi_crank::{Crank, CrankConfig};
+use marginfi_sdk::state::MarginfiState;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::read_keypair_file,
+};
+
+#[derive(Debug, Parser)]
+#[clap(
+    version,
+    about = "Accrue interest and collect fees on every bank of a marginfi group"
+)]
+pub struct Opts {
+    #[clap(long)]
+    pub rpc_url: String,
+    /// Keypair paying for the crank transactions
+    #[clap(long)]
+    pub keypair: String,
+    #[clap(long, default_value_t = marginfi::ID)]
+    pub program_id: Pubkey,
+    #[clap(long)]
+    pub group: Pubkey,
+    #[clap(long, default_value_t = 60)]
+    pub interval_secs: u64,
+    /// Accrual instructions per transaction
+    #[clap(long, default_value_t = 10)]
+    pub accrue_batch_size: usize,
+    /// Fee collection instructions per transaction, 0 disables fee collection
+    #[clap(long, default_value_t = 4)]
+    pub collect_fees_batch_size: usize,
+    /// Compute unit price in micro lamports
+    #[clap(long, default_value_t = 0)]
+    pub priority_fee: u64,
+    /// Compute unit limit per transaction, 0 keeps the runtime default
+    #[clap(long, default_value_t = 0)]
+    pub compute_unit_limit: u32,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    solana_metrics::set_panic_hook("marginfi-crank", None);
+
+    let opts = Opts::parse();
+
+    let rpc_client = RpcClient::new_with_commitment(opts.rpc_url, CommitmentConfig::confirmed());
+    let payer = read_keypair_file(&opts.keypair)
+        .map_err(|err| anyhow!("Failed to read keypair {}: {}", opts.keypair, err))?;
+
+    let mut state = MarginfiState::load(&rpc_client, opts.program_id, opts.group)?;
+
+    let crank = Crank::new(CrankConfig {
+        accrue_batch_size: opts.accrue_batch_size,
+        collect_fees_batch_size: opts.collect_fees_batch_size,
+        priority_fee: opts.priority_fee,
+        compute_unit_limit: opts.compute_unit_limit,
+    });
+
+    crank.run(
+        &rpc_client,
+        &mut state,
+        &payer,
+        Duration::from_secs(opts.interval_secs),
+    )
+}
@@ -0,0 +1,390 @@
+//! Interest accrual crank for a marginfi group.
+//!
+//! Every round reloads the group's banks, accrues interest on all of them, collects outstanding
+//! group and insurance fees, and reports oracles the program would reject as stale. Round results
+//! are exported as `solana-metrics` datapoints, configured through `SOLANA_METRICS_CONFIG`.
+
+use {
+    anyhow::Result,
+    fixed::types::I80F48,
+    log::{debug, info, warn},
+    marginfi::{
+        constants::{PYTH_ID, PYTH_PUSH_ORACLE_ID, SWITCHBOARD_PULL_ID},
+        state::{
+            marginfi_group::Bank,
+            price::{PythPushOraclePriceFeed, SwitchboardPullPriceFeed},
+        },
+    },
+    marginfi_sdk::{
+        instructions,
+        state::{fetch_clock, load_price_feed, MarginfiState},
+    },
+    solana_client::rpc_client::RpcClient,
+    solana_metrics::datapoint_info,
+    solana_sdk::{
+        account::Account,
+        account_info::IntoAccountInfo,
+        compute_budget::ComputeBudgetInstruction,
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        signer::Signer,
+        transaction::Transaction,
+    },
+    std::{collections::HashMap, thread, time::Duration},
+    switchboard_v2::SWITCHBOARD_PROGRAM_ID,
+};
+
+/// `get_multiple_accounts` limit.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Clone, Copy, Debug)]
+pub struct CrankConfig {
+    /// `lending_pool_accrue_bank_interest` instructions per transaction.
+    pub accrue_batch_size: usize,
+    /// `lending_pool_collect_bank_fees` instructions per transaction, 0 disables fee collection.
+    pub collect_fees_batch_size: usize,
+    /// Compute unit price in micro lamports, 0 sends without a priority fee.
+    pub priority_fee: u64,
+    /// Compute unit limit per transaction, 0 keeps the runtime default.
+    pub compute_unit_limit: u32,
+}
+
+/// Oracle of a bank, as seen by the program at the time of the round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OracleStatus {
+    pub bank_pk: Pubkey,
+    pub mint: Pubkey,
+    pub oracle_key: Pubkey,
+    /// Seconds since the last price update, `None` if the oracle account can't be read.
+    pub age: Option<i64>,
+    pub max_age: u64,
+    /// The program rejects the price feed, so any health check over the bank fails.
+    pub stale: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CrankReport {
+    pub accrued_banks: usize,
+    pub collected_fee_banks: usize,
+    pub failed_transactions: usize,
+    pub oracles: Vec<OracleStatus>,
+    pub signatures: Vec<Signature>,
+}
+
+impl CrankReport {
+    pub fn stale_oracles(&self) -> impl Iterator<Item = &OracleStatus> {
+        self.oracles.iter().filter(|oracle| oracle.stale)
+    }
+}
+
+pub struct Crank {
+    pub config: CrankConfig,
+}
+
+impl Crank {
+    pub fn new(config: CrankConfig) -> Self {
+        Self { config }
+    }
+
+    /// One accrual instruction per bank of the group. Banks with a price guard also refresh their
+    /// reference price, so their oracles must be live and rounds must run more often than
+    /// `ORACLE_PRICE_GUARD_WINDOW_SEC`.
+    pub fn accrue_ixs(&self, state: &MarginfiState) -> Vec<Instruction> {
+        let mut banks = state.banks.iter().collect::<Vec<_>>();
+        banks.sort_by_key(|(bank_pk, _)| **bank_pk);
+
+        banks
+            .into_iter()
+            .map(|(bank_pk, bank)| {
+                if I80F48::from(bank.oracle_max_price_change) > I80F48::ZERO {
+                    instructions::lending_pool_accrue_bank_interest_with_oracles(
+                        &state.program_id,
+                        state.group_pk,
+                        *bank_pk,
+                        bank.config.get_oracle_keys(),
+                    )
+                } else {
+                    instructions::lending_pool_accrue_bank_interest(
+                        &state.program_id,
+                        state.group_pk,
+                        *bank_pk,
+                    )
+                }
+            })
+            .collect()
+    }
+
+    /// Fee collection instructions for banks with outstanding group or insurance fees. Bank
+    /// interest in `state` must be accrued, as `MarginfiState::refresh_banks` does.
+    pub fn collect_fees_ixs(&self, state: &MarginfiState) -> Result<Vec<Instruction>> {
+        let mut bank_pks = state
+            .banks
+            .iter()
+            .filter(|(_, bank)| has_outstanding_fees(bank))
+            .map(|(bank_pk, _)| *bank_pk)
+            .collect::<Vec<_>>();
+        bank_pks.sort();
+
+        Ok(bank_pks
+            .into_iter()
+            .map(|bank_pk| state.collect_bank_fees_ix(bank_pk))
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Split `ixs` into transactions of at most `batch_size` instructions, each prefixed with the
+    /// configured compute budget instructions.
+    pub fn batch_ixs(&self, ixs: Vec<Instruction>, batch_size: usize) -> Vec<Vec<Instruction>> {
+        let mut compute_budget_ixs = vec![];
+        if self.config.compute_unit_limit > 0 {
+            compute_budget_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(
+                self.config.compute_unit_limit,
+            ));
+        }
+        if self.config.priority_fee > 0 {
+            compute_budget_ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
+                self.config.priority_fee,
+            ));
+        }
+
+        ixs.chunks(batch_size.max(1))
+            .map(|chunk| {
+                let mut batch = compute_budget_ixs.clone();
+                batch.extend_from_slice(chunk);
+                batch
+            })
+            .collect()
+    }
+
+    /// Run a single round: accrue, collect fees, check oracles and export metrics.
+    pub fn crank_once(
+        &self,
+        rpc_client: &RpcClient,
+        state: &mut MarginfiState,
+        payer: &Keypair,
+    ) -> Result<CrankReport> {
+        state.refresh_banks(rpc_client)?;
+
+        let mut report = CrankReport::default();
+
+        let accrue_ixs = self.accrue_ixs(state);
+        let accrue_batches = self.batch_ixs(accrue_ixs, self.config.accrue_batch_size);
+        for batch in accrue_batches {
+            let banks = batch.len() - self.compute_budget_ix_count();
+            match send_batch(rpc_client, payer, &batch) {
+                Ok(signature) => {
+                    debug!("Accrued interest on {} banks: {}", banks, signature);
+                    report.accrued_banks += banks;
+                    report.signatures.push(signature);
+                }
+                Err(err) => {
+                    warn!("Failed to accrue interest on {} banks: {}", banks, err);
+                    report.failed_transactions += 1;
+                }
+            }
+        }
+
+        if self.config.collect_fees_batch_size > 0 {
+            let collect_fees_ixs = self.collect_fees_ixs(state)?;
+            let collect_fees_batches =
+                self.batch_ixs(collect_fees_ixs, self.config.collect_fees_batch_size);
+            for batch in collect_fees_batches {
+                let banks = batch.len() - self.compute_budget_ix_count();
+                match send_batch(rpc_client, payer, &batch) {
+                    Ok(signature) => {
+                        debug!("Collected fees of {} banks: {}", banks, signature);
+                        report.collected_fee_banks += banks;
+                        report.signatures.push(signature);
+                    }
+                    Err(err) => {
+                        warn!("Failed to collect fees of {} banks: {}", banks, err);
+                        report.failed_transactions += 1;
+                    }
+                }
+            }
+        }
+
+        report.oracles = fetch_oracle_statuses(rpc_client, state)?;
+        for oracle in report.stale_oracles() {
+            warn!(
+                "Stale oracle {} for bank {} (mint {}): age {:?}s, max {}s",
+                oracle.oracle_key, oracle.bank_pk, oracle.mint, oracle.age, oracle.max_age
+            );
+        }
+
+        export_metrics(&report);
+
+        Ok(report)
+    }
+
+    /// Crank every `interval` until an RPC error occurs outside of sending.
+    pub fn run(
+        &self,
+        rpc_client: &RpcClient,
+        state: &mut MarginfiState,
+        payer: &Keypair,
+        interval: Duration,
+    ) -> Result<()> {
+        loop {
+            let report = self.crank_once(rpc_client, state, payer)?;
+            info!(
+                "Accrued {} banks, collected fees of {} banks, {} failed transactions, {} stale oracles",
+                report.accrued_banks,
+                report.collected_fee_banks,
+                report.failed_transactions,
+                report.stale_oracles().count()
+            );
+
+            thread::sleep(interval);
+        }
+    }
+
+    fn compute_budget_ix_count(&self) -> usize {
+        usize::from(self.config.compute_unit_limit > 0) + usize::from(self.config.priority_fee > 0)
+    }
+}
+
+/// Status of the oracle of `bank` read from `oracle_accounts`, in `BankConfig::get_oracle_keys`
+/// order, checked against `current_timestamp` like the program does. Aggregated oracles and
+/// oracles with a fallback report the age of their freshest source.
+pub fn oracle_status(
+    bank_pk: Pubkey,
+    bank: &Bank,
+    oracle_accounts: Option<&mut [(Pubkey, Account)]>,
+    current_timestamp: i64,
+) -> OracleStatus {
+    let (age, stale) = match oracle_accounts {
+        Some(oracle_accounts) => {
+            let age = oracle_accounts
+                .iter()
+                .filter_map(|(oracle_key, account)| {
+                    oracle_publish_time(oracle_key, account, &bank.config.pyth_push_feed_id)
+                })
+                .max()
+                .map(|publish_time| current_timestamp - publish_time);
+            let stale = load_price_feed(bank, oracle_accounts, current_timestamp).is_err();
+
+            (age, stale)
+        }
+        None => (None, true),
+    };
+
+    OracleStatus {
+        bank_pk,
+        mint: bank.mint,
+        oracle_key: bank.config.oracle_keys[0],
+        age,
+        max_age: bank.config.get_oracle_max_age(),
+        stale,
+    }
+}
+
+/// Oracle status of every bank in `state`, at the cluster time.
+pub fn fetch_oracle_statuses(
+    rpc_client: &RpcClient,
+    state: &MarginfiState,
+) -> Result<Vec<OracleStatus>> {
+    let current_timestamp = fetch_clock(rpc_client)?.unix_timestamp;
+
+    let mut banks = state.banks.iter().collect::<Vec<_>>();
+    banks.sort_by_key(|(bank_pk, _)| **bank_pk);
+
+    let oracle_keys = banks
+        .iter()
+        .flat_map(|(_, bank)| bank.config.get_oracle_keys().iter().copied())
+        .collect::<Vec<_>>();
+    let mut oracle_accounts = HashMap::new();
+    for oracle_keys in oracle_keys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = rpc_client.get_multiple_accounts(oracle_keys)?;
+        for (oracle_key, account) in oracle_keys.iter().zip(accounts) {
+            if let Some(account) = account {
+                oracle_accounts.insert(*oracle_key, account);
+            }
+        }
+    }
+
+    Ok(banks
+        .into_iter()
+        .map(|(bank_pk, bank)| {
+            let mut accounts = bank
+                .config
+                .get_oracle_keys()
+                .iter()
+                .map(|oracle_key| {
+                    oracle_accounts
+                        .get(oracle_key)
+                        .map(|account| (*oracle_key, account.clone()))
+                })
+                .collect::<Option<Vec<_>>>();
+
+            oracle_status(*bank_pk, bank, accounts.as_deref_mut(), current_timestamp)
+        })
+        .collect())
+}
+
+fn oracle_publish_time(
+    oracle_key: &Pubkey,
+    account: &Account,
+    pyth_feed_id: &[u8; 32],
+) -> Option<i64> {
+    if account.owner.eq(&PYTH_ID) {
+        pyth_sdk_solana::state::load_price_account(&account.data)
+            .ok()
+            .map(|price_account| price_account.timestamp)
+    } else if account.owner.eq(&SWITCHBOARD_PROGRAM_ID) {
+        switchboard_v2::AggregatorAccountData::new_from_bytes(&account.data)
+            .ok()
+            .map(|aggregator| aggregator.latest_confirmed_round.round_open_timestamp)
+    } else if account.owner.eq(&PYTH_PUSH_ORACLE_ID) {
+        let mut account = account.clone();
+        let ai = (oracle_key, &mut account).into_account_info();
+        PythPushOraclePriceFeed::load_checked(&ai, pyth_feed_id, 0, u64::MAX)
+            .ok()
+            .map(|price_feed| price_feed.get_publish_time())
+    } else if account.owner.eq(&SWITCHBOARD_PULL_ID) {
+        let mut account = account.clone();
+        let ai = (oracle_key, &mut account).into_account_info();
+        SwitchboardPullPriceFeed::load_checked(&ai, 0, u64::MAX)
+            .ok()
+            .map(|price_feed| price_feed.get_publish_time())
+    } else {
+        None
+    }
+}
+
+fn has_outstanding_fees(bank: &Bank) -> bool {
+    I80F48::from(bank.collected_group_fees_outstanding) >= I80F48::ONE
+        || I80F48::from(bank.collected_insurance_fees_outstanding) >= I80F48::ONE
+}
+
+fn send_batch(rpc_client: &RpcClient, payer: &Keypair, ixs: &[Instruction]) -> Result<Signature> {
+    let tx = Transaction::new_signed_with_payer(
+        ixs,
+        Some(&payer.pubkey()),
+        &[payer],
+        rpc_client.get_latest_blockhash()?,
+    );
+
+    Ok(rpc_client.send_and_confirm_transaction(&tx)?)
+}
+
+fn export_metrics(report: &CrankReport) {
+    datapoint_info!(
+        "marginfi-crank",
+        ("accrued_banks", report.accrued_banks, i64),
+        ("collected_fee_banks", report.collected_fee_banks, i64),
+        ("failed_transactions", report.failed_transactions, i64),
+        ("stale_oracles", report.stale_oracles().count(), i64),
+    );
+
+    for oracle in &report.oracles {
+        datapoint_info!(
+            "marginfi-crank-oracle",
+            ("bank", oracle.bank_pk.to_string(), String),
+            ("mint", oracle.mint.to_string(), String),
+            ("age", oracle.age.unwrap_or(-1), i64),
+            ("max_age", oracle.max_age, i64),
+            ("stale", oracle.stale, bool),
+        );
+    }
+}
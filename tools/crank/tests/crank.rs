@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use fixtures::prelude::*;
+use marginfi::state::marginfi_group::{Bank, MarginfiGroup};
+use marginfi_crank::{oracle_status, Crank, CrankConfig};
+use marginfi_sdk::state::MarginfiState;
+use solana_program_test::tokio;
+use solana_sdk::{
+    account::Account, instruction::Instruction, pubkey::Pubkey, signer::Signer,
+    transaction::Transaction,
+};
+
+/// Banks of the group as `MarginfiState::load` reads them over RPC, accrued to the cluster time.
+async fn load_state(test_f: &TestFixture) -> MarginfiState {
+    let current_timestamp = test_f.get_clock().await.unix_timestamp;
+
+    let mut banks = HashMap::new();
+    let mut token_programs = HashMap::new();
+    for bank_f in test_f.banks.values() {
+        let mut bank: Bank = bank_f.load().await;
+        bank.accrue_interest(current_timestamp).unwrap();
+
+        let mint_account = test_f.try_load(&bank.mint).await.unwrap().unwrap();
+        token_programs.insert(bank.mint, mint_account.owner);
+        banks.insert(bank_f.key, bank);
+    }
+
+    MarginfiState {
+        program_id: marginfi::ID,
+        group_pk: test_f.marginfi_group.key,
+        group: test_f
+            .load_and_deserialize::<MarginfiGroup>(&test_f.marginfi_group.key)
+            .await,
+        banks,
+        token_programs,
+        price_feeds: HashMap::new(),
+        marginfi_accounts: HashMap::new(),
+    }
+}
+
+async fn load_oracle_accounts(
+    test_f: &TestFixture,
+    bank: &Bank,
+) -> anyhow::Result<Vec<(Pubkey, Account)>> {
+    let mut oracle_accounts = vec![];
+    for oracle_key in bank.config.get_oracle_keys() {
+        let oracle_account = test_f.try_load(oracle_key).await?.unwrap();
+        oracle_accounts.push((*oracle_key, oracle_account));
+    }
+
+    Ok(oracle_accounts)
+}
+
+async fn send(test_f: &TestFixture, ixs: &[Instruction]) -> anyhow::Result<()> {
+    let mut ctx = test_f.context.borrow_mut();
+    let tx = Transaction::new_signed_with_payer(
+        ixs,
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+
+    ctx.banks_client.process_transaction(tx).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn crank_accrues_all_banks_and_reports_stale_oracles() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 500)
+        .await?;
+
+    let crank = Crank::new(CrankConfig {
+        accrue_batch_size: 2,
+        collect_fees_batch_size: 2,
+        priority_fee: 1,
+        compute_unit_limit: 400_000,
+    });
+
+    // Fresh oracles pass the program's staleness check.
+    let state = load_state(&test_f).await;
+    let current_timestamp = test_f.get_clock().await.unix_timestamp;
+    for (bank_pk, bank) in state.banks.iter() {
+        let mut oracle_accounts = load_oracle_accounts(&test_f, bank).await?;
+        let status = oracle_status(
+            *bank_pk,
+            bank,
+            Some(&mut oracle_accounts),
+            current_timestamp,
+        );
+        assert!(!status.stale, "{:?}", status);
+    }
+
+    test_f.advance_time(3600).await;
+
+    let state = load_state(&test_f).await;
+    let accrue_ixs = crank.accrue_ixs(&state);
+    assert_eq!(accrue_ixs.len(), test_f.banks.len());
+
+    let batches = crank.batch_ixs(accrue_ixs, 2);
+    assert_eq!(batches.len(), (test_f.banks.len() + 1) / 2);
+    for batch in batches {
+        send(&test_f, &batch).await?;
+    }
+
+    let current_timestamp = test_f.get_clock().await.unix_timestamp;
+    for bank_f in test_f.banks.values() {
+        assert_eq!(bank_f.load().await.last_update, current_timestamp);
+    }
+
+    for batch in crank.batch_ixs(crank.collect_fees_ixs(&state)?, 2) {
+        send(&test_f, &batch).await?;
+    }
+
+    // An hour later nothing updated the oracles.
+    for (bank_pk, bank) in state.banks.iter() {
+        let mut oracle_accounts = load_oracle_accounts(&test_f, bank).await?;
+        let status = oracle_status(
+            *bank_pk,
+            bank,
+            Some(&mut oracle_accounts),
+            current_timestamp,
+        );
+        assert!(status.stale, "{:?}", status);
+        assert!(status.age.unwrap() >= 3600, "{:?}", status);
+    }
+
+    Ok(())
+}
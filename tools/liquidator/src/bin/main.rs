@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use fixed::types::I80F48;
+use marginfi_liquidator::{Liquidator, LiquidatorConfig};
+use marginfi_sdk::state::MarginfiState;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::read_keypair_file,
+};
+
+#[derive(Debug, Parser)]
+#[clap(version, about = "Liquidate unhealthy marginfi accounts of a group")]
+pub struct Opts {
+    #[clap(long)]
+    pub rpc_url: String,
+    /// Keypair signing the liquidations, authority of the liquidator account
+    #[clap(long)]
+    pub keypair: String,
+    #[clap(long, default_value_t = marginfi::ID)]
+    pub program_id: Pubkey,
+    #[clap(long)]
+    pub group: Pubkey,
+    /// Marginfi account receiving the seized collateral
+    #[clap(long)]
+    pub marginfi_account: Pubkey,
+    #[clap(long, default_value_t = 10)]
+    pub poll_interval_secs: u64,
+    /// Minimum profit in USD for a liquidation to be sent
+    #[clap(long, default_value = "0")]
+    pub min_profit: f64,
+    /// Fraction shaved off the largest liquidation the program would accept
+    #[clap(long, default_value = "0.01")]
+    pub size_buffer: f64,
+    /// Repay from the keypair's token accounts inside a flashloan instead of taking on the
+    /// liability in the marginfi account
+    #[clap(long)]
+    pub flashloan: bool,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let opts = Opts::parse();
+
+    let rpc_client = RpcClient::new_with_commitment(opts.rpc_url, CommitmentConfig::confirmed());
+    let signer = read_keypair_file(&opts.keypair)
+        .map_err(|err| anyhow!("Failed to read keypair {}: {}", opts.keypair, err))?;
+
+    let mut state = MarginfiState::load(&rpc_client, opts.program_id, opts.group)?;
+
+    let liquidator = Liquidator::new(LiquidatorConfig {
+        liquidator_account: opts.marginfi_account,
+        use_flashloan: opts.flashloan,
+        min_profit: I80F48::from_num(opts.min_profit),
+        size_buffer: I80F48::from_num(opts.size_buffer),
+    });
+
+    liquidator.run(
+        &rpc_client,
+        &mut state,
+        &signer,
+        Duration::from_secs(opts.poll_interval_secs),
+    )
+}
@@ -0,0 +1,332 @@
+//! Liquidator for a marginfi group.
+//!
+//! Every poll reloads the group's banks, price feeds and marginfi accounts over RPC, sizes a
+//! liquidation for each account below its maintenance requirement with
+//! [`marginfi_sdk::liquidation`], and sends the most profitable one.
+//!
+//! Liquidations are funded either by the liquidator's marginfi account, which takes on the
+//! liability and must stay above its initial requirement, or by a flashloan. In flashloan mode
+//! the seized collateral is withdrawn to the signer's wallet and the liability is repaid from it
+//! in the same transaction, so the marginfi account is left untouched.
+
+use {
+    anchor_spl::token_2022::spl_token_2022::{self, extension::StateWithExtensions},
+    anyhow::{bail, Result},
+    fixed::types::I80F48,
+    log::{debug, info, warn},
+    marginfi_sdk::{
+        health::compute_account_health,
+        instructions,
+        liquidation::{
+            calc_liquidation_profit, check_liquidation, find_best_liquidation, LiquidationPlan,
+        },
+        state::{fetch_clock, MarginfiState},
+    },
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{
+        compute_budget::ComputeBudgetInstruction,
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        signer::Signer,
+        transaction::Transaction,
+    },
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent,
+    std::{thread, time::Duration},
+};
+
+const COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LiquidatorConfig {
+    /// Marginfi account receiving the seized collateral, owned by the signer.
+    pub liquidator_account: Pubkey,
+    /// Fund the repayment from the signer's wallet inside a flashloan.
+    pub use_flashloan: bool,
+    /// Minimum profit in USD, at oracle price, for a liquidation to be sent.
+    pub min_profit: I80F48,
+    /// Fraction shaved off the largest passing asset amount, so price or interest moves between
+    /// planning and execution don't make the post liquidation check fail.
+    pub size_buffer: I80F48,
+}
+
+pub struct Liquidator {
+    pub config: LiquidatorConfig,
+}
+
+impl Liquidator {
+    pub fn new(config: LiquidatorConfig) -> Self {
+        Self { config }
+    }
+
+    /// Liquidation to send for `liquidatee_pk`, `None` if the account is healthy or no
+    /// liquidation clears `min_profit`. Both marginfi accounts must be loaded in `state`.
+    pub fn plan(
+        &self,
+        state: &MarginfiState,
+        liquidatee_pk: &Pubkey,
+        current_timestamp: i64,
+    ) -> Result<Option<LiquidationPlan>> {
+        let liquidatee = state.get_marginfi_account(liquidatee_pk)?;
+        let liquidator = state.get_marginfi_account(&self.config.liquidator_account)?;
+
+        if !compute_account_health(&state.group, liquidatee, &state.banks, &state.price_feeds)?
+            .is_liquidatable()
+        {
+            return Ok(None);
+        }
+
+        let Some(plan) = find_best_liquidation(
+            &state.group,
+            liquidatee,
+            liquidator,
+            self.config.use_flashloan,
+            &state.banks,
+            &state.price_feeds,
+            current_timestamp,
+        )?
+        else {
+            return Ok(None);
+        };
+
+        // Withdrawing and repaying everything at the end of the flashloan would also close any
+        // position the liquidator already holds in these banks.
+        if self.config.use_flashloan
+            && liquidator
+                .lending_account
+                .get_active_balances_iter()
+                .any(|balance| {
+                    balance.bank_pk == plan.asset_bank_pk || balance.bank_pk == plan.liab_bank_pk
+                })
+        {
+            warn!(
+                "Skipping {}: liquidator holds a balance in bank {} or {}",
+                liquidatee_pk, plan.asset_bank_pk, plan.liab_bank_pk
+            );
+            return Ok(None);
+        }
+
+        let asset_amount = (I80F48::ONE - self.config.size_buffer)
+            .checked_mul_int(plan.amounts.asset_amount as i128)
+            .and_then(|amount| amount.checked_to_num::<u64>())
+            .unwrap_or(0);
+        if asset_amount == 0 {
+            return Ok(None);
+        }
+
+        let amounts = check_liquidation(
+            &state.group,
+            liquidatee,
+            liquidator,
+            self.config.use_flashloan,
+            &state.banks,
+            &state.price_feeds,
+            &plan.asset_bank_pk,
+            &plan.liab_bank_pk,
+            asset_amount,
+            current_timestamp,
+        )?;
+        let profit = calc_liquidation_profit(
+            &plan.asset_bank_pk,
+            state.get_bank(&plan.asset_bank_pk)?,
+            &plan.liab_bank_pk,
+            state.get_bank(&plan.liab_bank_pk)?,
+            &state.price_feeds,
+            &amounts,
+        )?;
+
+        if profit < self.config.min_profit {
+            debug!(
+                "Skipping {}: profit {} below minimum {}",
+                liquidatee_pk, profit, self.config.min_profit
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(LiquidationPlan {
+            amounts,
+            profit,
+            ..plan
+        }))
+    }
+
+    /// Instructions executing `plan` against `liquidatee_pk`, signed by the liquidator account
+    /// authority.
+    pub fn liquidation_ixs(
+        &self,
+        state: &MarginfiState,
+        signer: Pubkey,
+        liquidatee_pk: &Pubkey,
+        plan: &LiquidationPlan,
+    ) -> Result<Vec<Instruction>> {
+        let liquidator_pk = self.config.liquidator_account;
+
+        let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            COMPUTE_UNIT_LIMIT,
+        )];
+
+        let liquidate_ix = state.liquidate_ix(
+            liquidator_pk,
+            signer,
+            *liquidatee_pk,
+            plan.asset_bank_pk,
+            plan.liab_bank_pk,
+            plan.amounts.asset_amount,
+        )?;
+
+        if !self.config.use_flashloan {
+            ixs.push(liquidate_ix);
+            return Ok(ixs);
+        }
+
+        let asset_mint = state.get_bank(&plan.asset_bank_pk)?.mint;
+        ixs.push(create_associated_token_account_idempotent(
+            &signer,
+            &signer,
+            &asset_mint,
+            &state.get_token_program(&asset_mint)?,
+        ));
+
+        // start, liquidate, withdraw, repay, end
+        let end_index = ixs.len() + 4;
+        ixs.extend([
+            instructions::lending_account_start_flashloan(
+                &state.program_id,
+                liquidator_pk,
+                signer,
+                end_index as u64,
+            ),
+            liquidate_ix,
+            state.withdraw_ix(liquidator_pk, signer, plan.asset_bank_pk, 0, true)?,
+            state.repay_ix(liquidator_pk, signer, plan.liab_bank_pk, 0, true)?,
+            state.end_flashloan_ix(liquidator_pk, signer, &[], &[])?,
+        ]);
+
+        Ok(ixs)
+    }
+
+    /// Reload the group and send at most one liquidation per liquidatable account, returning the
+    /// signatures of the confirmed transactions.
+    pub fn poll_once(
+        &self,
+        rpc_client: &RpcClient,
+        state: &mut MarginfiState,
+        signer: &Keypair,
+    ) -> Result<Vec<Signature>> {
+        state.refresh_banks(rpc_client)?;
+        state.refresh_price_feeds(rpc_client)?;
+        state.marginfi_accounts.clear();
+        let marginfi_account_pks = state.load_all_marginfi_accounts(rpc_client)?;
+
+        let liquidator_pk = self.config.liquidator_account;
+        let liquidator = state.get_marginfi_account(&liquidator_pk)?;
+        if liquidator.authority != signer.pubkey() {
+            bail!(
+                "Signer {} is not the authority of {}",
+                signer.pubkey(),
+                liquidator_pk
+            );
+        }
+
+        let current_timestamp = fetch_clock(rpc_client)?.unix_timestamp;
+
+        let mut signatures = vec![];
+        for liquidatee_pk in marginfi_account_pks {
+            if liquidatee_pk == liquidator_pk {
+                continue;
+            }
+
+            let plan = match self.plan(state, &liquidatee_pk, current_timestamp) {
+                Ok(Some(plan)) => plan,
+                Ok(None) => continue,
+                Err(err) => {
+                    debug!("Skipping {}: {}", liquidatee_pk, err);
+                    continue;
+                }
+            };
+
+            if self.config.use_flashloan
+                && !Self::can_repay_from_wallet(rpc_client, state, &signer.pubkey(), &plan)?
+            {
+                warn!(
+                    "Skipping {}: wallet can't cover {} of bank {}",
+                    liquidatee_pk, plan.amounts.liab_amount_liquidator, plan.liab_bank_pk
+                );
+                continue;
+            }
+
+            info!(
+                "Liquidating {}: {} of bank {} for bank {}, expected profit {}",
+                liquidatee_pk,
+                plan.amounts.asset_amount,
+                plan.asset_bank_pk,
+                plan.liab_bank_pk,
+                plan.profit
+            );
+
+            let ixs = self.liquidation_ixs(state, signer.pubkey(), &liquidatee_pk, &plan)?;
+            let tx = Transaction::new_signed_with_payer(
+                &ixs,
+                Some(&signer.pubkey()),
+                &[signer],
+                rpc_client.get_latest_blockhash()?,
+            );
+
+            match rpc_client.send_and_confirm_transaction(&tx) {
+                Ok(signature) => {
+                    info!("Liquidated {}: {}", liquidatee_pk, signature);
+                    signatures.push(signature);
+                }
+                Err(err) => warn!("Failed to liquidate {}: {}", liquidatee_pk, err),
+            }
+
+            // The liquidator balances moved, later plans must start from the new state.
+            state.load_marginfi_account(rpc_client, liquidator_pk)?;
+        }
+
+        Ok(signatures)
+    }
+
+    /// Poll the group every `poll_interval` until an RPC error occurs outside of sending.
+    pub fn run(
+        &self,
+        rpc_client: &RpcClient,
+        state: &mut MarginfiState,
+        signer: &Keypair,
+        poll_interval: Duration,
+    ) -> Result<()> {
+        loop {
+            let signatures = self.poll_once(rpc_client, state, signer)?;
+            debug!("Poll done, {} liquidations sent", signatures.len());
+
+            thread::sleep(poll_interval);
+        }
+    }
+
+    /// The flashloan repays the liability in full from the signer's token account.
+    fn can_repay_from_wallet(
+        rpc_client: &RpcClient,
+        state: &MarginfiState,
+        signer: &Pubkey,
+        plan: &LiquidationPlan,
+    ) -> Result<bool> {
+        let ata = state.get_bank_ata(signer, &plan.liab_bank_pk)?;
+        let Ok(account) = rpc_client.get_account(&ata) else {
+            return Ok(false);
+        };
+        let balance = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)?
+            .base
+            .amount;
+
+        // Repaying everything rounds the share amount up.
+        let repay_amount = plan
+            .amounts
+            .liab_amount_liquidator
+            .checked_ceil()
+            .and_then(|amount| amount.checked_to_num::<u64>())
+            .unwrap_or(u64::MAX)
+            .saturating_add(1);
+
+        Ok(balance >= repay_amount)
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::native;
+use fixtures::prelude::*;
+use marginfi::state::{
+    marginfi_account::MarginfiAccount,
+    marginfi_group::{Bank, BankConfig, BankConfigOpt, MarginfiGroup},
+};
+use marginfi_liquidator::{Liquidator, LiquidatorConfig};
+use marginfi_sdk::{
+    health::compute_account_health,
+    state::{load_price_feed, MarginfiState},
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signer::Signer, transaction::Transaction};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+
+/// Lender supplies 2000 USDC, borrower borrows 999 USDC against 100 SOL, then the SOL weights are
+/// lowered so the borrower falls below maintenance. Returns (lender, borrower).
+async fn setup_unhealthy_borrower(
+    test_f: &TestFixture,
+) -> anyhow::Result<(MarginfiAccountFixture, MarginfiAccountFixture)> {
+    let usdc_bank_f = test_f.get_bank(&BankMint::USDC);
+    let sol_bank_f = test_f.get_bank(&BankMint::SOL);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 2_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100)
+        .await?;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 999)
+        .await?;
+
+    sol_bank_f
+        .update_config(BankConfigOpt {
+            asset_weight_init: Some(I80F48!(0.25).into()),
+            asset_weight_maint: Some(I80F48!(0.5).into()),
+            ..Default::default()
+        })
+        .await?;
+
+    Ok((lender_mfi_account_f, borrower_mfi_account_f))
+}
+
+async fn new_test_fixture() -> TestFixture {
+    TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::USDC,
+                ..TestBankSetting::default()
+            },
+            TestBankSetting {
+                mint: BankMint::SOL,
+                config: Some(BankConfig {
+                    asset_weight_init: I80F48!(1).into(),
+                    asset_weight_maint: I80F48!(1).into(),
+                    ..*DEFAULT_SOL_TEST_BANK_CONFIG
+                }),
+            },
+        ],
+        group_config: Some(GroupConfig { admin: None }),
+    }))
+    .await
+}
+
+/// Same snapshot `MarginfiState::load` builds over RPC, read through the banks client.
+async fn load_state(test_f: &TestFixture, marginfi_account_pks: &[Pubkey]) -> MarginfiState {
+    let current_timestamp = test_f.get_clock().await.unix_timestamp;
+
+    let mut banks = HashMap::new();
+    let mut token_programs = HashMap::new();
+    let mut price_feeds = HashMap::new();
+    for bank_f in test_f.banks.values() {
+        let mut bank: Bank = bank_f.load().await;
+        bank.accrue_interest(current_timestamp).unwrap();
+
+        let mint_account = test_f.try_load(&bank.mint).await.unwrap().unwrap();
+        token_programs.insert(bank.mint, mint_account.owner);
+
+        let mut oracle_accounts = vec![];
+        for oracle_key in bank.config.get_oracle_keys() {
+            let oracle_account = test_f.try_load(oracle_key).await.unwrap().unwrap();
+            oracle_accounts.push((*oracle_key, oracle_account));
+        }
+        price_feeds.insert(
+            bank_f.key,
+            load_price_feed(&bank, &mut oracle_accounts, current_timestamp).unwrap(),
+        );
+
+        banks.insert(bank_f.key, bank);
+    }
+
+    let mut marginfi_accounts = HashMap::new();
+    for marginfi_account_pk in marginfi_account_pks {
+        marginfi_accounts.insert(
+            *marginfi_account_pk,
+            test_f
+                .load_and_deserialize::<MarginfiAccount>(marginfi_account_pk)
+                .await,
+        );
+    }
+
+    MarginfiState {
+        program_id: marginfi::ID,
+        group_pk: test_f.marginfi_group.key,
+        group: test_f
+            .load_and_deserialize::<MarginfiGroup>(&test_f.marginfi_group.key)
+            .await,
+        banks,
+        token_programs,
+        price_feeds,
+        marginfi_accounts,
+    }
+}
+
+async fn send(
+    test_f: &TestFixture,
+    ixs: &[solana_sdk::instruction::Instruction],
+) -> anyhow::Result<()> {
+    let mut ctx = test_f.context.borrow_mut();
+    let tx = Transaction::new_signed_with_payer(
+        ixs,
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+
+    ctx.banks_client.process_transaction(tx).await?;
+
+    Ok(())
+}
+
+fn maintenance_health(state: &MarginfiState, marginfi_account_pk: &Pubkey) -> I80F48 {
+    compute_account_health(
+        &state.group,
+        state.get_marginfi_account(marginfi_account_pk).unwrap(),
+        &state.banks,
+        &state.price_feeds,
+    )
+    .unwrap()
+    .maintenance
+    .health()
+}
+
+#[tokio::test]
+async fn liquidator_liquidates_unhealthy_account() -> anyhow::Result<()> {
+    let test_f = new_test_fixture().await;
+    let (lender_mfi_account_f, borrower_mfi_account_f) = setup_unhealthy_borrower(&test_f).await?;
+    let liquidator_pk = lender_mfi_account_f.key;
+    let borrower_pk = borrower_mfi_account_f.key;
+    let sol_bank_pk = test_f.get_bank(&BankMint::SOL).key;
+
+    let liquidator = Liquidator::new(LiquidatorConfig {
+        liquidator_account: liquidator_pk,
+        use_flashloan: false,
+        min_profit: I80F48::ZERO,
+        size_buffer: I80F48!(0.01),
+    });
+
+    let state = load_state(&test_f, &[liquidator_pk, borrower_pk]).await;
+    let current_timestamp = test_f.get_clock().await.unix_timestamp;
+
+    // The liquidator itself is healthy.
+    assert!(liquidator
+        .plan(&state, &liquidator_pk, current_timestamp)?
+        .is_none());
+
+    let plan = liquidator
+        .plan(&state, &borrower_pk, current_timestamp)?
+        .expect("borrower is liquidatable");
+    assert_eq!(plan.asset_bank_pk, sol_bank_pk);
+    assert_eq!(plan.liab_bank_pk, test_f.get_bank(&BankMint::USDC).key);
+    assert!(plan.profit > I80F48::ZERO);
+
+    let health_before = maintenance_health(&state, &borrower_pk);
+
+    send(
+        &test_f,
+        &liquidator.liquidation_ixs(&state, test_f.payer(), &borrower_pk, &plan)?,
+    )
+    .await?;
+
+    let state = load_state(&test_f, &[liquidator_pk, borrower_pk]).await;
+    let health_after = maintenance_health(&state, &borrower_pk);
+    assert!(health_after > health_before);
+    assert!(health_after <= I80F48::ZERO);
+
+    let sol_bank = state.get_bank(&sol_bank_pk)?;
+    let liquidator_sol_balance = state
+        .get_marginfi_account(&liquidator_pk)?
+        .lending_account
+        .get_balance(&sol_bank_pk)
+        .unwrap();
+    assert_eq!(
+        sol_bank
+            .get_asset_amount(liquidator_sol_balance.asset_shares.into())?
+            .to_num::<u64>(),
+        plan.amounts.asset_amount
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn liquidator_liquidates_with_flashloan() -> anyhow::Result<()> {
+    let mut test_f = new_test_fixture().await;
+    let (_, borrower_mfi_account_f) = setup_unhealthy_borrower(&test_f).await?;
+    let liquidator_mfi_account_f = test_f.create_marginfi_account().await;
+    let liquidator_pk = liquidator_mfi_account_f.key;
+    let borrower_pk = borrower_mfi_account_f.key;
+
+    // The liability is repaid from the signer's wallet.
+    let payer = test_f.payer();
+    let usdc_ata = get_associated_token_address(&payer, &test_f.usdc_mint.key);
+    let sol_ata = get_associated_token_address(&payer, &test_f.sol_mint.key);
+    send(
+        &test_f,
+        &[create_associated_token_account_idempotent(
+            &payer,
+            &payer,
+            &test_f.usdc_mint.key,
+            &anchor_spl::token::ID,
+        )],
+    )
+    .await?;
+    test_f.usdc_mint.mint_to(&usdc_ata, 1_000).await;
+
+    let liquidator = Liquidator::new(LiquidatorConfig {
+        liquidator_account: liquidator_pk,
+        use_flashloan: true,
+        min_profit: I80F48::ZERO,
+        size_buffer: I80F48!(0.01),
+    });
+
+    let state = load_state(&test_f, &[liquidator_pk, borrower_pk]).await;
+    let current_timestamp = test_f.get_clock().await.unix_timestamp;
+
+    let plan = liquidator
+        .plan(&state, &borrower_pk, current_timestamp)?
+        .expect("borrower is liquidatable");
+    let health_before = maintenance_health(&state, &borrower_pk);
+
+    send(
+        &test_f,
+        &liquidator.liquidation_ixs(&state, payer, &borrower_pk, &plan)?,
+    )
+    .await?;
+
+    let state = load_state(&test_f, &[liquidator_pk, borrower_pk]).await;
+    let health_after = maintenance_health(&state, &borrower_pk);
+    assert!(health_after > health_before);
+    assert!(health_after <= I80F48::ZERO);
+
+    // The flashloan leaves the liquidator account empty, the collateral sits in the wallet.
+    assert!(state
+        .get_marginfi_account(&liquidator_pk)?
+        .lending_account
+        .get_active_balances_iter()
+        .next()
+        .is_none());
+    assert_eq!(
+        balance_of(test_f.context.clone(), sol_ata).await,
+        plan.amounts.asset_amount
+    );
+    assert!(balance_of(test_f.context.clone(), usdc_ata).await < native!(1_000, "USDC"));
+
+    Ok(())
+}
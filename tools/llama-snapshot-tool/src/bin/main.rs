@@ -152,8 +152,7 @@ impl DefiLammaPoolInfo {
         };
 
         let (lending_rate, borrowing_rate, _, _) = bank
-            .config
-            .interest_rate_config
+            .get_interest_rate_config()
             .calc_interest_rate(ur)
             .ok_or_else(|| {
                 anyhow::anyhow!("Failed to calculate interest rate for bank {}", bank_pk)